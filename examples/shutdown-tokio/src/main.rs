@@ -11,6 +11,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let cancel = CancellationToken::new();
     let app: App<()> = App::new()
         .with_shutdown(cancel.clone())
+        .with_shutdown_timeout(Duration::from_secs(10))
         .with_stateless_route("/", hello);
 
     // Shutdown the main app after 5 seconds
@@ -19,8 +20,9 @@ async fn main() -> Result<(), Box<dyn Error>> {
         cancel.cancel();
     });
 
-    // Returns after shutdown signal
-    app.run("0.0.0.0:8080").await?;
+    // Returns after shutdown signal, once the requests in progress have finished
+    let summary = app.run("0.0.0.0:8080").await?;
+    println!("Shut down: {:?}", summary);
 
     Ok(())
 }
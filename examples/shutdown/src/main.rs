@@ -11,6 +11,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let app: App<()> = App::new()
         .with_shutdown(app_rx)
+        .with_shutdown_timeout(Duration::from_secs(10))
         .with_stateless_route("/hello", |_| Response::new(StatusCode::OK, "Hello world!"));
 
     // Shutdown the main app after 5 seconds
@@ -19,8 +20,9 @@ fn main() -> Result<(), Box<dyn Error>> {
         let _ = shutdown_app.send(());
     });
 
-    // Returns after shutdown signal
-    app.run("0.0.0.0:8080").unwrap();
+    // Returns after shutdown signal, once the requests in progress have finished
+    let summary = app.run("0.0.0.0:8080").unwrap();
+    println!("Shut down: {:?}", summary);
 
     Ok(())
 }
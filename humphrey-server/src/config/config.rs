@@ -4,7 +4,17 @@ use crate::config::extended_hashmap::ExtendedMap;
 use crate::config::tree::{parse_conf, ConfigNode};
 use crate::logger::LogLevel;
 use crate::proxy::{EqMutex, LoadBalancer};
-use crate::rand::Lcg;
+
+use humphrey::app::DEFAULT_SHUTDOWN_TIMEOUT;
+use humphrey::http::address::Cidr;
+use humphrey::http::compression::{
+    Encoding, DEFAULT_COMPRESSED_TYPES, DEFAULT_MIN_COMPRESSION_SIZE,
+};
+use humphrey::http::params::Params;
+use humphrey::http::proxy::{ConnectionPool, DEFAULT_IDLE_TIMEOUT, DEFAULT_MAX_IDLE_CONNECTIONS};
+use humphrey::krauss::wildcard_match;
+use humphrey::listener::Listener;
+use humphrey::route::Route;
 
 use std::collections::HashMap;
 use std::env::{args, var};
@@ -25,6 +35,8 @@ pub struct Config {
     pub port: u16,
     /// The number of threads to host the server on
     pub threads: usize,
+    /// The addresses to listen on, which default to the address and port
+    pub listen: Vec<Listener>,
     /// The TLS configuration to use
     #[cfg(feature = "tls")]
     pub tls_config: Option<TlsConfig>,
@@ -43,8 +55,18 @@ pub struct Config {
     pub cache: CacheConfig,
     /// Blacklist configuration
     pub blacklist: BlacklistConfig,
+    /// The ranges of addresses of proxies trusted to forward the origin address of requests
+    pub trusted_proxies: Vec<Cidr>,
+    /// Whether peers connected over a Unix domain socket are trusted to forward the origin address of requests
+    pub trust_unix_sockets: bool,
+    /// Compression configuration
+    pub compression: CompressionConfig,
     /// The amount of time to wait between requests
     pub connection_timeout: Option<Duration>,
+    /// The amount of time requests in progress are given to finish when the server shuts down
+    pub shutdown_timeout: Duration,
+    /// Whether to reload the configuration when one of its files changes
+    pub watch_config: bool,
 }
 
 /// Represents the configuration for a specific host.
@@ -84,6 +106,8 @@ pub struct RouteConfig {
     pub load_balancer: Option<EqMutex<LoadBalancer>>,
     /// The WebSocket proxy target for WebSocket connections to this route
     pub websocket_proxy: Option<String>,
+    /// Whether to list the contents of directories without an index file
+    pub listing: bool,
 }
 
 /// Represents configuration for the logger.
@@ -115,6 +139,51 @@ pub struct BlacklistConfig {
     pub mode: BlacklistMode,
 }
 
+/// Represents configuration for response compression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompressionConfig {
+    /// Whether to compress responses
+    pub enabled: bool,
+    /// The encodings to use, in order of preference
+    pub encodings: Vec<Encoding>,
+    /// The content types to compress
+    pub types: Vec<String>,
+    /// The minimum size of a response body to compress, in bytes
+    pub min_size: usize,
+}
+
+/// Represents configuration for the health checks of a proxy route's targets.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HealthCheckConfig {
+    /// The path requested from each target to check its health, or `None` to disable active health checks
+    pub path: Option<String>,
+    /// The amount of time between active health checks
+    pub interval: Duration,
+    /// The amount of time to wait for a target to respond to a health check
+    pub timeout: Duration,
+    /// The number of consecutive failed requests after which a target is ejected, or zero to never eject targets
+    ///   because of failed requests
+    pub max_fails: usize,
+    /// The amount of time an unhealthy target is ejected for before it is tried again
+    pub fail_timeout: Duration,
+}
+
+/// Represents configuration for how a proxy route forwards requests to its targets.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProxyConfig {
+    /// The amount of time to wait for a target to respond
+    pub timeout: Duration,
+    /// The headers to set on requests to the targets, replacing any sent by the client, where an empty value removes
+    ///   the header
+    pub set_headers: Vec<(String, String)>,
+    /// Whether to pass on the `Host` header sent by the client rather than the address of the target
+    pub preserve_host: bool,
+    /// Whether to rewrite `Location` headers which redirect to the target so they redirect through the route instead
+    pub rewrite_location: bool,
+    /// Whether to rewrite the paths of cookies set by the target so they are relative to the route
+    pub rewrite_cookie_path: bool,
+}
+
 /// Represents configuration for TLS.
 #[cfg(feature = "tls")]
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -142,10 +211,25 @@ pub struct PluginConfig {
 /// Represents an algorithm for load balancing.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum LoadBalancerMode {
-    /// Evenly distributes load in a repeating pattern
+    /// Evenly distributes load in a repeating pattern, in proportion to the weights of the targets
     RoundRobin,
-    /// Randomly distributes load
+    /// Randomly distributes load, in proportion to the weights of the targets
     Random,
+    /// Sends each request to the target with the fewest requests in progress relative to its weight
+    LeastConnections,
+    /// Consistently sends requests with the same hash key to the same target, for sticky sessions
+    Hash,
+}
+
+/// Represents the part of a request which is hashed to choose its target when load balancing by hash.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HashKey {
+    /// The origin address of the client
+    Address,
+    /// The value of the header with the given name, or the origin address if the header is not present
+    Header(String),
+    /// The value of the cookie with the given name, or the origin address if the cookie is not present
+    Cookie(String),
 }
 
 /// Represents a method of applying the blacklist.
@@ -173,15 +257,7 @@ pub enum ConfigSource {
 impl Config {
     /// Attempts to load the configuration.
     pub fn load() -> Result<Self, String> {
-        let (path, source) = if let Some(arg_path) = args().nth(1) {
-            (arg_path, ConfigSource::Argument)
-        } else if Path::new("humphrey.conf").exists() {
-            ("humphrey.conf".into(), ConfigSource::CurrentDirectory)
-        } else if let Ok(env_path) = var("HUMPHREY_CONF") {
-            (env_path, ConfigSource::EnvironmentVariable)
-        } else {
-            ("".into(), ConfigSource::Default)
-        };
+        let (path, source) = Self::locate();
 
         if let Ok((filename, config_string)) = load_config_file(path) {
             let tree = parse_conf(&config_string, &filename).map_err(|e| e.to_string())?;
@@ -194,6 +270,32 @@ impl Config {
         }
     }
 
+    /// Finds the path of the configuration file, returning it along with where it was found.
+    /// If no configuration file could be found, the path is empty and the source is `ConfigSource::Default`.
+    pub fn locate() -> (String, ConfigSource) {
+        if let Some(arg_path) = args().nth(1) {
+            (arg_path, ConfigSource::Argument)
+        } else if Path::new("humphrey.conf").exists() {
+            ("humphrey.conf".into(), ConfigSource::CurrentDirectory)
+        } else if let Ok(env_path) = var("HUMPHREY_CONF") {
+            (env_path, ConfigSource::EnvironmentVariable)
+        } else {
+            ("".into(), ConfigSource::Default)
+        }
+    }
+
+    /// Loads the configuration from the file at the given path, for example when reloading it.
+    /// Unlike `Config::load`, this does not fall back to the default configuration if the file cannot be read.
+    pub fn load_file(path: &str, source: ConfigSource) -> Result<Self, String> {
+        let (filename, config_string) = load_config_file(path)
+            .map_err(|_| format!("Could not read configuration file {}", path))?;
+        let tree = parse_conf(&config_string, &filename).map_err(|e| e.to_string())?;
+        let mut config = Self::from_tree(tree)?;
+        config.source = source;
+
+        Ok(config)
+    }
+
     /// Parses the config from the config tree.
     pub fn from_tree(tree: ConfigNode) -> Result<Self, &'static str> {
         let mut hashmap: HashMap<String, ConfigNode> = HashMap::new();
@@ -212,6 +314,17 @@ impl Config {
         } else {
             None
         };
+        let shutdown_timeout = Duration::from_secs(hashmap.get_optional_parsed(
+            "server.shutdown_timeout",
+            DEFAULT_SHUTDOWN_TIMEOUT.as_secs(),
+            "Invalid shutdown timeout",
+        )?);
+
+        let watch_config = hashmap.get_optional_parsed(
+            "server.watch_config",
+            false,
+            "server.watch_config must be a boolean",
+        )?;
 
         if threads < 1 {
             return Err("You cannot specify less than 1 thread");
@@ -243,6 +356,22 @@ impl Config {
             }
         };
 
+        // Get and validate the trusted proxies
+        let trusted_proxies = match hashmap.get_owned("server.trusted_proxies") {
+            Some(trusted_proxies) => trusted_proxies
+                .split(',')
+                .map(|range| range.parse::<Cidr>())
+                .collect::<Result<Vec<Cidr>, &'static str>>()
+                .map_err(|_| "Invalid trusted proxy address range")?,
+            None => Vec::new(),
+        };
+
+        let trust_unix_sockets = hashmap.get_optional_parsed(
+            "server.trust_unix_sockets",
+            false,
+            "server.trust_unix_sockets must be a boolean",
+        )?;
+
         #[cfg(feature = "tls")]
         let tls_config = {
             let cert_file = hashmap.get_owned("server.tls.cert_file");
@@ -253,10 +382,6 @@ impl Config {
                 return Err("A minimum of two threads are required to force HTTPS");
             }
 
-            if force == "true" && port != 443 {
-                return Err("Forcing HTTPS redirects requires the port to be 443");
-            }
-
             if let Some(cert_file) = cert_file {
                 if let Some(key_file) = key_file {
                     Some(TlsConfig {
@@ -272,6 +397,48 @@ impl Config {
             }
         };
 
+        // Get and validate the addresses to listen on
+        let listen = match hashmap.get_owned("server.listen") {
+            Some(listen) => listen
+                .split(',')
+                .map(|listener| parse_listener(listener.trim()))
+                .collect::<Result<Vec<Listener>, &'static str>>()?,
+            None => {
+                let addr = format!("{}:{}", address, port);
+
+                #[cfg(feature = "tls")]
+                if tls_config.is_some() {
+                    vec![Listener::Tls(addr)]
+                } else {
+                    vec![Listener::Tcp(addr)]
+                }
+
+                #[cfg(not(feature = "tls"))]
+                vec![Listener::Tcp(addr)]
+            }
+        };
+
+        #[cfg(feature = "tls")]
+        {
+            let tls_listeners: Vec<&String> = listen
+                .iter()
+                .filter_map(|listener| match listener {
+                    Listener::Tls(addr) => Some(addr),
+                    _ => None,
+                })
+                .collect();
+
+            if !tls_listeners.is_empty() && tls_config.is_none() {
+                return Err("HTTPS listeners require a TLS certificate and key");
+            }
+
+            if tls_config.as_ref().is_some_and(|tls| tls.force)
+                && !tls_listeners.iter().any(|addr| addr.ends_with(":443"))
+            {
+                return Err("Forcing HTTPS redirects requires the port to be 443");
+            }
+        }
+
         // Get and validate the logging configuration
         let logging = {
             let log_level = hashmap.get_optional_parsed(
@@ -306,6 +473,42 @@ impl Config {
             }
         };
 
+        // Get and validate the compression configuration
+        let compression = {
+            let enabled = hashmap.get_optional_parsed(
+                "server.compression.enabled",
+                false,
+                "server.compression.enabled must be a boolean",
+            )?;
+            let encodings = hashmap
+                .get_optional("server.compression.encodings", "gzip,deflate".into())
+                .split(',')
+                .map(|encoding| encoding.parse::<Encoding>())
+                .collect::<Result<Vec<Encoding>, _>>()
+                .map_err(|_| {
+                    "Invalid compression encoding, valid options are `gzip` or `deflate`"
+                })?;
+            let types = match hashmap.get_owned("server.compression.types") {
+                Some(types) => types.split(',').map(|t| t.trim().to_string()).collect(),
+                None => DEFAULT_COMPRESSED_TYPES
+                    .iter()
+                    .map(|t| t.to_string())
+                    .collect(),
+            };
+            let min_size = hashmap.get_optional_parsed(
+                "server.compression.min_size",
+                DEFAULT_MIN_COMPRESSION_SIZE,
+                "Invalid minimum compression size",
+            )?;
+
+            CompressionConfig {
+                enabled,
+                encodings,
+                types,
+                min_size,
+            }
+        };
+
         // Get and validate the configuration for the different routes
         let default_host = parse_host("*", &tree)?;
 
@@ -350,6 +553,7 @@ impl Config {
             address,
             port,
             threads,
+            listen,
             #[cfg(feature = "tls")]
             tls_config,
             default_websocket_proxy,
@@ -360,7 +564,12 @@ impl Config {
             logging,
             cache,
             blacklist,
+            trusted_proxies,
+            trust_unix_sockets,
+            compression,
             connection_timeout,
+            shutdown_timeout,
+            watch_config,
         })
     }
 
@@ -372,6 +581,222 @@ impl Config {
             &self.hosts[host - 1].routes[route]
         }
     }
+
+    /// Finds the route which should handle a request for the given host and path, returning the indices of its host
+    ///   and the route, along with the parameters captured from the path.
+    ///
+    /// The first host matching the request is used if it has a route for the path, otherwise the default host is
+    ///   used, in the same way as the sub-apps of a Humphrey app. Only routes which proxy WebSocket connections are
+    ///   considered for WebSocket requests.
+    pub fn find_route(
+        &self,
+        host: Option<&str>,
+        uri: &str,
+        websocket: bool,
+    ) -> Option<(usize, usize, Params)> {
+        let find = |routes: &[RouteConfig]| {
+            routes
+                .iter()
+                .enumerate()
+                .filter(|(_, route)| !websocket || route.websocket_proxy.is_some())
+                .find_map(|(index, route)| Some((index, route.matches.route_params(uri)?)))
+        };
+
+        if let Some((host_index, host_config)) = host.and_then(|host| {
+            self.hosts
+                .iter()
+                .enumerate()
+                .find(|(_, host_config)| wildcard_match(&host_config.matches, host))
+        }) {
+            if let Some((route, params)) = find(&host_config.routes) {
+                return Some((host_index + 1, route, params));
+            }
+        }
+
+        find(&self.default_host.routes).map(|(route, params)| (0, route, params))
+    }
+}
+
+/// Parses a proxy target, which is an address optionally followed by its weight, such as `127.0.0.1:8000 weight=3`.
+fn parse_proxy_target(target: &str) -> Result<(String, usize), &'static str> {
+    let mut parts = target.split_whitespace();
+    let address = parts.next().ok_or("Proxy targets cannot be empty")?;
+    let mut weight = 1;
+
+    for option in parts {
+        weight = match option.split_once('=') {
+            Some(("weight", value)) => value
+                .parse()
+                .ok()
+                .filter(|weight| *weight > 0)
+                .ok_or("Invalid proxy target weight, it must be a positive integer")?,
+            _ => return Err("Invalid proxy target option, the only valid option is `weight`"),
+        };
+    }
+
+    Ok((address.to_string(), weight))
+}
+
+/// Parses the hash key of a load balancer, which is either `address`, `header:<name>` or `cookie:<name>`.
+fn parse_hash_key(hash_key: &str) -> Result<HashKey, &'static str> {
+    match hash_key.split_once(':') {
+        None if hash_key == "address" => Ok(HashKey::Address),
+        Some(("header", name)) if !name.is_empty() => Ok(HashKey::Header(name.to_string())),
+        Some(("cookie", name)) if !name.is_empty() => Ok(HashKey::Cookie(name.to_string())),
+        _ => {
+            Err("Invalid hash key, valid options are `address`, `header:<name>` or `cookie:<name>`")
+        }
+    }
+}
+
+/// Parses the health check configuration of a proxy route.
+fn parse_health_check(
+    conf: &HashMap<String, ConfigNode>,
+) -> Result<HealthCheckConfig, &'static str> {
+    let default = HealthCheckConfig::default();
+
+    let path = conf.get_owned("health_check.path");
+    let interval: u64 = conf.get_optional_parsed(
+        "health_check.interval",
+        default.interval.as_secs(),
+        "Invalid health check interval",
+    )?;
+    let timeout: u64 = conf.get_optional_parsed(
+        "health_check.timeout",
+        default.timeout.as_secs(),
+        "Invalid health check timeout",
+    )?;
+    let max_fails = conf.get_optional_parsed(
+        "health_check.max_fails",
+        default.max_fails,
+        "Invalid health check maximum number of failures",
+    )?;
+    let fail_timeout: u64 = conf.get_optional_parsed(
+        "health_check.fail_timeout",
+        default.fail_timeout.as_secs(),
+        "Invalid health check failure timeout",
+    )?;
+
+    if path.as_ref().is_some_and(|path| !path.starts_with('/')) {
+        return Err("Health check path must begin with `/`");
+    }
+
+    if interval == 0 || timeout == 0 {
+        return Err("Health check interval and timeout must be at least one second");
+    }
+
+    Ok(HealthCheckConfig {
+        path,
+        interval: Duration::from_secs(interval),
+        timeout: Duration::from_secs(timeout),
+        max_fails,
+        fail_timeout: Duration::from_secs(fail_timeout),
+    })
+}
+
+/// Parses the connection pool configuration of a proxy route.
+fn parse_connection_pool(
+    conf: &HashMap<String, ConfigNode>,
+) -> Result<ConnectionPool, &'static str> {
+    let max_idle = conf.get_optional_parsed(
+        "connection_pool.max_idle",
+        DEFAULT_MAX_IDLE_CONNECTIONS,
+        "Invalid connection pool maximum number of idle connections",
+    )?;
+    let idle_timeout: u64 = conf.get_optional_parsed(
+        "connection_pool.idle_timeout",
+        DEFAULT_IDLE_TIMEOUT.as_secs(),
+        "Invalid connection pool idle timeout",
+    )?;
+
+    if idle_timeout == 0 {
+        return Err("Connection pool idle timeout must be at least one second");
+    }
+
+    Ok(ConnectionPool::new()
+        .with_max_idle_connections(max_idle)
+        .with_idle_timeout(Duration::from_secs(idle_timeout)))
+}
+
+/// Parses the configuration of how a proxy route forwards requests to its targets.
+fn parse_proxy_config(conf: &HashMap<String, ConfigNode>) -> Result<ProxyConfig, &'static str> {
+    let default = ProxyConfig::default();
+
+    let timeout: u64 = conf.get_optional_parsed(
+        "proxy_timeout",
+        default.timeout.as_secs(),
+        "Invalid proxy timeout",
+    )?;
+
+    if timeout == 0 {
+        return Err("Proxy timeout must be at least one second");
+    }
+
+    let mut set_headers: Vec<(String, String)> = conf
+        .iter()
+        .filter_map(|(key, node)| {
+            let name = key.strip_prefix("proxy_set_header.")?;
+            Some((name.to_string(), node.get_string().unwrap_or_default()))
+        })
+        .collect();
+
+    // The headers are stored in a hashmap, so they are sorted to keep the order consistent
+    set_headers.sort();
+
+    Ok(ProxyConfig {
+        timeout: Duration::from_secs(timeout),
+        set_headers,
+        preserve_host: conf.get_optional_parsed(
+            "preserve_host",
+            default.preserve_host,
+            "Preserve host must be a boolean",
+        )?,
+        rewrite_location: conf.get_optional_parsed(
+            "rewrite_location",
+            default.rewrite_location,
+            "Rewrite location must be a boolean",
+        )?,
+        rewrite_cookie_path: conf.get_optional_parsed(
+            "rewrite_cookie_path",
+            default.rewrite_cookie_path,
+            "Rewrite cookie path must be a boolean",
+        )?,
+    })
+}
+
+/// Parses a listen address, which is either a TCP socket address optionally prefixed with `http://` or `https://`,
+///   or the path of a Unix domain socket prefixed with `unix:`.
+fn parse_listener(listener: &str) -> Result<Listener, &'static str> {
+    #[cfg(unix)]
+    if let Some(path) = listener.strip_prefix("unix:") {
+        return match path.is_empty() {
+            true => Err("Invalid listen address"),
+            false => Ok(Listener::Unix(path.into())),
+        };
+    }
+
+    #[cfg(not(unix))]
+    if listener.starts_with("unix:") {
+        return Err("Unix domain sockets are not supported on this platform");
+    }
+
+    #[cfg(feature = "tls")]
+    if let Some(addr) = listener.strip_prefix("https://") {
+        return match addr.is_empty() {
+            true => Err("Invalid listen address"),
+            false => Ok(Listener::Tls(addr.into())),
+        };
+    }
+
+    #[cfg(not(feature = "tls"))]
+    if listener.starts_with("https://") {
+        return Err("HTTPS listeners require the `tls` feature");
+    }
+
+    match listener.strip_prefix("http://").unwrap_or(listener) {
+        "" => Err("Invalid listen address"),
+        addr => Ok(Listener::Tcp(addr.into())),
+    }
 }
 
 /// Loads the configuration file.
@@ -447,11 +872,14 @@ fn parse_route(
                 path: Some(file),
                 load_balancer: None,
                 websocket_proxy,
+                listing: false,
             });
         } else if conf.contains_key("directory") {
             // This is a regular directory-serving route
 
             let directory = conf.get_compulsory("directory", "").unwrap();
+            let listing =
+                conf.get_optional_parsed("listing", false, "Directory listing must be a boolean")?;
 
             routes.push(RouteConfig {
                 route_type: RouteType::Directory,
@@ -459,33 +887,51 @@ fn parse_route(
                 path: Some(directory),
                 load_balancer: None,
                 websocket_proxy,
+                listing,
             });
         } else if conf.contains_key("proxy") {
             // This is a proxy route
 
-            let targets: Vec<String> = conf
+            let (targets, weights): (Vec<String>, Vec<usize>) = conf
                 .get_compulsory("proxy", "")
                 .unwrap()
                 .split(',')
-                .map(|s| s.to_owned())
-                .collect();
+                .map(parse_proxy_target)
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .unzip();
 
             let load_balancer_mode = conf.get_optional("load_balancer_mode", "round-robin".into());
-            let load_balancer_mode =
-                match load_balancer_mode.as_str() {
-                    "round-robin" => LoadBalancerMode::RoundRobin,
-                    "random" => LoadBalancerMode::Random,
-                    _ => return Err(
-                        "Invalid load balancer mode, valid options are `round-robin` or `random`",
+            let load_balancer_mode = match load_balancer_mode.as_str() {
+                "round-robin" => LoadBalancerMode::RoundRobin,
+                "random" => LoadBalancerMode::Random,
+                "least-connections" => LoadBalancerMode::LeastConnections,
+                "hash" => LoadBalancerMode::Hash,
+                _ => return Err(
+                    "Invalid load balancer mode, valid options are `round-robin`, `random`, `least-connections` or `hash`",
+                ),
+            };
+
+            let hash_key =
+                match conf.get_owned("hash_key") {
+                    Some(_) if load_balancer_mode != LoadBalancerMode::Hash => return Err(
+                        "The `hash_key` field can only be used with the `hash` load balancer mode",
                     ),
+                    Some(hash_key) => parse_hash_key(&hash_key)?,
+                    None => HashKey::Address,
                 };
 
-            let load_balancer = EqMutex::new(LoadBalancer {
-                targets,
-                mode: load_balancer_mode,
-                lcg: Lcg::new(),
-                index: 0,
-            });
+            let health_check = parse_health_check(&conf)?;
+            let pool = parse_connection_pool(&conf)?;
+            let proxy_config = parse_proxy_config(&conf)?;
+
+            let load_balancer = EqMutex::new(
+                LoadBalancer::new(targets, load_balancer_mode, health_check)
+                    .with_weights(weights)
+                    .with_hash_key(hash_key)
+                    .with_pool(pool)
+                    .with_proxy_config(proxy_config),
+            );
 
             routes.push(RouteConfig {
                 route_type: RouteType::Proxy,
@@ -493,6 +939,7 @@ fn parse_route(
                 path: None,
                 load_balancer: Some(load_balancer),
                 websocket_proxy,
+                listing: false,
             });
         } else if conf.contains_key("redirect") {
             // This is a redirect route
@@ -505,6 +952,7 @@ fn parse_route(
                 path: Some(target),
                 load_balancer: None,
                 websocket_proxy,
+                listing: false,
             });
         } else if !conf.contains_key("websocket") {
             return Err("Invalid route configuration, every route must contain either the `file`, `directory`, `proxy` or `redirect` field, unless it defines a WebSocket proxy with the `websocket` field");
@@ -515,6 +963,7 @@ fn parse_route(
                 path: None,
                 load_balancer: None,
                 websocket_proxy,
+                listing: false,
             });
         }
     }
@@ -1,11 +1,19 @@
 //! Provides default values for the configuration.
 
 use crate::config::{
-    BlacklistConfig, BlacklistMode, Config, ConfigSource, HostConfig, LoggingConfig, RouteConfig,
-    RouteType,
+    BlacklistConfig, BlacklistMode, CompressionConfig, Config, ConfigSource, HealthCheckConfig,
+    HostConfig, LoggingConfig, ProxyConfig, RouteConfig, RouteType,
 };
 use crate::server::logger::LogLevel;
 
+use humphrey::app::DEFAULT_SHUTDOWN_TIMEOUT;
+use humphrey::http::compression::{
+    Encoding, DEFAULT_COMPRESSED_TYPES, DEFAULT_MIN_COMPRESSION_SIZE,
+};
+use humphrey::listener::Listener;
+
+use std::time::Duration;
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -13,6 +21,7 @@ impl Default for Config {
             address: "0.0.0.0".into(),
             port: 80,
             threads: 32,
+            listen: vec![Listener::Tcp("0.0.0.0:80".into())],
             #[cfg(feature = "tls")]
             tls_config: None,
             default_websocket_proxy: None,
@@ -23,7 +32,12 @@ impl Default for Config {
             logging: Default::default(),
             cache: Default::default(),
             blacklist: Default::default(),
+            trusted_proxies: Vec::new(),
+            trust_unix_sockets: false,
+            compression: Default::default(),
             connection_timeout: Default::default(),
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            watch_config: false,
         }
     }
 }
@@ -45,6 +59,7 @@ impl Default for RouteConfig {
             path: Some('.'.into()),
             load_balancer: None,
             websocket_proxy: None,
+            listing: false,
         }
     }
 }
@@ -67,3 +82,41 @@ impl Default for BlacklistConfig {
         }
     }
 }
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            encodings: vec![Encoding::Gzip, Encoding::Deflate],
+            types: DEFAULT_COMPRESSED_TYPES
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+            min_size: DEFAULT_MIN_COMPRESSION_SIZE,
+        }
+    }
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            set_headers: Vec::new(),
+            preserve_host: false,
+            rewrite_location: false,
+            rewrite_cookie_path: false,
+        }
+    }
+}
+
+impl Default for HealthCheckConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            interval: Duration::from_secs(10),
+            timeout: Duration::from_secs(5),
+            max_fails: 1,
+            fail_timeout: Duration::from_secs(10),
+        }
+    }
+}
@@ -156,6 +156,38 @@ pub fn parse_conf(conf: &str, filename: &str) -> Result<ConfigNode, ConfigError>
     parse_section("server", &mut lines, filename)
 }
 
+/// Finds the paths of the files included by a configuration string, including those included by the included files
+///   in turn. Files which cannot be read are still listed, but are not searched for further includes.
+pub fn included_files(conf: &str) -> Vec<String> {
+    let mut files = Vec::new();
+    find_includes(conf, &mut files);
+    files
+}
+
+/// Adds the paths of the files included by the configuration string to the list, searching each new one in turn.
+fn find_includes(conf: &str, files: &mut Vec<String>) {
+    for line in conf.lines().map(clean_up) {
+        let path = match line.split_once(' ') {
+            Some(("include", value)) if wildcard_match("\"*\"", value.trim()) => {
+                let value = value.trim();
+                value[1..value.len() - 1].to_string()
+            }
+            _ => continue,
+        };
+
+        if files.contains(&path) {
+            continue;
+        }
+
+        let included = std::fs::read_to_string(&path);
+        files.push(path);
+
+        if let Ok(included) = included {
+            find_includes(&included, files);
+        }
+    }
+}
+
 /// Recursively parses a section of the configuration.
 fn parse_section(
     name: &str,
@@ -0,0 +1,38 @@
+//! Provides middleware for passing requests and responses to plugins.
+
+use crate::server::server::AppState;
+
+use humphrey::app::Middleware;
+use humphrey::http::headers::HeaderType;
+use humphrey::http::{Request, Response};
+
+use std::sync::Arc;
+
+/// Middleware which passes requests and their responses to the loaded plugins, allowing them to respond to requests
+///   before the route does, including routes whose content is cached.
+///
+/// Requests which do not match a route are not passed to plugins.
+pub struct PluginMiddleware;
+
+impl Middleware<AppState> for PluginMiddleware {
+    fn before(&self, request: &mut Request, state: Arc<AppState>) -> Option<Response> {
+        let config = state.config();
+        let (host, route, params) =
+            config.find_route(request.headers.get(&HeaderType::Host), &request.uri, false)?;
+        request.params = params;
+
+        let plugins = state.plugin_manager.read().unwrap();
+        plugins.on_request(request, state.clone(), config.get_route(host, route))
+    }
+
+    fn after(&self, request: &Request, response: &mut Response, state: Arc<AppState>) {
+        let config = state.config();
+
+        if let Some((host, route, _)) =
+            config.find_route(request.headers.get(&HeaderType::Host), &request.uri, false)
+        {
+            let plugins = state.plugin_manager.read().unwrap();
+            plugins.on_response(response, state.clone(), config.get_route(host, route));
+        }
+    }
+}
@@ -1,4 +1,5 @@
 //! Provides functionality for dynamically-loaded plugins.
 
 pub mod manager;
+pub mod middleware;
 pub mod plugin;
@@ -0,0 +1,110 @@
+//! Provides support for socket activation, where the sockets to listen on are created by a service manager such as
+//!   systemd and passed to the server when it starts.
+//!
+//! The sockets are passed as consecutive file descriptors starting at 3. Their number is given by the `LISTEN_FDS`
+//!   environment variable, and the process they are intended for by `LISTEN_PID`.
+
+use humphrey::listener::PreboundListener;
+
+use std::env;
+use std::io;
+
+#[cfg(unix)]
+use std::net::TcpListener;
+#[cfg(unix)]
+use std::os::unix::io::{FromRawFd, IntoRawFd, RawFd};
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+
+/// The file descriptor of the first socket passed by the service manager.
+pub const LISTEN_FDS_START: i32 = 3;
+
+/// Returns the sockets passed to the server by the service manager, or `None` if it was not socket activated.
+///
+/// The environment variables are removed once they have been read, so they are not inherited by child processes
+///   which they are not intended for.
+pub fn inherited_listeners() -> io::Result<Option<Vec<PreboundListener>>> {
+    let count = match listen_fds(
+        env::var("LISTEN_PID").ok().as_deref(),
+        env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    ) {
+        Some(count) => count,
+        None => return Ok(None),
+    };
+
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+
+    take_listeners(count).map(Some)
+}
+
+/// Parses the values of the `LISTEN_PID` and `LISTEN_FDS` environment variables, returning the number of sockets
+///   passed to the process with the given ID, or `None` if there are none.
+pub fn listen_fds(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> Option<i32> {
+    let listen_pid: u32 = listen_pid?.trim().parse().ok()?;
+    let listen_fds: i32 = listen_fds?.trim().parse().ok()?;
+
+    (listen_pid == pid && listen_fds > 0).then_some(listen_fds)
+}
+
+/// Takes ownership of the given number of sockets passed by the service manager.
+#[cfg(unix)]
+fn take_listeners(count: i32) -> io::Result<Vec<PreboundListener>> {
+    (LISTEN_FDS_START..LISTEN_FDS_START + count)
+        // Safety: the service manager passes the sockets to this process only, and they are only taken once since
+        //   the environment variables are removed beforehand
+        .map(|fd| unsafe { listener_from_fd(fd) })
+        .collect()
+}
+
+/// Socket activation is not supported on this platform.
+#[cfg(not(unix))]
+fn take_listeners(_: i32) -> io::Result<Vec<PreboundListener>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Socket activation is not supported on this platform",
+    ))
+}
+
+/// Takes ownership of the listening socket with the given file descriptor, which can be either a TCP socket or a Unix
+///   domain socket. The descriptor is marked close-on-exec, so it is not leaked to child processes.
+///
+/// ## Safety
+/// The file descriptor must be open and must not be owned by anything else, since it is closed when the returned
+///   listener is dropped.
+#[cfg(unix)]
+pub unsafe fn listener_from_fd(fd: RawFd) -> io::Result<PreboundListener> {
+    set_cloexec(fd)?;
+
+    // Reading the address of a socket of the wrong family fails, which tells the two kinds of socket apart
+    let listener = UnixListener::from_raw_fd(fd);
+
+    if listener.local_addr().is_ok() {
+        return Ok(PreboundListener::Unix(listener));
+    }
+
+    let listener = TcpListener::from_raw_fd(listener.into_raw_fd());
+    listener.local_addr()?;
+
+    Ok(PreboundListener::Tcp(listener))
+}
+
+/// Sets the close-on-exec flag of the file descriptor.
+#[cfg(unix)]
+fn set_cloexec(fd: RawFd) -> io::Result<()> {
+    use std::os::raw::c_int;
+
+    const F_SETFD: c_int = 2;
+    const FD_CLOEXEC: c_int = 1;
+
+    extern "C" {
+        fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
+    }
+
+    match unsafe { fcntl(fd, F_SETFD, FD_CLOEXEC) } {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
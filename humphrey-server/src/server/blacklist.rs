@@ -0,0 +1,36 @@
+//! Provides middleware for enforcing the blacklist.
+
+use crate::server::server::AppState;
+
+use humphrey::app::Middleware;
+use humphrey::http::headers::HeaderType;
+use humphrey::http::{Request, Response, StatusCode};
+
+use std::sync::Arc;
+
+/// Middleware which responds with error 403 to requests from blacklisted addresses.
+pub struct BlacklistMiddleware;
+
+impl Middleware<AppState> for BlacklistMiddleware {
+    fn before(&self, request: &mut Request, state: Arc<AppState>) -> Option<Response> {
+        if state
+            .config()
+            .blacklist
+            .list
+            .contains(&request.address.origin_addr)
+        {
+            state.logger.warn(format!(
+                "{}: Blacklisted IP attempted to request {}",
+                request.address, request.uri
+            ));
+
+            return Some(
+                Response::empty(StatusCode::Forbidden)
+                    .with_header(HeaderType::ContentType, "text/html")
+                    .with_bytes(b"<h1>403 Forbidden</h1>"),
+            );
+        }
+
+        None
+    }
+}
@@ -1,8 +1,17 @@
 //! Provides caching functionality.
 
-use crate::config::Config;
+use crate::config::{Config, RouteType};
+use crate::server::r#static::status_line;
+use crate::server::server::AppState;
 
+use humphrey::app::Middleware;
+use humphrey::http::conditional::Validators;
+use humphrey::http::headers::HeaderType;
 use humphrey::http::mime::MimeType;
+use humphrey::http::range::serve_ranges;
+use humphrey::http::{Request, Response, StatusCode};
+
+use std::sync::Arc;
 use std::{collections::VecDeque, time::SystemTime};
 
 /// Represents the server's cache.
@@ -15,6 +24,10 @@ pub struct Cache {
     data: VecDeque<CachedItem>,
 }
 
+/// Middleware which answers requests to file and directory routes from the cache, if the file was cached when it
+///   was last served.
+pub struct CacheMiddleware;
+
 /// Represents a cached item.
 pub struct CachedItem {
     /// The route that this item was served at.
@@ -27,6 +40,8 @@ pub struct CachedItem {
     pub cache_time: u64,
     /// The item's data.
     pub data: Vec<u8>,
+    /// The validators of the item, used to answer conditional requests.
+    pub validators: Validators,
 }
 
 impl Cache {
@@ -57,7 +72,14 @@ impl Cache {
 
     /// Sets an item in the cache.
     /// Overwrites older versions if needed.
-    pub fn set(&mut self, route: &str, host: usize, value: Vec<u8>, mime_type: MimeType) {
+    pub fn set(
+        &mut self,
+        route: &str,
+        host: usize,
+        value: Vec<u8>,
+        mime_type: MimeType,
+        validators: Validators,
+    ) {
         while self.cache_size + value.len() > self.cache_limit {
             self.cache_size -= self.data[0].data.len();
             self.data.pop_front();
@@ -79,6 +101,7 @@ impl Cache {
             host,
             data: value,
             mime_type,
+            validators,
             cache_time: SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)
                 .unwrap()
@@ -97,3 +120,50 @@ impl From<&Config> for Cache {
         }
     }
 }
+
+impl Middleware<AppState> for CacheMiddleware {
+    fn before(&self, request: &mut Request, state: Arc<AppState>) -> Option<Response> {
+        let config = state.config();
+
+        if config.cache.size_limit == 0 {
+            return None;
+        }
+
+        // Only static content is cached, so requests to other routes with the same path are not answered from it
+        let (host, route, _) =
+            config.find_route(request.headers.get(&HeaderType::Host), &request.uri, false)?;
+
+        if !matches!(
+            config.get_route(host, route).route_type,
+            RouteType::File | RouteType::Directory
+        ) {
+            return None;
+        }
+
+        let cache = state.cache.read().unwrap();
+        let cached = cache.get(&request.uri, host)?;
+
+        if cached.validators.is_not_modified(request) {
+            state.logger.info(format!(
+                "{}: 304 Not Modified (cached) {}",
+                request.address, request.uri
+            ));
+            return Some(cached.validators.not_modified());
+        }
+
+        let mut response = Response::empty(StatusCode::OK)
+            .with_header(HeaderType::ContentType, cached.mime_type.to_string())
+            .with_bytes(cached.data.clone());
+        cached.validators.set_headers(&mut response.headers);
+
+        let response = serve_ranges(request, response);
+        state.logger.info(format!(
+            "{}: {} (cached) {}",
+            request.address,
+            status_line(response.status_code),
+            request.uri
+        ));
+
+        Some(response)
+    }
+}
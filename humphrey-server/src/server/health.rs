@@ -0,0 +1,114 @@
+//! Provides active health checks of the targets of proxy routes.
+//!
+//! Each target of a route with a health check path is periodically sent a `GET` request for the path. Targets which
+//!   do not respond successfully are ejected from the load balancer, and are brought back as soon as they pass a
+//!   health check again.
+
+use crate::config::Config;
+use crate::proxy::{EqMutex, LoadBalancer};
+use crate::server::server::AppState;
+
+use humphrey::http::Response;
+
+use std::error::Error;
+use std::io::Write;
+use std::iter::once;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// The interval at which the load balancers are checked for health checks which are due.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Periodically runs the health checks of every load balancer in the current configuration.
+pub fn health_check_thread(state: Arc<AppState>) {
+    loop {
+        let config = state.config();
+
+        for load_balancer in load_balancers(&config) {
+            check_targets(load_balancer, &state);
+        }
+
+        drop(config);
+        sleep(POLL_INTERVAL);
+    }
+}
+
+/// Returns the load balancers of every proxy route in the configuration.
+fn load_balancers(config: &Config) -> impl Iterator<Item = &EqMutex<LoadBalancer>> {
+    config
+        .hosts
+        .iter()
+        .chain(once(&config.default_host))
+        .flat_map(|host| host.routes.iter())
+        .filter_map(|route| route.load_balancer.as_ref())
+}
+
+/// Runs the health checks of the load balancer's targets if they are due, updating their health accordingly.
+///
+/// The load balancer is not locked while the targets are being checked, so requests are not held up.
+fn check_targets(load_balancer: &EqMutex<LoadBalancer>, state: &AppState) {
+    let (targets, path, timeout) = {
+        let mut load_balancer = load_balancer.lock().unwrap();
+        let now = Instant::now();
+
+        if !load_balancer.health_check_due(now) {
+            return;
+        }
+
+        load_balancer.last_health_check = Some(now);
+
+        (
+            load_balancer.targets.clone(),
+            load_balancer.health_check.path.clone().unwrap(),
+            load_balancer.health_check.timeout,
+        )
+    };
+
+    for (index, target) in targets.iter().enumerate() {
+        let healthy = check_target(target, &path, timeout);
+        let mut load_balancer = load_balancer.lock().unwrap();
+
+        if healthy {
+            if load_balancer.record_success(index) {
+                state
+                    .logger
+                    .info(format!("Proxy target {} passed its health check", target));
+            }
+        } else if load_balancer.eject(index) {
+            state.logger.warn(format!(
+                "Proxy target {} failed its health check and was ejected",
+                target
+            ));
+        }
+    }
+}
+
+/// Sends a health check request for the path to the target, returning `true` if it responds with a successful or
+///   redirection status code before the timeout.
+pub fn check_target(target: &str, path: &str, timeout: Duration) -> bool {
+    let check = || -> Result<bool, Box<dyn Error>> {
+        let addr = target
+            .to_socket_addrs()?
+            .next()
+            .ok_or("No socket address found")?;
+
+        let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            path, target
+        )?;
+
+        let response = Response::from_stream_without_body(&mut stream)?;
+        let status: u16 = response.status_code.into();
+
+        Ok((200..400).contains(&status))
+    };
+
+    check().unwrap_or(false)
+}
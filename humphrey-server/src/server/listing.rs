@@ -0,0 +1,183 @@
+//! Provides functionality for listing the contents of directories without an index file.
+
+use humphrey::http::date::DateTime;
+use humphrey::http::headers::HeaderType;
+use humphrey::http::{Request, Response, StatusCode};
+use humphrey::percent::{PercentDecode, PercentEncode};
+use humphrey_json::prelude::*;
+use humphrey_json::Value;
+
+use std::fs::{metadata, read_dir};
+use std::path::Path;
+
+/// Represents a file or directory within a listed directory.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ListingEntry {
+    /// The name of the file or directory
+    pub name: String,
+    /// Whether the entry is a directory
+    pub is_dir: bool,
+    /// The size of the file in bytes, or `None` for directories
+    pub size: Option<u64>,
+    /// The date and time at which the entry was last modified, if known
+    pub modified: Option<DateTime>,
+}
+
+/// Generates a listing of the directory at the given path, which must already have been located safely.
+///
+/// The listing is sent as JSON if the client's `Accept` header asks for it, and as HTML otherwise.
+///   `is_root` should be set when the directory is the root of its route, so no link to the parent is given.
+///   Returns `None` if the directory could not be read.
+pub fn listing_response(request: &Request, path: &Path, is_root: bool) -> Option<Response> {
+    let entries = read_entries(path)?;
+
+    let accepts_json = request
+        .headers
+        .get(HeaderType::Accept)
+        .is_some_and(|accept| {
+            accept.split(',').any(|media_range| {
+                media_range
+                    .split(';')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .eq_ignore_ascii_case("application/json")
+            })
+        });
+
+    let response = if accepts_json {
+        Response::new(StatusCode::OK, render_json(&entries))
+            .with_header(HeaderType::ContentType, "application/json")
+    } else {
+        let uri = request
+            .uri
+            .percent_decode()
+            .and_then(|uri| String::from_utf8(uri).ok())
+            .unwrap_or_else(|| request.uri.clone());
+
+        Response::new(StatusCode::OK, render_html(&uri, &entries, is_root))
+            .with_header(HeaderType::ContentType, "text/html; charset=utf-8")
+    };
+
+    Some(response.with_header(HeaderType::Vary, "Accept"))
+}
+
+/// Reads the entries of a directory, sorted with directories first and then by name.
+///
+/// Hidden entries, whose names begin with a dot, and entries whose names are not valid UTF-8 are left out.
+pub fn read_entries(path: &Path) -> Option<Vec<ListingEntry>> {
+    let mut entries: Vec<ListingEntry> = read_dir(path)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+
+            if name.starts_with('.') {
+                return None;
+            }
+
+            // Symbolic links are described by their targets, and broken links are left out
+            let meta = metadata(entry.path()).ok()?;
+
+            Some(ListingEntry {
+                name,
+                is_dir: meta.is_dir(),
+                size: (!meta.is_dir()).then_some(meta.len()),
+                modified: meta.modified().ok().map(DateTime::from),
+            })
+        })
+        .collect();
+
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+
+    Some(entries)
+}
+
+/// Renders the listing as an HTML page.
+fn render_html(uri: &str, entries: &[ListingEntry], is_root: bool) -> String {
+    let title = format!("Index of {}", escape_html(uri));
+    let mut rows = String::new();
+
+    if !is_root {
+        rows.push_str("<tr><td><a href=\"../\">../</a></td><td>-</td><td>-</td></tr>\n");
+    }
+
+    for entry in entries {
+        let suffix = if entry.is_dir { "/" } else { "" };
+
+        rows.push_str(&format!(
+            "<tr><td><a href=\"{}{}\">{}{}</a></td><td>{}</td><td>{}</td></tr>\n",
+            entry.name.percent_encode(),
+            suffix,
+            escape_html(&entry.name),
+            suffix,
+            entry.size.map_or_else(|| "-".into(), format_size),
+            entry
+                .modified
+                .map_or_else(|| "-".into(), |modified| modified.to_string()),
+        ));
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n</head>\n<body>\n\
+         <h1>{0}</h1>\n<table>\n<tr><th>Name</th><th>Size</th><th>Last modified</th></tr>\n{1}</table>\n\
+         </body>\n</html>\n",
+        title, rows
+    )
+}
+
+/// Renders the listing as a JSON array of objects.
+fn render_json(entries: &[ListingEntry]) -> String {
+    let items = entries
+        .iter()
+        .map(|entry| {
+            let kind = if entry.is_dir { "directory" } else { "file" };
+
+            json!({
+                "name": entry.name.as_str(),
+                "type": kind,
+                "size": entry.size,
+                "modified": entry.modified.map(|modified| modified.timestamp)
+            })
+        })
+        .collect();
+
+    Value::Array(items).serialize()
+}
+
+/// Formats a size in bytes using binary units, for example `1.5 KiB`.
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Escapes the characters which have special meaning in HTML text and attribute values.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
@@ -2,11 +2,17 @@
 
 #![allow(clippy::module_inception)]
 
+pub mod activation;
+pub mod blacklist;
 pub mod cache;
+pub mod health;
+pub mod listing;
 pub mod logger;
 pub mod proxy;
 pub mod rand;
+pub mod reload;
 pub mod server;
+pub mod signal;
 pub mod r#static;
 
 pub use server::*;
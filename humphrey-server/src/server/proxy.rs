@@ -1,106 +1,553 @@
 //! Provides HTTP proxy functionality.
 
-use crate::config::LoadBalancerMode;
-use crate::rand::{Choose, Lcg};
+use crate::config::{HashKey, HealthCheckConfig, LoadBalancerMode, ProxyConfig};
+use crate::rand::Lcg;
 use crate::server::server::AppState;
 
 use humphrey::http::headers::HeaderType;
-use humphrey::http::proxy::proxy_request;
-use humphrey::http::{Request, Response, StatusCode};
+use humphrey::http::proxy::{bad_gateway, forwarded_request, ConnectionPool, ProxyError};
+use humphrey::http::{Request, Response};
 
 use std::net::ToSocketAddrs;
 use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
-use std::time::Duration;
+use std::time::Instant;
 
 /// Represents a load balancer.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct LoadBalancer {
     /// The targets of the load balancer.
     pub targets: Vec<String>,
+    /// The weight of each target, in the same order as the targets.
+    pub weights: Vec<usize>,
     /// The algorithm used to choose a target.
     pub mode: LoadBalancerMode,
-    /// The current target.
-    pub index: usize,
+    /// The part of the request which is hashed to choose a target, if the mode is `LoadBalancerMode::Hash`.
+    pub hash_key: HashKey,
+    /// The current weight of each target, used to distribute requests in proportion to the weights.
+    pub current_weights: Vec<i64>,
+    /// The number of requests in progress to each target.
+    pub connections: Vec<usize>,
     /// The random number generator used by the load balancer.
     pub lcg: Lcg,
+    /// The health check configuration of the load balancer.
+    pub health_check: HealthCheckConfig,
+    /// The health of each target, in the same order as the targets.
+    pub health: Vec<TargetHealth>,
+    /// The time of the last active health check, if any have taken place.
+    pub last_health_check: Option<Instant>,
+    /// The pool of persistent connections to the targets.
+    pub pool: Arc<ConnectionPool>,
+    /// The configuration of how requests are forwarded to the targets.
+    pub proxy_config: ProxyConfig,
+}
+
+impl PartialEq for LoadBalancer {
+    /// Compares the configuration and state of the load balancers. Their connection pools are compared by their
+    ///   limits, ignoring the connections they currently hold.
+    fn eq(&self, other: &Self) -> bool {
+        self.targets == other.targets
+            && self.weights == other.weights
+            && self.mode == other.mode
+            && self.hash_key == other.hash_key
+            && self.current_weights == other.current_weights
+            && self.connections == other.connections
+            && self.lcg == other.lcg
+            && self.health_check == other.health_check
+            && self.health == other.health
+            && self.last_health_check == other.last_health_check
+            && self.pool.max_idle_connections() == other.pool.max_idle_connections()
+            && self.pool.idle_timeout() == other.pool.idle_timeout()
+            && self.proxy_config == other.proxy_config
+    }
+}
+
+impl Eq for LoadBalancer {}
+
+/// Represents the health of a load balancer target.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TargetHealth {
+    /// The number of consecutive failures of the target.
+    pub fails: usize,
+    /// The time until which the target is ejected, or `None` if it is not ejected.
+    pub ejected_until: Option<Instant>,
 }
 
 impl LoadBalancer {
+    /// Creates a new load balancer with the given targets, all of which are initially healthy and have a weight of
+    ///   one.
+    pub fn new(
+        targets: Vec<String>,
+        mode: LoadBalancerMode,
+        health_check: HealthCheckConfig,
+    ) -> Self {
+        Self {
+            weights: vec![1; targets.len()],
+            current_weights: vec![0; targets.len()],
+            connections: vec![0; targets.len()],
+            health: vec![TargetHealth::default(); targets.len()],
+            targets,
+            mode,
+            hash_key: HashKey::Address,
+            lcg: Lcg::new(),
+            health_check,
+            last_health_check: None,
+            pool: Arc::new(ConnectionPool::new()),
+            proxy_config: ProxyConfig::default(),
+        }
+    }
+
+    /// Sets the weights of the targets, which must be in the same order as the targets.
+    pub fn with_weights(mut self, weights: Vec<usize>) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Sets the part of the request which is hashed to choose a target.
+    pub fn with_hash_key(mut self, hash_key: HashKey) -> Self {
+        self.hash_key = hash_key;
+        self
+    }
+
+    /// Sets the pool of persistent connections to the targets.
+    pub fn with_pool(mut self, pool: ConnectionPool) -> Self {
+        self.pool = Arc::new(pool);
+        self
+    }
+
+    /// Sets the configuration of how requests are forwarded to the targets.
+    pub fn with_proxy_config(mut self, proxy_config: ProxyConfig) -> Self {
+        self.proxy_config = proxy_config;
+        self
+    }
+
     /// Selects a target according to the load balancer mode.
+    ///
+    /// Ejected targets are skipped unless every target is ejected, in which case they are all tried rather than
+    ///   failing every request.
     pub fn select_target(&mut self) -> String {
-        match self.mode {
-            LoadBalancerMode::RoundRobin => {
-                let target_index = self.index;
-                self.index += 1;
-                if self.index == self.targets.len() {
-                    self.index = 0;
+        let index = self.select_target_index(None, &[]).unwrap();
+        self.targets[index].clone()
+    }
+
+    /// Selects the index of a target for the request according to the load balancer mode, excluding the targets which
+    ///   have already been tried for it, or returns `None` if there are no more targets to try.
+    ///
+    /// Ejected targets are skipped, unless no targets have been tried yet and every target is ejected. Without a
+    ///   request to hash, the `LoadBalancerMode::Hash` mode falls back to round-robin.
+    pub fn select_target_index(
+        &mut self,
+        request: Option<&Request>,
+        tried: &[usize],
+    ) -> Option<usize> {
+        let now = Instant::now();
+        let mut candidates: Vec<usize> = (0..self.targets.len())
+            .filter(|index| !tried.contains(index) && !self.is_ejected(*index, now))
+            .collect();
+
+        if candidates.is_empty() && tried.is_empty() {
+            candidates = (0..self.targets.len()).collect();
+        }
+
+        if candidates.is_empty() {
+            return None;
+        }
+
+        Some(match (self.mode, request) {
+            (LoadBalancerMode::RoundRobin, _) => self.select_round_robin(&candidates),
+            (LoadBalancerMode::Random, _) => self.select_random(&candidates),
+            (LoadBalancerMode::LeastConnections, _) => {
+                // Connections are compared relative to the weights by cross-multiplying, avoiding division
+                let load = |a: usize, b: usize| self.connections[a] * self.weights[b];
+                let least = candidates
+                    .iter()
+                    .copied()
+                    .reduce(
+                        |least, index| match load(index, least) < load(least, index) {
+                            true => index,
+                            false => least,
+                        },
+                    )
+                    .unwrap();
+
+                // Ties are broken by round-robin, so idle targets share the load
+                let tied: Vec<usize> = candidates
+                    .iter()
+                    .copied()
+                    .filter(|index| load(*index, least) == load(least, *index))
+                    .collect();
+
+                self.select_round_robin(&tied)
+            }
+            (LoadBalancerMode::Hash, Some(request)) => {
+                let key = self.hash_key(request);
+                self.select_hash(&candidates, &key)
+            }
+            (LoadBalancerMode::Hash, None) => self.select_round_robin(&candidates),
+        })
+    }
+
+    /// Selects one of the candidates using smooth weighted round-robin, which spreads out the requests to each target
+    ///   rather than sending them in bursts.
+    fn select_round_robin(&mut self, candidates: &[usize]) -> usize {
+        let total: i64 = candidates.iter().map(|i| self.weights[*i] as i64).sum();
+
+        for index in candidates {
+            self.current_weights[*index] += self.weights[*index] as i64;
+        }
+
+        let selected = candidates
+            .iter()
+            .copied()
+            .reduce(|selected, index| {
+                match self.current_weights[index] > self.current_weights[selected] {
+                    true => index,
+                    false => selected,
                 }
+            })
+            .unwrap();
+
+        self.current_weights[selected] -= total;
+
+        selected
+    }
 
-                self.targets[target_index].clone()
+    /// Selects one of the candidates at random, in proportion to their weights.
+    fn select_random(&mut self, candidates: &[usize]) -> usize {
+        let total: usize = candidates.iter().map(|i| self.weights[*i]).sum();
+        let mut value = self.lcg.next().unwrap() as usize % total;
+
+        for index in candidates {
+            if value < self.weights[*index] {
+                return *index;
             }
-            LoadBalancerMode::Random => self.targets.choose(&mut self.lcg).unwrap().clone(),
+
+            value -= self.weights[*index];
         }
+
+        unreachable!()
+    }
+
+    /// Selects the candidate with the highest score for the key using weighted rendezvous hashing.
+    ///
+    /// Each key is consistently sent to the same target, and when a target becomes unavailable, only the keys which
+    ///   were sent to it are moved to other targets.
+    fn select_hash(&self, candidates: &[usize], key: &str) -> usize {
+        let score = |index: usize| {
+            let hash = hash(format!("{}\0{}", self.targets[index], key).as_bytes());
+            // Maps the hash to a number in the range (0, 1)
+            let uniform = ((hash >> 11) as f64 + 1.0) / ((1_u64 << 53) as f64 + 2.0);
+
+            self.weights[index] as f64 / -uniform.ln()
+        };
+
+        candidates
+            .iter()
+            .copied()
+            .map(|index| (index, score(index)))
+            .reduce(|best, next| match next.1 > best.1 {
+                true => next,
+                false => best,
+            })
+            .unwrap()
+            .0
+    }
+
+    /// Returns the part of the request which is hashed to choose its target.
+    pub fn hash_key(&self, request: &Request) -> String {
+        let value = match &self.hash_key {
+            HashKey::Address => None,
+            HashKey::Header(name) => request.headers.get(name.as_str()).map(str::to_string),
+            HashKey::Cookie(name) => request.get_cookie(name).map(|cookie| cookie.value),
+        };
+
+        value.unwrap_or_else(|| request.address.origin_addr.to_string())
+    }
+
+    /// Returns `true` if the target is currently ejected.
+    pub fn is_ejected(&self, index: usize, now: Instant) -> bool {
+        self.health[index]
+            .ejected_until
+            .is_some_and(|until| until > now)
+    }
+
+    /// Records that a request to the target succeeded, returning `true` if the target was ejected and is now
+    ///   considered healthy again.
+    pub fn record_success(&mut self, index: usize) -> bool {
+        let health = &mut self.health[index];
+        let was_ejected = health.ejected_until.is_some();
+
+        health.fails = 0;
+        health.ejected_until = None;
+
+        was_ejected
+    }
+
+    /// Records that a request to the target failed, ejecting it if it has failed too many times in a row.
+    /// Returns `true` if the target was ejected as a result.
+    pub fn record_failure(&mut self, index: usize) -> bool {
+        let max_fails = self.health_check.max_fails;
+        let fails = {
+            let health = &mut self.health[index];
+            health.fails += 1;
+            health.fails
+        };
+
+        max_fails > 0 && fails >= max_fails && self.eject(index)
+    }
+
+    /// Ejects the target for the configured amount of time, returning `true` if it was not already ejected.
+    pub fn eject(&mut self, index: usize) -> bool {
+        let now = Instant::now();
+        let was_ejected = self.is_ejected(index, now);
+
+        self.health[index].ejected_until = Some(now + self.health_check.fail_timeout);
+
+        !was_ejected
+    }
+
+    /// Returns `true` if active health checks are enabled and the next one is due.
+    pub fn health_check_due(&self, now: Instant) -> bool {
+        self.health_check.path.is_some()
+            && self
+                .last_health_check
+                .is_none_or(|last| now.duration_since(last) >= self.health_check.interval)
     }
 }
 
-/// Handles proxy requests.
+/// Hashes the bytes using the 64-bit FNV-1a algorithm, which is stable across runs unlike the standard library's
+///   hasher, followed by a finaliser which mixes every byte into the upper bits of the hash.
+fn hash(bytes: &[u8]) -> u64 {
+    let hash = bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    });
+
+    let hash = (hash ^ (hash >> 33)).wrapping_mul(0xff51afd7ed558ccd);
+    let hash = (hash ^ (hash >> 33)).wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^ (hash >> 33)
+}
+
+/// Handles proxy requests to the route matching `matches`.
+///
+/// If a target cannot be reached, the failure is recorded and the request is retried with another target, as long
+///   as it was not sent or it is safe to send again.
 pub fn proxy_handler(
     request: Request,
     state: Arc<AppState>,
     load_balancer: &EqMutex<LoadBalancer>,
     matches: &str,
 ) -> Response {
-    let mut simplified_uri = request.uri.clone();
-
-    for ch in matches.chars() {
-        if ch != '*' {
-            simplified_uri.remove(0);
-        } else {
-            break;
-        }
-    }
+    let mut simplified_uri = request.params.wildcard().unwrap_or_default().to_string();
 
     if !simplified_uri.starts_with('/') {
         simplified_uri.insert(0, '/');
     }
 
-    // Return error 403 if the address was blacklisted
-    if state
-        .config
-        .blacklist
-        .list
-        .contains(&request.address.origin_addr)
-    {
-        state.logger.warn(format!(
-            "{}: Blacklisted IP attempted to request {}",
-            request.address, request.uri
-        ));
-        Response::empty(StatusCode::Forbidden)
-            .with_header(HeaderType::ContentType, "text/html")
-            .with_bytes(b"<h1>403 Forbidden</h1>")
-    } else {
+    let mut proxied_request = forwarded_request(&request);
+    proxied_request.uri = simplified_uri;
+
+    let proxy_config = load_balancer.lock().unwrap().proxy_config.clone();
+
+    let mut tried: Vec<usize> = Vec::new();
+
+    let response = loop {
         // Gets a load balancer target using the thread-safe `Mutex`
         let mut load_balancer_lock = load_balancer.lock().unwrap();
-        let target = load_balancer_lock.select_target();
+        let target_index = match load_balancer_lock.select_target_index(Some(&request), &tried) {
+            Some(target_index) => target_index,
+            None => break bad_gateway(),
+        };
+        let target = load_balancer_lock.targets[target_index].clone();
+        let pool = load_balancer_lock.pool.clone();
+        load_balancer_lock.connections[target_index] += 1;
         drop(load_balancer_lock);
 
-        let mut proxied_request = request.clone();
-        proxied_request.uri = simplified_uri;
+        tried.push(target_index);
+
+        let result = match target
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+        {
+            Some(target_sock) => pool.send_request(
+                &target_request(&proxied_request, &target, &proxy_config),
+                target_sock,
+                proxy_config.timeout,
+            ),
+            None => Err(ProxyError::Connect),
+        };
+
+        let mut load_balancer_lock = load_balancer.lock().unwrap();
+        load_balancer_lock.connections[target_index] -= 1;
+
+        match result {
+            Ok(mut response) => {
+                rewrite_response(&mut response, &request, &target, matches, &proxy_config);
+
+                if load_balancer_lock.record_success(target_index) {
+                    state
+                        .logger
+                        .info(format!("Proxy target {} is healthy again", target));
+                }
+
+                break response;
+            }
+            Err(e) => {
+                state.logger.warn(format!(
+                    "{}: Proxy target {} failed: {}",
+                    request.address, target, e
+                ));
+
+                if load_balancer_lock.record_failure(target_index) {
+                    state.logger.warn(format!(
+                        "Proxy target {} ejected for {} seconds",
+                        target,
+                        load_balancer_lock.health_check.fail_timeout.as_secs()
+                    ));
+                }
+
+                if e != ProxyError::Connect && !request.method.is_idempotent() {
+                    break bad_gateway();
+                }
+            }
+        }
+    };
+
+    let status: u16 = response.status_code.into();
+    let status_string: &str = response.status_code.into();
+
+    state.logger.info(format!(
+        "{}: {} {} {}",
+        request.address, status, status_string, request.uri
+    ));
+
+    response
+}
+
+/// Returns the request to send to the target, with its `Host` header and the configured headers set.
+fn target_request(request: &Request, target: &str, proxy_config: &ProxyConfig) -> Request {
+    let mut request = request.clone();
+
+    if !proxy_config.preserve_host {
+        request.headers.remove(HeaderType::Host);
+        request.headers.add(HeaderType::Host, target);
+    }
+
+    for (name, value) in &proxy_config.set_headers {
+        request.headers.remove(name);
+
+        if !value.is_empty() {
+            request.headers.add(name, value);
+        }
+    }
+
+    request
+}
+
+/// Rewrites the `Location` and `Set-Cookie` headers of the target's response, if configured, so that they refer to
+///   the route rather than to the target.
+fn rewrite_response(
+    response: &mut Response,
+    request: &Request,
+    target: &str,
+    matches: &str,
+    proxy_config: &ProxyConfig,
+) {
+    let prefix = match route_prefix(matches) {
+        Some(prefix) => prefix,
+        None => return,
+    };
+
+    if proxy_config.rewrite_location {
+        let host = request.headers.get(HeaderType::Host).unwrap_or(target);
+        let location = response
+            .headers
+            .get(HeaderType::Location)
+            .map(|location| rewrite_location(location, &[target, host], prefix));
+
+        if let Some(location) = location {
+            response.headers.remove(HeaderType::Location);
+            response.headers.add(HeaderType::Location, location);
+        }
+    }
+
+    if proxy_config.rewrite_cookie_path {
+        let cookies: Vec<String> = response
+            .headers
+            .get_all(HeaderType::SetCookie)
+            .into_iter()
+            .map(|cookie| rewrite_cookie_path(cookie, prefix))
+            .collect();
+
+        response.headers.remove(HeaderType::SetCookie);
 
-        let target_sock = target.to_socket_addrs().unwrap().next().unwrap();
-        let response = proxy_request(&proxied_request, target_sock, Duration::from_secs(5));
-        let status: u16 = response.status_code.into();
-        let status_string: &str = response.status_code.into();
+        for cookie in cookies {
+            response.headers.add(HeaderType::SetCookie, cookie);
+        }
+    }
+}
 
-        state.logger.info(format!(
-            "{}: {} {} {}",
-            request.address, status, status_string, request.uri
-        ));
+/// Returns the path under which a route's targets are exposed, without a trailing slash, such as `/app` for the route
+///   `/app/*`. Returns `None` if the route does not end in a single wildcard, since its paths cannot be mapped.
+pub fn route_prefix(matches: &str) -> Option<&str> {
+    let prefix = matches.strip_suffix('*')?;
 
-        response
+    match prefix.contains('*') {
+        true => None,
+        false => Some(prefix.trim_end_matches('/')),
     }
 }
 
+/// Rewrites a `Location` header from a target so that it redirects through the route with the given prefix.
+///
+/// Absolute URLs are rewritten if they refer to one of the given hosts, and absolute paths are moved under the
+///   prefix. Any other locations are left unchanged.
+pub fn rewrite_location(location: &str, hosts: &[&str], prefix: &str) -> String {
+    let path = ["http://", "https://"]
+        .iter()
+        .filter_map(|scheme| location.strip_prefix(scheme))
+        .find_map(|rest| {
+            let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+
+            hosts
+                .iter()
+                .any(|host| authority.eq_ignore_ascii_case(host))
+                .then_some(path)
+        });
+
+    match path {
+        Some("") => format!("{}/", prefix),
+        Some(path) => format!("{}{}", prefix, path),
+        None if location.starts_with('/') && !location.starts_with("//") => {
+            format!("{}{}", prefix, location)
+        }
+        None => location.to_string(),
+    }
+}
+
+/// Rewrites the `Path` attribute of a `Set-Cookie` header from a target so that it is under the given prefix.
+pub fn rewrite_cookie_path(cookie: &str, prefix: &str) -> String {
+    cookie
+        .split(';')
+        .enumerate()
+        .map(|(index, attribute)| {
+            // The first part is the name and value of the cookie rather than an attribute
+            let path = Some(attribute)
+                .filter(|_| index > 0)
+                .and_then(|attribute| attribute.split_once('='))
+                .filter(|(name, _)| name.trim().eq_ignore_ascii_case("path"))
+                .map(|(_, path)| path.trim())
+                .filter(|path| path.starts_with('/'));
+
+            match path {
+                Some("/") if !prefix.is_empty() => format!(" Path={}", prefix),
+                Some(path) => format!(" Path={}{}", prefix, path),
+                None => attribute.to_string(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(";")
+}
+
 /// A `Mutex` which implements `PartialEq` for testing.
 #[derive(Debug)]
 pub struct EqMutex<T> {
@@ -109,7 +556,7 @@ pub struct EqMutex<T> {
 
 impl<T> EqMutex<T> {
     /// Locks the mutex.
-    pub fn lock(&self) -> Result<MutexGuard<T>, PoisonError<MutexGuard<T>>> {
+    pub fn lock(&self) -> Result<MutexGuard<'_, T>, PoisonError<MutexGuard<'_, T>>> {
         self.mutex.lock()
     }
 
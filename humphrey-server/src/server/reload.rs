@@ -0,0 +1,152 @@
+//! Provides reloading of the configuration while the server is running.
+//!
+//! The configuration is reloaded when the server receives `SIGHUP`, or when the configuration file or one of the files
+//!   it includes changes if `watch_config` is enabled. If the new configuration is invalid, the server keeps using the
+//!   current one.
+//!
+//! Routes, hosts, the blacklist, the cache and load balancers are changed immediately. Settings which are used when
+//!   the server starts, such as the addresses it listens on, only take effect when it is restarted.
+
+use crate::config::tree::included_files;
+use crate::config::{Config, ConfigSource};
+use crate::server::server::AppState;
+use crate::signal::reload_signal;
+
+use std::fs::{metadata, read_to_string};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::thread::spawn;
+use std::time::{Duration, SystemTime};
+
+/// The interval at which the configuration files are checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Starts a thread which reloads the configuration when asked to by a signal or when its files change.
+pub fn watch_config(state: Arc<AppState>) {
+    let signal = reload_signal();
+
+    spawn(move || {
+        let (path, source) = match state.config().source {
+            ConfigSource::Default => (None, ConfigSource::Default),
+            _ => {
+                let (path, source) = Config::locate();
+                (Some(path), source)
+            }
+        };
+
+        let mut files = path.as_deref().map(modified_times).unwrap_or_default();
+
+        loop {
+            match signal.recv_timeout(WATCH_INTERVAL) {
+                Ok(()) => state.logger.info("Received reload signal"),
+                Err(RecvTimeoutError::Timeout) => {
+                    let changed = match &path {
+                        Some(path) if state.config().watch_config => modified_times(path) != files,
+                        _ => false,
+                    };
+
+                    if !changed {
+                        continue;
+                    }
+
+                    state.logger.info("Configuration file changed");
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            match &path {
+                Some(path) => {
+                    reload(&state, path, source);
+                    files = modified_times(path);
+                }
+                None => state
+                    .logger
+                    .warn("Configuration cannot be reloaded since the defaults are being used"),
+            }
+        }
+    });
+}
+
+/// Reloads the configuration from the file at the given path, keeping the current configuration if it is invalid.
+pub fn reload(state: &AppState, path: &str, source: ConfigSource) {
+    match Config::load_file(path, source) {
+        Ok(config) => {
+            for setting in restart_required(&state.config(), &config) {
+                state.logger.warn(format!(
+                    "Changes to {} will only take effect when the server is restarted",
+                    setting
+                ));
+            }
+
+            state.set_config(config);
+            state.logger.info("Configuration reloaded");
+        }
+        Err(e) => state.logger.error(format!(
+            "Could not reload the configuration, so the current one will continue to be used: {}",
+            e
+        )),
+    }
+}
+
+/// Returns the names of the settings which differ between the two configurations but can only be changed by
+///   restarting the server.
+pub fn restart_required(old: &Config, new: &Config) -> Vec<&'static str> {
+    let mut settings = Vec::new();
+
+    if old.address != new.address || old.port != new.port || old.listen != new.listen {
+        settings.push("the listen addresses");
+    }
+
+    if old.threads != new.threads {
+        settings.push("the number of threads");
+    }
+
+    #[cfg(feature = "tls")]
+    if old.tls_config != new.tls_config {
+        settings.push("the TLS configuration");
+    }
+
+    #[cfg(feature = "plugins")]
+    if old.plugins != new.plugins {
+        settings.push("the plugins");
+    }
+
+    if old.logging != new.logging {
+        settings.push("the logging configuration");
+    }
+
+    if old.compression != new.compression {
+        settings.push("the compression configuration");
+    }
+
+    if old.trusted_proxies != new.trusted_proxies
+        || old.trust_unix_sockets != new.trust_unix_sockets
+    {
+        settings.push("the trusted proxies");
+    }
+
+    if old.connection_timeout != new.connection_timeout
+        || old.shutdown_timeout != new.shutdown_timeout
+    {
+        settings.push("the timeouts");
+    }
+
+    settings
+}
+
+/// Returns the configuration file and the files it includes, along with the times they were last modified.
+fn modified_times(path: &str) -> Vec<(String, Option<SystemTime>)> {
+    let mut files = vec![path.to_string()];
+
+    if let Ok(conf) = read_to_string(path) {
+        files.extend(included_files(&conf));
+    }
+
+    files
+        .into_iter()
+        .map(|file| {
+            let modified = metadata(&file).and_then(|m| m.modified()).ok();
+            (file, modified)
+        })
+        .collect()
+}
@@ -1,24 +1,36 @@
 //! Provides the core server functionality and manages the underlying Humphrey app.
 
+use humphrey::app::error_handler;
+use humphrey::http::compression::Compression;
+use humphrey::http::headers::HeaderType;
 use humphrey::http::{Request, Response, StatusCode};
+#[cfg(feature = "tls")]
+use humphrey::listener::{Listener, PreboundListener};
 use humphrey::monitor::event::ToEventMask;
 use humphrey::monitor::MonitorConfig;
 use humphrey::stream::Stream;
-use humphrey::{App, SubApp};
+use humphrey::App;
 
 #[cfg(feature = "plugins")]
 use crate::plugins::manager::PluginManager;
 #[cfg(feature = "plugins")]
+use crate::plugins::middleware::PluginMiddleware;
+#[cfg(feature = "plugins")]
 use crate::plugins::plugin::PluginLoadResult;
 #[cfg(feature = "plugins")]
 use std::process::exit;
 use std::thread::spawn;
 
-use crate::cache::Cache;
-use crate::config::{BlacklistMode, Config, ConfigSource, HostConfig, RouteType};
+use crate::activation::inherited_listeners;
+use crate::blacklist::BlacklistMiddleware;
+use crate::cache::{Cache, CacheMiddleware};
+use crate::config::{BlacklistMode, Config, ConfigSource, RouteConfig, RouteType};
+use crate::health::health_check_thread;
 use crate::logger::{monitor_thread, Logger};
 use crate::proxy::proxy_handler;
 use crate::r#static::{directory_handler, file_handler, redirect_handler};
+use crate::reload::watch_config;
+use crate::signal::shutdown_signal;
 
 use std::error::Error;
 use std::io::{Read, Write};
@@ -29,8 +41,8 @@ use std::sync::{Arc, RwLock};
 /// Represents the application state.
 /// Includes the target directory, cache state, and the logger.
 pub struct AppState {
-    /// The app's configuration.
-    pub config: Config,
+    /// The app's configuration, which is replaced when it is reloaded.
+    config: RwLock<Arc<Config>>,
     /// The app's cache.
     pub cache: RwLock<Cache>,
     /// The app's logger.
@@ -45,7 +57,7 @@ impl From<Config> for AppState {
         let cache = RwLock::new(Cache::from(&config));
         let logger = Logger::from(&config);
         Self {
-            config,
+            config: RwLock::new(Arc::new(config)),
             cache,
             logger,
             #[cfg(feature = "plugins")]
@@ -54,9 +66,32 @@ impl From<Config> for AppState {
     }
 }
 
+impl AppState {
+    /// Returns the current configuration.
+    ///
+    /// Each request should use a single snapshot of the configuration throughout, so that it is handled consistently
+    ///   even if the configuration is reloaded in the meantime.
+    pub fn config(&self) -> Arc<Config> {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Replaces the configuration, for example when it is reloaded.
+    ///
+    /// The cache is cleared, since its entries refer to hosts by their position in the configuration and its limits
+    ///   may have changed.
+    pub fn set_config(&self, config: Config) {
+        let mut cache = self.cache.write().unwrap();
+        *cache = Cache::from(&config);
+        *self.config.write().unwrap() = Arc::new(config);
+    }
+}
+
 /// Main function for the static server.
 pub fn main(config: Config) {
     let connection_timeout = config.connection_timeout;
+    let shutdown_timeout = config.shutdown_timeout;
+    let trusted_proxies = config.trusted_proxies.clone();
+    let trust_unix_sockets = config.trust_unix_sockets;
     let source = config.source;
 
     let (monitor_tx, monitor_rx) = channel();
@@ -64,41 +99,67 @@ pub fn main(config: Config) {
 
     let mut app: App<AppState> = App::new_with_config(config.threads, AppState::from(config))
         .with_connection_condition(verify_connection)
+        .with_middleware(BlacklistMiddleware)
         .with_connection_timeout(connection_timeout)
+        .with_shutdown(shutdown_signal())
+        .with_shutdown_timeout(shutdown_timeout)
+        .with_trusted_proxies(trusted_proxies)
+        .with_trust_unix_sockets(trust_unix_sockets)
         .with_monitor(MonitorConfig::new(monitor_tx).with_subscription_to(mask));
 
+    // Plugins see every routed request, including those answered from the cache
+    #[cfg(feature = "plugins")]
+    {
+        app = app.with_middleware(PluginMiddleware);
+    }
+
+    app = app.with_middleware(CacheMiddleware);
+
     let state = app.get_state();
     let monitor_state = app.get_state();
     spawn(move || monitor_thread(monitor_rx, monitor_state));
+    watch_config(app.get_state());
 
-    let top_level_routes = init_app_routes(&state.config.default_host, 0);
+    let health_check_state = app.get_state();
+    spawn(move || health_check_thread(health_check_state));
 
-    app = app.with_default_subapp(top_level_routes);
+    let config = state.config();
 
-    for (host_index, host) in state.config.hosts.iter().enumerate() {
-        app = app.with_host(&host.matches, init_app_routes(host, host_index + 1));
+    if config.compression.enabled {
+        let compression = &config.compression;
+
+        app = app.with_compression(
+            Compression::new()
+                .with_encodings(&compression.encodings)
+                .with_content_types(&compression.types)
+                .with_min_size(compression.min_size),
+        );
     }
 
+    // Requests are routed using the current configuration rather than the app's routes, so the routes can be
+    //   changed when the configuration is reloaded
+    app = app
+        .with_route("*", request_handler)
+        .with_websocket_route("*", websocket_handler);
+
     #[cfg(feature = "tls")]
-    if let Some(tls_config) = &state.config.tls_config {
+    if let Some(tls_config) = &config.tls_config {
         app = app
             .with_cert(&tls_config.cert_file, &tls_config.key_file)
             .with_forced_https(tls_config.force);
 
-        if state.config.port != 443 {
-            state.logger.warn(format!(
-                "HTTPS is typically served on port 443, so your setting of {} may cause issues.",
-                state.config.port,
-            ));
+        for listener in &config.listen {
+            if let Listener::Tls(addr) = listener {
+                if !addr.ends_with(":443") {
+                    state.logger.warn(format!(
+                        "HTTPS is typically served on port 443, so your listener at {} may cause issues.",
+                        addr,
+                    ));
+                }
+            }
         }
     }
 
-    #[cfg(feature = "plugins")]
-    {
-        app = app.with_websocket_route("/*", catch_all_websocket_route);
-    }
-
-    let addr = format!("{}:{}", state.config.address, state.config.port);
     let logger = &state.logger;
 
     match source {
@@ -114,54 +175,83 @@ pub fn main(config: Config) {
         }
     }
 
-    logger.debug(format!("Configuration: {:?}", state.config));
+    logger.debug(format!("Configuration: {:?}", config));
 
     logger.info("Starting server");
 
     #[cfg(feature = "plugins")]
-    if let Ok(plugins_count) = load_plugins(&state.config, state.clone()) {
+    if let Ok(plugins_count) = load_plugins(&config, state.clone()) {
         logger.info(format!("Loaded {} plugins", plugins_count))
     } else {
         exit(1);
     };
 
-    logger.info(format!("Running at {}", addr));
-
-    #[cfg(feature = "tls")]
-    if state.config.tls_config.is_some() {
-        app.run_tls(addr).unwrap();
-    } else {
-        app.run(addr).unwrap();
-    }
+    let inherited = match inherited_listeners() {
+        Ok(inherited) => inherited,
+        Err(e) => {
+            logger.error(format!(
+                "Could not use the sockets passed by socket activation: {}",
+                e
+            ));
+            return;
+        }
+    };
 
-    #[cfg(not(feature = "tls"))]
-    app.run(addr).unwrap();
-}
+    let summary = match inherited {
+        Some(listeners) => {
+            logger.info(
+                "Using the sockets passed by socket activation instead of the configured listeners",
+            );
+
+            // Inherited TCP sockets are secured with TLS in the same way as the default listener
+            #[cfg(feature = "tls")]
+            let listeners: Vec<PreboundListener> = match config.tls_config {
+                Some(_) => listeners
+                    .into_iter()
+                    .map(|listener| match listener {
+                        PreboundListener::Tcp(listener) => PreboundListener::Tls(listener),
+                        listener => listener,
+                    })
+                    .collect(),
+                None => listeners,
+            };
 
-fn init_app_routes(host: &HostConfig, host_index: usize) -> SubApp<AppState> {
-    let mut subapp: SubApp<AppState> = SubApp::new();
+            for listener in &listeners {
+                logger.info(format!("Running at {}", listener));
+            }
 
-    for (route_index, route) in host.routes.iter().enumerate() {
-        subapp = subapp.with_route(&route.matches, move |request, state| {
-            request_handler(request, state, host_index, route_index)
-        });
+            app.run_with_listeners(listeners).unwrap()
+        }
+        None => {
+            for listener in &config.listen {
+                logger.info(format!("Running at {}", listener));
+            }
 
-        if route.websocket_proxy.is_some() {
-            subapp = subapp.with_websocket_route(&route.matches, move |request, stream, state| {
-                websocket_handler(request, stream, state, host_index, route_index)
-            });
+            app.run_listeners(&config.listen).unwrap()
         }
-    }
+    };
 
-    subapp
+    logger.info(format!(
+        "Shut down in {:.2?}, closing {} idle connections, draining {} and forcibly closing {}",
+        summary.duration,
+        summary.idle_connections,
+        summary.drained_connections,
+        summary.forced_connections
+    ));
 }
 
 /// Verifies that the client is allowed to connect by checking with the blacklist config.
-fn verify_connection(stream: &mut TcpStream, state: Arc<AppState>) -> bool {
+fn verify_connection(stream: Option<&mut TcpStream>, state: Arc<AppState>) -> bool {
+    // Peers connected over a Unix domain socket have no address to blacklist
+    let stream = match stream {
+        Some(stream) => stream,
+        None => return true,
+    };
+
     if let Ok(address) = stream.peer_addr() {
-        if state.config.blacklist.mode == BlacklistMode::Block
-            && state.config.blacklist.list.contains(&address.ip())
-        {
+        let blacklist = &state.config().blacklist;
+
+        if blacklist.mode == BlacklistMode::Block && blacklist.list.contains(&address.ip()) {
             state.logger.warn(format!(
                 "{}: Blacklisted IP attempted to connect",
                 &address.ip()
@@ -176,46 +266,32 @@ fn verify_connection(stream: &mut TcpStream, state: Arc<AppState>) -> bool {
     true
 }
 
-#[cfg(feature = "plugins")]
-fn request_handler(
-    mut request: Request,
-    state: Arc<AppState>,
-    host: usize,
-    route: usize,
-) -> Response {
-    let plugins = state.plugin_manager.read().unwrap();
-
-    let route_config = state.config.get_route(host, route);
-    let mut response = plugins
-        .on_request(&mut request, state.clone(), route_config) // If the plugin overrides the response, return it
-        .unwrap_or_else(|| inner_request_handler(request, state.clone(), host, route)); // If no plugin overrides the response, generate it in the normal way
+/// Routes the request using the current configuration, passing it to the handler of the matching route.
+fn request_handler(mut request: Request, state: Arc<AppState>) -> Response {
+    let config = state.config();
 
-    // Pass the response to plugins before it is sent to the client
-    plugins.on_response(&mut response, state.clone(), route_config);
-
-    response
-}
-
-#[cfg(not(feature = "plugins"))]
-fn request_handler(request: Request, state: Arc<AppState>, host: usize, route: usize) -> Response {
-    inner_request_handler(request, state, host, route)
+    match config.find_route(request.headers.get(&HeaderType::Host), &request.uri, false) {
+        Some((host, route, params)) => {
+            request.params = params;
+            route_request_handler(request, state, config.get_route(host, route), host)
+        }
+        None => error_handler(StatusCode::NotFound),
+    }
 }
 
-fn inner_request_handler(
+fn route_request_handler(
     request: Request,
     state: Arc<AppState>,
+    route: &RouteConfig,
     host: usize,
-    route: usize,
 ) -> Response {
-    let route = state.config.get_route(host, route);
-
     match route.route_type {
         RouteType::File => file_handler(request, state.clone(), route.path.as_ref().unwrap(), host),
         RouteType::Directory => directory_handler(
             request,
             state.clone(),
             route.path.as_ref().unwrap(),
-            &route.matches,
+            route.listing,
             host,
         ),
         RouteType::Proxy => proxy_handler(
@@ -234,33 +310,43 @@ fn inner_request_handler(
     }
 }
 
+/// Routes the WebSocket request using the current configuration, passing it to the matching route which proxies
+///   WebSocket connections.
+fn websocket_handler(mut request: Request, stream: Stream, state: Arc<AppState>) {
+    let config = state.config();
+
+    match config.find_route(request.headers.get(&HeaderType::Host), &request.uri, true) {
+        Some((host, route, params)) => {
+            request.params = params;
+            route_websocket_handler(request, stream, state, config.get_route(host, route))
+        }
+        None => catch_all_websocket_handler(request, stream, state),
+    }
+}
+
 #[cfg(not(feature = "plugins"))]
-fn websocket_handler(
+fn route_websocket_handler(
     request: Request,
     stream: Stream,
     state: Arc<AppState>,
-    host: usize,
-    route: usize,
+    route: &RouteConfig,
 ) {
-    inner_websocket_handler(request, stream, state, host, route)
+    inner_websocket_handler(request, stream, state, route)
 }
 
 #[cfg(feature = "plugins")]
-fn websocket_handler(
+fn route_websocket_handler(
     mut request: Request,
     stream: Stream,
     state: Arc<AppState>,
-    host: usize,
-    route: usize,
+    route: &RouteConfig,
 ) {
     let plugins = state.plugin_manager.read().unwrap();
 
-    let route_config = state.config.get_route(host, route);
-
     if let Some(stream) =
-        plugins.on_websocket_request(&mut request, stream, state.clone(), Some(route_config))
+        plugins.on_websocket_request(&mut request, stream, state.clone(), Some(route))
     {
-        inner_websocket_handler(request, stream, state.clone(), host, route)
+        inner_websocket_handler(request, stream, state.clone(), route)
     }
 }
 
@@ -268,23 +354,23 @@ fn inner_websocket_handler(
     request: Request,
     stream: Stream,
     state: Arc<AppState>,
-    host: usize,
-    route: usize,
+    route: &RouteConfig,
 ) {
-    let route = state.config.get_route(host, route);
-
     if let Some(target) = route.websocket_proxy.as_ref() {
-        proxy_websocket(request, stream, &target.clone(), state).ok();
+        proxy_websocket(request, stream, target, state).ok();
     }
 }
 
 #[cfg(feature = "plugins")]
-fn catch_all_websocket_route(mut request: Request, stream: Stream, state: Arc<AppState>) {
+fn catch_all_websocket_handler(mut request: Request, stream: Stream, state: Arc<AppState>) {
     let plugins = state.plugin_manager.read().unwrap();
 
     plugins.on_websocket_request(&mut request, stream, state.clone(), None);
 }
 
+#[cfg(not(feature = "plugins"))]
+fn catch_all_websocket_handler(_: Request, _: Stream, _: Arc<AppState>) {}
+
 fn proxy_websocket(
     request: Request,
     mut source: Stream,
@@ -0,0 +1,138 @@
+//! Provides handling of the signals which ask the server to shut down or to reload its configuration.
+//!
+//! On Unix, these are `SIGINT` and `SIGTERM`, and on Windows, the console control events such as Ctrl+C. The first
+//!   signal starts a graceful shutdown, and a second one exits immediately.
+//!
+//! On Unix, `SIGHUP` asks the server to reload its configuration.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread::{sleep, spawn};
+use std::time::Duration;
+
+/// Whether a shutdown signal has been received.
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Whether a reload signal has been received since it was last handled.
+static RELOAD_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// The exit code used when a second signal is received during the shutdown.
+const FORCED_EXIT_CODE: i32 = 130;
+
+/// The interval at which the signal is checked for.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Registers the signal handlers, returning a receiver which is sent a message when a shutdown signal is received.
+///
+/// Signal handlers can only safely set a flag, so a thread checks it and sends the message.
+pub fn shutdown_signal() -> Receiver<()> {
+    let (tx, rx) = channel();
+
+    sys::register();
+
+    spawn(move || {
+        while !SHUTDOWN_REQUESTED.load(Ordering::SeqCst) {
+            sleep(POLL_INTERVAL);
+        }
+
+        let _ = tx.send(());
+    });
+
+    rx
+}
+
+/// Registers the reload signal handler, returning a receiver which is sent a message each time a reload signal is
+///   received. Signals received while the previous one is still being handled are combined into one message.
+pub fn reload_signal() -> Receiver<()> {
+    let (tx, rx) = channel();
+
+    sys::register_reload();
+
+    spawn(move || loop {
+        if RELOAD_REQUESTED.swap(false, Ordering::SeqCst) && tx.send(()).is_err() {
+            break;
+        }
+
+        sleep(POLL_INTERVAL);
+    });
+
+    rx
+}
+
+/// Records a shutdown signal, returning `true` if one had already been received.
+fn request_shutdown() -> bool {
+    SHUTDOWN_REQUESTED.swap(true, Ordering::SeqCst)
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::os::raw::c_int;
+
+    const SIGHUP: c_int = 1;
+    const SIGINT: c_int = 2;
+    const SIGTERM: c_int = 15;
+
+    extern "C" {
+        fn signal(signum: c_int, handler: usize) -> usize;
+        fn _exit(status: c_int) -> !;
+    }
+
+    extern "C" fn handle(_: c_int) {
+        if super::request_shutdown() {
+            unsafe { _exit(super::FORCED_EXIT_CODE) }
+        }
+    }
+
+    extern "C" fn handle_reload(_: c_int) {
+        super::RELOAD_REQUESTED.store(true, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    pub fn register() {
+        let handler: extern "C" fn(c_int) = handle;
+
+        unsafe {
+            signal(SIGINT, handler as usize);
+            signal(SIGTERM, handler as usize);
+        }
+    }
+
+    pub fn register_reload() {
+        let handler: extern "C" fn(c_int) = handle_reload;
+
+        unsafe {
+            signal(SIGHUP, handler as usize);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    type Handler = unsafe extern "system" fn(u32) -> i32;
+
+    extern "system" {
+        fn SetConsoleCtrlHandler(handler: Option<Handler>, add: i32) -> i32;
+    }
+
+    unsafe extern "system" fn handle(_: u32) -> i32 {
+        if super::request_shutdown() {
+            std::process::exit(super::FORCED_EXIT_CODE);
+        }
+
+        1
+    }
+
+    pub fn register() {
+        unsafe {
+            SetConsoleCtrlHandler(Some(handle), 1);
+        }
+    }
+
+    pub fn register_reload() {}
+}
+
+#[cfg(not(any(unix, windows)))]
+mod sys {
+    pub fn register() {}
+
+    pub fn register_reload() {}
+}
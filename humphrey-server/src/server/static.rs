@@ -1,11 +1,14 @@
 //! Provides functionality for serving static content.
 
+use crate::server::listing::listing_response;
 use crate::server::server::AppState;
 
+use humphrey::http::conditional::Validators;
 use humphrey::http::headers::HeaderType;
 use humphrey::http::mime::MimeType;
+use humphrey::http::range::{serve_file_ranges, serve_ranges};
 use humphrey::http::{Request, Response, StatusCode};
-use humphrey::route::{try_find_path, LocatedPath};
+use humphrey::route::{try_find_directory, try_find_path, LocatedPath};
 
 use std::fs::File;
 use std::io::Read;
@@ -16,45 +19,22 @@ const INDEX_FILES: [&str; 2] = ["index.html", "index.htm"];
 
 /// Request handler for files.
 pub fn file_handler(request: Request, state: Arc<AppState>, file: &str, host: usize) -> Response {
-    if let Some(response) = blacklist_check(&request, state.clone()) {
-        return response;
-    }
-
-    if let Some(response) = cache_check(&request, state.clone(), host) {
-        return response;
-    }
-
     inner_file_handler(request, state, file.into(), host)
 }
 
 /// Request handler for directories.
 /// Attempts to open a given file relative to the binary and returns error 404 if not found.
+/// If `listing` is enabled, directories without an index file are answered with a listing of their contents.
 pub fn directory_handler(
     request: Request,
     state: Arc<AppState>,
     directory: &str,
-    matches: &str,
+    listing: bool,
     host: usize,
 ) -> Response {
-    if let Some(response) = blacklist_check(&request, state.clone()) {
-        return response;
-    }
-
-    if let Some(response) = cache_check(&request, state.clone(), host) {
-        return response;
-    }
+    let simplified_uri = request.params.wildcard().unwrap_or_default();
 
-    let mut simplified_uri = request.uri.clone();
-
-    for ch in matches.chars() {
-        if ch != '*' {
-            simplified_uri.remove(0);
-        } else {
-            break;
-        }
-    }
-
-    if let Some(located) = try_find_path(directory, &simplified_uri, &INDEX_FILES) {
+    if let Some(located) = try_find_path(directory, simplified_uri, &INDEX_FILES) {
         match located {
             LocatedPath::Directory => {
                 state.logger.info(format!(
@@ -66,6 +46,18 @@ pub fn directory_handler(
             }
             LocatedPath::File(path) => inner_file_handler(request, state, path, host),
         }
+    } else if let Some(response) = listing
+        .then(|| try_find_directory(directory, simplified_uri))
+        .flatten()
+        .and_then(|path| {
+            listing_response(&request, &path, simplified_uri.trim_matches('/').is_empty())
+        })
+    {
+        state.logger.info(format!(
+            "{}: 200 OK (listing) {}",
+            request.address, request.uri
+        ));
+        response
     } else {
         state.logger.warn(format!(
             "{}: 404 Not Found {}",
@@ -77,10 +69,6 @@ pub fn directory_handler(
 
 /// Request handler for redirects.
 pub fn redirect_handler(request: Request, state: Arc<AppState>, target: &str) -> Response {
-    if let Some(response) = blacklist_check(&request, state.clone()) {
-        return response;
-    }
-
     state.logger.info(format!(
         "{}: 301 Moved Permanently {}",
         request.address, request.uri
@@ -99,67 +87,79 @@ fn inner_file_handler(
     let mime_type = MimeType::from_extension(file_extension);
     let mut contents: Vec<u8> = Vec::new();
 
-    let mut file = File::open(path).unwrap();
+    let mut file = File::open(&path).unwrap();
+    let validators = file
+        .metadata()
+        .map(|metadata| Validators::from_metadata(&metadata))
+        .unwrap_or_default();
+
+    if validators.is_not_modified(&request) {
+        state.logger.info(format!(
+            "{}: 304 Not Modified {}",
+            request.address, request.uri
+        ));
+        return validators.not_modified();
+    }
+
+    // Range requests are streamed from the file without caching it, so large files such as videos are not read
+    //   into memory just to send part of them
+    if request.headers.get(HeaderType::Range).is_some() {
+        let mut response = Response::empty(StatusCode::OK)
+            .with_header(HeaderType::ContentType, mime_type.to_string());
+        validators.set_headers(&mut response.headers);
+
+        let response = serve_file_ranges(&request, response, file, &path)
+            .unwrap_or_else(|_| Response::empty(StatusCode::InternalError));
+        state.logger.info(format!(
+            "{}: {} {}",
+            request.address,
+            status_line(response.status_code),
+            request.uri
+        ));
+
+        return response;
+    }
+
     file.read_to_end(&mut contents).unwrap();
 
-    if state.config.cache.size_limit >= contents.len() {
+    if state.config().cache.size_limit >= contents.len() {
         let mut cache = state.cache.write().unwrap();
-        cache.set(&request.uri, host, contents.clone(), mime_type);
+        cache.set(
+            &request.uri,
+            host,
+            contents.clone(),
+            mime_type,
+            validators.clone(),
+        );
         state.logger.debug(format!("Cached route {}", request.uri));
-    } else if state.config.cache.size_limit > 0 {
+    } else if state.config().cache.size_limit > 0 {
         state
             .logger
             .warn(format!("Couldn't cache, cache too small {}", request.uri));
     }
 
-    state
-        .logger
-        .info(format!("{}: 200 OK {}", request.address, request.uri));
-    Response::empty(StatusCode::OK)
+    let mut response = Response::empty(StatusCode::OK)
         .with_header(HeaderType::ContentType, mime_type.to_string())
-        .with_bytes(contents)
-}
+        .with_bytes(contents);
+    validators.set_headers(&mut response.headers);
 
-fn blacklist_check(request: &Request, state: Arc<AppState>) -> Option<Response> {
-    // Return error 403 if the address was blacklisted
-    if state
-        .config
-        .blacklist
-        .list
-        .contains(&request.address.origin_addr)
-    {
-        state.logger.warn(format!(
-            "{}: Blacklisted IP attempted to request {}",
-            request.address, request.uri
-        ));
-        return Some(
-            Response::empty(StatusCode::Forbidden)
-                .with_header(HeaderType::ContentType, "text/html")
-                .with_bytes(b"<h1>403 Forbidden</h1>"),
-        );
-    }
+    let response = serve_ranges(&request, response);
+    state.logger.info(format!(
+        "{}: {} {}",
+        request.address,
+        status_line(response.status_code),
+        request.uri
+    ));
 
-    None
+    response
 }
 
-fn cache_check(request: &Request, state: Arc<AppState>, host: usize) -> Option<Response> {
-    if state.config.cache.size_limit > 0 {
-        let cache = state.cache.read().unwrap();
-        if let Some(cached) = cache.get(&request.uri, host) {
-            state.logger.info(format!(
-                "{}: 200 OK (cached) {}",
-                request.address, request.uri
-            ));
-            return Some(
-                Response::empty(StatusCode::OK)
-                    .with_header(HeaderType::ContentType, cached.mime_type.to_string())
-                    .with_bytes(cached.data.clone()),
-            );
-        }
-        drop(cache);
-    }
+/// Formats the status code and its name for logging, for example `206 Partial Content`.
+pub(crate) fn status_line(status_code: StatusCode) -> String {
+    let code: u16 = status_code.into();
+    let name: &str = status_code.into();
 
-    None
+    format!("{} {}", code, name)
 }
 
 /// Generates a 404 response.
@@ -0,0 +1,52 @@
+#![allow(unused_imports)]
+use humphrey::listener::PreboundListener;
+use humphrey_server::server::activation::listen_fds;
+
+use std::net::TcpListener;
+
+#[cfg(unix)]
+use humphrey_server::server::activation::listener_from_fd;
+#[cfg(unix)]
+use std::os::unix::io::IntoRawFd;
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+
+#[test]
+fn test_listen_fds() {
+    assert_eq!(listen_fds(Some("1234"), Some("2"), 1234), Some(2));
+    assert_eq!(listen_fds(Some("1234"), Some("2"), 4321), None);
+    assert_eq!(listen_fds(Some("1234"), Some("0"), 1234), None);
+    assert_eq!(listen_fds(None, Some("2"), 1234), None);
+    assert_eq!(listen_fds(Some("1234"), None, 1234), None);
+    assert_eq!(listen_fds(Some("1234"), Some("two"), 1234), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_listener_from_fd() {
+    let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    match unsafe { listener_from_fd(tcp.into_raw_fd()) }.unwrap() {
+        PreboundListener::Tcp(listener) => assert_eq!(listener.local_addr().unwrap(), addr),
+        listener => panic!("expected a TCP listener, got {:?}", listener),
+    }
+
+    let path = std::env::temp_dir().join("humphrey-server-test-activation.sock");
+    let _ = std::fs::remove_file(&path);
+    let unix = UnixListener::bind(&path).unwrap();
+
+    match unsafe { listener_from_fd(unix.into_raw_fd()) }.unwrap() {
+        PreboundListener::Unix(listener) => assert_eq!(
+            listener.local_addr().unwrap().as_pathname(),
+            Some(path.as_path())
+        ),
+        listener => panic!("expected a Unix listener, got {:?}", listener),
+    }
+
+    let _ = std::fs::remove_file(&path);
+
+    // Descriptors which are not sockets are rejected
+    let file = std::fs::File::open(std::env::current_exe().unwrap()).unwrap();
+    assert!(unsafe { listener_from_fd(file.into_raw_fd()) }.is_err());
+}
@@ -0,0 +1,90 @@
+use humphrey::app::Middleware;
+use humphrey::http::conditional::Validators;
+use humphrey::http::headers::HeaderType;
+use humphrey::http::method::Method;
+use humphrey::http::mime::MimeType;
+use humphrey::http::{Request, StatusCode};
+use humphrey_server::cache::CacheMiddleware;
+use humphrey_server::config::tree::parse_conf;
+use humphrey_server::config::Config;
+use humphrey_server::AppState;
+
+use std::sync::Arc;
+
+/// Creates the app state for a configuration with a directory route and a redirect route, caching the same content
+///   for a path on each.
+fn state() -> Arc<AppState> {
+    let conf = "server {
+        log {
+            console false
+        }
+
+        cache {
+            size 1M
+            time 60
+        }
+
+        route /static/* {
+            directory \"/var/www\"
+        }
+
+        route /* {
+            redirect \"/static/\"
+        }
+    }";
+
+    let tree = parse_conf(conf, "cache.conf").unwrap();
+    let state = Arc::new(AppState::from(Config::from_tree(tree).unwrap()));
+
+    let mut cache = state.cache.write().unwrap();
+    for route in ["/static/index.html", "/about"] {
+        cache.set(
+            route,
+            0,
+            b"cached".to_vec(),
+            MimeType::TextHtml,
+            Validators::new().with_etag("abc"),
+        );
+    }
+    drop(cache);
+
+    state
+}
+
+#[test]
+fn test_cache_middleware() {
+    let state = state();
+
+    let response = CacheMiddleware
+        .before(
+            &mut Request::new(Method::Get, "/static/index.html"),
+            state.clone(),
+        )
+        .unwrap();
+    assert_eq!(response.status_code, StatusCode::OK);
+    assert_eq!(response.body, b"cached");
+    assert_eq!(
+        response.headers.get(HeaderType::ContentType),
+        Some("text/html")
+    );
+
+    let response = CacheMiddleware
+        .before(
+            &mut Request::new(Method::Get, "/static/index.html")
+                .with_header(HeaderType::IfNoneMatch, "\"abc\""),
+            state.clone(),
+        )
+        .unwrap();
+    assert_eq!(response.status_code, StatusCode::NotModified);
+
+    // Requests for uncached files, and to routes which do not serve static content, are passed on to the handler
+    assert!(CacheMiddleware
+        .before(
+            &mut Request::new(Method::Get, "/static/missing.html"),
+            state.clone()
+        )
+        .is_none());
+    assert!(CacheMiddleware
+        .before(&mut Request::new(Method::Get, "/about"), state)
+        .is_none());
+}
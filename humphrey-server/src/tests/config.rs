@@ -1,18 +1,23 @@
 #![allow(unused_imports)]
 use super::tree::CONF;
+use humphrey::http::compression::Encoding;
+use humphrey::http::proxy::ConnectionPool;
+use humphrey::listener::Listener;
 use humphrey_server::config::config::{
-    BlacklistConfig, BlacklistMode, CacheConfig, Config, ConfigSource, HostConfig,
-    LoadBalancerMode, LoggingConfig, RouteConfig, RouteType,
+    BlacklistConfig, BlacklistMode, CacheConfig, CompressionConfig, Config, ConfigSource, HashKey,
+    HealthCheckConfig, HostConfig, LoadBalancerMode, LoggingConfig, ProxyConfig, RouteConfig,
+    RouteType,
 };
 use humphrey_server::config::tree::{parse_conf, ConfigNode};
 use humphrey_server::logger::LogLevel;
 
 #[cfg(feature = "plugins")]
 use humphrey_server::config::config::PluginConfig;
-use humphrey_server::proxy::{EqMutex, LoadBalancer};
+use humphrey_server::proxy::{EqMutex, LoadBalancer, TargetHealth};
 use humphrey_server::rand::Lcg;
 
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 
 #[test]
@@ -34,6 +39,7 @@ fn test_parse_config() {
         address: "0.0.0.0".into(),
         port: 80,
         threads: 32,
+        listen: vec![Listener::Tcp("0.0.0.0:80".into())],
         #[cfg(feature = "tls")]
         tls_config: None,
         default_websocket_proxy: Some("localhost:1234".into()),
@@ -46,6 +52,7 @@ fn test_parse_config() {
                     path: Some("/var/www".into()),
                     load_balancer: None,
                     websocket_proxy: None,
+                    listing: true,
                 },
                 RouteConfig {
                     route_type: RouteType::Proxy,
@@ -53,11 +60,29 @@ fn test_parse_config() {
                     path: None,
                     load_balancer: Some(EqMutex::new(LoadBalancer {
                         targets: vec!["127.0.0.1:8000".into(), "127.0.0.1:8080".into()],
+                        weights: vec![2, 1],
                         mode: LoadBalancerMode::RoundRobin,
-                        index: 0,
+                        hash_key: HashKey::Address,
+                        current_weights: vec![0; 2],
+                        connections: vec![0; 2],
                         lcg: Lcg::new(),
+                        health_check: HealthCheckConfig {
+                            path: Some("/health".into()),
+                            interval: Duration::from_secs(5),
+                            timeout: Duration::from_secs(5),
+                            max_fails: 3,
+                            fail_timeout: Duration::from_secs(10),
+                        },
+                        health: vec![TargetHealth::default(); 2],
+                        last_health_check: None,
+                        pool: Arc::new(ConnectionPool::new().with_max_idle_connections(16)),
+                        proxy_config: ProxyConfig {
+                            timeout: Duration::from_secs(10),
+                            ..Default::default()
+                        },
                     })),
                     websocket_proxy: None,
+                    listing: false,
                 },
             ],
         },
@@ -81,7 +106,17 @@ fn test_parse_config() {
             list: Vec::new(),
             mode: BlacklistMode::Block,
         },
+        trusted_proxies: vec!["10.0.0.0/8".parse().unwrap(), "::1".parse().unwrap()],
+        trust_unix_sockets: true,
+        compression: CompressionConfig {
+            enabled: true,
+            encodings: vec![Encoding::Gzip],
+            types: vec!["text/html".into(), "application/json".into()],
+            min_size: 512,
+        },
         connection_timeout: Some(Duration::from_secs(5)),
+        shutdown_timeout: Duration::from_secs(10),
+        watch_config: true,
     };
 
     assert_eq!(conf, expected_conf);
@@ -97,6 +132,7 @@ fn test_host_config() {
         address: "0.0.0.0".into(),
         port: 80,
         threads: 32,
+        listen: vec![Listener::Tcp("0.0.0.0:80".into())],
         #[cfg(feature = "tls")]
         tls_config: None,
         default_websocket_proxy: None,
@@ -108,6 +144,7 @@ fn test_host_config() {
                 path: Some("/var/www".into()),
                 load_balancer: None,
                 websocket_proxy: None,
+                listing: false,
             }],
         },
         hosts: vec![
@@ -119,6 +156,7 @@ fn test_host_config() {
                     path: Some("/app/dev".into()),
                     load_balancer: None,
                     websocket_proxy: None,
+                    listing: false,
                 }],
             },
             HostConfig {
@@ -129,6 +167,7 @@ fn test_host_config() {
                     path: Some("/app/prod".into()),
                     load_balancer: None,
                     websocket_proxy: None,
+                    listing: false,
                 }],
             },
         ],
@@ -147,7 +186,12 @@ fn test_host_config() {
             list: Vec::new(),
             mode: BlacklistMode::Block,
         },
+        trusted_proxies: Vec::new(),
+        trust_unix_sockets: false,
+        compression: CompressionConfig::default(),
         connection_timeout: None,
+        shutdown_timeout: Duration::from_secs(30),
+        watch_config: false,
     };
 
     assert_eq!(conf, expected_conf);
@@ -163,6 +207,7 @@ fn comma_separated_routes() {
         address: "0.0.0.0".into(),
         port: 80,
         threads: 32,
+        listen: vec![Listener::Tcp("0.0.0.0:80".into())],
         #[cfg(feature = "tls")]
         tls_config: None,
         default_websocket_proxy: None,
@@ -175,6 +220,7 @@ fn comma_separated_routes() {
                     path: Some("/var/www".into()),
                     load_balancer: None,
                     websocket_proxy: None,
+                    listing: false,
                 },
                 RouteConfig {
                     route_type: RouteType::Directory,
@@ -182,6 +228,7 @@ fn comma_separated_routes() {
                     path: Some("/var/www".into()),
                     load_balancer: None,
                     websocket_proxy: None,
+                    listing: false,
                 },
             ],
         },
@@ -201,8 +248,33 @@ fn comma_separated_routes() {
             list: Vec::new(),
             mode: BlacklistMode::Block,
         },
+        trusted_proxies: Vec::new(),
+        trust_unix_sockets: false,
+        compression: CompressionConfig::default(),
         connection_timeout: None,
+        shutdown_timeout: Duration::from_secs(30),
+        watch_config: false,
     };
 
     assert_eq!(conf, expected_conf);
 }
+
+#[cfg(unix)]
+#[test]
+fn test_listen_config() {
+    let tree = parse_conf(include_str!("testcases/listen.conf"), "listen.conf").unwrap();
+    let conf = Config::from_tree(tree).unwrap();
+
+    assert_eq!(
+        conf.listen,
+        vec![
+            Listener::Tcp("0.0.0.0:80".into()),
+            Listener::Tcp("[::]:8080".into()),
+            Listener::Unix("/run/humphrey.sock".into()),
+        ]
+    );
+
+    // HTTPS listeners cannot be used without a certificate
+    let tree = parse_conf("server {\n listen \"https://0.0.0.0:443\"\n}", "https.conf").unwrap();
+    assert!(Config::from_tree(tree).is_err());
+}
@@ -0,0 +1,131 @@
+use humphrey_server::config::{HealthCheckConfig, LoadBalancerMode};
+use humphrey_server::health::check_target;
+use humphrey_server::proxy::LoadBalancer;
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread::spawn;
+use std::time::{Duration, Instant};
+
+fn load_balancer(mode: LoadBalancerMode, max_fails: usize) -> LoadBalancer {
+    LoadBalancer::new(
+        vec!["a".into(), "b".into(), "c".into()],
+        mode,
+        HealthCheckConfig {
+            max_fails,
+            ..Default::default()
+        },
+    )
+}
+
+#[test]
+fn test_ejected_targets_skipped() {
+    let mut lb = load_balancer(LoadBalancerMode::RoundRobin, 2);
+
+    // The target is only ejected after the maximum number of consecutive failures
+    assert!(!lb.record_failure(1));
+    assert!(lb.record_failure(1));
+    assert!(lb.is_ejected(1, Instant::now()));
+
+    let targets: Vec<String> = (0..4).map(|_| lb.select_target()).collect();
+    assert_eq!(targets, vec!["a", "c", "a", "c"]);
+
+    for _ in 0..10 {
+        let mut random = load_balancer(LoadBalancerMode::Random, 1);
+        random.record_failure(0);
+        assert_ne!(random.select_target(), "a");
+    }
+
+    // A successful request brings the target back straight away
+    assert!(lb.record_success(1));
+    assert!(!lb.is_ejected(1, Instant::now()));
+    assert_eq!(lb.select_target(), "a");
+    assert_eq!(lb.select_target(), "b");
+}
+
+#[test]
+fn test_retry_targets() {
+    let mut lb = load_balancer(LoadBalancerMode::RoundRobin, 1);
+
+    assert_eq!(lb.select_target_index(None, &[]), Some(0));
+    assert_eq!(lb.select_target_index(None, &[0]), Some(1));
+    assert_eq!(lb.select_target_index(None, &[0, 1]), Some(2));
+    assert_eq!(lb.select_target_index(None, &[0, 1, 2]), None);
+
+    // When every target is ejected, they are all still tried once
+    lb.record_failure(0);
+    lb.record_failure(1);
+    lb.record_failure(2);
+
+    assert!(lb.select_target_index(None, &[]).is_some());
+    assert_eq!(lb.select_target_index(None, &[0]), None);
+}
+
+#[test]
+fn test_passive_checks_disabled() {
+    let mut lb = load_balancer(LoadBalancerMode::RoundRobin, 0);
+
+    for _ in 0..5 {
+        assert!(!lb.record_failure(0));
+    }
+
+    assert!(!lb.is_ejected(0, Instant::now()));
+}
+
+#[test]
+fn test_health_check_due() {
+    let mut lb = load_balancer(LoadBalancerMode::RoundRobin, 1);
+    let now = Instant::now();
+
+    assert!(!lb.health_check_due(now));
+
+    lb.health_check.path = Some("/health".into());
+    assert!(lb.health_check_due(now));
+
+    lb.last_health_check = Some(now);
+    assert!(!lb.health_check_due(now));
+    assert!(lb.health_check_due(now + lb.health_check.interval));
+}
+
+#[test]
+fn test_check_target() {
+    for (response, expected) in [
+        (&b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n"[..], true),
+        (
+            &b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n"[..],
+            false,
+        ),
+    ] {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target = listener.local_addr().unwrap().to_string();
+
+        let server = spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+
+            while !request.ends_with(b"\r\n\r\n") {
+                let read = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..read]);
+            }
+
+            stream.write_all(response).unwrap();
+            request
+        });
+
+        assert_eq!(
+            check_target(&target, "/health", Duration::from_secs(5)),
+            expected
+        );
+
+        let request = String::from_utf8(server.join().unwrap()).unwrap();
+        assert!(request.starts_with("GET /health HTTP/1.1\r\n"));
+    }
+
+    // Nothing is listening once the listener is dropped
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let target = listener.local_addr().unwrap().to_string();
+    drop(listener);
+
+    assert!(!check_target(&target, "/health", Duration::from_secs(5)));
+}
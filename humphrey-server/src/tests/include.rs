@@ -1,14 +1,19 @@
+use humphrey::http::proxy::ConnectionPool;
+use humphrey::listener::Listener;
 use humphrey_server::config::tree::parse_conf;
 use humphrey_server::config::{
-    BlacklistConfig, BlacklistMode, CacheConfig, Config, ConfigSource, HostConfig,
-    LoadBalancerMode, LoggingConfig, RouteConfig, RouteType,
+    BlacklistConfig, BlacklistMode, CacheConfig, CompressionConfig, Config, ConfigSource, HashKey,
+    HealthCheckConfig, HostConfig, LoadBalancerMode, LoggingConfig, ProxyConfig, RouteConfig,
+    RouteType,
 };
 use humphrey_server::logger::LogLevel;
-use humphrey_server::proxy::{EqMutex, LoadBalancer};
+use humphrey_server::proxy::{EqMutex, LoadBalancer, TargetHealth};
 use humphrey_server::rand::Lcg;
 
 use std::env::set_current_dir;
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[test]
 fn include_route() {
@@ -24,6 +29,7 @@ fn include_route() {
         address: "0.0.0.0".into(),
         port: 80,
         threads: 32,
+        listen: vec![Listener::Tcp("0.0.0.0:80".into())],
         #[cfg(feature = "tls")]
         tls_config: None,
         default_websocket_proxy: None,
@@ -35,6 +41,7 @@ fn include_route() {
                 path: Some("/var/www".into()),
                 load_balancer: None,
                 websocket_proxy: None,
+                listing: false,
             }],
         },
         hosts: Vec::new(),
@@ -53,7 +60,12 @@ fn include_route() {
             list: Vec::new(),
             mode: BlacklistMode::Block,
         },
+        trusted_proxies: Vec::new(),
+        trust_unix_sockets: false,
+        compression: CompressionConfig::default(),
         connection_timeout: None,
+        shutdown_timeout: Duration::from_secs(30),
+        watch_config: false,
     });
 
     assert_eq!(config, expected_conf);
@@ -73,6 +85,7 @@ fn nested_include() {
         address: "0.0.0.0".into(),
         port: 80,
         threads: 32,
+        listen: vec![Listener::Tcp("0.0.0.0:80".into())],
         #[cfg(feature = "tls")]
         tls_config: None,
         default_websocket_proxy: None,
@@ -84,11 +97,20 @@ fn nested_include() {
                 path: None,
                 load_balancer: Some(EqMutex::new(LoadBalancer {
                     targets: vec!["127.0.0.1".into()],
+                    weights: vec![1],
                     mode: LoadBalancerMode::Random,
-                    index: 0,
+                    hash_key: HashKey::Address,
+                    current_weights: vec![0],
+                    connections: vec![0],
                     lcg: Lcg::new(),
+                    health_check: HealthCheckConfig::default(),
+                    health: vec![TargetHealth::default()],
+                    last_health_check: None,
+                    pool: Arc::new(ConnectionPool::new()),
+                    proxy_config: ProxyConfig::default(),
                 })),
                 websocket_proxy: None,
+                listing: false,
             }],
         },
         hosts: Vec::new(),
@@ -107,7 +129,12 @@ fn nested_include() {
             list: Vec::new(),
             mode: BlacklistMode::Block,
         },
+        trusted_proxies: Vec::new(),
+        trust_unix_sockets: false,
+        compression: CompressionConfig::default(),
         connection_timeout: None,
+        shutdown_timeout: Duration::from_secs(30),
+        watch_config: false,
     });
 
     assert_eq!(config, expected_conf);
@@ -0,0 +1,86 @@
+#![allow(unused_imports)]
+use humphrey::http::headers::HeaderType;
+use humphrey::http::method::Method;
+use humphrey::http::{Request, StatusCode};
+use humphrey_server::server::listing::{escape_html, format_size, listing_response, read_entries};
+
+use std::fs::{create_dir_all, remove_dir_all, write};
+use std::path::PathBuf;
+
+#[test]
+fn test_format_size() {
+    assert_eq!(format_size(0), "0 B");
+    assert_eq!(format_size(1023), "1023 B");
+    assert_eq!(format_size(1536), "1.5 KiB");
+    assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+}
+
+#[test]
+fn test_escape_html() {
+    assert_eq!(
+        escape_html("<script>alert(\"x & 'y'\")</script>"),
+        "&lt;script&gt;alert(&quot;x &amp; &#39;y&#39;&quot;)&lt;/script&gt;"
+    );
+}
+
+#[test]
+fn test_directory_listing() {
+    let directory = test_directory("listing");
+    create_dir_all(directory.join("sub")).unwrap();
+    write(directory.join("b.txt"), "hello").unwrap();
+    write(directory.join("<a>&\"b\".txt"), "").unwrap();
+    write(directory.join(".hidden"), "").unwrap();
+
+    let entries = read_entries(&directory).unwrap();
+    let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+    assert_eq!(names, vec!["sub", "<a>&\"b\".txt", "b.txt"]);
+    assert_eq!(entries[0].size, None);
+    assert_eq!(entries[2].size, Some(5));
+
+    let response =
+        listing_response(&Request::new(Method::Get, "/files/"), &directory, false).unwrap();
+    let html = String::from_utf8(response.body).unwrap();
+
+    assert_eq!(response.status_code, StatusCode::OK);
+    assert!(html.contains("<title>Index of /files/</title>"));
+    assert!(html.contains("<a href=\"../\">../</a>"));
+    assert!(html.contains("<a href=\"sub/\">sub/</a>"));
+    assert!(html.contains("<a href=\"%3Ca%3E%26%22b%22.txt\">&lt;a&gt;&amp;&quot;b&quot;.txt</a>"));
+    assert!(!html.contains("hidden"));
+
+    let response = listing_response(
+        &Request::new(Method::Get, "/files/")
+            .with_header(HeaderType::Accept, "application/json;q=0.9, */*"),
+        &directory,
+        false,
+    )
+    .unwrap();
+    let json = String::from_utf8(response.body).unwrap();
+
+    assert_eq!(
+        response.headers.get(HeaderType::ContentType),
+        Some("application/json")
+    );
+    assert!(json.starts_with("[{\"name\":\"sub\",\"type\":\"directory\",\"size\":null,"));
+    assert!(json.contains("{\"name\":\"<a>&\\\"b\\\".txt\",\"type\":\"file\",\"size\":0,"));
+    assert!(json.contains("{\"name\":\"b.txt\",\"type\":\"file\",\"size\":5,"));
+
+    let response = listing_response(&Request::new(Method::Get, "/"), &directory, true).unwrap();
+    let html = String::from_utf8(response.body).unwrap();
+
+    assert!(!html.contains("../"));
+    assert!(html.contains("<a href=\"sub/\">sub/</a>"));
+
+    remove_dir_all(&directory).unwrap();
+}
+
+fn test_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!(
+        "humphrey-server-test-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = remove_dir_all(&directory);
+
+    directory
+}
@@ -0,0 +1,206 @@
+use humphrey::http::proxy::{DEFAULT_IDLE_TIMEOUT, DEFAULT_MAX_IDLE_CONNECTIONS};
+use humphrey::http::Request;
+use humphrey_server::config::tree::parse_conf;
+use humphrey_server::config::{Config, HashKey, HealthCheckConfig, LoadBalancerMode};
+use humphrey_server::proxy::LoadBalancer;
+
+use std::time::Duration;
+
+fn load_balancer(mode: LoadBalancerMode, weights: Vec<usize>) -> LoadBalancer {
+    let targets = (0..weights.len())
+        .map(|i| format!("target-{}", i))
+        .collect();
+    LoadBalancer::new(targets, mode, HealthCheckConfig::default()).with_weights(weights)
+}
+
+fn request(address: &str, headers: &str) -> Request {
+    let data = format!("GET / HTTP/1.1\r\nHost: localhost\r\n{}\r\n", headers);
+    Request::from_stream(&mut data.as_bytes(), address.parse().unwrap()).unwrap()
+}
+
+fn select(lb: &mut LoadBalancer, request: Option<&Request>, count: usize) -> Vec<usize> {
+    (0..count)
+        .map(|_| lb.select_target_index(request, &[]).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_weighted_round_robin() {
+    let mut lb = load_balancer(LoadBalancerMode::RoundRobin, vec![1, 1, 1]);
+    assert_eq!(select(&mut lb, None, 6), vec![0, 1, 2, 0, 1, 2]);
+
+    // Requests to the heavier target are spread out rather than sent in a burst
+    let mut lb = load_balancer(LoadBalancerMode::RoundRobin, vec![5, 1, 1]);
+    assert_eq!(select(&mut lb, None, 7), vec![0, 0, 1, 0, 2, 0, 0]);
+}
+
+#[test]
+fn test_weighted_random() {
+    let mut lb = load_balancer(LoadBalancerMode::Random, vec![3, 1]);
+    let selected = select(&mut lb, None, 4000);
+    let first = selected.iter().filter(|index| **index == 0).count();
+
+    assert!((2700..3300).contains(&first));
+}
+
+#[test]
+fn test_least_connections() {
+    let mut lb = load_balancer(LoadBalancerMode::LeastConnections, vec![1, 1, 2]);
+
+    // Idle targets share the load
+    assert_eq!(select(&mut lb, None, 3), vec![2, 0, 1]);
+
+    lb.connections = vec![1, 0, 1];
+    assert_eq!(select(&mut lb, None, 2), vec![1, 1]);
+
+    // The third target has twice the weight, so it is less loaded with two connections than the others with one
+    lb.connections = vec![1, 1, 1];
+    assert_eq!(select(&mut lb, None, 2), vec![2, 2]);
+}
+
+#[test]
+fn test_hash() {
+    let mut lb = load_balancer(LoadBalancerMode::Hash, vec![1, 1, 1, 1]);
+
+    // The same client is always sent to the same target
+    let client = request("1.2.3.4:5678", "");
+    let target = lb.select_target_index(Some(&client), &[]).unwrap();
+    assert_eq!(select(&mut lb, Some(&client), 10), vec![target; 10]);
+
+    // Different clients are spread across the targets
+    let mut selected: Vec<usize> = (0..64)
+        .map(|i| {
+            let client = request(&format!("10.0.0.{}:80", i), "");
+            lb.select_target_index(Some(&client), &[]).unwrap()
+        })
+        .collect();
+    selected.sort_unstable();
+    selected.dedup();
+    assert_eq!(selected, vec![0, 1, 2, 3]);
+
+    // If the target is unavailable, the client is sent to another one, and only its clients are moved
+    let fallback = lb.select_target_index(Some(&client), &[target]).unwrap();
+    assert_ne!(fallback, target);
+
+    for i in 0..64 {
+        let client = request(&format!("10.0.0.{}:80", i), "");
+        let before = lb.select_target_index(Some(&client), &[]).unwrap();
+        let after = lb.select_target_index(Some(&client), &[target]).unwrap();
+
+        if before != target {
+            assert_eq!(before, after);
+        }
+    }
+}
+
+#[test]
+fn test_hash_key() {
+    let lb = load_balancer(LoadBalancerMode::Hash, vec![1]);
+    let with_header = request("1.2.3.4:5678", "X-Session: abc\r\nCookie: session=def\r\n");
+    let without_header = request("1.2.3.4:5678", "");
+
+    assert_eq!(lb.hash_key(&with_header), "1.2.3.4");
+
+    let lb = lb.with_hash_key(HashKey::Header("X-Session".into()));
+    assert_eq!(lb.hash_key(&with_header), "abc");
+    assert_eq!(lb.hash_key(&without_header), "1.2.3.4");
+
+    let lb = lb.with_hash_key(HashKey::Cookie("session".into()));
+    assert_eq!(lb.hash_key(&with_header), "def");
+    assert_eq!(lb.hash_key(&without_header), "1.2.3.4");
+}
+
+fn parse_route(route: &str) -> Result<Config, &'static str> {
+    let conf = format!("server {{\nroute /* {{\n{}\n}}\n}}", route);
+    Config::from_tree(parse_conf(&conf, "test.conf").unwrap())
+}
+
+#[test]
+fn test_parse_load_balancer() {
+    let config = parse_route(
+        "proxy \"127.0.0.1:8000 weight=3, 127.0.0.1:8080\"\nload_balancer_mode \"hash\"\nhash_key \"cookie:session\"",
+    )
+    .unwrap();
+    let lb = config.default_host.routes[0]
+        .load_balancer
+        .as_ref()
+        .unwrap()
+        .lock()
+        .unwrap()
+        .clone();
+
+    assert_eq!(lb.targets, vec!["127.0.0.1:8000", "127.0.0.1:8080"]);
+    assert_eq!(lb.weights, vec![3, 1]);
+    assert_eq!(lb.mode, LoadBalancerMode::Hash);
+    assert_eq!(lb.hash_key, HashKey::Cookie("session".into()));
+
+    let config =
+        parse_route("proxy \"127.0.0.1:8000\"\nload_balancer_mode \"least-connections\"").unwrap();
+    let route = &config.default_host.routes[0];
+    let mode = route.load_balancer.as_ref().unwrap().lock().unwrap().mode;
+    assert_eq!(mode, LoadBalancerMode::LeastConnections);
+
+    assert_eq!(
+        parse_route("proxy \"127.0.0.1:8000 weight=0\"").unwrap_err(),
+        "Invalid proxy target weight, it must be a positive integer"
+    );
+    assert_eq!(
+        parse_route("proxy \"127.0.0.1:8000 backup\"").unwrap_err(),
+        "Invalid proxy target option, the only valid option is `weight`"
+    );
+    assert_eq!(
+        parse_route("proxy \"127.0.0.1:8000,\"").unwrap_err(),
+        "Proxy targets cannot be empty"
+    );
+    assert_eq!(
+        parse_route("proxy \"127.0.0.1:8000\"\nhash_key \"address\"").unwrap_err(),
+        "The `hash_key` field can only be used with the `hash` load balancer mode"
+    );
+    assert_eq!(
+        parse_route("proxy \"127.0.0.1:8000\"\nload_balancer_mode \"hash\"\nhash_key \"query\"")
+            .unwrap_err(),
+        "Invalid hash key, valid options are `address`, `header:<name>` or `cookie:<name>`"
+    );
+}
+
+#[test]
+fn test_parse_connection_pool() {
+    let config =
+        parse_route("proxy \"127.0.0.1:8000\"\nconnection_pool {\nmax_idle 8\nidle_timeout 30\n}")
+            .unwrap();
+    let route = &config.default_host.routes[0];
+    let pool = route
+        .load_balancer
+        .as_ref()
+        .unwrap()
+        .lock()
+        .unwrap()
+        .pool
+        .clone();
+
+    assert_eq!(pool.max_idle_connections(), 8);
+    assert_eq!(pool.idle_timeout(), Duration::from_secs(30));
+
+    let config = parse_route("proxy \"127.0.0.1:8000\"").unwrap();
+    let route = &config.default_host.routes[0];
+    let pool = route
+        .load_balancer
+        .as_ref()
+        .unwrap()
+        .lock()
+        .unwrap()
+        .pool
+        .clone();
+
+    assert_eq!(pool.max_idle_connections(), DEFAULT_MAX_IDLE_CONNECTIONS);
+    assert_eq!(pool.idle_timeout(), DEFAULT_IDLE_TIMEOUT);
+
+    assert_eq!(
+        parse_route("proxy \"127.0.0.1:8000\"\nconnection_pool {\nidle_timeout 0\n}").unwrap_err(),
+        "Connection pool idle timeout must be at least one second"
+    );
+    assert_eq!(
+        parse_route("proxy \"127.0.0.1:8000\"\nconnection_pool {\nmax_idle -1\n}").unwrap_err(),
+        "Invalid connection pool maximum number of idle connections"
+    );
+}
@@ -1,4 +1,11 @@
+pub mod activation;
+pub mod cache;
 pub mod config;
+pub mod health;
 pub mod include;
 pub mod invalid_config;
+pub mod listing;
+pub mod load_balancer;
+pub mod proxy;
+pub mod reload;
 pub mod tree;
@@ -0,0 +1,109 @@
+use humphrey_server::config::tree::parse_conf;
+use humphrey_server::config::{Config, ProxyConfig};
+use humphrey_server::proxy::{rewrite_cookie_path, rewrite_location, route_prefix};
+
+use std::time::Duration;
+
+fn parse_proxy_config(route: &str) -> Result<ProxyConfig, &'static str> {
+    let conf = format!(
+        "server {{\nroute /app/* {{\nproxy \"127.0.0.1:8000\"\n{}\n}}\n}}",
+        route
+    );
+    let config = Config::from_tree(parse_conf(&conf, "test.conf").unwrap())?;
+    let route = &config.default_host.routes[0];
+    let load_balancer = route.load_balancer.as_ref().unwrap().lock().unwrap();
+
+    Ok(load_balancer.proxy_config.clone())
+}
+
+#[test]
+fn test_route_prefix() {
+    assert_eq!(route_prefix("/app/*"), Some("/app"));
+    assert_eq!(route_prefix("/app*"), Some("/app"));
+    assert_eq!(route_prefix("/*"), Some(""));
+    assert_eq!(route_prefix("/app"), None);
+    assert_eq!(route_prefix("/*/app/*"), None);
+}
+
+#[test]
+fn test_rewrite_location() {
+    let hosts = ["127.0.0.1:8000", "example.com"];
+
+    assert_eq!(
+        rewrite_location("http://127.0.0.1:8000/login?next=/", &hosts, "/app"),
+        "/app/login?next=/"
+    );
+    assert_eq!(
+        rewrite_location("https://EXAMPLE.com", &hosts, "/app"),
+        "/app/"
+    );
+    assert_eq!(rewrite_location("/login", &hosts, "/app"), "/app/login");
+    assert_eq!(rewrite_location("/login", &hosts, ""), "/login");
+
+    // Locations elsewhere are left unchanged
+    assert_eq!(
+        rewrite_location("https://example.org/login", &hosts, "/app"),
+        "https://example.org/login"
+    );
+    assert_eq!(
+        rewrite_location("//example.org/login", &hosts, "/app"),
+        "//example.org/login"
+    );
+    assert_eq!(rewrite_location("login", &hosts, "/app"), "login");
+}
+
+#[test]
+fn test_rewrite_cookie_path() {
+    assert_eq!(
+        rewrite_cookie_path("session=abc; Path=/; HttpOnly", "/app"),
+        "session=abc; Path=/app; HttpOnly"
+    );
+    assert_eq!(
+        rewrite_cookie_path("session=abc; path=/account", "/app"),
+        "session=abc; Path=/app/account"
+    );
+    assert_eq!(
+        rewrite_cookie_path("session=abc; Path=/", ""),
+        "session=abc; Path=/"
+    );
+
+    // Cookies without a path, or named `path`, are left unchanged
+    assert_eq!(
+        rewrite_cookie_path("session=abc; Secure", "/app"),
+        "session=abc; Secure"
+    );
+    assert_eq!(rewrite_cookie_path("path=/x", "/app"), "path=/x");
+}
+
+#[test]
+fn test_parse_proxy_config() {
+    assert_eq!(parse_proxy_config("").unwrap(), ProxyConfig::default());
+
+    let proxy_config = parse_proxy_config(
+        "proxy_timeout 30\npreserve_host true\nrewrite_location true\nrewrite_cookie_path true\nproxy_set_header {\nX-Real-Ip \"\"\nX-Forwarded-Proto \"https\"\n}",
+    )
+    .unwrap();
+
+    assert_eq!(
+        proxy_config,
+        ProxyConfig {
+            timeout: Duration::from_secs(30),
+            set_headers: vec![
+                ("X-Forwarded-Proto".into(), "https".into()),
+                ("X-Real-Ip".into(), "".into()),
+            ],
+            preserve_host: true,
+            rewrite_location: true,
+            rewrite_cookie_path: true,
+        }
+    );
+
+    assert_eq!(
+        parse_proxy_config("proxy_timeout 0").unwrap_err(),
+        "Proxy timeout must be at least one second"
+    );
+    assert_eq!(
+        parse_proxy_config("preserve_host \"yes\"").unwrap_err(),
+        "Preserve host must be a boolean"
+    );
+}
@@ -0,0 +1,86 @@
+use humphrey_server::config::tree::{included_files, parse_conf};
+use humphrey_server::config::{Config, ConfigSource};
+use humphrey_server::reload::restart_required;
+
+use std::env::set_current_dir;
+use std::path::Path;
+
+#[test]
+fn test_included_files() {
+    // Set current directory to testcases directory so the included files can be found
+    let testcases_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/testcases");
+    set_current_dir(testcases_path).unwrap();
+
+    let files = included_files(include_str!("./testcases/nested_include_root.conf"));
+    assert_eq!(
+        files,
+        vec!["nested_include_1.conf", "nested_include_2.conf"]
+    );
+
+    let files = included_files("include \"missing.conf\"\ninclude \"missing.conf\"");
+    assert_eq!(files, vec!["missing.conf"]);
+
+    assert!(included_files(include_str!("./testcases/valid.conf")).is_empty());
+}
+
+#[test]
+fn test_find_route() {
+    let tree = parse_conf(include_str!("testcases/hosts.conf"), "hosts.conf").unwrap();
+    let conf = Config::from_tree(tree).unwrap();
+
+    let (host, route, _) = conf.find_route(Some("localhost"), "/", false).unwrap();
+    assert_eq!((host, route), (1, 0));
+
+    let (host, route, _) = conf
+        .find_route(Some("www.example.com"), "/", false)
+        .unwrap();
+    assert_eq!((host, route), (2, 0));
+
+    // Hosts without a matching route fall back to the default host
+    let (host, route, params) = conf
+        .find_route(Some("localhost"), "/index.html", false)
+        .unwrap();
+    assert_eq!((host, route), (0, 0));
+    assert_eq!(params.wildcard(), Some("index.html"));
+
+    let (host, route, _) = conf.find_route(None, "/", false).unwrap();
+    assert_eq!((host, route), (0, 0));
+
+    // None of the routes proxy WebSocket connections
+    assert!(conf.find_route(Some("localhost"), "/", true).is_none());
+}
+
+#[test]
+fn test_load_file() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/testcases/hosts.conf");
+    let conf = Config::load_file(path.to_str().unwrap(), ConfigSource::Argument).unwrap();
+
+    assert_eq!(conf.source, ConfigSource::Argument);
+    assert_eq!(conf.hosts.len(), 2);
+
+    assert!(Config::load_file("missing.conf", ConfigSource::Argument).is_err());
+}
+
+#[test]
+fn test_restart_required() {
+    let old = Config::from_tree(parse_conf("server {\nport 80\n}", "old.conf").unwrap()).unwrap();
+    let new = Config::from_tree(
+        parse_conf(
+            "server {\nport 80\nroute /* {\ndirectory \"/var/www\"\n}\n}",
+            "new.conf",
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert!(restart_required(&old, &new).is_empty());
+
+    let new =
+        Config::from_tree(parse_conf("server {\nport 8080\nthreads 4\n}", "new.conf").unwrap())
+            .unwrap();
+
+    assert_eq!(
+        restart_required(&old, &new),
+        vec!["the listen addresses", "the number of threads"]
+    );
+}
@@ -15,6 +15,10 @@ fn test_build_tree() {
         ConfigNode::Number("threads".into(), "32".into()),
         ConfigNode::String("websocket".into(), "localhost:1234".into()),
         ConfigNode::Number("timeout".into(), "5".into()),
+        ConfigNode::Number("shutdown_timeout".into(), "10".into()),
+        ConfigNode::String("trusted_proxies".into(), "10.0.0.0/8,::1".into()),
+        ConfigNode::Boolean("trust_unix_sockets".into(), "true".into()),
+        ConfigNode::Boolean("watch_config".into(), "true".into()),
         ConfigNode::Section("plugins".into(), vec![
             ConfigNode::Section("php".into(), vec![
                 ConfigNode::String("library".into(), "plugins/php/target/release/php.dll".into()),
@@ -35,12 +39,28 @@ fn test_build_tree() {
             ConfigNode::Number("size".into(), "134217728".into()),
             ConfigNode::Number("time".into(), "60".into())
         ]),
+        ConfigNode::Section("compression".into(), vec![
+            ConfigNode::Boolean("enabled".into(), "true".into()),
+            ConfigNode::String("encodings".into(), "gzip".into()),
+            ConfigNode::String("types".into(), "text/html,application/json".into()),
+            ConfigNode::Number("min_size".into(), "512".into())
+        ]),
         ConfigNode::Route("/static/*".into(), vec![
             ConfigNode::String("directory".into(), "/var/www".into()),
+            ConfigNode::Boolean("listing".into(), "true".into()),
         ]),
         ConfigNode::Route("/*".into(), vec![
-            ConfigNode::String("proxy".into(), "127.0.0.1:8000,127.0.0.1:8080".into()),
-            ConfigNode::String("load_balancer_mode".into(), "round-robin".into())
+            ConfigNode::String("proxy".into(), "127.0.0.1:8000 weight=2,127.0.0.1:8080".into()),
+            ConfigNode::String("load_balancer_mode".into(), "round-robin".into()),
+            ConfigNode::Number("proxy_timeout".into(), "10".into()),
+            ConfigNode::Section("health_check".into(), vec![
+                ConfigNode::String("path".into(), "/health".into()),
+                ConfigNode::Number("interval".into(), "5".into()),
+                ConfigNode::Number("max_fails".into(), "3".into()),
+            ]),
+            ConfigNode::Section("connection_pool".into(), vec![
+                ConfigNode::Number("max_idle".into(), "16".into()),
+            ])
         ])
     ]);
 
@@ -60,12 +80,20 @@ fn test_flatten_config() {
     expected_hashmap.insert("server.threads".into(), ConfigNode::Number("threads".into(), "32".into()));
     expected_hashmap.insert("server.websocket".into(), ConfigNode::String("websocket".into(), "localhost:1234".into()));
     expected_hashmap.insert("server.timeout".into(), ConfigNode::Number("timeout".into(), "5".into()));
+    expected_hashmap.insert("server.shutdown_timeout".into(), ConfigNode::Number("shutdown_timeout".into(), "10".into()));
+    expected_hashmap.insert("server.trusted_proxies".into(), ConfigNode::String("trusted_proxies".into(), "10.0.0.0/8,::1".into()));
+    expected_hashmap.insert("server.trust_unix_sockets".into(), ConfigNode::Boolean("trust_unix_sockets".into(), "true".into()));
+    expected_hashmap.insert("server.watch_config".into(), ConfigNode::Boolean("watch_config".into(), "true".into()));
     expected_hashmap.insert("server.blacklist.mode".into(), ConfigNode::String("mode".into(), "block".into()));
     expected_hashmap.insert("server.log.level".into(), ConfigNode::String("level".into(), "info".into()));
     expected_hashmap.insert("server.log.console".into(), ConfigNode::Boolean("console".into(), "true".into()));
     expected_hashmap.insert("server.log.file".into(), ConfigNode::String("file".into(), "humphrey.log".into()));
     expected_hashmap.insert("server.cache.size".into(), ConfigNode::Number("size".into(), "134217728".into()));
     expected_hashmap.insert("server.cache.time".into(), ConfigNode::Number("time".into(), "60".into()));
+    expected_hashmap.insert("server.compression.enabled".into(), ConfigNode::Boolean("enabled".into(), "true".into()));
+    expected_hashmap.insert("server.compression.encodings".into(), ConfigNode::String("encodings".into(), "gzip".into()));
+    expected_hashmap.insert("server.compression.types".into(), ConfigNode::String("types".into(), "text/html,application/json".into()));
+    expected_hashmap.insert("server.compression.min_size".into(), ConfigNode::Number("min_size".into(), "512".into()));
 
     let mut actual_hashmap: HashMap<String, ConfigNode> = HashMap::new();
     parsed_conf.flatten(&mut actual_hashmap, &Vec::new());
@@ -82,11 +110,17 @@ fn test_get_routes() {
 
     let mut static_hashmap: HashMap<String, ConfigNode> = HashMap::new();
     static_hashmap.insert("directory".into(), ConfigNode::String("directory".into(), "/var/www".into()));
+    static_hashmap.insert("listing".into(), ConfigNode::Boolean("listing".into(), "true".into()));
     expected_map.push(("/static/*".into(), static_hashmap));
 
     let mut proxy_hashmap: HashMap<String, ConfigNode> = HashMap::new();
-    proxy_hashmap.insert("proxy".into(), ConfigNode::String("proxy".into(), "127.0.0.1:8000,127.0.0.1:8080".into()));
+    proxy_hashmap.insert("proxy".into(), ConfigNode::String("proxy".into(), "127.0.0.1:8000 weight=2,127.0.0.1:8080".into()));
     proxy_hashmap.insert("load_balancer_mode".into(), ConfigNode::String("load_balancer_mode".into(), "round-robin".into()));
+    proxy_hashmap.insert("proxy_timeout".into(), ConfigNode::Number("proxy_timeout".into(), "10".into()));
+    proxy_hashmap.insert("health_check.path".into(), ConfigNode::String("path".into(), "/health".into()));
+    proxy_hashmap.insert("health_check.interval".into(), ConfigNode::Number("interval".into(), "5".into()));
+    proxy_hashmap.insert("health_check.max_fails".into(), ConfigNode::Number("max_fails".into(), "3".into()));
+    proxy_hashmap.insert("connection_pool.max_idle".into(), ConfigNode::Number("max_idle".into(), "16".into()));
     expected_map.push(("/*".into(), proxy_hashmap));
 
     let routes = parsed_conf.get_routes();
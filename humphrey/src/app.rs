@@ -2,30 +2,44 @@
 
 #![allow(clippy::new_without_default)]
 
+use crate::http::address::{is_trusted, Address, Cidr};
+use crate::http::body::{BodyDecoder, RequestBody};
+use crate::http::compression::Compression;
 use crate::http::cors::Cors;
 use crate::http::date::DateTime;
 use crate::http::headers::HeaderType;
-use crate::http::method::Method;
+use crate::http::method::{join_methods, Method};
 use crate::http::request::{Request, RequestError};
 use crate::http::response::Response;
 use crate::http::status::StatusCode;
 use crate::krauss::wildcard_match;
+use crate::listener::{Listener, PreboundListener};
 use crate::monitor::event::{Event, EventType};
 use crate::monitor::MonitorConfig;
-use crate::route::{Route, RouteHandler, SubApp};
+use crate::route::{Handler, Route, RouteHandler, SubApp};
+use crate::shutdown::{ConnectionTracker, ShutdownSummary, TrackedConnection};
 use crate::stream::Stream;
 use crate::thread::pool::ThreadPool;
 
-use std::io::Write;
+use std::io::BufReader;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
+#[cfg(unix)]
+use crate::listener::remove_stale_socket;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::PathBuf;
+
 #[cfg(feature = "tls")]
 use rustls::ServerConfig;
+#[cfg(feature = "tls")]
+use std::io::Write;
 
 /// Represents the Humphrey app.
 ///
@@ -45,27 +59,61 @@ where
     connection_handler: ConnectionHandler<State>,
     connection_condition: ConnectionCondition<State>,
     connection_timeout: Option<Duration>,
+    max_body_size: Option<usize>,
+    keep_alive_timeout: Option<Duration>,
+    max_keep_alive_requests: Option<usize>,
+    trusted_proxies: Arc<Vec<Cidr>>,
+    trust_unix_sockets: bool,
+    middleware: Vec<Arc<dyn Middleware<State>>>,
     shutdown: Option<Receiver<()>>,
+    shutdown_timeout: Duration,
     #[cfg(feature = "tls")]
     tls_config: Option<Arc<ServerConfig>>,
     #[cfg(feature = "tls")]
     force_https: bool,
 }
 
+/// The default amount of time an idle connection is kept open waiting for another request.
+pub const DEFAULT_KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The default amount of time requests in progress are given to finish when the app is shut down.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// Represents a function able to handle a connection.
 /// In most cases, the default connection handler should be used.
-pub type ConnectionHandler<State> = fn(
-    Stream,
-    Arc<Vec<SubApp<State>>>,
-    Arc<SubApp<State>>,
-    Arc<ErrorHandler>,
-    Arc<State>,
-    MonitorConfig,
-    Option<Duration>,
-);
+pub type ConnectionHandler<State> = fn(Stream, ConnectionContext<State>);
+
+/// The app's routes, state and settings, given to the connection handler along with each connection.
+pub struct ConnectionContext<State> {
+    /// The host sub-apps of the app.
+    pub subapps: Arc<Vec<SubApp<State>>>,
+    /// The sub-app used when no host sub-app matches the request.
+    pub default_subapp: Arc<SubApp<State>>,
+    /// The function used to generate error pages.
+    pub error_handler: Arc<ErrorHandler>,
+    /// The state of the app.
+    pub state: Arc<State>,
+    /// The monitor to which events are sent.
+    pub monitor: MonitorConfig,
+    /// The time within which the first request must be received.
+    pub timeout: Option<Duration>,
+    /// The maximum size of a request body, unless overridden by the route.
+    pub max_body_size: Option<usize>,
+    /// The time an idle connection is kept open waiting for another request.
+    pub keep_alive_timeout: Option<Duration>,
+    /// The maximum number of requests served over a single connection.
+    pub max_keep_alive_requests: Option<usize>,
+    /// The ranges of addresses whose forwarding headers are trusted.
+    pub trusted_proxies: Arc<Vec<Cidr>>,
+    /// Whether peers connected over a Unix domain socket are trusted to forward the origin address.
+    pub trust_unix_sockets: bool,
+    /// The connection's entry in the app's connection tracker, used to shut down gracefully.
+    pub connection: TrackedConnection,
+}
 
 /// Represents a function able to calculate whether a connection will be accepted.
-pub type ConnectionCondition<State> = fn(&mut TcpStream, Arc<State>) -> bool;
+/// The stream is `None` for connections over a Unix domain socket, which have no TCP stream.
+pub type ConnectionCondition<State> = fn(Option<&mut TcpStream>, Arc<State>) -> bool;
 
 pub use crate::handler_traits::*;
 
@@ -117,7 +165,14 @@ where
             connection_handler: client_handler,
             connection_condition: |_, _| true,
             connection_timeout: None,
+            max_body_size: None,
+            keep_alive_timeout: Some(DEFAULT_KEEP_ALIVE_TIMEOUT),
+            max_keep_alive_requests: None,
+            trusted_proxies: Arc::new(Vec::new()),
+            trust_unix_sockets: false,
+            middleware: Vec::new(),
             shutdown: None,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
             #[cfg(feature = "tls")]
             tls_config: None,
             #[cfg(feature = "tls")]
@@ -137,7 +192,14 @@ where
             connection_handler: client_handler,
             connection_condition: |_, _| true,
             connection_timeout: None,
+            max_body_size: None,
+            keep_alive_timeout: Some(DEFAULT_KEEP_ALIVE_TIMEOUT),
+            max_keep_alive_requests: None,
+            trusted_proxies: Arc::new(Vec::new()),
+            trust_unix_sockets: false,
+            middleware: Vec::new(),
             shutdown: None,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
             #[cfg(feature = "tls")]
             tls_config: None,
             #[cfg(feature = "tls")]
@@ -146,101 +208,101 @@ where
     }
 
     /// Runs the Humphrey app on the given socket address.
-    /// This function will only return if a fatal error is thrown such as the port being in use.
-    pub fn run<A>(mut self, addr: A) -> Result<(), HumphreyError>
+    /// This function will only return if a fatal error is thrown such as the port being in use, or once the app
+    ///   has been shut down using the shutdown signal, in which case a summary of the shutdown is returned.
+    pub fn run<A>(self, addr: A) -> Result<ShutdownSummary, HumphreyError>
     where
-        A: ToSocketAddrs + Clone,
+        A: ToSocketAddrs,
     {
-        let socket = TcpListener::bind(addr.clone())?;
-        let subapps = Arc::new(self.subapps);
-        let default_subapp = Arc::new(self.default_subapp);
-        let error_handler = Arc::new(self.error_handler);
+        let listener = BoundListener::Tcp(TcpListener::bind(addr)?);
 
-        self.thread_pool.register_monitor(self.monitor.clone());
-        self.thread_pool.start();
+        self.run_bound(vec![listener])
+    }
 
-        // Shared shutdown signal between socket.incoming() and shutdown signal receiver.
-        let shutdown = Arc::new(AtomicBool::new(false));
-        let shutdown_clone = shutdown.clone();
-        let main_app_thread = thread::spawn(move || {
-            for stream in socket.incoming() {
-                if shutdown_clone.load(Ordering::SeqCst) {
-                    break;
-                }
+    /// Securely runs the Humphrey app on the given socket address.
+    /// This function will only return if a fatal error is thrown such as the port being in use or the TLS certificate being invalid,
+    ///   or once the app has been shut down using the shutdown signal, in which case a summary of the shutdown is returned.
+    #[cfg(feature = "tls")]
+    pub fn run_tls<A>(self, addr: A) -> Result<ShutdownSummary, HumphreyError>
+    where
+        A: ToSocketAddrs,
+    {
+        let listener = BoundListener::Tls(TcpListener::bind(addr)?);
 
-                match stream {
-                    Ok(mut stream) => {
-                        let cloned_state = self.state.clone();
-
-                        // Check that the client is allowed to connect
-                        if (self.connection_condition)(&mut stream, cloned_state) {
-                            let cloned_state = self.state.clone();
-                            let cloned_monitor = self.monitor.clone();
-                            let cloned_subapps = subapps.clone();
-                            let cloned_default_subapp = default_subapp.clone();
-                            let cloned_error_handler = error_handler.clone();
-                            let cloned_handler = self.connection_handler;
-                            let cloned_timeout = self.connection_timeout;
-
-                            cloned_monitor.send(
-                                Event::new(EventType::ConnectionSuccess)
-                                    .with_peer_result(stream.peer_addr()),
-                            );
-
-                            // Spawn a new thread to handle the connection
-                            self.thread_pool.execute(move || {
-                                cloned_monitor.send(
-                                    Event::new(EventType::ThreadPoolProcessStarted)
-                                        .with_peer_result(stream.peer_addr()),
-                                );
-
-                                (cloned_handler)(
-                                    Stream::Tcp(stream),
-                                    cloned_subapps,
-                                    cloned_default_subapp,
-                                    cloned_error_handler,
-                                    cloned_state,
-                                    cloned_monitor,
-                                    cloned_timeout,
-                                )
-                            });
-                        } else {
-                            self.monitor.send(
-                                Event::new(EventType::ConnectionDenied)
-                                    .with_peer_result(stream.peer_addr()),
-                            );
-                        }
-                    }
-                    Err(e) => self
-                        .monitor
-                        .send(Event::new(EventType::ConnectionError).with_info(e.to_string())),
-                }
-            }
-            self.thread_pool.stop();
-        });
+        self.run_bound(vec![listener])
+    }
 
-        if let Some(s) = self.shutdown {
-            // We wait for the shutdown signal, then wake up the main app thread with a new connection
-            let _ = s.recv();
-            shutdown.store(true, Ordering::SeqCst);
-            let _ = TcpStream::connect(unspecified_socket_to_loopback(addr));
-        };
+    /// Runs the Humphrey app on the given listener, which has already been bound.
+    /// This is useful when the socket is created elsewhere, for example by binding to port 0 to get an ephemeral
+    ///   port, or when it is inherited from a service manager.
+    /// This function will only return if a fatal error is thrown, or once the app has been shut down using the
+    ///   shutdown signal, in which case a summary of the shutdown is returned.
+    pub fn run_with_listener(
+        self,
+        listener: TcpListener,
+    ) -> Result<ShutdownSummary, HumphreyError> {
+        self.run_bound(vec![BoundListener::Tcp(listener)])
+    }
+
+    /// Securely runs the Humphrey app on the given listener, which has already been bound.
+    /// This function will only return if a fatal error is thrown such as the TLS certificate being invalid, or once
+    ///   the app has been shut down using the shutdown signal, in which case a summary of the shutdown is returned.
+    #[cfg(feature = "tls")]
+    pub fn run_tls_with_listener(
+        self,
+        listener: TcpListener,
+    ) -> Result<ShutdownSummary, HumphreyError> {
+        self.run_bound(vec![BoundListener::Tls(listener)])
+    }
 
-        let _ = main_app_thread.join();
+    /// Runs the Humphrey app on all of the given listeners at once, which have already been bound, sharing its
+    ///   routes and state between them.
+    /// This function will only return if a fatal error is thrown, or once the app has been shut down using the
+    ///   shutdown signal, in which case a summary of the shutdown is returned.
+    pub fn run_with_listeners(
+        self,
+        listeners: Vec<PreboundListener>,
+    ) -> Result<ShutdownSummary, HumphreyError> {
+        if listeners.is_empty() {
+            return Err("No listeners were specified".into());
+        }
 
-        Ok(())
+        self.run_bound(listeners.into_iter().map(BoundListener::from).collect())
     }
 
-    /// Securely runs the Humphrey app on the given socket address.
-    /// This function will only return if a fatal error is thrown such as the port being in use or the TLS certificate being invalid.
-    #[cfg(feature = "tls")]
-    pub fn run_tls<A>(mut self, addr: A) -> Result<(), HumphreyError>
-    where
-        A: ToSocketAddrs + Clone,
-    {
-        use rustls::ServerConnection;
+    /// Runs the Humphrey app on all of the given listeners at once, sharing its routes and state between them.
+    /// This function will only return if a fatal error is thrown such as one of the addresses being in use, or once
+    ///   the app has been shut down using the shutdown signal, in which case a summary of the shutdown is returned.
+    pub fn run_listeners(self, listeners: &[Listener]) -> Result<ShutdownSummary, HumphreyError> {
+        if listeners.is_empty() {
+            return Err("No listeners were specified".into());
+        }
+
+        let listeners = listeners
+            .iter()
+            .map(BoundListener::bind)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.run_bound(listeners)
+    }
+
+    /// Runs the app on the given bound listeners, each of which is given its own thread to accept connections.
+    fn run_bound(
+        mut self,
+        listeners: Vec<BoundListener>,
+    ) -> Result<ShutdownSummary, HumphreyError> {
+        #[cfg(feature = "tls")]
+        let tls_config = match listeners.iter().any(BoundListener::is_tls) {
+            true => Some(
+                self.tls_config
+                    .clone()
+                    .ok_or("TLS certificate not supplied")?,
+            ),
+            false => None,
+        };
+
+        self.prepare_subapps();
 
-        let socket = TcpListener::bind(addr.clone())?;
         let subapps = Arc::new(self.subapps);
         let default_subapp = Arc::new(self.default_subapp);
         let error_handler = Arc::new(self.error_handler);
@@ -248,7 +310,8 @@ where
         self.thread_pool.register_monitor(self.monitor.clone());
         self.thread_pool.start();
 
-        if self.force_https {
+        #[cfg(feature = "tls")]
+        if self.force_https && tls_config.is_some() {
             let cloned_monitor = self.monitor.clone();
 
             if self.thread_pool.thread_count() < 2 {
@@ -260,84 +323,105 @@ where
                 .execute(|| force_https_thread(cloned_monitor).unwrap_or(()));
         }
 
-        // Shared shutdown signal between socket.incoming() and shutdown signal receiver.
+        // Shared shutdown signal between the listener threads and shutdown signal receiver.
         let shutdown = Arc::new(AtomicBool::new(false));
-        let shutdown_clone = shutdown.clone();
-        let main_app_thread = thread::spawn(move || {
-            for sock in socket.incoming() {
-                if shutdown_clone.load(Ordering::SeqCst) {
-                    break;
-                }
+        let (tx, rx) = channel();
+        let mut wakers = Vec::with_capacity(listeners.len());
 
-                match sock {
-                    Ok(mut sock) => {
-                        let cloned_state = self.state.clone();
-
-                        // Check that the client is allowed to connect
-                        if (self.connection_condition)(&mut sock, cloned_state) {
-                            let cloned_state = self.state.clone();
-                            let cloned_subapps = subapps.clone();
-                            let cloned_default_subapp = default_subapp.clone();
-                            let cloned_error_handler = error_handler.clone();
-                            let cloned_handler = self.connection_handler;
-                            let cloned_timeout = self.connection_timeout;
-                            let cloned_monitor = self.monitor.clone();
-                            let cloned_config = self
-                                .tls_config
-                                .as_ref()
-                                .expect("TLS certificate not supplied")
-                                .clone();
-
-                            cloned_monitor.send(
-                                Event::new(EventType::ConnectionSuccess)
-                                    .with_peer_result(sock.peer_addr()),
-                            );
-
-                            // Spawn a new thread to handle the connection
-                            self.thread_pool.execute(move || {
-                                cloned_monitor.send(
-                                    Event::new(EventType::ThreadPoolProcessStarted)
-                                        .with_peer_result(sock.peer_addr()),
-                                );
-
-                                let server = ServerConnection::new(cloned_config).unwrap();
-                                let tls_stream = rustls::StreamOwned::new(server, sock);
-                                let stream = Stream::Tls(tls_stream);
-
-                                (cloned_handler)(
-                                    stream,
-                                    cloned_subapps,
-                                    cloned_default_subapp,
-                                    cloned_error_handler,
-                                    cloned_state,
-                                    cloned_monitor,
-                                    cloned_timeout,
-                                )
-                            });
-                        } else {
-                            self.monitor.send(
-                                Event::new(EventType::ConnectionDenied)
-                                    .with_peer_result(sock.peer_addr()),
-                            );
-                        }
+        for listener in listeners {
+            wakers.push(listener.try_clone()?);
+
+            let tx = tx.clone();
+            let shutdown = shutdown.clone();
+            thread::spawn(move || listener.accept(tx, shutdown));
+        }
+
+        // The main app thread stops once every listener thread has stopped and dropped its sender
+        drop(tx);
+
+        let connections = ConnectionTracker::new();
+        let main_app_thread = thread::spawn(move || {
+            for incoming in rx {
+                let mut incoming = match incoming {
+                    Ok(incoming) => incoming,
+                    Err(e) => {
+                        self.monitor
+                            .send(Event::new(EventType::ConnectionError).with_info(e.to_string()));
+
+                        continue;
                     }
-                    Err(e) => self
-                        .monitor
-                        .send(Event::new(EventType::ConnectionError).with_info(e.to_string())),
+                };
+
+                // Check that the client is allowed to connect
+                let allowed =
+                    (self.connection_condition)(incoming.tcp_stream(), self.state.clone());
+
+                if !allowed {
+                    self.monitor.send(
+                        Event::new(EventType::ConnectionDenied)
+                            .with_peer_result(incoming.peer_addr()),
+                    );
+
+                    continue;
                 }
+
+                let connection = connections.register(incoming.try_clone());
+
+                #[cfg(feature = "tls")]
+                let stream = incoming.into_stream(tls_config.as_ref());
+                #[cfg(not(feature = "tls"))]
+                let stream = incoming.into_stream();
+
+                let cloned_handler = self.connection_handler;
+                let context = ConnectionContext {
+                    subapps: subapps.clone(),
+                    default_subapp: default_subapp.clone(),
+                    error_handler: error_handler.clone(),
+                    state: self.state.clone(),
+                    monitor: self.monitor.clone(),
+                    timeout: self.connection_timeout,
+                    max_body_size: self.max_body_size,
+                    keep_alive_timeout: self.keep_alive_timeout,
+                    max_keep_alive_requests: self.max_keep_alive_requests,
+                    trusted_proxies: self.trusted_proxies.clone(),
+                    trust_unix_sockets: self.trust_unix_sockets,
+                    connection,
+                };
+
+                context.monitor.send(
+                    Event::new(EventType::ConnectionSuccess).with_peer_result(stream.peer_addr()),
+                );
+
+                // Spawn a new thread to handle the connection
+                self.thread_pool.execute(move || {
+                    context.monitor.send(
+                        Event::new(EventType::ThreadPoolProcessStarted)
+                            .with_peer_result(stream.peer_addr()),
+                    );
+
+                    (cloned_handler)(stream, context)
+                });
             }
+
+            let summary = connections.drain(self.shutdown_timeout);
             self.thread_pool.stop();
+
+            summary
         });
+
         if let Some(s) = self.shutdown {
-            // We wait for the shutdown signal, then wake up the main app thread with a new connection
+            // We wait for the shutdown signal, then wake up each listener thread with a new connection
             let _ = s.recv();
             shutdown.store(true, Ordering::SeqCst);
-            let _ = TcpStream::connect(unspecified_socket_to_loopback(addr));
-        };
 
-        let _ = main_app_thread.join();
+            for waker in &wakers {
+                waker.wake();
+            }
+        };
 
-        Ok(())
+        main_app_thread
+            .join()
+            .map_err(|_| "The main app thread panicked".into())
     }
 
     /// Sets the default state for the server.
@@ -388,6 +472,63 @@ where
         self
     }
 
+    /// Adds a route and associated handler to the server which only matches requests with the given method.
+    /// Routes can include wildcards, for example `/blog/*`.
+    ///
+    /// Requests to the route with other methods are rejected with 405 Method Not Allowed,
+    ///   unless another handler for the route accepts them.
+    pub fn with_method_route<T>(mut self, method: Method, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.default_subapp = self
+            .default_subapp
+            .with_method_route(method, route, handler);
+        self
+    }
+
+    /// Adds a route and associated handler to the server which only matches `GET` requests.
+    /// `HEAD` requests to the route are also answered by the handler, with the body removed, unless a route for the
+    ///   path was added for the `HEAD` method with `with_method_route`.
+    pub fn with_get<T>(self, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.with_method_route(Method::Get, route, handler)
+    }
+
+    /// Adds a route and associated handler to the server which only matches `POST` requests.
+    pub fn with_post<T>(self, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.with_method_route(Method::Post, route, handler)
+    }
+
+    /// Adds a route and associated handler to the server which only matches `PUT` requests.
+    pub fn with_put<T>(self, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.with_method_route(Method::Put, route, handler)
+    }
+
+    /// Adds a route and associated handler to the server which only matches `PATCH` requests.
+    pub fn with_patch<T>(self, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.with_method_route(Method::Patch, route, handler)
+    }
+
+    /// Adds a route and associated handler to the server which only matches `DELETE` requests.
+    pub fn with_delete<T>(self, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.with_method_route(Method::Delete, route, handler)
+    }
+
     /// Adds a path-aware route and associated handler to the server.
     /// Routes can include wildcards, for example `/blog/*`.
     /// Will also pass the route to the handler at runtime.
@@ -399,6 +540,17 @@ where
         self
     }
 
+    /// Adds a streaming route and associated handler to the server.
+    /// Routes can include wildcards, for example `/upload/*`.
+    /// Instead of being read into memory, the request body is passed to the handler to be read as it arrives.
+    pub fn with_streaming_route<T>(mut self, route: &str, handler: T) -> Self
+    where
+        T: StreamingRequestHandler<State> + 'static,
+    {
+        self.default_subapp = self.default_subapp.with_streaming_route(route, handler);
+        self
+    }
+
     /// Adds a WebSocket route and associated handler to the server.
     /// Routes can include wildcards, for example `/ws/*`.
     /// The handler is passed the stream, state, and the request which triggered its calling.
@@ -429,6 +581,14 @@ where
         self
     }
 
+    /// Sets the amount of time requests in progress are given to finish when the app is shut down.
+    /// Idle connections are closed immediately, and connections still handling requests at the end of this time
+    ///   are closed regardless. Defaults to 30 seconds.
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
     /// Sets the error handler for the server.
     pub fn with_error_handler(mut self, handler: ErrorHandler) -> Self {
         self.error_handler = handler;
@@ -437,17 +597,94 @@ where
 
     /// Sets the connection condition, a function which decides whether to accept the connection.
     /// For example, this could be used for implementing whitelists and blacklists.
+    /// Connections over a Unix domain socket are also checked, but are given no TCP stream.
     pub fn with_connection_condition(mut self, condition: ConnectionCondition<State>) -> Self {
         self.connection_condition = condition;
         self
     }
 
-    /// Sets the connection timeout, the amount of time to wait between keep-alive requests.
+    /// Sets the connection timeout, the amount of time to wait for a request to start.
+    /// Requests which do not start in time are answered with 408 Request Timeout.
     pub fn with_connection_timeout(mut self, timeout: Option<Duration>) -> Self {
         self.connection_timeout = timeout;
         self
     }
 
+    /// Sets the keep-alive timeout, the amount of time an idle connection is kept open waiting for another request.
+    /// Idle connections are closed without a response. Defaults to five seconds, and `None` falls back to the
+    ///   connection timeout.
+    pub fn with_keep_alive_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.keep_alive_timeout = timeout;
+        self
+    }
+
+    /// Sets the maximum number of requests which can be served on one connection.
+    /// The connection is closed after the response to the last request. Defaults to no maximum.
+    pub fn with_max_keep_alive_requests(mut self, max_requests: Option<usize>) -> Self {
+        self.max_keep_alive_requests = max_requests;
+        self
+    }
+
+    /// Sets the trusted proxies, the ranges of addresses from which forwarding headers such as `X-Forwarded-For`
+    ///   are honoured when finding the origin address of a request. Defaults to none, so the headers are ignored.
+    ///
+    /// Peers connected over a Unix domain socket have no address, so are only trusted if `with_trust_unix_sockets`
+    ///   is enabled.
+    pub fn with_trusted_proxies(mut self, proxies: Vec<Cidr>) -> Self {
+        self.trusted_proxies = Arc::new(proxies);
+        self
+    }
+
+    /// Sets whether peers connected over a Unix domain socket are trusted to forward the origin address of requests,
+    ///   like the trusted proxies. This should only be enabled if the sockets can only be connected to by a proxy.
+    ///   Defaults to `false`.
+    pub fn with_trust_unix_sockets(mut self, trust: bool) -> Self {
+        self.trust_unix_sockets = trust;
+        self
+    }
+
+    /// Sets the maximum size of request bodies in bytes, which can be overridden for individual routes.
+    /// Requests with larger bodies are rejected with 413 Payload Too Large. Defaults to no maximum.
+    pub fn with_max_body_size(mut self, max_body_size: Option<usize>) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Sets the maximum size of the request body in bytes for the specified route, overriding the app's maximum.
+    pub fn with_max_body_size_config(mut self, route: &str, max_body_size: usize) -> Self {
+        self.default_subapp = self
+            .default_subapp
+            .with_max_body_size_config(route, max_body_size);
+        self
+    }
+
+    /// Adds middleware which runs for every request to the app, before any sub-app or route middleware.
+    pub fn with_middleware<T>(mut self, middleware: T) -> Self
+    where
+        T: Middleware<State> + 'static,
+    {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Enables compression of responses with the given configuration.
+    ///
+    /// Responses are compressed after every other middleware has run, so the body which is compressed is the one
+    ///   which would otherwise have been sent.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.middleware.insert(0, Arc::new(compression));
+        self
+    }
+
+    /// Adds middleware which runs for requests to a given route, after the app's middleware.
+    pub fn with_route_middleware<T>(mut self, route: &str, middleware: T) -> Self
+    where
+        T: Middleware<State> + 'static,
+    {
+        self.default_subapp = self.default_subapp.with_route_middleware(route, middleware);
+        self
+    }
+
     /// Sets the CORS configuration for the app.
     ///
     /// This overrides the CORS configuration for existing and future individual routes.
@@ -536,6 +773,21 @@ where
         self
     }
 
+    /// Prepares the sub-apps to be run by adding the app's middleware to them and compiling their routes
+    ///   so they can be looked up quickly.
+    fn prepare_subapps(&mut self) {
+        for subapp in self
+            .subapps
+            .iter_mut()
+            .chain(std::iter::once(&mut self.default_subapp))
+        {
+            subapp
+                .middleware
+                .splice(0..0, self.middleware.iter().cloned());
+            subapp.compile();
+        }
+    }
+
     /// Gets a reference to the app's state.
     /// This should only be used in the main thread, as the state is passed to request handlers otherwise.
     pub fn get_state(&self) -> Arc<State> {
@@ -544,18 +796,25 @@ where
 }
 
 /// Handles a connection with a client.
-/// The connection will be opened upon the first request and closed as soon as a request is
-///   received without the `Connection: Keep-Alive` header.
-#[allow(clippy::too_many_arguments)]
-fn client_handler<State>(
-    mut stream: Stream,
-    subapps: Arc<Vec<SubApp<State>>>,
-    default_subapp: Arc<SubApp<State>>,
-    error_handler: Arc<ErrorHandler>,
-    state: Arc<State>,
-    monitor: MonitorConfig,
-    timeout: Option<Duration>,
-) {
+/// The connection is kept open between requests unless the client asks for it to be closed, as determined by
+///   `Request::is_keep_alive`, until it is idle for the keep-alive timeout or the maximum number of requests
+///   has been served. Pipelined requests are read from the same buffer, so none of their data is lost.
+fn client_handler<State>(stream: Stream, context: ConnectionContext<State>) {
+    let ConnectionContext {
+        subapps,
+        default_subapp,
+        error_handler,
+        state,
+        monitor,
+        timeout,
+        max_body_size,
+        keep_alive_timeout,
+        max_keep_alive_requests,
+        trusted_proxies,
+        trust_unix_sockets,
+        connection,
+    } = context;
+
     let addr = if let Ok(addr) = stream.peer_addr() {
         addr
     } else {
@@ -564,13 +823,50 @@ fn client_handler<State>(
         return;
     };
 
+    // Peers connected over a Unix domain socket are identified by the path of the socket, and have no address to
+    //   match against the trusted proxies
+    let unix_socket = stream.unix_socket();
+    let tls = stream.is_tls();
+    let peer_trusted = if stream.is_unix() {
+        trust_unix_sockets
+    } else {
+        is_trusted(&addr.ip(), &trusted_proxies)
+    };
+
+    // The reader persists between requests, since it may have buffered the start of the next request
+    let mut reader = BufReader::new(stream);
+    let mut requests_served: usize = 0;
+
     loop {
-        // Parses the request from the stream
-        let request = match timeout {
-            Some(timeout) => Request::from_stream_with_timeout(&mut stream, addr, timeout),
-            None => Request::from_stream(&mut stream, addr),
+        // The first request must start within the connection timeout, and later ones within the keep-alive timeout
+        let request_timeout = match requests_served {
+            0 => timeout,
+            _ => keep_alive_timeout.or(timeout),
         };
 
+        // Parses the start line and headers of the request, leaving the body to be read once the route is known
+        let request = Request::head_from_stream(&mut reader, addr, request_timeout);
+
+        // Idle connections are closed quietly rather than answered with 408 Request Timeout
+        if requests_served > 0 && matches!(request, Err(RequestError::Timeout)) {
+            break;
+        }
+
+        // Connections closed by a shutdown while waiting for a request are also closed quietly
+        if request.is_err() && connection.is_draining() {
+            break;
+        }
+
+        connection.start_request();
+
+        let request = request.map(|mut request| {
+            request.address =
+                Address::from_forwarded(&request.headers, addr, &trusted_proxies, peer_trusted);
+            request.address.unix_socket = unix_socket.clone();
+            request.address.tls = tls;
+            request
+        });
+
         let cloned_state = state.clone();
 
         // If the request is valid an is a WebSocket request, call the corresponding handler
@@ -578,73 +874,198 @@ fn client_handler<State>(
             if req.headers.get(&HeaderType::Upgrade) == Some("websocket") {
                 monitor.send(Event::new(EventType::WebsocketConnectionRequested).with_peer(addr));
 
-                call_websocket_handler(req, &subapps, &default_subapp, cloned_state, stream);
+                call_websocket_handler(
+                    req,
+                    &subapps,
+                    &default_subapp,
+                    cloned_state,
+                    reader.into_inner(),
+                );
 
                 monitor.send(Event::new(EventType::WebsocketConnectionClosed).with_peer(addr));
                 break;
             }
         }
 
+        // HEAD requests are answered by a route registered for them if there is one, and otherwise by the GET handler,
+        //   with the body removed from the response either way
+        let is_head = matches!(&request, Ok(request) if request.method == Method::Head);
+        let request = request.map(|mut request| {
+            if is_implicit_head(&request, &subapps, &default_subapp) {
+                request.method = Method::Get;
+            }
+
+            request
+        });
+
+        let handler = request
+            .as_ref()
+            .ok()
+            .and_then(|request| get_handler(request, &subapps, &default_subapp));
+        let max_body_size = handler
+            .and_then(|handler| handler.max_body_size)
+            .or(max_body_size);
+
+        // Capture the parameters from the path using the matched route
+        let request = request.map(|mut request| {
+            if let Some(params) =
+                handler.and_then(|handler| handler.route.route_params(&request.uri))
+            {
+                request.params = params;
+            }
+
+            request
+        });
+
+        // Read the body of the request, unless the route streams it to the handler
+        let mut decoder: Option<BodyDecoder> = None;
+        let request = match request {
+            Ok(mut request) => match handler.map(|handler| &handler.handler) {
+                Some(Handler::Streaming(_)) if request.method != Method::Options => {
+                    BodyDecoder::new(&request.headers, max_body_size).map(|body_decoder| {
+                        decoder = Some(body_decoder);
+                        request
+                    })
+                }
+                _ => request
+                    .read_body(&mut reader, max_body_size)
+                    .map(|_| request),
+            },
+            Err(e) => Err(e),
+        };
+
         // Get the keep alive information from the request before it is consumed by the handler
-        let keep_alive = if let Ok(request) = &request {
-            if let Some(connection) = request.headers.get(&HeaderType::Connection) {
-                connection.to_ascii_lowercase() == "keep-alive"
-            } else {
-                false
+        let keep_alive = match &request {
+            Ok(request) => {
+                request.is_keep_alive()
+                    && max_keep_alive_requests.is_none_or(|max| requests_served + 1 < max)
+                    && !connection.is_draining()
             }
-        } else {
-            false
+            Err(_) => false,
+        };
+
+        // Whether the whole request body has been read, since otherwise the connection cannot be reused
+        let mut body_complete = true;
+
+        // Find the middleware for the request, with the sub-app's middleware before the route's
+        let middleware: Vec<&Arc<dyn Middleware<State>>> = match &request {
+            Ok(request) => get_subapp(request, &subapps, &default_subapp)
+                .middleware
+                .iter()
+                .chain(
+                    handler
+                        .into_iter()
+                        .flat_map(|handler| handler.middleware.iter()),
+                )
+                .collect(),
+            Err(_) => Vec::new(),
         };
 
+        // Run the middleware before the request is handled, stopping if any of them responds
+        let mut request = request;
+        let mut middleware_run = 0;
+        let mut early_response: Option<Response> = None;
+
+        if let Ok(request) = &mut request {
+            for middleware in &middleware {
+                middleware_run += 1;
+
+                if let Some(response) = middleware.before(request, state.clone()) {
+                    early_response = Some(response);
+                    break;
+                }
+            }
+        }
+
+        // If the request was answered by middleware, a streamed body will not have been read
+        if early_response.is_some() && decoder.as_ref().is_some_and(BodyDecoder::has_body) {
+            body_complete = false;
+        }
+
         // Generate the response based on the handlers
         let response = match &request {
-            Ok(request) if request.method == Method::Options => {
-                let handler = get_handler(request, &subapps, &default_subapp);
-
-                match handler {
-                    Some(handler) => {
-                        let mut response = Response::empty(StatusCode::NoContent)
-                            .with_header(HeaderType::Date, DateTime::now().to_string())
-                            .with_header(HeaderType::Server, "Humphrey")
-                            .with_header(
-                                HeaderType::Connection,
-                                match keep_alive {
-                                    true => "Keep-Alive",
-                                    false => "Close",
-                                },
-                            );
+            Ok(request) => {
+                let mut response = match (early_response.take(), handler) {
+                    (Some(response), _) => response,
+                    (None, Some(handler)) if request.method == Method::Options => {
+                        let mut response = Response::empty(StatusCode::NoContent);
 
                         handler.cors.set_headers(&mut response.headers);
 
+                        // Report the methods accepted by the route, unless the CORS configuration specifies them
+                        if let Some(methods) = get_subapp(request, &subapps, &default_subapp)
+                            .allowed_methods(&request.uri)
+                        {
+                            let methods = join_methods(&methods);
+
+                            if response
+                                .headers
+                                .get(HeaderType::AccessControlAllowMethods)
+                                .is_none()
+                            {
+                                response
+                                    .headers
+                                    .add(HeaderType::AccessControlAllowMethods, &methods);
+                            }
+
+                            response.headers.add(HeaderType::Allow, methods);
+                        }
+
                         response
                     }
-                    None => error_handler(StatusCode::NotFound),
-                }
-            }
-            Ok(request) => {
-                let handler = get_handler(request, &subapps, &default_subapp);
-
-                let mut response = match handler {
-                    Some(handler) => {
-                        let mut response: Response =
-                            handler.handler.serve(request.clone(), state.clone());
+                    (None, Some(handler)) => {
+                        let mut response: Response = match (&handler.handler, decoder.take()) {
+                            (Handler::Buffered(handler), _) => {
+                                handler.serve(request.clone(), state.clone())
+                            }
+                            (Handler::Streaming(handler), Some(decoder)) => {
+                                let mut body = RequestBody::new(&mut reader, decoder);
+                                let response =
+                                    handler.serve(request.clone(), &mut body, state.clone());
+                                body_complete = body.is_complete();
+
+                                response
+                            }
+                            (Handler::Streaming(_), None) => {
+                                error_handler(StatusCode::InternalError)
+                            }
+                        };
 
                         handler.cors.set_headers(&mut response.headers);
 
                         response
                     }
-                    None => error_handler(StatusCode::NotFound),
+                    (None, None) => match get_subapp(request, &subapps, &default_subapp)
+                        .allowed_methods(&request.uri)
+                    {
+                        Some(methods) => error_handler(StatusCode::MethodNotAllowed)
+                            .with_header(HeaderType::Allow, join_methods(&methods)),
+                        None => error_handler(StatusCode::NotFound),
+                    },
                 };
 
+                // Run the middleware after the response has been generated, in reverse order
+                for middleware in middleware[..middleware_run].iter().rev() {
+                    middleware.after(request, &mut response, state.clone());
+                }
+
+                // The connection cannot be reused if the handler did not read the whole request body
+                if !body_complete {
+                    response.headers.remove(HeaderType::Connection);
+                    response.headers.add(HeaderType::Connection, "Close");
+                }
+
                 // Automatically generate required headers
                 match response.headers.get_mut(HeaderType::Connection) {
                     Some(_) => (),
                     None => {
-                        if let Some(connection) = &request.headers.get(&HeaderType::Connection) {
-                            response.headers.add(HeaderType::Connection, connection);
-                        } else {
-                            response.headers.add(HeaderType::Connection, "Close");
-                        }
+                        response.headers.add(
+                            HeaderType::Connection,
+                            match keep_alive {
+                                true => "Keep-Alive",
+                                false => "Close",
+                            },
+                        );
                     }
                 }
 
@@ -666,33 +1087,58 @@ fn client_handler<State>(
 
                 match response.headers.get_mut(HeaderType::ContentLength) {
                     Some(_) => (),
-                    None => {
-                        response
-                            .headers
-                            .add(HeaderType::ContentLength, response.body.len().to_string());
-                    }
+                    None if !response.status_code.allows_body() => (),
+                    None => match response.body_length() {
+                        Some(length) => {
+                            response
+                                .headers
+                                .add(HeaderType::ContentLength, length.to_string());
+                        }
+                        None if request.version == "HTTP/1.0" => {
+                            // Chunked encoding is unavailable, so the end of the body is marked by closing the connection
+                            response.headers.remove(HeaderType::Connection);
+                            response.headers.add(HeaderType::Connection, "Close");
+                        }
+                        None => {
+                            response
+                                .headers
+                                .add(HeaderType::TransferEncoding, "chunked");
+                        }
+                    },
                 }
 
                 // Set HTTP version
                 response.version = request.version.clone();
 
+                // Remove the body of responses to HEAD requests, keeping the headers which describe it
+                if is_head {
+                    response.body.clear();
+                    response.take_stream();
+                }
+
                 response
             }
             Err(e) => match e {
                 RequestError::Request => error_handler(StatusCode::BadRequest),
                 RequestError::Timeout => error_handler(StatusCode::RequestTimeout),
+                RequestError::TooLarge => error_handler(StatusCode::RequestEntityTooLarge),
                 RequestError::Disconnected => return,
                 RequestError::Stream => {
                     return monitor.send(Event::new(EventType::RequestServedError))
                 }
-            },
+            }
+            .with_header(HeaderType::Connection, "Close"),
         };
 
         // Write the response to the stream
         let status = response.status_code;
-        let response_bytes: Vec<u8> = response.into();
+        let keep_alive = keep_alive
+            && !response
+                .headers
+                .get(HeaderType::Connection)
+                .is_some_and(|connection| connection.eq_ignore_ascii_case("close"));
 
-        if let Err(e) = stream.write_all(&response_bytes) {
+        if let Err(e) = response.write_to(reader.get_mut()) {
             monitor.send(
                 Event::new(EventType::RequestServedError)
                     .with_peer(addr)
@@ -732,8 +1178,10 @@ fn client_handler<State>(
             }
         }
 
-        // If the request specified to keep the connection open, respect this
-        if !keep_alive {
+        requests_served += 1;
+
+        // If the request specified to keep the connection open, respect this unless the app is shutting down
+        if !connection.finish_request() || !keep_alive {
             break;
         }
 
@@ -743,40 +1191,67 @@ fn client_handler<State>(
     monitor.send(Event::new(EventType::ConnectionClosed).with_peer(addr));
 }
 
-/// Gets the correct handler for the given request.
-pub(crate) fn get_handler<'a, State>(
-    request: &'a Request,
+/// Gets the sub-app which should handle the given request.
+///
+/// This is the sub-app matching the request's host if it has a route for the path, otherwise the default sub-app.
+pub(crate) fn get_subapp<'a, State>(
+    request: &Request,
     subapps: &'a [SubApp<State>],
     default_subapp: &'a SubApp<State>,
-) -> Option<&'a RouteHandler<State>> {
+) -> &'a SubApp<State> {
     // Iterate over the sub-apps and find the one which matches the host
     if let Some(host) = request.headers.get(&HeaderType::Host) {
         if let Some(subapp) = subapps
             .iter()
             .find(|subapp| wildcard_match(&subapp.host, host))
         {
-            // If the sub-app has a handler for this route, call it
-            if let Some(handler) = subapp
-                .routes // Get the routes of the sub-app
-                .iter() // Iterate over the routes
-                .find(|route| route.route.route_matches(&request.uri))
-            // Find the route that matches
-            {
-                return Some(handler);
+            // If the sub-app has a route for this path, use it
+            if subapp.has_route(&request.uri) {
+                return subapp;
             }
         }
     }
 
-    // If no sub-app was found, try to use the handler on the default sub-app
-    if let Some(handler) = default_subapp
-        .routes
-        .iter()
-        .find(|route| route.route.route_matches(&request.uri))
-    {
-        return Some(handler);
+    // If no sub-app was found, use the default sub-app
+    default_subapp
+}
+
+/// Gets the correct handler for the given request.
+///
+/// `OPTIONS` requests match a route regardless of its methods, since they are answered automatically. `HEAD` requests
+///   match a route registered for the `HEAD` method in preference to an earlier one which accepts every method.
+pub(crate) fn get_handler<'a, State>(
+    request: &Request,
+    subapps: &'a [SubApp<State>],
+    default_subapp: &'a SubApp<State>,
+) -> Option<&'a RouteHandler<State>> {
+    let subapp = get_subapp(request, subapps, default_subapp);
+
+    if request.method == Method::Head {
+        if let Some(route) = subapp
+            .matching_routes(&request.uri)
+            .find(|route| route.method_registered(&Method::Head))
+        {
+            return Some(route);
+        }
     }
 
-    None
+    // Find the first route which matches the path and accepts the method
+    subapp
+        .matching_routes(&request.uri)
+        .find(|route| request.method == Method::Options || route.method_matches(&request.method))
+}
+
+/// Returns `true` if the request is a `HEAD` request which should be answered by the `GET` handler for its path,
+///   since no route was registered for `HEAD` requests to it.
+pub(crate) fn is_implicit_head<State>(
+    request: &Request,
+    subapps: &[SubApp<State>],
+    default_subapp: &SubApp<State>,
+) -> bool {
+    request.method == Method::Head
+        && get_handler(request, subapps, default_subapp)
+            .is_none_or(|route| !route.method_registered(&Method::Head))
 }
 
 /// Calls the correct WebSocket handler for the given request.
@@ -848,7 +1323,7 @@ fn force_https_thread(monitor: MonitorConfig) -> Result<(), Box<dyn std::error::
 
 /// The default error handler for every Humphrey app.
 /// This can be overridden by using the `with_error_handler` method when building the app.
-pub(crate) fn error_handler(status_code: StatusCode) -> Response {
+pub fn error_handler(status_code: StatusCode) -> Response {
     let body = format!(
         "<html><body><h1>{} {}</h1></body></html>",
         Into::<u16>::into(status_code),
@@ -871,3 +1346,179 @@ where
     }
     socket
 }
+
+/// A listener which has been bound to its address, ready to accept connections.
+///
+/// Unix domain sockets created by the app hold their path, so they can be removed when it shuts down.
+enum BoundListener {
+    Tcp(TcpListener),
+    #[cfg(feature = "tls")]
+    Tls(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, Option<PathBuf>),
+}
+
+/// A connection accepted by one of the app's listeners.
+enum Incoming {
+    Tcp(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl BoundListener {
+    /// Binds the listener to its address.
+    fn bind(listener: &Listener) -> Result<Self, HumphreyError> {
+        Ok(match listener {
+            Listener::Tcp(addr) => Self::Tcp(TcpListener::bind(addr)?),
+            #[cfg(feature = "tls")]
+            Listener::Tls(addr) => Self::Tls(TcpListener::bind(addr)?),
+            #[cfg(unix)]
+            Listener::Unix(path) => {
+                remove_stale_socket(path)?;
+                Self::Unix(UnixListener::bind(path)?, Some(path.clone()))
+            }
+        })
+    }
+
+    /// Returns `true` if connections accepted by the listener are secured with TLS.
+    #[cfg(feature = "tls")]
+    fn is_tls(&self) -> bool {
+        matches!(self, Self::Tls(_))
+    }
+
+    /// Creates a new handle to the listener, which can be used to wake it up.
+    fn try_clone(&self) -> std::io::Result<Self> {
+        Ok(match self {
+            Self::Tcp(listener) => Self::Tcp(listener.try_clone()?),
+            #[cfg(feature = "tls")]
+            Self::Tls(listener) => Self::Tls(listener.try_clone()?),
+            #[cfg(unix)]
+            Self::Unix(listener, path) => Self::Unix(listener.try_clone()?, path.clone()),
+        })
+    }
+
+    /// Accepts connections until the app is shut down, sending them to the main app thread.
+    ///
+    /// The sender is only dropped once the listener has been cleaned up, so the app does not finish shutting down
+    ///   before its Unix domain sockets have been removed.
+    fn accept(self, tx: Sender<std::io::Result<Incoming>>, shutdown: Arc<AtomicBool>) {
+        match self {
+            Self::Tcp(listener) => {
+                accept_connections(listener.incoming(), Incoming::Tcp, &tx, shutdown)
+            }
+            #[cfg(feature = "tls")]
+            Self::Tls(listener) => {
+                accept_connections(listener.incoming(), Incoming::Tls, &tx, shutdown)
+            }
+            #[cfg(unix)]
+            Self::Unix(listener, path) => {
+                accept_connections(listener.incoming(), Incoming::Unix, &tx, shutdown);
+
+                if let Some(path) = path {
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+        }
+    }
+
+    /// Connects to the listener, waking up its thread so it can see that the app is shutting down.
+    fn wake(&self) {
+        match self {
+            Self::Tcp(listener) => wake_tcp_listener(listener),
+            #[cfg(feature = "tls")]
+            Self::Tls(listener) => wake_tcp_listener(listener),
+            #[cfg(unix)]
+            Self::Unix(listener, _) => {
+                if let Ok(addr) = listener.local_addr() {
+                    let _ = UnixStream::connect_addr(&addr);
+                }
+            }
+        }
+    }
+}
+
+impl From<PreboundListener> for BoundListener {
+    fn from(listener: PreboundListener) -> Self {
+        match listener {
+            PreboundListener::Tcp(listener) => Self::Tcp(listener),
+            #[cfg(feature = "tls")]
+            PreboundListener::Tls(listener) => Self::Tls(listener),
+            #[cfg(unix)]
+            PreboundListener::Unix(listener) => Self::Unix(listener, None),
+        }
+    }
+}
+
+impl Incoming {
+    /// Returns the underlying TCP stream, or `None` if the connection is not over TCP.
+    fn tcp_stream(&mut self) -> Option<&mut TcpStream> {
+        match self {
+            Self::Tcp(stream) => Some(stream),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => Some(stream),
+            #[cfg(unix)]
+            Self::Unix(_) => None,
+        }
+    }
+
+    /// Returns the socket address of the remote peer of the connection.
+    fn peer_addr(&self) -> std::io::Result<SocketAddr> {
+        match self {
+            Self::Tcp(stream) => stream.peer_addr(),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => stream.peer_addr(),
+            #[cfg(unix)]
+            Self::Unix(_) => Ok(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))),
+        }
+    }
+
+    /// Creates a new handle to the underlying socket, which can be used to close it from another thread.
+    fn try_clone(&self) -> Option<Stream> {
+        match self {
+            Self::Tcp(stream) => stream.try_clone().ok().map(Stream::Tcp),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => stream.try_clone().ok().map(Stream::Tcp),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.try_clone().ok().map(Stream::Unix),
+        }
+    }
+
+    /// Converts the connection into a stream, starting a TLS session if the listener is secure.
+    fn into_stream(self, #[cfg(feature = "tls")] tls_config: Option<&Arc<ServerConfig>>) -> Stream {
+        match self {
+            Self::Tcp(stream) => Stream::Tcp(stream),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => {
+                let config = tls_config.expect("TLS certificate not supplied").clone();
+                let server = rustls::ServerConnection::new(config).unwrap();
+
+                Stream::Tls(rustls::StreamOwned::new(server, stream))
+            }
+            #[cfg(unix)]
+            Self::Unix(stream) => Stream::Unix(stream),
+        }
+    }
+}
+
+/// Sends the connections from the listener to the main app thread, stopping once the app is shut down.
+fn accept_connections<S>(
+    incoming: impl Iterator<Item = std::io::Result<S>>,
+    wrap: fn(S) -> Incoming,
+    tx: &Sender<std::io::Result<Incoming>>,
+    shutdown: Arc<AtomicBool>,
+) {
+    for stream in incoming {
+        if shutdown.load(Ordering::SeqCst) || tx.send(stream.map(wrap)).is_err() {
+            break;
+        }
+    }
+}
+
+/// Connects to the TCP listener to wake up its thread.
+fn wake_tcp_listener(listener: &TcpListener) {
+    if let Ok(addr) = listener.local_addr() {
+        let _ = TcpStream::connect(unspecified_socket_to_loopback(addr));
+    }
+}
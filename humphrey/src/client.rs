@@ -4,6 +4,7 @@ use crate::http::address::Address;
 use crate::http::cookie::Cookie;
 use crate::http::headers::{Header, HeaderLike, HeaderType, Headers};
 use crate::http::method::Method;
+use crate::http::params::Params;
 use crate::http::{Request, Response, StatusCode};
 
 use std::error::Error;
@@ -36,7 +37,7 @@ impl Client {
     }
 
     /// Creates a GET request to the given URL.
-    pub fn get(&mut self, url: impl AsRef<str>) -> Result<ClientRequest, Box<dyn Error>> {
+    pub fn get(&mut self, url: impl AsRef<str>) -> Result<ClientRequest<'_>, Box<dyn Error>> {
         let url = Self::parse_url(url).ok_or("Invalid URL")?;
         let request = Request {
             method: Method::Get,
@@ -46,6 +47,31 @@ impl Client {
             version: "HTTP/1.1".to_string(),
             content: None,
             address: Address::new(url.host).unwrap(),
+            params: Params::new(),
+        };
+
+        Ok(ClientRequest {
+            address: url.host,
+            client: self,
+            protocol: url.protocol,
+            request,
+            follow_redirects: false,
+            cookies: Vec::new(),
+        })
+    }
+
+    /// Creates a HEAD request to the given URL.
+    pub fn head(&mut self, url: impl AsRef<str>) -> Result<ClientRequest<'_>, Box<dyn Error>> {
+        let url = Self::parse_url(url).ok_or("Invalid URL")?;
+        let request = Request {
+            method: Method::Head,
+            uri: url.path,
+            headers: url.host_headers,
+            query: url.query,
+            version: "HTTP/1.1".to_string(),
+            content: None,
+            address: Address::new(url.host).unwrap(),
+            params: Params::new(),
         };
 
         Ok(ClientRequest {
@@ -63,7 +89,7 @@ impl Client {
         &mut self,
         url: impl AsRef<str>,
         data: Vec<u8>,
-    ) -> Result<ClientRequest, Box<dyn Error>> {
+    ) -> Result<ClientRequest<'_>, Box<dyn Error>> {
         let url = Self::parse_url(url).ok_or("Invalid URL")?;
         let content_length = Header::new("Content-Length", data.len().to_string());
 
@@ -75,6 +101,7 @@ impl Client {
             version: "HTTP/1.1".to_string(),
             content: Some(data),
             address: Address::new(url.host).unwrap(),
+            params: Params::new(),
         };
 
         request.headers.push(content_length);
@@ -94,7 +121,7 @@ impl Client {
         &mut self,
         url: impl AsRef<str>,
         data: Vec<u8>,
-    ) -> Result<ClientRequest, Box<dyn Error>> {
+    ) -> Result<ClientRequest<'_>, Box<dyn Error>> {
         let url = Self::parse_url(url).ok_or("Invalid URL")?;
         let content_length = Header::new("Content-Length", data.len().to_string());
 
@@ -106,6 +133,39 @@ impl Client {
             version: "HTTP/1.1".to_string(),
             content: Some(data),
             address: Address::new(url.host).unwrap(),
+            params: Params::new(),
+        };
+
+        request.headers.push(content_length);
+
+        Ok(ClientRequest {
+            address: url.host,
+            client: self,
+            protocol: url.protocol,
+            request,
+            follow_redirects: false,
+            cookies: Vec::new(),
+        })
+    }
+
+    /// Creates a PATCH request to the given URL.
+    pub fn patch(
+        &mut self,
+        url: impl AsRef<str>,
+        data: Vec<u8>,
+    ) -> Result<ClientRequest<'_>, Box<dyn Error>> {
+        let url = Self::parse_url(url).ok_or("Invalid URL")?;
+        let content_length = Header::new("Content-Length", data.len().to_string());
+
+        let mut request = Request {
+            method: Method::Patch,
+            uri: url.path,
+            headers: url.host_headers,
+            query: url.query,
+            version: "HTTP/1.1".to_string(),
+            content: Some(data),
+            address: Address::new(url.host).unwrap(),
+            params: Params::new(),
         };
 
         request.headers.push(content_length);
@@ -121,7 +181,7 @@ impl Client {
     }
 
     /// Creates a DELETE request to the given URL.
-    pub fn delete(&mut self, url: impl AsRef<str>) -> Result<ClientRequest, Box<dyn Error>> {
+    pub fn delete(&mut self, url: impl AsRef<str>) -> Result<ClientRequest<'_>, Box<dyn Error>> {
         let url = Self::parse_url(url).ok_or("Invalid URL")?;
         let request = Request {
             method: Method::Delete,
@@ -131,6 +191,7 @@ impl Client {
             version: "HTTP/1.1".to_string(),
             content: None,
             address: Address::new(url.host).unwrap(),
+            params: Params::new(),
         };
 
         Ok(ClientRequest {
@@ -150,10 +211,15 @@ impl Client {
         request: Request,
     ) -> Result<Response, Box<dyn Error>> {
         let mut stream = TcpStream::connect(address)?;
+        let is_head = request.method == Method::Head;
         let request_bytes: Vec<u8> = request.into();
         stream.write_all(&request_bytes)?;
 
-        let response = Response::from_stream(&mut stream)?;
+        let response = if is_head {
+            Response::from_stream_without_body(&mut stream)?
+        } else {
+            Response::from_stream(&mut stream)?
+        };
 
         Ok(response)
     }
@@ -201,10 +267,15 @@ impl Client {
         let sock = TcpStream::connect(address)?;
         let mut tls = StreamOwned::new(conn, sock);
 
+        let is_head = request.method == Method::Head;
         let request_bytes: Vec<u8> = request.into();
         tls.write_all(&request_bytes)?;
 
-        let response = Response::from_stream(&mut tls)?;
+        let response = if is_head {
+            Response::from_stream_without_body(&mut tls)?
+        } else {
+            Response::from_stream(&mut tls)?
+        };
 
         Ok(response)
     }
@@ -327,6 +398,7 @@ impl<'a> ClientRequest<'a> {
                             version: "HTTP/1.1".to_string(),
                             content: self.request.content,
                             address: Address::new(new_url.host).unwrap(),
+                            params: Params::new(),
                         };
 
                         self.protocol = new_url.protocol;
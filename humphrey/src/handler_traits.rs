@@ -1,5 +1,6 @@
 //! Defines traits for handler functions.
 
+use crate::http::body::RequestBody;
 use crate::http::{Request, Response};
 use crate::stream::Stream;
 
@@ -88,3 +89,63 @@ where
         self(request, state, route)
     }
 }
+
+/// Represents a function able to handle a request whose body is streamed from the client.
+/// It is passed the request without its content, the request body to read from, and the app's state,
+///   and must return a response.
+///
+/// ## Example
+/// A streaming request handler which saves the body to a file would be as follows:
+/// ```
+/// fn handler(_: Request, body: &mut RequestBody, _: Arc<()>) -> Response {
+///     let mut file = File::create("upload.bin").unwrap();
+///     std::io::copy(body, &mut file).unwrap();
+///
+///     Response::new(StatusCode::OK, b"Uploaded")
+/// }
+/// ```
+pub trait StreamingRequestHandler<State>: Send + Sync {
+    #[allow(missing_docs)]
+    fn serve(&self, request: Request, body: &mut RequestBody, state: Arc<State>) -> Response;
+}
+impl<F, State> StreamingRequestHandler<State> for F
+where
+    F: Fn(Request, &mut RequestBody, Arc<State>) -> Response + Send + Sync,
+{
+    fn serve(&self, request: Request, body: &mut RequestBody, state: Arc<State>) -> Response {
+        self(request, body, state)
+    }
+}
+
+/// Represents middleware which runs before and after requests are handled.
+/// Middleware can be registered for the whole app, for a sub-app, or for an individual route.
+///
+/// Before the request is handled, `before` is called with mutable access to the request. Returning a response
+///   from it short-circuits the request, so the handler and any later middleware are skipped.
+/// After the response has been generated, `after` is called with mutable access to the response, in the
+///   reverse order to `before`, for every middleware whose `before` was called.
+///
+/// ## Example
+/// A middleware which adds a header to every response would be as follows:
+/// ```
+/// struct PoweredBy;
+///
+/// impl Middleware<()> for PoweredBy {
+///     fn after(&self, _: &Request, response: &mut Response, _: Arc<()>) {
+///         response.headers.add("X-Powered-By", "Humphrey");
+///     }
+/// }
+/// ```
+pub trait Middleware<State>: Send + Sync {
+    /// Called before the request is handled.
+    /// Returning a response skips the handler and sends the response instead.
+    fn before(&self, request: &mut Request, state: Arc<State>) -> Option<Response> {
+        let _ = (request, state);
+        None
+    }
+
+    /// Called after the response has been generated.
+    fn after(&self, request: &Request, response: &mut Response, state: Arc<State>) {
+        let _ = (request, response, state);
+    }
+}
@@ -1,14 +1,16 @@
 //! Provides a number of useful handlers for Humphrey apps.
 
 use crate::app::error_handler;
+use crate::http::conditional::Validators;
 use crate::http::headers::HeaderType;
 use crate::http::mime::MimeType;
+use crate::http::range::{serve_file_ranges, serve_ranges};
 use crate::http::{Request, Response, StatusCode};
 use crate::route::{try_find_path, LocatedPath};
 
 use std::fs::File;
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 const INDEX_FILES: [&str; 2] = ["index.html", "index.htm"];
@@ -17,22 +19,8 @@ const INDEX_FILES: [&str; 2] = ["index.html", "index.htm"];
 pub fn serve_file<T>(file_path: &'static str) -> impl Fn(Request, Arc<T>) -> Response {
     let path_buf = PathBuf::from(file_path);
 
-    move |_, _| {
-        if let Ok(mut file) = File::open(&path_buf) {
-            let mut buf = Vec::new();
-            if file.read_to_end(&mut buf).is_ok() {
-                return if let Some(extension) = path_buf.extension() {
-                    Response::new(StatusCode::OK, buf).with_header(
-                        HeaderType::ContentType,
-                        MimeType::from_extension(extension.to_str().unwrap()).to_string(),
-                    )
-                } else {
-                    Response::new(StatusCode::OK, buf)
-                };
-            }
-        }
-
-        error_handler(StatusCode::NotFound)
+    move |request: Request, _| {
+        file_response(&request, &path_buf).unwrap_or_else(|| error_handler(StatusCode::NotFound))
     }
 }
 
@@ -52,21 +40,7 @@ pub fn serve_as_file_path<T>(directory_path: &'static str) -> impl Fn(Request, A
 
         let path_buf = PathBuf::from(path);
 
-        if let Ok(mut file) = File::open(&path_buf) {
-            let mut buf = Vec::new();
-            if file.read_to_end(&mut buf).is_ok() {
-                return if let Some(extension) = path_buf.extension() {
-                    Response::new(StatusCode::OK, buf).with_header(
-                        HeaderType::ContentType,
-                        MimeType::from_extension(extension.to_str().unwrap()).to_string(),
-                    )
-                } else {
-                    Response::new(StatusCode::OK, buf)
-                };
-            }
-        }
-
-        error_handler(StatusCode::NotFound)
+        file_response(&request, &path_buf).unwrap_or_else(|| error_handler(StatusCode::NotFound))
     }
 }
 
@@ -89,24 +63,8 @@ pub fn serve_dir<T>(directory_path: &'static str) -> impl Fn(Request, Arc<T>, &s
             match located {
                 LocatedPath::Directory => Response::empty(StatusCode::MovedPermanently)
                     .with_header(HeaderType::Location, format!("{}/", &request.uri)),
-                LocatedPath::File(path) => {
-                    if let Ok(mut file) = File::open(&path) {
-                        let mut buf = Vec::new();
-                        if file.read_to_end(&mut buf).is_ok() {
-                            return if let Some(extension) = path.extension() {
-                                Response::new(StatusCode::OK, buf).with_header(
-                                    HeaderType::ContentType,
-                                    MimeType::from_extension(extension.to_str().unwrap())
-                                        .to_string(),
-                                )
-                            } else {
-                                Response::new(StatusCode::OK, buf)
-                            };
-                        }
-                    }
-
-                    error_handler(StatusCode::InternalError)
-                }
+                LocatedPath::File(path) => file_response(&request, &path)
+                    .unwrap_or_else(|| error_handler(StatusCode::InternalError)),
             }
         } else {
             error_handler(StatusCode::NotFound)
@@ -114,6 +72,53 @@ pub fn serve_dir<T>(directory_path: &'static str) -> impl Fn(Request, Arc<T>, &s
     }
 }
 
+/// Serves the file at the given path, answering with 304 Not Modified if the client's copy is up to date,
+///   or with only the requested ranges of the file if the request has a `Range` header.
+/// Returns `None` if the file could not be read.
+fn file_response(request: &Request, path: &Path) -> Option<Response> {
+    let mut file = File::open(path).ok()?;
+    let validators = file
+        .metadata()
+        .map(|metadata| Validators::from_metadata(&metadata))
+        .unwrap_or_default();
+
+    if validators.is_not_modified(request) {
+        return Some(validators.not_modified());
+    }
+
+    // Range requests are streamed from the file, so large files are not read into memory just to send part of them
+    if request.headers.get(HeaderType::Range).is_some() {
+        let mut response = Response::empty(StatusCode::OK);
+
+        if let Some(extension) = path.extension() {
+            response.headers.add(
+                HeaderType::ContentType,
+                MimeType::from_extension(extension.to_str().unwrap()).to_string(),
+            );
+        }
+
+        validators.set_headers(&mut response.headers);
+
+        return serve_file_ranges(request, response, file, path).ok();
+    }
+
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).ok()?;
+
+    let mut response = if let Some(extension) = path.extension() {
+        Response::new(StatusCode::OK, buf).with_header(
+            HeaderType::ContentType,
+            MimeType::from_extension(extension.to_str().unwrap()).to_string(),
+        )
+    } else {
+        Response::new(StatusCode::OK, buf)
+    };
+
+    validators.set_headers(&mut response.headers);
+
+    Some(serve_ranges(request, response))
+}
+
 /// Redirects requests to the given location with status code 301.
 pub fn redirect<T>(location: &'static str) -> impl Fn(Request, Arc<T>) -> Response {
     move |_, _| Response::redirect(location)
@@ -1,10 +1,11 @@
 //! Provides functionality for parsing and representing network addresses.
 
-use crate::http::headers::Headers;
+use crate::http::headers::{HeaderType, Headers};
 
 use std::error::Error;
 use std::fmt::Display;
-use std::net::{IpAddr, ToSocketAddrs};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
 use std::str::FromStr;
 
 /// Represents a request's address.
@@ -17,6 +18,28 @@ pub struct Address {
     pub proxies: Vec<IpAddr>,
     /// The port of the request.
     pub port: u16,
+    /// The path of the Unix domain socket the request was received on, or `None` if it was received over TCP.
+    ///
+    /// Peers connected over a Unix domain socket have no IP address, so the origin address is the unspecified
+    ///   address unless the app trusts Unix domain sockets and a proxy in front of the socket forwarded the original
+    ///   one.
+    pub unix_socket: Option<PathBuf>,
+    /// Whether the request was received over a connection secured with TLS.
+    pub tls: bool,
+    /// The protocol used by the client to connect to the first proxy, such as `https`, if it was forwarded by a
+    ///   trusted proxy.
+    pub forwarded_proto: Option<String>,
+    /// The host requested by the client from the first proxy, if it was forwarded by a trusted proxy.
+    pub forwarded_host: Option<String>,
+}
+
+/// Represents a range of IP addresses in CIDR notation, such as `10.0.0.0/8` or `fd00::/8`.
+///
+/// A single address without a prefix length, such as `127.0.0.1`, is a range containing only that address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
 }
 
 impl Address {
@@ -27,57 +50,281 @@ impl Address {
             .next()
             .ok_or("No socket address found")?;
 
-        Ok(Self {
-            origin_addr: addr.ip(),
-            proxies: Vec::new(),
-            port: addr.port(),
-        })
+        Ok(Self::from_socket_addr(addr))
     }
 
     /// Create a new `Address` object from a request's headers and the socket address.
-    /// This looks for the `X-Forwarded-For` header, used by proxies and CDNs, to find the origin address.
+    ///
+    /// The forwarding headers, used by proxies and CDNs to pass on the origin address, are only honoured when the
+    ///   request was received from one of the trusted proxies, since any other client could use them to spoof its
+    ///   address. The standard `Forwarded` header is preferred, falling back to `X-Forwarded-For` along with
+    ///   `X-Forwarded-Proto` and `X-Forwarded-Host`.
+    ///
+    /// The forwarded addresses are followed from the last to the first for as long as they are trusted, so the
+    ///   origin address is the first one which was not added by a trusted proxy.
     pub fn from_headers(
         headers: &Headers,
         addr: impl ToSocketAddrs,
+        trusted_proxies: &[Cidr],
     ) -> Result<Self, Box<dyn Error>> {
-        if let Some(forwarded) = headers.get("X-Forwarded-For") {
-            let mut proxies: Vec<IpAddr> = forwarded
-                .split(',')
-                .filter_map(|s| IpAddr::from_str(s).ok())
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or("No socket address found")?;
+
+        let peer_trusted = is_trusted(&addr.ip(), trusted_proxies);
+
+        Ok(Self::from_forwarded(
+            headers,
+            addr,
+            trusted_proxies,
+            peer_trusted,
+        ))
+    }
+
+    /// Create a new `Address` object from the socket address, without any proxy data.
+    pub(crate) fn from_socket_addr(addr: SocketAddr) -> Self {
+        Self {
+            origin_addr: addr.ip(),
+            proxies: Vec::new(),
+            port: addr.port(),
+            unix_socket: None,
+            tls: false,
+            forwarded_proto: None,
+            forwarded_host: None,
+        }
+    }
+
+    /// Create a new `Address` object from the socket address, following the forwarding headers through the
+    ///   trusted proxies if the peer itself is trusted.
+    ///
+    /// Whether the peer is trusted is given separately, since peers connected over a Unix domain socket have no
+    ///   address to match against the trusted proxies.
+    pub(crate) fn from_forwarded(
+        headers: &Headers,
+        addr: SocketAddr,
+        trusted_proxies: &[Cidr],
+        peer_trusted: bool,
+    ) -> Self {
+        let mut address = Self::from_socket_addr(addr);
+        let is_trusted = |addr: &IpAddr| is_trusted(addr, trusted_proxies);
+
+        if !peer_trusted {
+            return address;
+        }
+
+        let forwarded = headers.get_all(HeaderType::Forwarded).join(",");
+
+        if !forwarded.is_empty() {
+            let elements = forwarded_elements(&forwarded);
+            let origin_index = address.follow_hops(elements.iter().map(|e| e.addr), is_trusted);
+
+            // The protocol and host are those used by the origin, or by the nearest proxy if it is hidden
+            if let Some(element) = origin_index.or(elements.len().checked_sub(1)) {
+                address.forwarded_proto = elements[element].proto.clone();
+                address.forwarded_host = elements[element].host.clone();
+            }
+        } else {
+            let hops: Vec<Option<IpAddr>> = header_values(headers, "X-Forwarded-For")
+                .map(parse_node)
                 .collect();
 
-            if proxies.is_empty() {
-                return Self::new(addr);
+            address.follow_hops(hops.into_iter(), is_trusted);
+            address.forwarded_proto = header_values(headers, "X-Forwarded-Proto")
+                .last()
+                .map(str::to_ascii_lowercase);
+            address.forwarded_host = header_values(headers, "X-Forwarded-Host")
+                .last()
+                .map(str::to_string);
+        }
+
+        address
+    }
+
+    /// Walks back through the forwarded hops, from the last to the first, for as long as each one was added by a
+    ///   trusted proxy, moving the origin address to the hop and recording the proxy it came through.
+    ///
+    /// Returns the index of the hop which became the origin address, if any. Hidden or invalid hops end the walk.
+    fn follow_hops(
+        &mut self,
+        hops: impl DoubleEndedIterator<Item = Option<IpAddr>> + ExactSizeIterator,
+        is_trusted: impl Fn(&IpAddr) -> bool,
+    ) -> Option<usize> {
+        let mut origin_index = None;
+
+        for (index, hop) in hops.enumerate().rev() {
+            let hop = match hop {
+                Some(hop) if is_trusted(&self.origin_addr) => hop,
+                _ => break,
+            };
+
+            self.proxies.insert(0, self.origin_addr);
+            self.origin_addr = hop;
+            origin_index = Some(index);
+        }
+
+        origin_index
+    }
+}
+
+/// A single hop of the forwarding headers.
+#[derive(Default)]
+struct ForwardedElement {
+    /// The address of the client which made the request to the proxy, or `None` if it was hidden or invalid.
+    addr: Option<IpAddr>,
+    /// The protocol used by the client to connect to the proxy.
+    proto: Option<String>,
+    /// The host requested by the client from the proxy.
+    host: Option<String>,
+}
+
+/// Returns `true` if the address is within one of the ranges of trusted proxies.
+pub(crate) fn is_trusted(addr: &IpAddr, trusted_proxies: &[Cidr]) -> bool {
+    trusted_proxies.iter().any(|range| range.contains(addr))
+}
+
+/// Parses the elements of the `Forwarded` header, as defined in [RFC 7239](https://datatracker.ietf.org/doc/html/rfc7239).
+fn forwarded_elements(value: &str) -> Vec<ForwardedElement> {
+    value
+        .split(',')
+        .map(|element| {
+            let mut parsed = ForwardedElement::default();
+
+            for pair in element.split(';') {
+                let (name, value) = match pair.split_once('=') {
+                    Some((name, value)) => (name.trim(), value.trim().trim_matches('"')),
+                    None => continue,
+                };
+
+                if name.eq_ignore_ascii_case("for") {
+                    parsed.addr = parse_node(value);
+                } else if name.eq_ignore_ascii_case("proto") {
+                    parsed.proto = Some(value.to_ascii_lowercase());
+                } else if name.eq_ignore_ascii_case("host") {
+                    parsed.host = Some(value.to_string());
+                }
             }
 
-            let origin_addr = *proxies.last().ok_or("No socket address found")?;
-            proxies.remove(proxies.len() - 1);
-            proxies.push(
-                addr.to_socket_addrs()?
-                    .next()
-                    .ok_or("No socket address found")?
-                    .ip(),
-            );
-
-            Ok(Self {
-                origin_addr,
-                proxies,
-                port: addr
-                    .to_socket_addrs()?
-                    .next()
-                    .ok_or("No socket address found")?
-                    .port(),
-            })
-        } else {
-            Self::new(addr)
+            parsed
+        })
+        .collect()
+}
+
+/// Returns the comma-separated values of every instance of the header, in order.
+fn header_values<'a>(headers: &'a Headers, name: &str) -> impl Iterator<Item = &'a str> {
+    headers
+        .get_all(name)
+        .into_iter()
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// Parses the IP address of a forwarded node, which may include a port and, for IPv6 addresses, square brackets.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    if let Some(rest) = node.strip_prefix('[') {
+        return rest.split(']').next()?.parse().ok();
+    }
+
+    node.parse()
+        .ok()
+        .or_else(|| node.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+impl Cidr {
+    /// Creates a new range from its first address and the length of its prefix in bits.
+    ///
+    /// Ranges of IPv4 addresses mapped to IPv6, such as `::ffff:10.0.0.0/104`, are converted to the equivalent IPv4
+    ///   range, so their prefix must cover at least the 96 bits of the mapping.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Self, &'static str> {
+        let max_len = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+
+        if prefix_len > max_len {
+            return Err("Prefix length is too long for the address");
+        }
+
+        match (addr, addr.to_canonical()) {
+            (IpAddr::V6(_), IpAddr::V4(mapped)) => match prefix_len.checked_sub(96) {
+                Some(prefix_len) => Ok(Self {
+                    addr: IpAddr::V4(mapped),
+                    prefix_len,
+                }),
+                None => Err("Prefix length is too short for an IPv4-mapped address"),
+            },
+            _ => Ok(Self { addr, prefix_len }),
         }
     }
+
+    /// Returns `true` if the address is within the range.
+    ///
+    /// IPv4 addresses mapped to IPv6, as reported by dual-stack sockets, are treated as their IPv4 equivalents.
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        match (self.addr, addr.to_canonical()) {
+            (IpAddr::V4(range), IpAddr::V4(addr)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u32::from(range) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(range), IpAddr::V6(addr)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u128::from(range) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        match s.split_once('/') {
+            Some((addr, prefix_len)) => Self::new(
+                addr.parse().map_err(|_| "Invalid IP address")?,
+                prefix_len.parse().map_err(|_| "Invalid prefix length")?,
+            ),
+            None => Ok(Self::from(
+                s.parse::<IpAddr>().map_err(|_| "Invalid IP address")?,
+            )),
+        }
+    }
+}
+
+impl From<IpAddr> for Cidr {
+    fn from(addr: IpAddr) -> Self {
+        match addr.to_canonical() {
+            IpAddr::V4(addr) => Self {
+                addr: IpAddr::V4(addr),
+                prefix_len: 32,
+            },
+            IpAddr::V6(addr) => Self {
+                addr: IpAddr::V6(addr),
+                prefix_len: 128,
+            },
+        }
+    }
+}
+
+impl Display for Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
 }
 
 impl Display for Address {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if !self.proxies.is_empty() {
             write!(f, "{} (proxied)", self.origin_addr)
+        } else if let Some(path) = &self.unix_socket {
+            write!(f, "unix:{}", path.display())
         } else {
             write!(f, "{}", self.origin_addr)
         }
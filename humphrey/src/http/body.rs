@@ -0,0 +1,426 @@
+//! Provides functionality for reading request bodies.
+
+use crate::http::headers::{HeaderType, Headers};
+use crate::http::request::RequestError;
+use crate::http::response::{is_chunked, parse_chunk_length, parse_trailer_line};
+
+use std::io::{Error, ErrorKind};
+
+#[cfg(not(feature = "tokio"))]
+use std::io::{BufRead, Read};
+
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
+#[cfg(feature = "tokio")]
+use std::task::{Context, Poll};
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, ReadBuf};
+#[cfg(feature = "tokio")]
+use tokio::sync::mpsc::{Receiver, Sender};
+
+/// The size of the buffer used when reading a request body.
+pub(crate) const BODY_BUFFER_SIZE: usize = 8192;
+
+/// The maximum length of a chunk size line, including any chunk extensions.
+pub(crate) const MAX_CHUNK_LINE_SIZE: usize = 4096;
+
+/// The maximum total size of the trailer section following a chunked body.
+pub(crate) const MAX_TRAILER_SIZE: usize = 8192;
+
+/// Decodes a request body of a fixed length or using chunked transfer encoding,
+///   enforcing a maximum size on the decoded body.
+///
+/// The decoder does not own the underlying stream, so it can be used both to read the body into
+///   memory and to stream it to a handler.
+pub(crate) struct BodyDecoder {
+    state: DecoderState,
+    has_body: bool,
+    chunked: bool,
+    max_size: Option<usize>,
+    length: usize,
+    trailers: Headers,
+    trailer_size: usize,
+    too_large: bool,
+}
+
+/// The position of the decoder within the body.
+#[derive(Clone, Copy)]
+enum DecoderState {
+    /// Reading a body of fixed length, with the given number of bytes remaining.
+    Length(usize),
+    /// Expecting the size line of the next chunk.
+    ChunkSize,
+    /// Reading the data of a chunk, with the given number of bytes remaining.
+    ChunkData(usize),
+    /// Expecting the line break following the data of a chunk.
+    ChunkEnd,
+    /// Reading the trailer section following the final chunk.
+    Trailers,
+    /// The entire body has been read.
+    Done,
+}
+
+impl BodyDecoder {
+    /// Creates a decoder for the body described by the given headers.
+    ///
+    /// Returns `RequestError::TooLarge` if the body is declared to be larger than the maximum size,
+    ///   or `RequestError::Request` if its length is invalid or ambiguous.
+    ///
+    /// As required by [RFC 9112](https://datatracker.ietf.org/doc/html/rfc9112#section-6.3), requests whose final
+    ///   transfer coding is not `chunked` are rejected, since their length cannot be determined. Requests with both a
+    ///   `Transfer-Encoding` and a `Content-Length` header, or with conflicting `Content-Length` headers, are also
+    ///   rejected, since servers and proxies which disagree on the length of such a request could be made to read a
+    ///   different request from its body.
+    pub(crate) fn new(headers: &Headers, max_size: Option<usize>) -> Result<Self, RequestError> {
+        let transfer_encoding = headers.get_all(&HeaderType::TransferEncoding);
+        let content_lengths = headers.get_all(&HeaderType::ContentLength);
+
+        if !transfer_encoding.is_empty() && !content_lengths.is_empty() {
+            return Err(RequestError::Request);
+        }
+
+        let chunked = is_chunked(headers);
+
+        if !transfer_encoding.is_empty() && !chunked {
+            return Err(RequestError::Request);
+        }
+
+        let state = if chunked {
+            DecoderState::ChunkSize
+        } else if let Some(content_length) = content_lengths.first() {
+            if content_lengths
+                .iter()
+                .any(|length| length != content_length)
+            {
+                return Err(RequestError::Request);
+            }
+
+            let content_length: usize =
+                content_length.parse().map_err(|_| RequestError::Request)?;
+
+            if max_size.is_some_and(|max_size| content_length > max_size) {
+                return Err(RequestError::TooLarge);
+            }
+
+            DecoderState::Length(content_length)
+        } else {
+            DecoderState::Done
+        };
+
+        Ok(Self {
+            has_body: !matches!(state, DecoderState::Done),
+            state,
+            chunked,
+            max_size,
+            length: 0,
+            trailers: Headers::new(),
+            trailer_size: 0,
+            too_large: false,
+        })
+    }
+
+    /// Returns `true` if the request has a body, even if it is empty.
+    pub(crate) fn has_body(&self) -> bool {
+        self.has_body
+    }
+
+    /// Returns `true` if the body was sent using chunked transfer encoding.
+    pub(crate) fn is_chunked(&self) -> bool {
+        self.chunked
+    }
+
+    /// Returns `true` once the entire body has been read.
+    #[cfg(not(feature = "tokio"))]
+    pub(crate) fn is_complete(&self) -> bool {
+        matches!(self.state, DecoderState::Done | DecoderState::Length(0))
+    }
+
+    /// Returns the trailers sent after a chunked body.
+    pub(crate) fn trailers(&self) -> &Headers {
+        &self.trailers
+    }
+
+    /// Converts an error which occurred while decoding the body into a `RequestError`.
+    pub(crate) fn to_request_error(&self, e: Error) -> RequestError {
+        if self.too_large {
+            RequestError::TooLarge
+        } else if e.kind() == ErrorKind::InvalidData {
+            RequestError::Request
+        } else {
+            RequestError::Stream
+        }
+    }
+
+    /// Reads part of the body from the given reader into the buffer, returning the number of bytes read.
+    /// Returns zero once the entire body has been read.
+    #[cfg(not(feature = "tokio"))]
+    pub(crate) fn read<R>(&mut self, reader: &mut R, buf: &mut [u8]) -> std::io::Result<usize>
+    where
+        R: BufRead + ?Sized,
+    {
+        loop {
+            match self.state {
+                DecoderState::Length(0) | DecoderState::Done => return Ok(0),
+                DecoderState::Length(remaining) | DecoderState::ChunkData(remaining) => {
+                    if buf.is_empty() {
+                        return Ok(0);
+                    }
+
+                    let limit = buf.len().min(remaining);
+                    let n = reader.read(&mut buf[..limit])?;
+
+                    return self.advance(n);
+                }
+                DecoderState::ChunkSize | DecoderState::Trailers => {
+                    let mut line_buf: Vec<u8> = Vec::new();
+                    reader
+                        .take(self.line_limit() as u64)
+                        .read_until(0xA, &mut line_buf)?;
+                    self.parse_line(&line_buf)?;
+                }
+                DecoderState::ChunkEnd => {
+                    let mut crlf = [0u8; 2];
+                    reader.read_exact(&mut crlf)?;
+                    self.parse_chunk_end(&crlf)?;
+                }
+            }
+        }
+    }
+
+    /// Reads part of the body from the given asynchronous reader into the buffer, returning the number of bytes read.
+    /// Returns zero once the entire body has been read.
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn read<R>(&mut self, reader: &mut R, buf: &mut [u8]) -> std::io::Result<usize>
+    where
+        R: AsyncBufRead + Unpin + ?Sized,
+    {
+        loop {
+            match self.state {
+                DecoderState::Length(0) | DecoderState::Done => return Ok(0),
+                DecoderState::Length(remaining) | DecoderState::ChunkData(remaining) => {
+                    if buf.is_empty() {
+                        return Ok(0);
+                    }
+
+                    let limit = buf.len().min(remaining);
+                    let n = reader.read(&mut buf[..limit]).await?;
+
+                    return self.advance(n);
+                }
+                DecoderState::ChunkSize | DecoderState::Trailers => {
+                    let mut line_buf: Vec<u8> = Vec::new();
+                    reader
+                        .take(self.line_limit() as u64)
+                        .read_until(0xA, &mut line_buf)
+                        .await?;
+                    self.parse_line(&line_buf)?;
+                }
+                DecoderState::ChunkEnd => {
+                    let mut crlf = [0u8; 2];
+                    reader.read_exact(&mut crlf).await?;
+                    self.parse_chunk_end(&crlf)?;
+                }
+            }
+        }
+    }
+
+    /// Updates the state after `n` bytes of data have been read.
+    fn advance(&mut self, n: usize) -> std::io::Result<usize> {
+        if n == 0 {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+
+        match self.state {
+            DecoderState::Length(remaining) => {
+                self.state = DecoderState::Length(remaining - n);
+                self.length += n;
+                Ok(n)
+            }
+            DecoderState::ChunkData(remaining) => {
+                self.state = match remaining - n {
+                    0 => DecoderState::ChunkEnd,
+                    remaining => DecoderState::ChunkData(remaining),
+                };
+                self.length += n;
+                Ok(n)
+            }
+            _ => Ok(n),
+        }
+    }
+
+    /// Returns the maximum length of the next chunk size line or trailer line, so that a client cannot make the
+    ///   server buffer an unlimited amount of data which is not part of the body.
+    fn line_limit(&self) -> usize {
+        match self.state {
+            DecoderState::Trailers => MAX_TRAILER_SIZE - self.trailer_size,
+            _ => MAX_CHUNK_LINE_SIZE,
+        }
+    }
+
+    /// Parses a chunk size line or trailer line.
+    fn parse_line(&mut self, line: &[u8]) -> std::io::Result<()> {
+        if line.is_empty() && self.line_limit() > 0 {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+
+        if !line.ends_with(b"\n") && line.len() == self.line_limit() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "chunk size line or trailer section is too long",
+            ));
+        }
+
+        if let DecoderState::Trailers = self.state {
+            self.trailer_size += line.len();
+
+            if parse_trailer_line(line, &mut self.trailers)? {
+                self.state = DecoderState::Done;
+            }
+
+            return Ok(());
+        }
+
+        let length = parse_chunk_length(line)?;
+
+        if length == 0 {
+            self.state = DecoderState::Trailers;
+        } else if self
+            .max_size
+            .is_some_and(|max_size| self.length.saturating_add(length) > max_size)
+        {
+            self.too_large = true;
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "request body exceeds maximum size",
+            ));
+        } else {
+            self.state = DecoderState::ChunkData(length);
+        }
+
+        Ok(())
+    }
+
+    /// Checks the line break following the data of a chunk.
+    fn parse_chunk_end(&mut self, crlf: &[u8]) -> std::io::Result<()> {
+        if crlf != b"\r\n" {
+            return Err(ErrorKind::InvalidData.into());
+        }
+
+        self.state = DecoderState::ChunkSize;
+
+        Ok(())
+    }
+}
+
+/// The body of a request to a streaming route, which can be read as it arrives from the client.
+///
+/// If the body was sent using chunked transfer encoding, it is decoded transparently.
+/// Reading fails with an error of kind `InvalidData` if the body exceeds the maximum body size.
+#[cfg(not(feature = "tokio"))]
+pub struct RequestBody<'a> {
+    reader: &'a mut dyn BufRead,
+    decoder: BodyDecoder,
+}
+
+#[cfg(not(feature = "tokio"))]
+impl<'a> RequestBody<'a> {
+    /// Creates a new request body from the given reader and decoder.
+    pub(crate) fn new(reader: &'a mut dyn BufRead, decoder: BodyDecoder) -> Self {
+        Self { reader, decoder }
+    }
+
+    /// Returns `true` once the entire body has been read.
+    pub fn is_complete(&self) -> bool {
+        self.decoder.is_complete()
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+impl Read for RequestBody<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.decoder.read(self.reader, buf)
+    }
+}
+
+/// The body of a request to a streaming route, which can be read as it arrives from the client.
+///
+/// If the body was sent using chunked transfer encoding, it is decoded transparently.
+/// Reading fails with an error of kind `InvalidData` if the body exceeds the maximum body size.
+#[cfg(feature = "tokio")]
+pub struct RequestBody {
+    receiver: Receiver<std::io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+#[cfg(feature = "tokio")]
+impl RequestBody {
+    /// Creates a new request body which receives its data from the given channel.
+    pub(crate) fn new(receiver: Receiver<std::io::Result<Vec<u8>>>) -> Self {
+        Self {
+            receiver,
+            chunk: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncRead for RequestBody {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        while self.position == self.chunk.len() {
+            match self.receiver.poll_recv(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(e)),
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        let n = buf.remaining().min(self.chunk.len() - self.position);
+        buf.put_slice(&self.chunk[self.position..self.position + n]);
+        self.position += n;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Reads the body from the given reader and sends it over the channel to a `RequestBody`.
+///
+/// Returns `true` if the entire body was read, or `false` if the receiver was dropped or an error occurred
+///   before the end of the body.
+#[cfg(feature = "tokio")]
+pub(crate) async fn pump_body<R>(
+    decoder: &mut BodyDecoder,
+    reader: &mut R,
+    sender: Sender<std::io::Result<Vec<u8>>>,
+) -> bool
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    loop {
+        let mut buf = vec![0u8; BODY_BUFFER_SIZE];
+
+        match decoder.read(reader, &mut buf).await {
+            Ok(0) => return true,
+            Ok(n) => {
+                buf.truncate(n);
+
+                if sender.send(Ok(buf)).await.is_err() {
+                    return false;
+                }
+            }
+            Err(e) => {
+                let _ = sender.send(Err(e)).await;
+                return false;
+            }
+        }
+    }
+}
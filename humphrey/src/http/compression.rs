@@ -0,0 +1,319 @@
+//! Provides functionality for compressing responses according to the client's `Accept-Encoding` header.
+
+use crate::app::Middleware;
+use crate::http::deflate::{gzip, zlib};
+use crate::http::headers::HeaderType;
+use crate::http::{Request, Response, StatusCode};
+
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[cfg(feature = "tokio")]
+use std::future::Future;
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
+
+/// The content types which are compressed by default.
+pub const DEFAULT_COMPRESSED_TYPES: [&str; 8] = [
+    "text/*",
+    "application/javascript",
+    "application/json",
+    "application/manifest+json",
+    "application/wasm",
+    "application/xml",
+    "application/xhtml+xml",
+    "image/svg+xml",
+];
+
+/// The default minimum size of a response body to compress, in bytes.
+/// Smaller bodies are sent uncompressed, since compressing them saves little or nothing.
+pub const DEFAULT_MIN_COMPRESSION_SIZE: usize = 1024;
+
+/// Represents a content encoding which responses can be compressed with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// The gzip format, as defined in [RFC 1952](https://datatracker.ietf.org/doc/html/rfc1952).
+    Gzip,
+    /// The zlib format, as defined in [RFC 1950](https://datatracker.ietf.org/doc/html/rfc1950).
+    Deflate,
+}
+
+/// Represents the configuration for compressing responses.
+///
+/// Responses are compressed when the client accepts one of the configured encodings, the response has a content type
+///   in the allowlist and its body is at least the minimum size. Responses which already have a `Content-Encoding`,
+///   have a streamed body, or are partial or have no content are never compressed. Compressible responses are given
+///   the `Vary: Accept-Encoding` header whether or not they were compressed, so caches keep the encodings apart.
+///
+/// Compression can be enabled for the whole app with `App::with_compression`, or used as middleware for
+///   individual sub-apps or routes.
+///
+/// ## Example
+/// ```
+/// let app: App<()> = App::new()
+///     .with_compression(Compression::new().with_min_size(512))
+///     .with_route("/", handler);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Compression {
+    encodings: Vec<Encoding>,
+    content_types: Vec<String>,
+    min_size: usize,
+}
+
+impl Compression {
+    /// Creates a new compression configuration with the default settings.
+    ///
+    /// By default, gzip is preferred over deflate, the content types in `DEFAULT_COMPRESSED_TYPES` are compressed,
+    ///   and bodies smaller than `DEFAULT_MIN_COMPRESSION_SIZE` bytes are not.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the encodings which can be used, in order of preference.
+    pub fn with_encodings(mut self, encodings: &[Encoding]) -> Self {
+        self.encodings = encodings.to_vec();
+        self
+    }
+
+    /// Sets the content types which are compressed, replacing the defaults.
+    /// Types can use a wildcard for the subtype, for example `text/*`.
+    pub fn with_content_types<T>(mut self, content_types: &[T]) -> Self
+    where
+        T: AsRef<str>,
+    {
+        self.content_types = content_types
+            .iter()
+            .map(|content_type| content_type.as_ref().trim().to_ascii_lowercase())
+            .collect();
+        self
+    }
+
+    /// Adds a content type to those which are compressed.
+    pub fn with_content_type(mut self, content_type: impl AsRef<str>) -> Self {
+        self.content_types
+            .push(content_type.as_ref().trim().to_ascii_lowercase());
+        self
+    }
+
+    /// Sets the minimum size of a response body to compress, in bytes.
+    pub fn with_min_size(mut self, min_size: usize) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// Chooses the encoding to use for a response from the value of the request's `Accept-Encoding` header.
+    ///
+    /// The encoding with the highest quality value is chosen, with ties broken by the configured order of preference.
+    ///   Returns `None` if the client accepts none of the configured encodings.
+    pub fn negotiate(&self, accept_encoding: &str) -> Option<Encoding> {
+        let accepted: Vec<(String, f32)> = accept_encoding
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.split(';');
+                let coding = parts.next()?.trim().to_ascii_lowercase();
+                let quality = parts
+                    .filter_map(|param| param.trim().split_once('='))
+                    .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+                    .map_or(Some(1.0), |(_, value)| value.trim().parse::<f32>().ok())?;
+
+                (!coding.is_empty()).then_some((coding, quality))
+            })
+            .collect();
+
+        let quality_of = |encoding: &Encoding| {
+            accepted
+                .iter()
+                .find(|(coding, _)| encoding.matches(coding))
+                .or_else(|| accepted.iter().find(|(coding, _)| coding == "*"))
+                .map(|(_, quality)| *quality)
+                .unwrap_or(0.0)
+        };
+
+        let mut best: Option<(Encoding, f32)> = None;
+
+        for encoding in &self.encodings {
+            let quality = quality_of(encoding);
+
+            if quality > 0.0 && best.is_none_or(|(_, best_quality)| quality > best_quality) {
+                best = Some((*encoding, quality));
+            }
+        }
+
+        best.map(|(encoding, _)| encoding)
+    }
+
+    /// Compresses the response to the given request if it is eligible, updating its headers accordingly.
+    pub fn compress(&self, request: &Request, response: &mut Response) {
+        if !self.is_compressible(response) {
+            return;
+        }
+
+        add_vary(response);
+
+        let encoding = match request
+            .headers
+            .get(HeaderType::AcceptEncoding)
+            .and_then(|accept_encoding| self.negotiate(accept_encoding))
+        {
+            Some(encoding) => encoding,
+            None => return,
+        };
+
+        let compressed = encoding.compress(&response.body);
+
+        // Compression can make data with little repetition larger, in which case it is not worthwhile
+        if compressed.len() >= response.body.len() {
+            return;
+        }
+
+        response.body = compressed;
+        response
+            .headers
+            .add(HeaderType::ContentEncoding, encoding.to_string());
+
+        if response.headers.get(HeaderType::ContentLength).is_some() {
+            response.headers.remove(HeaderType::ContentLength);
+            response
+                .headers
+                .add(HeaderType::ContentLength, response.body.len().to_string());
+        }
+
+        // Ranges are served from the uncompressed representation, so they cannot be requested of this one
+        response.headers.remove(HeaderType::AcceptRanges);
+
+        // The compressed body is a different representation, so a strong entity tag no longer applies to it
+        if let Some(etag) = response.headers.get_mut(HeaderType::ETag) {
+            if !etag.starts_with("W/") {
+                etag.insert_str(0, "W/");
+            }
+        }
+    }
+
+    /// Returns `true` if the response could be compressed, regardless of the encodings accepted by the client.
+    fn is_compressible(&self, response: &Response) -> bool {
+        if !response.status_code.allows_body()
+            || response.status_code == StatusCode::PartialContent
+            || response.is_streamed()
+            || response.body.len() < self.min_size.max(1)
+            || response.headers.get(HeaderType::ContentEncoding).is_some()
+        {
+            return false;
+        }
+
+        response
+            .headers
+            .get(HeaderType::ContentType)
+            .is_some_and(|content_type| self.allows_content_type(content_type))
+    }
+
+    /// Returns `true` if the content type is in the allowlist, ignoring any parameters such as the charset.
+    fn allows_content_type(&self, content_type: &str) -> bool {
+        let media_type = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        self.content_types
+            .iter()
+            .any(|allowed| match allowed.strip_suffix("/*") {
+                Some(main_type) => media_type
+                    .split_once('/')
+                    .is_some_and(|(media_main_type, _)| media_main_type == main_type),
+                None => *allowed == media_type,
+            })
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            encodings: vec![Encoding::Gzip, Encoding::Deflate],
+            content_types: DEFAULT_COMPRESSED_TYPES
+                .iter()
+                .map(|content_type| content_type.to_string())
+                .collect(),
+            min_size: DEFAULT_MIN_COMPRESSION_SIZE,
+        }
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+impl<State> Middleware<State> for Compression {
+    fn after(&self, request: &Request, response: &mut Response, _: Arc<State>) {
+        self.compress(request, response);
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<State> Middleware<State> for Compression {
+    fn after<'a>(
+        &'a self,
+        request: &'a Request,
+        response: &'a mut Response,
+        _: Arc<State>,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(async move {
+            self.compress(request, response);
+        })
+    }
+}
+
+impl Encoding {
+    /// Compresses the data with this encoding.
+    pub fn compress(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Encoding::Gzip => gzip(data),
+            Encoding::Deflate => zlib(data),
+        }
+    }
+
+    /// Returns `true` if the content coding from an `Accept-Encoding` header refers to this encoding.
+    fn matches(&self, coding: &str) -> bool {
+        match self {
+            Encoding::Gzip => coding == "gzip" || coding == "x-gzip",
+            Encoding::Deflate => coding == "deflate",
+        }
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Encoding::Gzip => write!(f, "gzip"),
+            Encoding::Deflate => write!(f, "deflate"),
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "gzip" => Ok(Encoding::Gzip),
+            "deflate" => Ok(Encoding::Deflate),
+            _ => Err("Invalid encoding"),
+        }
+    }
+}
+
+/// Adds `Accept-Encoding` to the response's `Vary` header, unless it is already present.
+fn add_vary(response: &mut Response) {
+    match response.headers.get_mut(HeaderType::Vary) {
+        Some(vary) => {
+            let present = vary.split(',').any(|field| {
+                let field = field.trim();
+                field == "*" || field.eq_ignore_ascii_case("accept-encoding")
+            });
+
+            if !present {
+                vary.push_str(", Accept-Encoding");
+            }
+        }
+        None => response.headers.add(HeaderType::Vary, "Accept-Encoding"),
+    }
+}
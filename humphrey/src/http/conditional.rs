@@ -0,0 +1,180 @@
+//! Provides functionality for conditional requests, as defined in [RFC 9110, section 13](https://datatracker.ietf.org/doc/html/rfc9110#section-13).
+
+use crate::http::date::DateTime;
+use crate::http::deflate::crc32;
+use crate::http::headers::{HeaderType, Headers};
+use crate::http::method::Method;
+use crate::http::{Request, Response, StatusCode};
+
+use std::fs::Metadata;
+use std::time::UNIX_EPOCH;
+
+/// The validators of a representation, which clients use to check whether their cached copy is still up to date.
+///
+/// Responses to `GET` and `HEAD` requests whose `If-None-Match` or `If-Modified-Since` headers show that the client
+///   already has the current representation can be answered with `304 Not Modified` instead of the full body.
+///
+/// ## Example
+/// ```
+/// let validators = Validators::from_metadata(&file.metadata()?);
+///
+/// if validators.is_not_modified(&request) {
+///     return validators.not_modified();
+/// }
+///
+/// let mut response = Response::new(StatusCode::OK, contents);
+/// validators.set_headers(&mut response.headers);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Validators {
+    etag: Option<String>,
+    last_modified: Option<DateTime>,
+}
+
+impl Validators {
+    /// Creates a new empty set of validators.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Generates validators from a file's metadata, using its modification time and size.
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        let modified = match metadata.modified() {
+            Ok(modified) => modified,
+            Err(_) => return Self::new(),
+        };
+
+        let (seconds, nanos) = match modified.duration_since(UNIX_EPOCH) {
+            Ok(duration) => (duration.as_secs(), duration.subsec_nanos()),
+            Err(_) => return Self::new(),
+        };
+
+        Self::new()
+            .with_etag(format!("{:x}.{:x}-{:x}", seconds, nanos, metadata.len()))
+            .with_last_modified(DateTime::from(modified))
+    }
+
+    /// Generates validators from the content of a representation, using a checksum of it.
+    pub fn from_content(content: &[u8]) -> Self {
+        Self::new().with_etag(format!("{:08x}-{:x}", crc32(content), content.len()))
+    }
+
+    /// Sets the entity tag.
+    ///
+    /// The tag can be given with or without its surrounding quotes, and is treated as a weak tag if it begins with `W/`.
+    pub fn with_etag(mut self, etag: impl AsRef<str>) -> Self {
+        let etag = etag.as_ref();
+
+        self.etag = Some(if etag.starts_with('"') || etag.starts_with("W/\"") {
+            etag.to_string()
+        } else {
+            format!("\"{}\"", etag)
+        });
+
+        self
+    }
+
+    /// Sets the date and time at which the representation was last modified.
+    ///
+    /// Dates in the future are replaced with the current time, since a representation cannot be modified later
+    ///   than the response is sent.
+    pub fn with_last_modified(mut self, last_modified: DateTime) -> Self {
+        let now = DateTime::now();
+
+        self.last_modified = Some(if last_modified.timestamp > now.timestamp {
+            now
+        } else {
+            last_modified
+        });
+
+        self
+    }
+
+    /// Gets the entity tag, including its quotes.
+    pub fn etag(&self) -> Option<&str> {
+        self.etag.as_deref()
+    }
+
+    /// Gets the date and time at which the representation was last modified.
+    pub fn last_modified(&self) -> Option<&DateTime> {
+        self.last_modified.as_ref()
+    }
+
+    /// Returns `true` if the request's preconditions show that the client already has this representation.
+    ///
+    /// `If-None-Match` is evaluated using weak comparison, and takes precedence over `If-Modified-Since`, which is
+    ///   ignored if it is not a valid HTTP date. Only `GET` and `HEAD` requests can be answered as not modified.
+    pub fn is_not_modified(&self, request: &Request) -> bool {
+        if request.method != Method::Get && request.method != Method::Head {
+            return false;
+        }
+
+        if let Some(if_none_match) = request.headers.get(HeaderType::IfNoneMatch) {
+            if if_none_match.trim() == "*" {
+                return true;
+            }
+
+            return match &self.etag {
+                Some(etag) => {
+                    entity_tags(if_none_match).any(|tag| opaque_tag(tag) == opaque_tag(etag))
+                }
+                None => false,
+            };
+        }
+
+        match (
+            &self.last_modified,
+            request
+                .headers
+                .get(HeaderType::IfModifiedSince)
+                .and_then(|date| date.parse::<DateTime>().ok()),
+        ) {
+            (Some(last_modified), Some(since)) => last_modified.timestamp <= since.timestamp,
+            _ => false,
+        }
+    }
+
+    /// Adds the `ETag` and `Last-Modified` headers to the given headers.
+    pub fn set_headers(&self, headers: &mut Headers) {
+        if let Some(etag) = &self.etag {
+            headers.add(HeaderType::ETag, etag);
+        }
+
+        if let Some(last_modified) = &self.last_modified {
+            headers.add(HeaderType::LastModified, last_modified.to_string());
+        }
+    }
+
+    /// Generates a `304 Not Modified` response with these validators.
+    pub fn not_modified(&self) -> Response {
+        let mut response = Response::empty(StatusCode::NotModified);
+        self.set_headers(&mut response.headers);
+
+        response
+    }
+}
+
+/// Splits a list of entity tags, such as the value of an `If-None-Match` header, into the individual tags.
+fn entity_tags(list: &str) -> impl Iterator<Item = &str> {
+    let mut rest = list;
+
+    std::iter::from_fn(move || {
+        rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+
+        let start = if rest.starts_with("W/") { 2 } else { 0 };
+        let end = rest[start..]
+            .strip_prefix('"')
+            .and_then(|tag| tag.find('"'))
+            .map(|end| start + end + 2)?;
+
+        let (tag, remaining) = rest.split_at(end);
+        rest = remaining;
+
+        Some(tag)
+    })
+}
+
+/// Returns the quoted part of an entity tag, without the weakness indicator.
+fn opaque_tag(tag: &str) -> &str {
+    tag.strip_prefix("W/").unwrap_or(tag)
+}
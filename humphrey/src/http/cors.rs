@@ -1,7 +1,7 @@
 //! Contains the CORS implementation for Humphrey.
 
 use crate::http::headers::{HeaderLike, HeaderType, Headers};
-use crate::http::method::Method;
+use crate::http::method::{join_methods, Method};
 
 #[derive(Clone)]
 enum Wildcardable<T> {
@@ -108,11 +108,7 @@ impl Cors {
                 Wildcardable::Value(ref methods) if !methods.is_empty() => {
                     headers.add(
                         HeaderType::AccessControlAllowMethods,
-                        &methods
-                            .iter()
-                            .map(|m| m.to_string())
-                            .collect::<Vec<_>>()
-                            .join(", "),
+                        &join_methods(methods),
                     );
                 }
                 _ => (),
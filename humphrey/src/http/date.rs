@@ -1,5 +1,8 @@
 //! Provides functionality for handling HTTP date timestamps.
 
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
 use std::time::SystemTime;
 
 const DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
@@ -18,6 +21,7 @@ const DAYS_400_YEARS: i64 = 365 * 400 + 97;
 const MARCH_01_2000: i64 = 951868800;
 
 /// Represents a date and time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct DateTime {
     /// The UNIX timestamp of the date.
     pub timestamp: i64,
@@ -52,6 +56,119 @@ impl DateTime {
     pub fn get_timestamp(&self) -> i64 {
         self.timestamp
     }
+
+    /// Creates a new `DateTime` from the given date and time, returning `None` if it is invalid.
+    /// The month is numbered from 1 for January.
+    fn from_parts(
+        year: i64,
+        month: i64,
+        day: i64,
+        hour: i64,
+        minute: i64,
+        second: i64,
+    ) -> Option<Self> {
+        let days_in_month = match month {
+            2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            1..=12 => 31,
+            _ => return None,
+        };
+
+        if !(1..=days_in_month).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+
+        // Count the days since the epoch, with years starting in March so that leap days come last
+        // Implementation modified from https://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * DAYS_400_YEARS + day_of_era - 719468;
+
+        Some(Self::from(
+            days * DAY + hour * HOUR + minute * MINUTE + second,
+        ))
+    }
+}
+
+/// Represents an error parsing an HTTP date.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DateTimeError;
+
+impl Display for DateTimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DateTimeError")
+    }
+}
+
+impl Error for DateTimeError {}
+
+impl FromStr for DateTime {
+    type Err = DateTimeError;
+
+    /// Parses an HTTP date, as defined in [RFC 9110, section 5.6.7](https://datatracker.ietf.org/doc/html/rfc9110#section-5.6.7).
+    ///
+    /// As well as the preferred format, `Sun, 06 Nov 1994 08:49:37 GMT`, the obsolete RFC 850 format,
+    ///   `Sunday, 06-Nov-94 08:49:37 GMT`, and the ANSI C `asctime()` format, `Sun Nov  6 08:49:37 1994`,
+    ///   are also accepted. The day of the week is not checked.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+
+        let (year, month, day, time) = match parts.as_slice() {
+            [_, day, month, year, time, "GMT"] => (parse_digits(year, 4..=4)?, *month, *day, *time),
+            [_, date, time, "GMT"] => {
+                let mut date = date.split('-');
+                let day = date.next().ok_or(DateTimeError)?;
+                let month = date.next().ok_or(DateTimeError)?;
+                let year = date.next().ok_or(DateTimeError)?;
+
+                if date.next().is_some() {
+                    return Err(DateTimeError);
+                }
+
+                // Two-digit years are assumed to be between 1970 and 2069
+                let year = parse_digits(year, 2..=2)?;
+                let year = if year < 70 { 2000 + year } else { 1900 + year };
+
+                (year, month, day, *time)
+            }
+            [_, month, day, time, year] => (parse_digits(year, 4..=4)?, *month, *day, *time),
+            _ => return Err(DateTimeError),
+        };
+
+        let month = MONTHS
+            .iter()
+            .position(|name| *name == month)
+            .ok_or(DateTimeError)? as i64
+            + 1;
+        let day = parse_digits(day, 1..=2)?;
+
+        let mut time = time.split(':');
+        let hour = parse_digits(time.next().ok_or(DateTimeError)?, 2..=2)?;
+        let minute = parse_digits(time.next().ok_or(DateTimeError)?, 2..=2)?;
+        let second = parse_digits(time.next().ok_or(DateTimeError)?, 2..=2)?;
+
+        if time.next().is_some() {
+            return Err(DateTimeError);
+        }
+
+        Self::from_parts(year, month, day, hour, minute, second).ok_or(DateTimeError)
+    }
+}
+
+impl From<SystemTime> for DateTime {
+    /// Converts from a system time into a date and time, discarding any fractions of a second.
+    fn from(time: SystemTime) -> Self {
+        let timestamp = match time.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs_f64().ceil() as i64),
+        };
+
+        Self::from(timestamp)
+    }
 }
 
 impl From<i64> for DateTime {
@@ -150,3 +267,12 @@ impl ToString for DateTime {
         )
     }
 }
+
+/// Parses a number made of the given number of digits.
+fn parse_digits(s: &str, digits: std::ops::RangeInclusive<usize>) -> Result<i64, DateTimeError> {
+    if digits.contains(&s.len()) && s.bytes().all(|b| b.is_ascii_digit()) {
+        s.parse().map_err(|_| DateTimeError)
+    } else {
+        Err(DateTimeError)
+    }
+}
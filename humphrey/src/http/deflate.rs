@@ -0,0 +1,296 @@
+//! Provides a DEFLATE compressor, as defined in [RFC 1951](https://datatracker.ietf.org/doc/html/rfc1951),
+//!   along with the zlib and gzip formats which wrap it.
+//!
+//! Repeated data is found with LZ77 over a 32 KiB window, then encoded with the fixed Huffman codes.
+
+/// The size of the sliding window in which repeated data is searched for.
+const WINDOW_SIZE: usize = 32768;
+
+/// The number of bits in the hash of three bytes used to find previous occurrences of them.
+const HASH_BITS: u32 = 15;
+
+/// The maximum number of previous occurrences to compare against when searching for a match.
+const MAX_CHAIN_LENGTH: usize = 64;
+
+/// The shortest match which can be encoded.
+const MIN_MATCH: usize = 3;
+
+/// The longest match which can be encoded.
+const MAX_MATCH: usize = 258;
+
+/// Marks an empty entry in the hash chains.
+const NONE: usize = usize::MAX;
+
+/// The smallest length encoded by each length code, starting from code 257.
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+
+/// The number of extra bits following each length code.
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// The smallest distance encoded by each distance code.
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+
+/// The number of extra bits following each distance code.
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// The lookup table for the CRC-32 checksum used by gzip.
+const CRC_TABLE: [u32; 256] = crc_table();
+
+/// Compresses the data into a raw DEFLATE stream.
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::with_capacity(data.len() / 2 + 16);
+
+    // A single final block using the fixed Huffman codes
+    writer.write_bits(1, 1);
+    writer.write_bits(1, 2);
+
+    let mut head: Vec<usize> = vec![NONE; 1 << HASH_BITS];
+    let mut prev: Vec<usize> = vec![NONE; WINDOW_SIZE];
+    let mut i = 0;
+
+    while i < data.len() {
+        let (length, distance) = longest_match(data, i, &head, &prev);
+
+        if length >= MIN_MATCH {
+            writer.write_length(length);
+            writer.write_distance(distance);
+
+            for position in i..i + length {
+                insert(data, position, &mut head, &mut prev);
+            }
+
+            i += length;
+        } else {
+            writer.write_literal(data[i]);
+            insert(data, i, &mut head, &mut prev);
+
+            i += 1;
+        }
+    }
+
+    // End of block
+    writer.write_literal_code(256);
+
+    writer.finish()
+}
+
+/// Compresses the data into the zlib format, as defined in [RFC 1950](https://datatracker.ietf.org/doc/html/rfc1950).
+///
+/// This is the format used by the `deflate` content encoding.
+pub fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut compressed = vec![0x78, 0x9c];
+    compressed.extend(deflate(data));
+    compressed.extend(adler32(data).to_be_bytes());
+
+    compressed
+}
+
+/// Compresses the data into the gzip format, as defined in [RFC 1952](https://datatracker.ietf.org/doc/html/rfc1952).
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    // Magic number, compression method, no flags, no modification time, no extra flags and an unknown OS
+    let mut compressed = vec![0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff];
+    compressed.extend(deflate(data));
+    compressed.extend(crc32(data).to_le_bytes());
+    compressed.extend((data.len() as u32).to_le_bytes());
+
+    compressed
+}
+
+/// Calculates the CRC-32 checksum of the data, as used by gzip.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(0xffffffff, |crc, byte| {
+        CRC_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Calculates the Adler-32 checksum of the data, as used by zlib.
+pub fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1_u32, 0_u32), |(a, b), byte| {
+        let a = (a + *byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+
+    (b << 16) | a
+}
+
+/// Generates the lookup table for the CRC-32 checksum.
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut n = 0;
+
+    while n < 256 {
+        let mut crc = n as u32;
+        let mut k = 0;
+
+        while k < 8 {
+            crc = if crc & 1 == 1 {
+                0xedb88320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            k += 1;
+        }
+
+        table[n] = crc;
+        n += 1;
+    }
+
+    table
+}
+
+/// Hashes the three bytes starting at the given position.
+fn hash(data: &[u8], position: usize) -> usize {
+    let value = (data[position] as u32) << 16
+        | (data[position + 1] as u32) << 8
+        | data[position + 2] as u32;
+
+    (value.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+}
+
+/// Records the position in the hash chains, so later data can refer back to it.
+fn insert(data: &[u8], position: usize, head: &mut [usize], prev: &mut [usize]) {
+    if position + MIN_MATCH <= data.len() {
+        let hash = hash(data, position);
+        prev[position % WINDOW_SIZE] = head[hash];
+        head[hash] = position;
+    }
+}
+
+/// Finds the longest match for the data at the given position within the window, returning its length and distance.
+fn longest_match(data: &[u8], position: usize, head: &[usize], prev: &[usize]) -> (usize, usize) {
+    if position + MIN_MATCH > data.len() {
+        return (0, 0);
+    }
+
+    let max_length = MAX_MATCH.min(data.len() - position);
+    let mut best = (0, 0);
+    let mut candidate = head[hash(data, position)];
+    let mut chain = 0;
+
+    while candidate != NONE && position - candidate <= WINDOW_SIZE && chain < MAX_CHAIN_LENGTH {
+        let length = data[candidate..]
+            .iter()
+            .zip(&data[position..position + max_length])
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        if length > best.0 {
+            best = (length, position - candidate);
+
+            if length == max_length {
+                break;
+            }
+        }
+
+        // Entries older than the window may have been overwritten by later positions
+        let next = prev[candidate % WINDOW_SIZE];
+        if next >= candidate {
+            break;
+        }
+
+        candidate = next;
+        chain += 1;
+    }
+
+    best
+}
+
+/// Writes values to a byte buffer, starting from the least significant bit of each byte.
+struct BitWriter {
+    buffer: Vec<u8>,
+    bits: u64,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    /// Creates a new bit writer with the given capacity in bytes.
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buffer: Vec::with_capacity(capacity),
+            bits: 0,
+            bit_count: 0,
+        }
+    }
+
+    /// Writes the lowest `count` bits of the value.
+    fn write_bits(&mut self, value: u32, count: u32) {
+        self.bits |= (value as u64) << self.bit_count;
+        self.bit_count += count;
+
+        while self.bit_count >= 8 {
+            self.buffer.push(self.bits as u8);
+            self.bits >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    /// Writes a Huffman code, which is packed starting from its most significant bit.
+    fn write_code(&mut self, code: u32, length: u32) {
+        self.write_bits(code.reverse_bits() >> (32 - length), length);
+    }
+
+    /// Writes the fixed Huffman code for the given literal/length symbol.
+    fn write_literal_code(&mut self, symbol: u16) {
+        let symbol = symbol as u32;
+
+        match symbol {
+            0..=143 => self.write_code(0x30 + symbol, 8),
+            144..=255 => self.write_code(0x190 + symbol - 144, 9),
+            256..=279 => self.write_code(symbol - 256, 7),
+            _ => self.write_code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    /// Writes a literal byte.
+    fn write_literal(&mut self, byte: u8) {
+        self.write_literal_code(byte as u16);
+    }
+
+    /// Writes the length of a match, followed by its extra bits.
+    fn write_length(&mut self, length: usize) {
+        let code = LENGTH_BASES
+            .iter()
+            .rposition(|base| *base as usize <= length)
+            .unwrap();
+
+        self.write_literal_code(257 + code as u16);
+        self.write_bits(
+            (length - LENGTH_BASES[code] as usize) as u32,
+            LENGTH_EXTRA_BITS[code] as u32,
+        );
+    }
+
+    /// Writes the distance of a match, followed by its extra bits.
+    fn write_distance(&mut self, distance: usize) {
+        let code = DISTANCE_BASES
+            .iter()
+            .rposition(|base| *base as usize <= distance)
+            .unwrap();
+
+        self.write_code(code as u32, 5);
+        self.write_bits(
+            (distance - DISTANCE_BASES[code] as usize) as u32,
+            DISTANCE_EXTRA_BITS[code] as u32,
+        );
+    }
+
+    /// Writes any remaining bits, padding the final byte with zeroes, and returns the buffer.
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.buffer.push(self.bits as u8);
+        }
+
+        self.buffer
+    }
+}
@@ -176,10 +176,18 @@ pub enum HeaderType {
     From,
     /// Specifies the host to which the request is being sent, e.g. "www.example.com".
     Host,
+    /// Makes the request conditional on the resource having been modified since the given date.
+    IfModifiedSince,
+    /// Makes the request conditional on the resource not matching any of the given entity tags.
+    IfNoneMatch,
+    /// Makes a range request conditional on the resource matching the given entity tag or date.
+    IfRange,
     /// Indicates the origin that caused the request.
     Origin,
     /// Contains backwards-compatible caching information.
     Pragma,
+    /// Requests only the given ranges of the resource.
+    Range,
     /// Indicates the absolute or partial address of the page making the request.
     Referer,
     /// Indicates that the connection is to be upgraded to a different protocol, e.g. WebSocket.
@@ -197,6 +205,8 @@ pub enum HeaderType {
     AccessControlAllowHeaders,
     /// Indicates whether certain methods can be used.
     AccessControlAllowMethods,
+    /// Indicates whether the server supports range requests, and the unit in which ranges can be requested.
+    AcceptRanges,
     /// Contains the time in seconds that the object has been cached.
     Age,
     /// The set of methods supported by the resource.
@@ -207,6 +217,8 @@ pub enum HeaderType {
     ContentLanguage,
     /// Indicates an alternative location for the returned data.
     ContentLocation,
+    /// Indicates the position of a partial payload body within the full representation.
+    ContentRange,
     /// Identifies a specific version of a resource.
     ETag,
     /// Contains the date and time at which the response is considered expired.
@@ -223,6 +235,8 @@ pub enum HeaderType {
     SetCookie,
     /// Indicates the encoding used in the transfer of the payload body.
     TransferEncoding,
+    /// Lists the request headers which were used to select the representation in the response.
+    Vary,
 
     /// Custom header with a lowercase name
     Custom(String),
@@ -265,8 +279,12 @@ impl From<&str> for HeaderType {
             "forwarded" => Self::Forwarded,
             "from" => Self::From,
             "host" => Self::Host,
+            "if-modified-since" => Self::IfModifiedSince,
+            "if-none-match" => Self::IfNoneMatch,
+            "if-range" => Self::IfRange,
             "origin" => Self::Origin,
             "pragma" => Self::Pragma,
+            "range" => Self::Range,
             "referer" => Self::Referer,
             "upgrade" => Self::Upgrade,
             "user-agent" => Self::UserAgent,
@@ -275,11 +293,13 @@ impl From<&str> for HeaderType {
             "access-control-allow-origin" => Self::AccessControlAllowOrigin,
             "access-control-allow-headers" => Self::AccessControlAllowHeaders,
             "access-control-allow-methods" => Self::AccessControlAllowMethods,
+            "accept-ranges" => Self::AcceptRanges,
             "age" => Self::Age,
             "allow" => Self::Allow,
             "content-disposition" => Self::ContentDisposition,
             "content-language" => Self::ContentLanguage,
             "content-location" => Self::ContentLocation,
+            "content-range" => Self::ContentRange,
             "etag" => Self::ETag,
             "expires" => Self::Expires,
             "last-modified" => Self::LastModified,
@@ -288,6 +308,7 @@ impl From<&str> for HeaderType {
             "server" => Self::Server,
             "set-cookie" => Self::SetCookie,
             "transfer-encoding" => Self::TransferEncoding,
+            "vary" => Self::Vary,
             custom => Self::Custom(custom.to_string()),
         }
     }
@@ -318,8 +339,12 @@ impl ToString for HeaderType {
             HeaderType::Forwarded => "Forwarded",
             HeaderType::From => "From",
             HeaderType::Host => "Host",
+            HeaderType::IfModifiedSince => "If-Modified-Since",
+            HeaderType::IfNoneMatch => "If-None-Match",
+            HeaderType::IfRange => "If-Range",
             HeaderType::Origin => "Origin",
             HeaderType::Pragma => "Pragma",
+            HeaderType::Range => "Range",
             HeaderType::Referer => "Referer",
             HeaderType::Upgrade => "Upgrade",
             HeaderType::UserAgent => "User-Agent",
@@ -328,11 +353,13 @@ impl ToString for HeaderType {
             HeaderType::AccessControlAllowOrigin => "Access-Control-Allow-Origin",
             HeaderType::AccessControlAllowHeaders => "Access-Control-Allow-Headers",
             HeaderType::AccessControlAllowMethods => "Access-Control-Allow-Methods",
+            HeaderType::AcceptRanges => "Accept-Ranges",
             HeaderType::Age => "Age",
             HeaderType::Allow => "Allow",
             HeaderType::ContentDisposition => "Content-Disposition",
             HeaderType::ContentLanguage => "Content-Language",
             HeaderType::ContentLocation => "Content-Location",
+            HeaderType::ContentRange => "Content-Range",
             HeaderType::ETag => "ETag",
             HeaderType::Expires => "Expires",
             HeaderType::LastModified => "Last-Modified",
@@ -341,6 +368,7 @@ impl ToString for HeaderType {
             HeaderType::Server => "Server",
             HeaderType::SetCookie => "Set-Cookie",
             HeaderType::TransferEncoding => "Transfer-Encoding",
+            HeaderType::Vary => "Vary",
             _ => "",
         }
         .to_string()
@@ -365,6 +393,7 @@ impl HeaderType {
             HeaderType::AccessControlAllowMethods => HeaderCategory::Other,
             HeaderType::Age => HeaderCategory::Response,
             HeaderType::Allow => HeaderCategory::Entity,
+            HeaderType::AcceptRanges => HeaderCategory::Response,
             HeaderType::CacheControl => HeaderCategory::General,
             HeaderType::Connection => HeaderCategory::General,
             HeaderType::ContentDisposition => HeaderCategory::Entity,
@@ -372,6 +401,7 @@ impl HeaderType {
             HeaderType::ContentLanguage => HeaderCategory::Entity,
             HeaderType::ContentLength => HeaderCategory::Entity,
             HeaderType::ContentLocation => HeaderCategory::Entity,
+            HeaderType::ContentRange => HeaderCategory::Entity,
             HeaderType::ContentType => HeaderCategory::Entity,
             HeaderType::Date => HeaderCategory::General,
             HeaderType::ETag => HeaderCategory::Response,
@@ -383,6 +413,7 @@ impl HeaderType {
             HeaderType::Server => HeaderCategory::Response,
             HeaderType::SetCookie => HeaderCategory::Other,
             HeaderType::TransferEncoding => HeaderCategory::Entity,
+            HeaderType::Vary => HeaderCategory::Response,
             HeaderType::Upgrade => HeaderCategory::General,
             HeaderType::Via => HeaderCategory::General,
             HeaderType::Warning => HeaderCategory::General,
@@ -398,6 +429,10 @@ impl HeaderType {
             HeaderType::Forwarded => HeaderCategory::Response,
             HeaderType::From => HeaderCategory::Response,
             HeaderType::Host => HeaderCategory::General,
+            HeaderType::IfModifiedSince => HeaderCategory::General,
+            HeaderType::IfNoneMatch => HeaderCategory::General,
+            HeaderType::IfRange => HeaderCategory::General,
+            HeaderType::Range => HeaderCategory::General,
             HeaderType::Origin => HeaderCategory::General,
             HeaderType::Referer => HeaderCategory::General,
             HeaderType::UserAgent => HeaderCategory::General,
@@ -17,11 +17,24 @@ pub enum Method {
     Delete,
     /// The `OPTIONS` method.
     Options,
+    /// The `HEAD` method.
+    Head,
+    /// The `PATCH` method.
+    Patch,
+    /// The `CONNECT` method.
+    Connect,
+    /// The `TRACE` method.
+    Trace,
+    /// Any other method, for example `PROPFIND`.
+    Other(String),
 }
 
 impl Method {
     /// Attempts to convert from the HTTP verb into an enum variant.
     ///
+    /// Methods are case-sensitive, so unrecognised methods are only accepted as `Method::Other`
+    ///   if they are made up of uppercase letters, digits, hyphens and underscores.
+    ///
     /// ## Example
     /// ```
     /// let method = Method::from_name("GET");
@@ -34,9 +47,25 @@ impl Method {
             "PUT" => Ok(Self::Put),
             "DELETE" => Ok(Self::Delete),
             "OPTIONS" => Ok(Self::Options),
+            "HEAD" => Ok(Self::Head),
+            "PATCH" => Ok(Self::Patch),
+            "CONNECT" => Ok(Self::Connect),
+            "TRACE" => Ok(Self::Trace),
+            other if is_extension_method(other) => Ok(Self::Other(other.to_string())),
             _ => Err(RequestError::Request),
         }
     }
+
+    /// Returns `true` if the method is idempotent, meaning that making the same request several times has the same
+    ///   effect as making it once, so it can safely be retried.
+    ///
+    /// Unrecognised methods are assumed not to be idempotent.
+    pub fn is_idempotent(&self) -> bool {
+        matches!(
+            self,
+            Self::Get | Self::Head | Self::Put | Self::Delete | Self::Options | Self::Trace
+        )
+    }
 }
 
 impl Display for Method {
@@ -50,7 +79,29 @@ impl Display for Method {
                 Method::Put => "PUT",
                 Method::Delete => "DELETE",
                 Method::Options => "OPTIONS",
+                Method::Head => "HEAD",
+                Method::Patch => "PATCH",
+                Method::Connect => "CONNECT",
+                Method::Trace => "TRACE",
+                Method::Other(name) => name,
             }
         )
     }
 }
+
+/// Checks whether the given name is acceptable as an extension method.
+fn is_extension_method(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'-' || b == b'_')
+}
+
+/// Joins the given methods into a comma-separated list, as used in the `Allow` header.
+pub(crate) fn join_methods(methods: &[Method]) -> String {
+    methods
+        .iter()
+        .map(|m| m.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -1,13 +1,21 @@
 //! Contains the Humphrey HTTP implementation.
 
 pub mod address;
+pub mod body;
+pub mod compression;
+pub mod conditional;
 pub mod cookie;
 pub mod cors;
 pub mod date;
+pub mod deflate;
 pub mod headers;
 pub mod method;
 pub mod mime;
+pub mod multipart;
+pub mod params;
 pub mod proxy;
+pub mod query;
+pub mod range;
 pub mod request;
 pub mod response;
 pub mod status;
@@ -0,0 +1,414 @@
+//! Provides functionality for parsing `multipart/form-data` request bodies.
+
+use crate::http::headers::{HeaderType, Headers};
+
+use std::error::Error;
+
+#[cfg(not(feature = "tokio"))]
+use std::io::Read;
+
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// The size of the buffer used when reading a multipart body from a stream.
+const MULTIPART_BUFFER_SIZE: usize = 8192;
+
+/// The maximum size of the headers of a single part.
+const MAX_PART_HEADERS_SIZE: usize = 8192;
+
+/// One part of a `multipart/form-data` body, such as a form field or an uploaded file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Part {
+    /// The headers of the part.
+    pub headers: Headers,
+    /// The name of the form field, from the `Content-Disposition` header.
+    pub name: Option<String>,
+    /// The name of the uploaded file, from the `Content-Disposition` header.
+    pub filename: Option<String>,
+    /// The content type of the part, from the `Content-Type` header.
+    pub content_type: Option<String>,
+    /// The body of the part.
+    pub body: Vec<u8>,
+}
+
+/// An error which occurred during the parsing of a multipart body.
+#[derive(Debug, PartialEq, Eq)]
+pub enum MultipartError {
+    /// The content type is not `multipart/form-data`, or has no boundary.
+    Boundary,
+    /// The body could not be parsed due to invalid data.
+    Malformed,
+    /// A part exceeded the maximum part size.
+    PartTooLarge,
+    /// The body exceeded the maximum size.
+    TooLarge,
+    /// The body could not be read due to an issue with the stream.
+    Stream,
+}
+
+impl std::fmt::Display for MultipartError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "MultipartError")
+    }
+}
+
+impl Error for MultipartError {}
+
+/// A parser for `multipart/form-data` bodies.
+///
+/// The body can be parsed from memory with `parse`, or read from a stream such as the body of a
+///   streaming route with `read_from`, in which case the size limits are enforced as the body arrives.
+///
+/// ## Example
+/// ```
+/// use humphrey::http::multipart::Multipart;
+///
+/// let body = b"--boundary\r\nContent-Disposition: form-data; name=\"field\"\r\n\r\nvalue\r\n--boundary--\r\n";
+/// let parts = Multipart::new("boundary").parse(body).unwrap();
+///
+/// assert_eq!(parts[0].name.as_deref(), Some("field"));
+/// assert_eq!(parts[0].body, b"value");
+/// ```
+#[derive(Debug)]
+pub struct Multipart {
+    delimiter: Vec<u8>,
+    max_part_size: Option<usize>,
+    max_size: Option<usize>,
+    state: ParserState,
+    buffer: Vec<u8>,
+    size: usize,
+    part: Option<Part>,
+    parts: Vec<Part>,
+}
+
+/// The position of the parser within the body.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ParserState {
+    /// Skipping the preamble before the first boundary.
+    Preamble,
+    /// Expecting the end of a boundary line, or `--` if it was the final boundary.
+    Boundary,
+    /// Reading the headers of a part.
+    Headers,
+    /// Reading the body of a part.
+    Body,
+    /// The final boundary has been read.
+    Done,
+}
+
+impl Multipart {
+    /// Creates a new parser for a body with the given boundary.
+    pub fn new(boundary: impl AsRef<str>) -> Self {
+        Self {
+            delimiter: format!("\r\n--{}", boundary.as_ref()).into_bytes(),
+            max_part_size: None,
+            max_size: None,
+            state: ParserState::Preamble,
+            // The first boundary is not necessarily preceded by a line break
+            buffer: b"\r\n".to_vec(),
+            size: 0,
+            part: None,
+            parts: Vec::new(),
+        }
+    }
+
+    /// Creates a new parser using the boundary from the `Content-Type` header in the given headers.
+    ///
+    /// Returns `MultipartError::Boundary` if the content type is not `multipart/form-data` or has no boundary.
+    pub fn from_headers(headers: &Headers) -> Result<Self, MultipartError> {
+        let content_type = headers
+            .get(HeaderType::ContentType)
+            .ok_or(MultipartError::Boundary)?;
+        let mut params = content_type.split(';');
+
+        if !params
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .eq_ignore_ascii_case("multipart/form-data")
+        {
+            return Err(MultipartError::Boundary);
+        }
+
+        let boundary = params
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("boundary"))
+            .map(|(_, boundary)| boundary.trim().trim_matches('"'))
+            .filter(|boundary| !boundary.is_empty())
+            .ok_or(MultipartError::Boundary)?;
+
+        Ok(Self::new(boundary))
+    }
+
+    /// Sets the maximum size of the body of a single part in bytes.
+    pub fn with_max_part_size(mut self, max_part_size: Option<usize>) -> Self {
+        self.max_part_size = max_part_size;
+        self
+    }
+
+    /// Sets the maximum size of the entire multipart body in bytes.
+    pub fn with_max_size(mut self, max_size: Option<usize>) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Parses the given multipart body, returning its parts.
+    pub fn parse(mut self, body: &[u8]) -> Result<Vec<Part>, MultipartError> {
+        self.feed(body)?;
+        self.finish()
+    }
+
+    /// Reads and parses a multipart body from the given reader, returning its parts.
+    #[cfg(not(feature = "tokio"))]
+    pub fn read_from<R>(mut self, reader: &mut R) -> Result<Vec<Part>, MultipartError>
+    where
+        R: Read + ?Sized,
+    {
+        let mut buf = vec![0u8; MULTIPART_BUFFER_SIZE];
+
+        loop {
+            match reader.read(&mut buf).map_err(|_| MultipartError::Stream)? {
+                0 => return self.finish(),
+                n => self.feed(&buf[..n])?,
+            }
+        }
+    }
+
+    /// Reads and parses a multipart body from the given asynchronous reader, returning its parts.
+    #[cfg(feature = "tokio")]
+    pub async fn read_from<R>(mut self, reader: &mut R) -> Result<Vec<Part>, MultipartError>
+    where
+        R: AsyncRead + Unpin + ?Sized,
+    {
+        let mut buf = vec![0u8; MULTIPART_BUFFER_SIZE];
+
+        loop {
+            match reader
+                .read(&mut buf)
+                .await
+                .map_err(|_| MultipartError::Stream)?
+            {
+                0 => return self.finish(),
+                n => self.feed(&buf[..n])?,
+            }
+        }
+    }
+
+    /// Adds the given data to the parser, parsing as much of it as possible.
+    fn feed(&mut self, data: &[u8]) -> Result<(), MultipartError> {
+        self.size += data.len();
+
+        if self.max_size.is_some_and(|max_size| self.size > max_size) {
+            return Err(MultipartError::TooLarge);
+        }
+
+        // Anything after the final boundary is an epilogue and is ignored
+        if self.state != ParserState::Done {
+            self.buffer.extend_from_slice(data);
+        }
+
+        while self.advance()? {}
+
+        Ok(())
+    }
+
+    /// Attempts to parse the next section of the buffered data, returning whether any progress was made.
+    fn advance(&mut self) -> Result<bool, MultipartError> {
+        match self.state {
+            ParserState::Preamble => match find(&self.buffer, &self.delimiter) {
+                Some(index) => {
+                    self.buffer.drain(..index + self.delimiter.len());
+                    self.state = ParserState::Boundary;
+                    Ok(true)
+                }
+                None => {
+                    let keep = self.delimiter.len() - 1;
+
+                    if self.buffer.len() > keep {
+                        self.buffer.drain(..self.buffer.len() - keep);
+                    }
+
+                    Ok(false)
+                }
+            },
+            ParserState::Boundary => {
+                // Skip any whitespace after the boundary
+                let start = self
+                    .buffer
+                    .iter()
+                    .position(|byte| *byte != b' ' && *byte != b'\t');
+
+                let Some(start) = start else {
+                    self.buffer.clear();
+                    return Ok(false);
+                };
+
+                match self.buffer.get(start..start + 2) {
+                    Some(b"--") => {
+                        self.buffer.clear();
+                        self.state = ParserState::Done;
+                        Ok(true)
+                    }
+                    Some(b"\r\n") => {
+                        self.buffer.drain(..start + 2);
+                        self.state = ParserState::Headers;
+                        Ok(true)
+                    }
+                    Some(_) => Err(MultipartError::Malformed),
+                    None if self.buffer[start] == b'-' || self.buffer[start] == b'\r' => Ok(false),
+                    None => Err(MultipartError::Malformed),
+                }
+            }
+            ParserState::Headers => {
+                let headers_end = if self.buffer.starts_with(b"\r\n") {
+                    Some(0)
+                } else {
+                    find(&self.buffer, b"\r\n\r\n").map(|index| index + 2)
+                };
+
+                match headers_end {
+                    Some(index) => {
+                        let part = parse_part_headers(&self.buffer[..index])?;
+                        self.buffer.drain(..index + 2);
+                        self.part = Some(part);
+                        self.state = ParserState::Body;
+                        Ok(true)
+                    }
+                    None if self.buffer.len() > MAX_PART_HEADERS_SIZE => {
+                        Err(MultipartError::Malformed)
+                    }
+                    None => Ok(false),
+                }
+            }
+            ParserState::Body => {
+                let (length, found) = match find(&self.buffer, &self.delimiter) {
+                    Some(index) => (index, true),
+                    // Keep enough data to recognise a delimiter which is split across reads
+                    None => (
+                        self.buffer.len().saturating_sub(self.delimiter.len() - 1),
+                        false,
+                    ),
+                };
+
+                let part = self.part.as_mut().ok_or(MultipartError::Malformed)?;
+
+                if self
+                    .max_part_size
+                    .is_some_and(|max_part_size| part.body.len() + length > max_part_size)
+                {
+                    return Err(MultipartError::PartTooLarge);
+                }
+
+                part.body.extend(self.buffer.drain(..length));
+
+                if found {
+                    self.buffer.drain(..self.delimiter.len());
+                    self.parts.extend(self.part.take());
+                    self.state = ParserState::Boundary;
+                }
+
+                Ok(found)
+            }
+            ParserState::Done => Ok(false),
+        }
+    }
+
+    /// Finishes parsing, returning the parts if the final boundary was reached.
+    fn finish(self) -> Result<Vec<Part>, MultipartError> {
+        match self.state {
+            ParserState::Done => Ok(self.parts),
+            _ => Err(MultipartError::Malformed),
+        }
+    }
+}
+
+/// Parses the headers of a part, including the trailing line break of the last header.
+fn parse_part_headers(data: &[u8]) -> Result<Part, MultipartError> {
+    let data = std::str::from_utf8(data).map_err(|_| MultipartError::Malformed)?;
+    let mut headers = Headers::new();
+
+    for line in data.split("\r\n").filter(|line| !line.is_empty()) {
+        let (name, value) = line.split_once(':').ok_or(MultipartError::Malformed)?;
+        headers.add(HeaderType::from(name.trim()), value.trim());
+    }
+
+    let disposition = headers
+        .get(HeaderType::ContentDisposition)
+        .map(parse_disposition);
+    let (name, filename) = disposition.unwrap_or_default();
+    let content_type = headers.get(HeaderType::ContentType).map(|s| s.to_string());
+
+    Ok(Part {
+        headers,
+        name,
+        filename,
+        content_type,
+        body: Vec::new(),
+    })
+}
+
+/// Extracts the `name` and `filename` parameters from a `Content-Disposition` header.
+fn parse_disposition(disposition: &str) -> (Option<String>, Option<String>) {
+    let mut name = None;
+    let mut filename = None;
+    let mut rest = disposition;
+
+    // Skip the disposition type
+    if let Some(index) = rest.find(';') {
+        rest = &rest[index + 1..];
+    } else {
+        return (name, filename);
+    }
+
+    loop {
+        rest = rest.trim_start_matches([' ', '\t', ';']);
+
+        let Some(equals) = rest.find('=') else {
+            break;
+        };
+
+        let key = rest[..equals].trim().to_ascii_lowercase();
+        rest = rest[equals + 1..].trim_start();
+
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            // Quoted values may contain semicolons and escaped characters
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = quoted.len();
+
+            while let Some((index, c)) = chars.next() {
+                match c {
+                    '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                    '"' => {
+                        end = index + 1;
+                        break;
+                    }
+                    c => value.push(c),
+                }
+            }
+
+            rest = &quoted[end..];
+            value
+        } else {
+            let end = rest.find(';').unwrap_or(rest.len());
+            let value = rest[..end].trim().to_string();
+            rest = &rest[end..];
+            value
+        };
+
+        match key.as_str() {
+            "name" => name = Some(value),
+            "filename" => filename = Some(value),
+            _ => (),
+        }
+    }
+
+    (name, filename)
+}
+
+/// Finds the first occurrence of the needle in the haystack.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
@@ -0,0 +1,74 @@
+//! Provides functionality for accessing the parameters captured from a request's path.
+
+use std::str::FromStr;
+
+/// The parameters captured from a request's path by the route which matched it.
+///
+/// Named parameters are captured by segments of the route beginning with `:`, for example the route
+///   `/users/:id/posts/:post_id` captures `id` and `post_id`. The part of the path matched by a trailing `*`
+///   is captured as the wildcard.
+///
+/// Every segment beginning with `:` is a named parameter, so it matches any non-empty segment of the path rather
+///   than only the literal text. A route which needs to match a segment such as `:latest` literally can use a
+///   wildcard in its place, for example `/files/*latest`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Params {
+    params: Vec<(String, String)>,
+    wildcard: Option<String>,
+}
+
+impl Params {
+    /// Creates a new empty set of parameters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a named parameter.
+    pub fn with_param(mut self, name: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        self.params
+            .push((name.as_ref().to_string(), value.as_ref().to_string()));
+        self
+    }
+
+    /// Sets the part of the path matched by the wildcard.
+    pub fn with_wildcard(mut self, wildcard: impl AsRef<str>) -> Self {
+        self.wildcard = Some(wildcard.as_ref().to_string());
+        self
+    }
+
+    /// Gets the percent-decoded value of the named parameter, if it was captured.
+    pub fn get(&self, name: impl AsRef<str>) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param == name.as_ref())
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Parses the value of the named parameter into the given type.
+    ///
+    /// Returns `None` if the parameter was not captured or could not be parsed.
+    pub fn parse<T>(&self, name: impl AsRef<str>) -> Option<T>
+    where
+        T: FromStr,
+    {
+        self.get(name).and_then(|value| value.parse().ok())
+    }
+
+    /// Gets the part of the path matched by a trailing wildcard, if the route ends with one.
+    /// Unlike named parameters, the wildcard is not percent-decoded.
+    ///
+    /// For example, the route `/static/*` matches the path `/static/css/style.css` with the wildcard `css/style.css`.
+    pub fn wildcard(&self) -> Option<&str> {
+        self.wildcard.as_deref()
+    }
+
+    /// Returns the number of named parameters.
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    /// Returns `true` if there are no named parameters.
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+}
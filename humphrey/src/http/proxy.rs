@@ -1,38 +1,435 @@
 //! Provides functionality for HTTP proxying.
 
+use crate::http::headers::{HeaderType, Headers};
+use crate::http::method::Method;
 use crate::http::response::ResponseError;
 use crate::http::{Request, Response, StatusCode};
 
-use std::io::Write;
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
 use std::net::{SocketAddr, TcpStream};
-use std::time::Duration;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The default maximum number of idle connections kept open to each target by a connection pool.
+pub const DEFAULT_MAX_IDLE_CONNECTIONS: usize = 32;
+
+/// The default amount of time an idle connection is kept open for by a connection pool.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The headers which only apply to a single connection, so must not be forwarded by proxies, as defined in
+///   [RFC 9110](https://datatracker.ietf.org/doc/html/rfc9110#section-7.6.1). Any other headers listed in the
+///   `Connection` header are also removed.
+pub const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "Connection",
+    "Keep-Alive",
+    "Proxy-Connection",
+    "Proxy-Authenticate",
+    "Proxy-Authorization",
+    "TE",
+    "Trailer",
+    "Transfer-Encoding",
+    "Upgrade",
+];
+
+/// The pseudonym used to identify the proxy in the `Via` header.
+const VIA_PSEUDONYM: &str = "humphrey";
+
+/// An error which occurred while proxying a request.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProxyError {
+    /// The target could not be connected to, so the request was not sent.
+    Connect,
+    /// The request was sent, but a valid response was not received.
+    Response(ResponseError),
+}
+
+impl std::fmt::Display for ProxyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Connect => write!(f, "Could not connect to the proxy target"),
+            Self::Response(_) => write!(f, "Invalid response from the proxy target"),
+        }
+    }
+}
+
+impl std::error::Error for ProxyError {}
 
 /// Proxies a request to the given target, timing out and returning an error 502 after `timeout`.
 /// Always returns a response.
 pub fn proxy_request(request: &Request, target: SocketAddr, timeout: Duration) -> Response {
-    match proxy_request_internal(request, target, timeout) {
+    match try_proxy_request(request, target, timeout) {
         Ok(response) => response,
-        Err(_) => Response::empty(StatusCode::BadGateway)
-            .with_bytes(b"<html><body><h1>502 Bad Gateway</h1></body></html>"),
+        Err(_) => bad_gateway(),
     }
 }
 
-fn proxy_request_internal(
+/// Proxies a request to the given target, timing out after `timeout`.
+///
+/// Unlike `proxy_request`, this returns an error if the target could not be reached, so the request can be retried
+///   with another target. The request is prepared with `forwarded_request` before it is sent, and a new connection
+///   is used which is closed afterwards. Use a `ConnectionPool` to reuse connections between requests.
+pub fn try_proxy_request(
     request: &Request,
     target: SocketAddr,
     timeout: Duration,
-) -> Result<Response, ResponseError> {
-    let mut stream =
-        TcpStream::connect_timeout(&target, timeout).map_err(|_| ResponseError::Stream)?;
+) -> Result<Response, ProxyError> {
+    ConnectionPool::new()
+        .with_max_idle_connections(0)
+        .proxy_request(request, target, timeout)
+}
+
+/// Returns a copy of the request prepared to be forwarded to a proxy target.
+///
+/// Hop-by-hop headers are removed, the `X-Forwarded-For`, `X-Forwarded-Proto` and `X-Forwarded-Host` headers are
+///   replaced with the details of the client and the proxies trusted to have forwarded the request, and the proxy is
+///   added to the `Via` header. The `Host` header is left unchanged.
+///
+/// Unless a trusted proxy forwarded the protocol used by the client, `X-Forwarded-Proto` is `https` if the request
+///   was received over TLS and `http` otherwise.
+pub fn forwarded_request(request: &Request) -> Request {
+    let mut proxied_request = request.clone();
+    let headers = &mut proxied_request.headers;
+
+    remove_hop_by_hop_headers(headers);
+
+    let forwarded_for = std::iter::once(&request.address.origin_addr)
+        .chain(request.address.proxies.iter())
+        .map(|addr| addr.to_string())
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    let forwarded_proto = request.address.forwarded_proto.clone().unwrap_or_else(|| {
+        if request.address.tls {
+            "https".to_string()
+        } else {
+            "http".to_string()
+        }
+    });
+
+    let forwarded_host = request
+        .address
+        .forwarded_host
+        .clone()
+        .or_else(|| request.headers.get(HeaderType::Host).map(str::to_string));
+
+    headers.remove("X-Forwarded-For");
+    headers.remove("X-Forwarded-Proto");
+    headers.remove("X-Forwarded-Host");
+
+    headers.add("X-Forwarded-For", forwarded_for);
+    headers.add("X-Forwarded-Proto", forwarded_proto);
+
+    if let Some(forwarded_host) = forwarded_host {
+        headers.add("X-Forwarded-Host", forwarded_host);
+    }
+
+    headers.add(HeaderType::Via, via(&request.version));
+
+    proxied_request
+}
 
-    let mut cloned_request = request.clone();
-    cloned_request
+/// Prepares a response received from a proxy target to be sent on to the client, removing its hop-by-hop headers and
+///   adding the proxy to the `Via` header.
+fn forwarded_response(mut response: Response) -> Response {
+    remove_hop_by_hop_headers(&mut response.headers);
+    response
         .headers
-        .add("X-Forwarded-For", request.address.origin_addr.to_string());
-    let request_bytes: Vec<u8> = cloned_request.into();
+        .add(HeaderType::Via, via(&response.version));
+    response
+}
+
+/// Removes the hop-by-hop headers, including any listed in the `Connection` header, from the headers.
+pub fn remove_hop_by_hop_headers(headers: &mut Headers) {
+    let listed: Vec<String> = headers
+        .get_all(HeaderType::Connection)
+        .into_iter()
+        .flat_map(|connection| connection.split(','))
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    for name in HOP_BY_HOP_HEADERS
+        .iter()
+        .copied()
+        .chain(listed.iter().map(String::as_str))
+    {
+        headers.remove(name);
+    }
+}
+
+/// Returns the value of the `Via` header identifying the proxy for a message with the given HTTP version.
+fn via(version: &str) -> String {
+    format!(
+        "{} {}",
+        version.strip_prefix("HTTP/").unwrap_or(version),
+        VIA_PSEUDONYM
+    )
+}
+
+/// Returns the error 502 response sent when a request could not be proxied.
+pub fn bad_gateway() -> Response {
+    Response::empty(StatusCode::BadGateway)
+        .with_bytes(b"<html><body><h1>502 Bad Gateway</h1></body></html>")
+}
+
+/// A pool of persistent connections to proxy targets, which are reused between requests rather than opening a new
+///   connection for each one.
+///
+/// Connections are only kept if the target allows it, and are closed once they have been idle for longer than the
+///   idle timeout. At most the maximum number of idle connections are kept open to each target.
+///
+/// The limit only applies to idle connections. Connections in use are not counted, so a new one is opened whenever
+///   a request is sent while every pooled connection to the target is busy, and closed afterwards if the pool is
+///   already full.
+#[derive(Debug)]
+pub struct ConnectionPool {
+    idle: Mutex<HashMap<SocketAddr, Vec<IdleConnection>>>,
+    max_idle_connections: usize,
+    idle_timeout: Duration,
+}
+
+/// A connection in the pool which is waiting to be reused.
+#[derive(Debug)]
+struct IdleConnection {
+    stream: TcpStream,
+    since: Instant,
+}
+
+/// An error which occurred while sending a request on a connection and reading the response.
+enum ExchangeError {
+    /// The request could not be sent.
+    Write,
+    /// The response could not be read, after receiving the given number of bytes of it.
+    Read(ResponseError, usize),
+}
+
+impl ConnectionPool {
+    /// Creates a new, empty connection pool with the default limits.
+    pub fn new() -> Self {
+        Self {
+            idle: Mutex::new(HashMap::new()),
+            max_idle_connections: DEFAULT_MAX_IDLE_CONNECTIONS,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+        }
+    }
+
+    /// Sets the maximum number of idle connections kept open to each target.
+    /// This does not limit the number of connections in use at once. If this is zero, connections are never reused.
+    pub fn with_max_idle_connections(mut self, max_idle_connections: usize) -> Self {
+        self.max_idle_connections = max_idle_connections;
+        self
+    }
+
+    /// Sets the amount of time an idle connection is kept open for.
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Returns the maximum number of idle connections kept open to each target.
+    pub fn max_idle_connections(&self) -> usize {
+        self.max_idle_connections
+    }
+
+    /// Returns the amount of time an idle connection is kept open for.
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    /// Returns the number of idle connections to the target which are currently in the pool.
+    pub fn idle_connections(&self, target: SocketAddr) -> usize {
+        self.idle
+            .lock()
+            .unwrap()
+            .get(&target)
+            .map_or(0, |connections| connections.len())
+    }
+
+    /// Proxies a request to the given target, reusing an idle connection from the pool if there is one, and timing
+    ///   out after `timeout`. The request is prepared with `forwarded_request` before it is sent.
+    pub fn proxy_request(
+        &self,
+        request: &Request,
+        target: SocketAddr,
+        timeout: Duration,
+    ) -> Result<Response, ProxyError> {
+        self.send_request(&forwarded_request(request), target, timeout)
+    }
+
+    /// Sends a request which has already been prepared to be forwarded to the given target, reusing an idle
+    ///   connection from the pool if there is one, and timing out after `timeout`.
+    ///
+    /// Targets may close idle connections at any time, so if a reused connection turns out to have been closed before
+    ///   any of the response was received, the request is sent again on another connection as long as it is safe to
+    ///   do so. The hop-by-hop headers of the response are removed.
+    pub fn send_request(
+        &self,
+        request: &Request,
+        target: SocketAddr,
+        timeout: Duration,
+    ) -> Result<Response, ProxyError> {
+        let mut proxied_request = request.clone();
+        proxied_request.headers.remove(HeaderType::Connection);
+
+        match self.max_idle_connections {
+            0 => proxied_request.headers.add(HeaderType::Connection, "close"),
+            _ => proxied_request
+                .headers
+                .add(HeaderType::Connection, "keep-alive"),
+        }
+
+        let request_bytes: Vec<u8> = proxied_request.into();
+        let head = request.method == Method::Head;
+
+        while let Some(mut stream) = self.take(target) {
+            match exchange(&mut stream, &request_bytes, head, timeout) {
+                Ok(response) => {
+                    self.release(target, stream, request, &response);
+                    return Ok(forwarded_response(response));
+                }
+                Err(ExchangeError::Write) => continue,
+                Err(ExchangeError::Read(_, 0)) if request.method.is_idempotent() => continue,
+                Err(ExchangeError::Read(e, _)) => return Err(ProxyError::Response(e)),
+            }
+        }
+
+        let mut stream =
+            TcpStream::connect_timeout(&target, timeout).map_err(|_| ProxyError::Connect)?;
+
+        match exchange(&mut stream, &request_bytes, head, timeout) {
+            Ok(response) => {
+                self.release(target, stream, request, &response);
+                Ok(forwarded_response(response))
+            }
+            Err(ExchangeError::Write) => Err(ProxyError::Response(ResponseError::Stream)),
+            Err(ExchangeError::Read(e, _)) => Err(ProxyError::Response(e)),
+        }
+    }
+
+    /// Takes the most recently used idle connection to the target which is still open out of the pool, closing any
+    ///   which have timed out or been closed by the target.
+    fn take(&self, target: SocketAddr) -> Option<TcpStream> {
+        let mut idle = self.idle.lock().unwrap();
+        let connections = idle.get_mut(&target)?;
+
+        while let Some(connection) = connections.pop() {
+            if connection.since.elapsed() < self.idle_timeout && is_open(&connection.stream) {
+                return Some(connection.stream);
+            }
+        }
+
+        None
+    }
+
+    /// Returns the connection to the pool after a response has been received on it, unless it cannot be reused or
+    ///   the pool is full, in which case it is closed.
+    fn release(
+        &self,
+        target: SocketAddr,
+        stream: TcpStream,
+        request: &Request,
+        response: &Response,
+    ) {
+        if !is_reusable(request, response) {
+            return;
+        }
+
+        let mut idle = self.idle.lock().unwrap();
+        let connections = idle.entry(target).or_default();
+        connections.retain(|connection| connection.since.elapsed() < self.idle_timeout);
+
+        if connections.len() < self.max_idle_connections {
+            connections.push(IdleConnection {
+                stream,
+                since: Instant::now(),
+            });
+        }
+    }
+}
+
+impl Default for ConnectionPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sends the request on the connection and reads the response.
+fn exchange(
+    stream: &mut TcpStream,
+    request_bytes: &[u8],
+    head: bool,
+    timeout: Duration,
+) -> Result<Response, ExchangeError> {
     stream
-        .write_all(&request_bytes)
-        .map_err(|_| ResponseError::Stream)?;
+        .set_write_timeout(Some(timeout))
+        .and_then(|_| stream.set_read_timeout(Some(timeout)))
+        .and_then(|_| stream.write_all(request_bytes))
+        .map_err(|_| ExchangeError::Write)?;
+
+    let mut reader = CountingReader {
+        inner: stream,
+        count: 0,
+    };
+
+    if head {
+        Response::from_stream_without_body(&mut reader)
+    } else {
+        Response::from_stream(&mut reader)
+    }
+    .map_err(|e| ExchangeError::Read(e, reader.count))
+}
+
+/// Returns `true` if the connection has not been closed by the target and has no unexpected data waiting to be read.
+fn is_open(stream: &TcpStream) -> bool {
+    let mut buf = [0; 1];
+
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
 
-    Response::from_stream(&mut stream)
+    let open = matches!(stream.peek(&mut buf), Err(e) if e.kind() == ErrorKind::WouldBlock);
+
+    stream.set_nonblocking(false).is_ok() && open
+}
+
+/// Returns `true` if the connection on which the response was received can be used for another request, which
+///   requires the target to allow it and the end of the response to be known.
+fn is_reusable(request: &Request, response: &Response) -> bool {
+    let has_option = |option: &str| {
+        response
+            .headers
+            .get(HeaderType::Connection)
+            .is_some_and(|connection| {
+                connection
+                    .split(',')
+                    .any(|token| token.trim().eq_ignore_ascii_case(option))
+            })
+    };
+
+    let keep_alive = if response.version == "HTTP/1.1" {
+        !has_option("close")
+    } else {
+        has_option("keep-alive")
+    };
+
+    let delimited = request.method == Method::Head
+        || !response.status_code.allows_body()
+        || response.headers.get(HeaderType::ContentLength).is_some();
+
+    keep_alive && delimited
+}
+
+/// A reader which counts the number of bytes read through it.
+struct CountingReader<'a, R> {
+    inner: &'a mut R,
+    count: usize,
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read;
+        Ok(read)
+    }
 }
@@ -0,0 +1,116 @@
+//! Provides functionality for parsing query strings and URL-encoded form bodies.
+
+use std::str::FromStr;
+
+/// A parsed query string or `application/x-www-form-urlencoded` body.
+///
+/// Keys and values are percent-decoded, with `+` decoded as a space. Invalid escape sequences are left as they are.
+/// Keys may be repeated, in which case every value is kept in the order it appeared.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Query {
+    pairs: Vec<(String, String)>,
+}
+
+impl Query {
+    /// Creates a new empty query.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes a query string or URL-encoded form body, for example `name=John+Smith&tag=a&tag=b`.
+    ///
+    /// Pairs without an `=` are given an empty value, and empty pairs are ignored.
+    pub fn decode(input: impl AsRef<str>) -> Self {
+        let pairs = input
+            .as_ref()
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (decode_component(key), decode_component(value))
+            })
+            .collect();
+
+        Self { pairs }
+    }
+
+    /// Gets the first value of the given key, if it is present.
+    pub fn get(&self, key: impl AsRef<str>) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(k, _)| k == key.as_ref())
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Gets every value of the given key, in the order they appeared.
+    pub fn get_all(&self, key: impl AsRef<str>) -> Vec<&str> {
+        self.pairs
+            .iter()
+            .filter(|(k, _)| k == key.as_ref())
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    /// Parses the first value of the given key into the given type.
+    ///
+    /// Returns `None` if the key is not present or its value could not be parsed.
+    pub fn parse<T>(&self, key: impl AsRef<str>) -> Option<T>
+    where
+        T: FromStr,
+    {
+        self.get(key).and_then(|value| value.parse().ok())
+    }
+
+    /// Returns `true` if the given key is present.
+    pub fn contains(&self, key: impl AsRef<str>) -> bool {
+        self.pairs.iter().any(|(k, _)| k == key.as_ref())
+    }
+
+    /// Returns an iterator over the keys and values, in the order they appeared.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns the number of pairs.
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Returns `true` if there are no pairs.
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+}
+
+/// Decodes one key or value, replacing `+` with a space and leaving invalid escape sequences as they are.
+fn decode_component(component: &str) -> String {
+    let bytes = component.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let escaped = bytes
+                    .get(i + 1..i + 3)
+                    .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+                match escaped {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
@@ -0,0 +1,449 @@
+//! Provides functionality for range requests, as defined in [RFC 9110, section 14](https://datatracker.ietf.org/doc/html/rfc9110#section-14).
+
+use crate::http::date::DateTime;
+use crate::http::headers::{HeaderType, Headers};
+use crate::http::method::Method;
+use crate::http::{Request, Response, StatusCode};
+
+use crate::http::response::BodyReader;
+
+use std::io::{Cursor, SeekFrom};
+use std::path::Path;
+use std::time::SystemTime;
+
+#[cfg(not(feature = "tokio"))]
+use std::fs::File;
+#[cfg(not(feature = "tokio"))]
+use std::io::{Read, Seek};
+
+#[cfg(feature = "tokio")]
+use tokio::fs::File;
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// The maximum number of ranges which will be served in one response.
+/// Requests for more ranges than this are answered with the whole representation.
+pub const MAX_RANGES: usize = 32;
+
+/// Represents a range of bytes within a representation, with both the start and end positions included.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ByteRange {
+    /// The position of the first byte in the range.
+    pub start: u64,
+    /// The position of the last byte in the range.
+    pub end: u64,
+}
+
+/// Represents the outcome of evaluating the `Range` header of a request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RangeRequest {
+    /// The whole representation should be sent, since no valid ranges were requested, or a precondition failed.
+    Full,
+    /// Only the given ranges of the representation should be sent.
+    Partial(Vec<ByteRange>),
+    /// None of the requested ranges overlap the representation, so `416 Range Not Satisfiable` should be sent.
+    Unsatisfiable,
+}
+
+impl ByteRange {
+    /// Creates a new byte range with the given inclusive start and end positions.
+    pub fn new(start: u64, end: u64) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns the number of bytes in the range.
+    pub fn length(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// Returns the value of the `Content-Range` header for this range of a representation of the given length.
+    pub fn content_range(&self, length: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, length)
+    }
+}
+
+impl RangeRequest {
+    /// Parses the value of a `Range` header for a representation of the given length.
+    ///
+    /// Headers which are invalid, use a unit other than `bytes`, or request more than `MAX_RANGES` ranges are
+    ///   ignored. Overlapping and adjacent ranges are combined.
+    pub fn parse(header: &str, length: u64) -> Self {
+        let ranges = match header.trim().split_once('=') {
+            Some((unit, ranges)) if unit.trim().eq_ignore_ascii_case("bytes") => ranges,
+            _ => return Self::Full,
+        };
+
+        let mut satisfiable: Vec<ByteRange> = Vec::new();
+        let mut count = 0;
+
+        for range in ranges.split(',').map(|range| range.trim()) {
+            if range.is_empty() {
+                continue;
+            }
+
+            count += 1;
+            if count > MAX_RANGES {
+                return Self::Full;
+            }
+
+            let (start, end) = match range.split_once('-') {
+                Some((start, end)) => (start.trim(), end.trim()),
+                None => return Self::Full,
+            };
+
+            let range = match (parse_position(start), parse_position(end)) {
+                // A range from the given start to end, inclusive
+                (Some(Some(start)), Some(Some(end))) if start <= end => {
+                    (start < length).then(|| ByteRange::new(start, end.min(length - 1)))
+                }
+                // A range from the given start to the end of the representation
+                (Some(Some(start)), Some(None)) => {
+                    (start < length).then(|| ByteRange::new(start, length - 1))
+                }
+                // A range of the given number of bytes at the end of the representation
+                (Some(None), Some(Some(suffix))) => (suffix > 0 && length > 0)
+                    .then(|| ByteRange::new(length.saturating_sub(suffix), length - 1)),
+                _ => return Self::Full,
+            };
+
+            satisfiable.extend(range);
+        }
+
+        if count == 0 {
+            return Self::Full;
+        }
+
+        if satisfiable.is_empty() {
+            return Self::Unsatisfiable;
+        }
+
+        satisfiable.sort_by_key(|range| range.start);
+
+        let mut combined: Vec<ByteRange> = Vec::with_capacity(satisfiable.len());
+
+        for range in satisfiable {
+            match combined.last_mut() {
+                Some(last) if range.start <= last.end.saturating_add(1) => {
+                    last.end = last.end.max(range.end);
+                }
+                _ => combined.push(range),
+            }
+        }
+
+        Self::Partial(combined)
+    }
+
+    /// Evaluates the request's `Range` header for a representation of the given length.
+    ///
+    /// The given headers are those of the full response, from which the `ETag` and `Last-Modified` headers are
+    ///   compared against the request's `If-Range` header. Ranges are only served for `GET` and `HEAD` requests.
+    pub fn from_request(request: &Request, length: u64, headers: &Headers) -> Self {
+        if request.method != Method::Get && request.method != Method::Head {
+            return Self::Full;
+        }
+
+        let range = match request.headers.get(HeaderType::Range) {
+            Some(range) => range,
+            None => return Self::Full,
+        };
+
+        if let Some(if_range) = request.headers.get(HeaderType::IfRange) {
+            if !if_range_matches(if_range.trim(), headers) {
+                return Self::Full;
+            }
+        }
+
+        Self::parse(range, length)
+    }
+
+    /// Generates the response for this outcome from the full response, whose body must be held in memory.
+    ///
+    /// A single range is sent with a `Content-Range` header, while multiple ranges are sent as a
+    ///   `multipart/byteranges` body with one part for each range.
+    pub fn respond(self, mut response: Response) -> Response {
+        let length = response.body.len() as u64;
+        let slice = |body: &[u8], range: ByteRange| {
+            body[range.start as usize..=range.end as usize].to_vec()
+        };
+
+        match self.prepare(&mut response, length) {
+            RangeBody::Full => (),
+            RangeBody::Empty => response.body.clear(),
+            RangeBody::Single(range) => response.body = slice(&response.body, range),
+            RangeBody::Multipart(parts, closing) => {
+                let mut body: Vec<u8> = Vec::new();
+
+                for (headers, range) in parts {
+                    body.extend(headers);
+                    body.extend(slice(&response.body, range));
+                }
+
+                body.extend(closing);
+                response.body = body;
+            }
+        }
+
+        response
+    }
+
+    /// Generates the response for this outcome from the full response, whose body is the file at the given path.
+    ///
+    /// Only the requested ranges are read from the file, and they are streamed to the client rather than held in
+    ///   memory. The file is opened again for each part of a `multipart/byteranges` body.
+    #[cfg(not(feature = "tokio"))]
+    pub fn respond_with_file(
+        self,
+        mut response: Response,
+        mut file: File,
+        path: &Path,
+        length: u64,
+    ) -> std::io::Result<Response> {
+        Ok(match self.prepare(&mut response, length) {
+            RangeBody::Full => response.with_reader(file, Some(length)),
+            RangeBody::Empty => response,
+            RangeBody::Single(range) => {
+                file.seek(SeekFrom::Start(range.start))?;
+                response.with_reader(file.take(range.length()), Some(range.length()))
+            }
+            RangeBody::Multipart(parts, closing) => {
+                let body_length = multipart_length(&parts, &closing);
+                let mut reader: BodyReader = Box::new(std::io::empty());
+
+                for (headers, range) in parts {
+                    let mut file = File::open(path)?;
+                    file.seek(SeekFrom::Start(range.start))?;
+
+                    reader = Box::new(
+                        reader
+                            .chain(Cursor::new(headers))
+                            .chain(file.take(range.length())),
+                    );
+                }
+
+                response.with_reader(reader.chain(Cursor::new(closing)), Some(body_length))
+            }
+        })
+    }
+
+    /// Generates the response for this outcome from the full response, whose body is the file at the given path.
+    ///
+    /// Only the requested ranges are read from the file, and they are streamed to the client rather than held in
+    ///   memory. The file is opened again for each part of a `multipart/byteranges` body.
+    #[cfg(feature = "tokio")]
+    pub async fn respond_with_file(
+        self,
+        mut response: Response,
+        mut file: File,
+        path: &Path,
+        length: u64,
+    ) -> std::io::Result<Response> {
+        Ok(match self.prepare(&mut response, length) {
+            RangeBody::Full => response.with_reader(file, Some(length)),
+            RangeBody::Empty => response,
+            RangeBody::Single(range) => {
+                file.seek(SeekFrom::Start(range.start)).await?;
+                response.with_reader(file.take(range.length()), Some(range.length()))
+            }
+            RangeBody::Multipart(parts, closing) => {
+                let body_length = multipart_length(&parts, &closing);
+                let mut reader: BodyReader = Box::new(tokio::io::empty());
+
+                for (headers, range) in parts {
+                    let mut file = File::open(path).await?;
+                    file.seek(SeekFrom::Start(range.start)).await?;
+
+                    reader = Box::new(
+                        reader
+                            .chain(Cursor::new(headers))
+                            .chain(file.take(range.length())),
+                    );
+                }
+
+                response.with_reader(reader.chain(Cursor::new(closing)), Some(body_length))
+            }
+        })
+    }
+
+    /// Sets the status code and headers of the response for this outcome, returning which parts of the full body of
+    ///   the given length should be sent.
+    fn prepare(self, response: &mut Response, length: u64) -> RangeBody {
+        response.headers.remove(HeaderType::ContentLength);
+
+        match self {
+            Self::Full => RangeBody::Full,
+            Self::Unsatisfiable => {
+                response.status_code = StatusCode::RequestedRangeNotSatisfiable;
+                response.headers.remove(HeaderType::ContentType);
+                response
+                    .headers
+                    .add(HeaderType::ContentRange, format!("bytes */{}", length));
+
+                RangeBody::Empty
+            }
+            Self::Partial(ranges) if ranges.len() == 1 => {
+                let range = ranges[0];
+
+                response.status_code = StatusCode::PartialContent;
+                response
+                    .headers
+                    .add(HeaderType::ContentRange, range.content_range(length));
+
+                RangeBody::Single(range)
+            }
+            Self::Partial(ranges) => {
+                let boundary = format!(
+                    "humphrey-byteranges-{:x}",
+                    SystemTime::now()
+                        .duration_since(SystemTime::UNIX_EPOCH)
+                        .map(|duration| duration.as_nanos())
+                        .unwrap_or_default()
+                );
+
+                let content_type = response
+                    .headers
+                    .get(HeaderType::ContentType)
+                    .map(|content_type| content_type.to_string());
+
+                let parts = ranges
+                    .into_iter()
+                    .map(|range| {
+                        let mut headers = format!("\r\n--{}\r\n", boundary);
+
+                        if let Some(content_type) = &content_type {
+                            headers.push_str(&format!("Content-Type: {}\r\n", content_type));
+                        }
+
+                        headers.push_str(&format!(
+                            "Content-Range: {}\r\n\r\n",
+                            range.content_range(length)
+                        ));
+
+                        (headers.into_bytes(), range)
+                    })
+                    .collect();
+
+                response.status_code = StatusCode::PartialContent;
+                response.headers.remove(HeaderType::ContentType);
+                response.headers.add(
+                    HeaderType::ContentType,
+                    format!("multipart/byteranges; boundary={}", boundary),
+                );
+
+                RangeBody::Multipart(parts, format!("\r\n--{}--\r\n", boundary).into_bytes())
+            }
+        }
+    }
+}
+
+/// The parts of a representation which make up the body of a response to a range request.
+enum RangeBody {
+    /// The whole representation.
+    Full,
+    /// No body.
+    Empty,
+    /// A single range of the representation.
+    Single(ByteRange),
+    /// A `multipart/byteranges` body, made up of the headers and range of each part followed by the closing
+    ///   delimiter.
+    Multipart(Vec<(Vec<u8>, ByteRange)>, Vec<u8>),
+}
+
+/// Returns the length in bytes of a `multipart/byteranges` body made up of the given parts.
+fn multipart_length(parts: &[(Vec<u8>, ByteRange)], closing: &[u8]) -> u64 {
+    parts
+        .iter()
+        .map(|(headers, range)| headers.len() as u64 + range.length())
+        .sum::<u64>()
+        + closing.len() as u64
+}
+
+/// Serves the ranges of the response requested by the request, adding the `Accept-Ranges` header.
+///
+/// Only successful responses whose body is held in memory and has no content encoding are affected, so this can be
+///   applied to any response. The `ETag` and `Last-Modified` headers of the response are used to evaluate `If-Range`.
+pub fn serve_ranges(request: &Request, mut response: Response) -> Response {
+    if response.status_code != StatusCode::OK
+        || response.is_streamed()
+        || response.headers.get(HeaderType::ContentEncoding).is_some()
+    {
+        return response;
+    }
+
+    if response.headers.get(HeaderType::AcceptRanges).is_none() {
+        response.headers.add(HeaderType::AcceptRanges, "bytes");
+    }
+
+    RangeRequest::from_request(request, response.body.len() as u64, &response.headers)
+        .respond(response)
+}
+
+/// Serves the ranges of the file at the given path requested by the request, adding the `Accept-Ranges` header.
+///
+/// The response should have an empty body, and its `ETag` and `Last-Modified` headers are used to evaluate
+///   `If-Range`. The ranges are checked against the length of the file and streamed from it, so the file is never
+///   read into memory.
+#[cfg(not(feature = "tokio"))]
+pub fn serve_file_ranges(
+    request: &Request,
+    mut response: Response,
+    file: File,
+    path: &Path,
+) -> std::io::Result<Response> {
+    let length = file.metadata()?.len();
+    response.headers.add(HeaderType::AcceptRanges, "bytes");
+
+    RangeRequest::from_request(request, length, &response.headers)
+        .respond_with_file(response, file, path, length)
+}
+
+/// Serves the ranges of the file at the given path requested by the request, adding the `Accept-Ranges` header.
+///
+/// The response should have an empty body, and its `ETag` and `Last-Modified` headers are used to evaluate
+///   `If-Range`. The ranges are checked against the length of the file and streamed from it, so the file is never
+///   read into memory.
+#[cfg(feature = "tokio")]
+pub async fn serve_file_ranges(
+    request: &Request,
+    mut response: Response,
+    file: File,
+    path: &Path,
+) -> std::io::Result<Response> {
+    let length = file.metadata().await?.len();
+    response.headers.add(HeaderType::AcceptRanges, "bytes");
+
+    RangeRequest::from_request(request, length, &response.headers)
+        .respond_with_file(response, file, path, length)
+        .await
+}
+
+/// Returns `true` if the value of an `If-Range` header matches the validators in the given headers.
+///
+/// Entity tags are compared using strong comparison, so weak tags never match, and dates must match exactly.
+fn if_range_matches(if_range: &str, headers: &Headers) -> bool {
+    if if_range.starts_with('"') || if_range.starts_with("W/") {
+        headers
+            .get(HeaderType::ETag)
+            .is_some_and(|etag| !if_range.starts_with("W/") && etag.trim() == if_range)
+    } else {
+        match (
+            if_range.parse::<DateTime>(),
+            headers
+                .get(HeaderType::LastModified)
+                .and_then(|last_modified| last_modified.parse::<DateTime>().ok()),
+        ) {
+            (Ok(date), Some(last_modified)) => date.timestamp == last_modified.timestamp,
+            _ => false,
+        }
+    }
+}
+
+/// Parses a position in a range, returning `Some(None)` if it is empty and `None` if it is invalid.
+fn parse_position(position: &str) -> Option<Option<u64>> {
+    if position.is_empty() {
+        Some(None)
+    } else if position.bytes().all(|b| b.is_ascii_digit()) {
+        position.parse().ok().map(Some)
+    } else {
+        None
+    }
+}
@@ -1,22 +1,27 @@
 //! Provides functionality for handling HTTP requests.
 
 use crate::http::address::Address;
+use crate::http::body::{BodyDecoder, BODY_BUFFER_SIZE};
 use crate::http::cookie::Cookie;
-use crate::http::headers::{HeaderType, Headers};
+use crate::http::headers::{HeaderLike, HeaderType, Headers};
 use crate::http::method::Method;
+use crate::http::params::Params;
+use crate::http::query::Query;
 
 use std::error::Error;
-use std::net::SocketAddr;
+use std::net::{Ipv4Addr, SocketAddr};
 
 #[cfg(not(feature = "tokio"))]
 use crate::stream::Stream;
 #[cfg(not(feature = "tokio"))]
+use std::borrow::BorrowMut;
+#[cfg(not(feature = "tokio"))]
 use std::io::{BufRead, BufReader, ErrorKind, Read};
 #[cfg(not(feature = "tokio"))]
 use std::time::Duration;
 
 #[cfg(feature = "tokio")]
-use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, BufReader};
 
 /// Represents a request to the server.
 /// Contains parsed information about the request's data.
@@ -36,6 +41,12 @@ pub struct Request {
     pub content: Option<Vec<u8>>,
     /// The address from which the request came
     pub address: Address,
+    /// The parameters captured from the path by the matched route.
+    /// These are set once the request has been routed, and are otherwise empty.
+    ///
+    /// Requests built with a struct literal must set this field, for example to `Params::new()`, or can be built
+    ///   with `Request::new` instead.
+    pub params: Params,
 }
 
 /// An error which occurred during the parsing of a request.
@@ -49,6 +60,8 @@ pub enum RequestError {
     Disconnected,
     /// The request timed out.
     Timeout,
+    /// The request body exceeded the maximum body size.
+    TooLarge,
 }
 
 trait OptionToRequestResult<T> {
@@ -70,18 +83,55 @@ impl std::fmt::Display for RequestError {
 impl Error for RequestError {}
 
 impl Request {
+    /// Creates a new HTTP/1.1 request to the given URI, with no headers or body, which appears to come from
+    ///   `127.0.0.1`.
+    ///
+    /// Requests received by the app are parsed from the stream, so this is mainly useful for testing handlers and
+    ///   middleware.
+    ///
+    /// ## Example
+    /// ```
+    /// let request = Request::new(Method::Get, "/index.html")
+    ///     .with_header(HeaderType::AcceptEncoding, "gzip");
+    /// ```
+    pub fn new(method: Method, uri: impl AsRef<str>) -> Self {
+        Self {
+            method,
+            uri: uri.as_ref().to_string(),
+            query: String::new(),
+            version: "HTTP/1.1".to_string(),
+            headers: Headers::new(),
+            content: None,
+            address: Address::from_socket_addr(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))),
+            params: Params::new(),
+        }
+    }
+
+    /// Adds the given header to the request.
+    /// Returns itself for use in a builder pattern.
+    pub fn with_header(mut self, header: impl HeaderLike, value: impl AsRef<str>) -> Self {
+        self.headers.add(header, value);
+        self
+    }
+
+    /// Sets the HTTP version of the request, such as `HTTP/1.0`.
+    /// Returns itself for use in a builder pattern.
+    pub fn with_version(mut self, version: impl AsRef<str>) -> Self {
+        self.version = version.as_ref().to_string();
+        self
+    }
+
     /// Attempts to read and parse one HTTP request from the given reader.
     #[cfg(not(feature = "tokio"))]
     pub fn from_stream<T>(stream: &mut T, address: SocketAddr) -> Result<Self, RequestError>
     where
         T: Read,
     {
-        let mut first_buf: [u8; 1] = [0; 1];
-        stream
-            .read_exact(&mut first_buf)
-            .map_err(|_| RequestError::Disconnected)?;
+        let mut reader = BufReader::new(stream);
+        let mut request = Self::head_from_reader(&mut reader, address)?;
+        request.read_body(&mut reader, None)?;
 
-        Self::from_stream_inner(stream, address, first_buf[0])
+        Ok(request)
     }
 
     /// Attempts to read and parse one HTTP request from the given reader.
@@ -90,13 +140,11 @@ impl Request {
     where
         T: AsyncReadExt + Unpin,
     {
-        let mut first_buf: [u8; 1] = [0; 1];
-        stream
-            .read_exact(&mut first_buf)
-            .await
-            .map_err(|_| RequestError::Disconnected)?;
+        let mut reader = BufReader::new(stream);
+        let mut request = Self::head_from_reader(&mut reader, address).await?;
+        request.read_body(&mut reader, None).await?;
 
-        Self::from_stream_inner(stream, address, first_buf[0]).await
+        Ok(request)
     }
 
     /// Attempts to read and parse one HTTP request from the given stream, timing out after the timeout.
@@ -106,22 +154,11 @@ impl Request {
         address: SocketAddr,
         timeout: Duration,
     ) -> Result<Self, RequestError> {
-        stream
-            .set_timeout(Some(timeout))
-            .map_err(|_| RequestError::Stream)?;
-
-        let mut first_buf: [u8; 1] = [0; 1];
-        stream
-            .read_exact(&mut first_buf)
-            .map_err(|e| match e.kind() {
-                ErrorKind::TimedOut => RequestError::Timeout,
-                ErrorKind::WouldBlock => RequestError::Timeout,
-                _ => RequestError::Disconnected,
-            })?;
-
-        stream.set_timeout(None).map_err(|_| RequestError::Stream)?;
+        let mut reader = BufReader::new(stream);
+        let mut request = Self::head_from_stream(&mut reader, address, Some(timeout))?;
+        request.read_body(&mut reader, None)?;
 
-        Self::from_stream_inner(stream, address, first_buf[0])
+        Ok(request)
     }
 
     /// Get the cookies from the request.
@@ -147,44 +184,135 @@ impl Request {
             .find(|cookie| cookie.name == name.as_ref())
     }
 
-    /// Attempts to read and parse one HTTP request from the given reader.
+    /// Get the parsed query string of the request.
+    pub fn get_query(&self) -> Query {
+        Query::decode(&self.query)
+    }
+
+    /// Attempts to get the parsed body of a form submitted with the `application/x-www-form-urlencoded` content type.
+    ///
+    /// Returns `None` if the request has a different content type, or its body is missing or is not valid UTF-8.
+    pub fn get_form(&self) -> Option<Query> {
+        let content_type = self.headers.get(HeaderType::ContentType)?;
+        let media_type = content_type.split(';').next().unwrap_or_default().trim();
+
+        if !media_type.eq_ignore_ascii_case("application/x-www-form-urlencoded") {
+            return None;
+        }
+
+        let content = std::str::from_utf8(self.content.as_ref()?).ok()?;
+
+        Some(Query::decode(content))
+    }
+
+    /// Returns `true` if the client wants the connection to be kept open after this request.
+    ///
+    /// HTTP/1.1 connections are persistent unless the client sends `Connection: close`, while connections using
+    ///   earlier versions are closed unless the client sends `Connection: keep-alive`.
+    pub fn is_keep_alive(&self) -> bool {
+        let has_option = |option: &str| {
+            self.headers
+                .get(HeaderType::Connection)
+                .is_some_and(|connection| {
+                    connection
+                        .split(',')
+                        .any(|token| token.trim().eq_ignore_ascii_case(option))
+                })
+        };
+
+        if self.version == "HTTP/1.1" {
+            !has_option("close")
+        } else {
+            has_option("keep-alive")
+        }
+    }
+
+    /// Attempts to read and parse the start line and headers of one HTTP request from the given stream,
+    ///   leaving the body unread. If a timeout is given, it applies to waiting for the request to start.
     #[cfg(not(feature = "tokio"))]
-    fn from_stream_inner<T>(
-        stream: &mut T,
+    pub(crate) fn head_from_stream<S>(
+        reader: &mut BufReader<S>,
         address: SocketAddr,
-        first_byte: u8,
+        timeout: Option<Duration>,
     ) -> Result<Self, RequestError>
     where
-        T: Read,
+        S: Read + BorrowMut<Stream>,
+    {
+        // Data which is already buffered, such as a pipelined request, is available without waiting
+        if timeout.is_some() && reader.buffer().is_empty() {
+            reader
+                .get_mut()
+                .borrow_mut()
+                .set_timeout(timeout)
+                .map_err(|_| RequestError::Stream)?;
+
+            reader.fill_buf().map_err(first_byte_error)?;
+
+            reader
+                .get_mut()
+                .borrow_mut()
+                .set_timeout(None)
+                .map_err(|_| RequestError::Stream)?;
+        }
+
+        Self::head_from_reader(reader, address)
+    }
+
+    /// Attempts to read and parse the start line and headers of one HTTP request from the given reader,
+    ///   leaving the body unread.
+    #[cfg(not(feature = "tokio"))]
+    pub(crate) fn head_from_reader<T>(
+        reader: &mut T,
+        address: SocketAddr,
+    ) -> Result<Self, RequestError>
+    where
+        T: BufRead,
     {
-        let mut reader = BufReader::new(stream);
         let mut start_line_buf: Vec<u8> = Vec::with_capacity(256);
+        start_line_buf.push(0);
+        reader
+            .read_exact(&mut start_line_buf[..1])
+            .map_err(first_byte_error)?;
         reader
             .read_until(0xA, &mut start_line_buf)
             .map_err(|_| RequestError::Stream)?;
 
-        start_line_buf.insert(0, first_byte);
+        let mut headers = Headers::new();
 
-        let start_line_string =
-            std::str::from_utf8(&start_line_buf).map_err(|_| RequestError::Request)?;
-        let mut start_line = start_line_string.split(' ');
+        loop {
+            let mut line_buf: Vec<u8> = Vec::with_capacity(256);
+            reader
+                .read_until(0xA, &mut line_buf)
+                .map_err(|_| RequestError::Stream)?;
 
-        let method = Method::from_name(start_line.next().to_error(RequestError::Request)?)?;
-        let mut uri_iter = start_line
-            .next()
-            .to_error(RequestError::Request)?
-            .splitn(2, '?');
-        let version = start_line
-            .next()
-            .to_error(RequestError::Request)?
-            .strip_suffix("\r\n")
-            .unwrap_or("")
-            .to_string();
+            if parse_header_line(&line_buf, &mut headers)? {
+                break;
+            }
+        }
 
-        safe_assert(!version.is_empty())?;
+        Self::from_head(&start_line_buf, headers, address)
+    }
 
-        let uri = uri_iter.next().unwrap().to_string();
-        let query = uri_iter.next().unwrap_or("").to_string();
+    /// Attempts to read and parse the start line and headers of one HTTP request from the given reader,
+    ///   leaving the body unread.
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn head_from_reader<T>(
+        reader: &mut T,
+        address: SocketAddr,
+    ) -> Result<Self, RequestError>
+    where
+        T: AsyncBufRead + Unpin,
+    {
+        let mut start_line_buf: Vec<u8> = Vec::with_capacity(256);
+        start_line_buf.push(0);
+        reader
+            .read_exact(&mut start_line_buf[..1])
+            .await
+            .map_err(|_| RequestError::Disconnected)?;
+        reader
+            .read_until(0xA, &mut start_line_buf)
+            .await
+            .map_err(|_| RequestError::Stream)?;
 
         let mut headers = Headers::new();
 
@@ -192,79 +320,91 @@ impl Request {
             let mut line_buf: Vec<u8> = Vec::with_capacity(256);
             reader
                 .read_until(0xA, &mut line_buf)
+                .await
                 .map_err(|_| RequestError::Stream)?;
-            let line = std::str::from_utf8(&line_buf).map_err(|_| RequestError::Request)?;
 
-            if line == "\r\n" {
+            if parse_header_line(&line_buf, &mut headers)? {
                 break;
-            } else {
-                safe_assert(line.len() >= 2)?;
-                let line_without_crlf = &line[0..line.len() - 2];
-                let mut line_parts = line_without_crlf.splitn(2, ':');
-                headers.add(
-                    HeaderType::from(line_parts.next().to_error(RequestError::Request)?),
-                    line_parts
-                        .next()
-                        .to_error(RequestError::Request)?
-                        .trim_start(),
-                );
             }
         }
 
-        let address =
-            Address::from_headers(&headers, address).map_err(|_| RequestError::Request)?;
+        Self::from_head(&start_line_buf, headers, address)
+    }
 
-        if let Some(content_length) = headers.get(&HeaderType::ContentLength) {
-            let content_length: usize =
-                content_length.parse().map_err(|_| RequestError::Request)?;
-            let mut content_buf: Vec<u8> = vec![0u8; content_length];
-            reader
-                .read_exact(&mut content_buf)
-                .map_err(|_| RequestError::Stream)?;
+    /// Reads the body of the request from the given reader into `content`, returning
+    ///   `RequestError::TooLarge` if it exceeds the maximum size.
+    #[cfg(not(feature = "tokio"))]
+    pub(crate) fn read_body<T>(
+        &mut self,
+        reader: &mut T,
+        max_size: Option<usize>,
+    ) -> Result<(), RequestError>
+    where
+        T: BufRead + ?Sized,
+    {
+        let mut decoder = BodyDecoder::new(&self.headers, max_size)?;
 
-            Ok(Self {
-                method,
-                uri,
-                query,
-                version,
-                headers,
-                content: Some(content_buf),
-                address,
-            })
-        } else {
-            Ok(Self {
-                method,
-                uri,
-                query,
-                version,
-                headers,
-                content: None,
-                address,
-            })
+        if !decoder.has_body() {
+            return Ok(());
+        }
+
+        let mut content: Vec<u8> = Vec::new();
+        let mut buf = vec![0u8; BODY_BUFFER_SIZE];
+
+        loop {
+            match decoder.read(reader, &mut buf) {
+                Ok(0) => break,
+                Ok(n) => content.extend_from_slice(&buf[..n]),
+                Err(e) => return Err(decoder.to_request_error(e)),
+            }
         }
+
+        self.set_decoded_content(&decoder, content);
+
+        Ok(())
     }
 
-    /// Attempts to read and parse one HTTP request from the given reader.
+    /// Reads the body of the request from the given reader into `content`, returning
+    ///   `RequestError::TooLarge` if it exceeds the maximum size.
     #[cfg(feature = "tokio")]
-    async fn from_stream_inner<T>(
-        stream: &mut T,
-        address: SocketAddr,
-        first_byte: u8,
-    ) -> Result<Self, RequestError>
+    pub(crate) async fn read_body<T>(
+        &mut self,
+        reader: &mut T,
+        max_size: Option<usize>,
+    ) -> Result<(), RequestError>
     where
-        T: AsyncReadExt + Unpin,
+        T: AsyncBufRead + Unpin + ?Sized,
     {
-        let mut reader = BufReader::new(stream);
-        let mut start_line_buf: Vec<u8> = Vec::with_capacity(256);
-        reader
-            .read_until(0xA, &mut start_line_buf)
-            .await
-            .map_err(|_| RequestError::Stream)?;
+        let mut decoder = BodyDecoder::new(&self.headers, max_size)?;
 
-        start_line_buf.insert(0, first_byte);
+        if !decoder.has_body() {
+            return Ok(());
+        }
 
+        let mut content: Vec<u8> = Vec::new();
+        let mut buf = vec![0u8; BODY_BUFFER_SIZE];
+
+        loop {
+            match decoder.read(reader, &mut buf).await {
+                Ok(0) => break,
+                Ok(n) => content.extend_from_slice(&buf[..n]),
+                Err(e) => return Err(decoder.to_request_error(e)),
+            }
+        }
+
+        self.set_decoded_content(&decoder, content);
+
+        Ok(())
+    }
+
+    /// Parses the start line of a request and combines it with the headers.
+    fn from_head(
+        start_line_buf: &[u8],
+        headers: Headers,
+        address: SocketAddr,
+    ) -> Result<Self, RequestError> {
         let start_line_string =
-            std::str::from_utf8(&start_line_buf).map_err(|_| RequestError::Request)?;
+            std::str::from_utf8(start_line_buf).map_err(|_| RequestError::Request)?;
         let mut start_line = start_line_string.split(' ');
 
         let method = Method::from_name(start_line.next().to_error(RequestError::Request)?)?;
@@ -284,64 +424,70 @@ impl Request {
         let uri = uri_iter.next().unwrap().to_string();
         let query = uri_iter.next().unwrap_or("").to_string();
 
-        let mut headers = Headers::new();
-
-        loop {
-            let mut line_buf: Vec<u8> = Vec::with_capacity(256);
-            reader
-                .read_until(0xA, &mut line_buf)
-                .await
-                .map_err(|_| RequestError::Stream)?;
-            let line = std::str::from_utf8(&line_buf).map_err(|_| RequestError::Request)?;
+        // Forwarding headers are only honoured once the app has checked the peer against its trusted proxies
+        let address = Address::from_socket_addr(address);
+
+        Ok(Self {
+            method,
+            uri,
+            query,
+            version,
+            headers,
+            content: None,
+            address,
+            params: Params::new(),
+        })
+    }
 
-            if line == "\r\n" {
-                break;
-            } else {
-                safe_assert(line.len() >= 2)?;
-                let line_without_crlf = &line[0..line.len() - 2];
-                let mut line_parts = line_without_crlf.splitn(2, ':');
-                headers.add(
-                    HeaderType::from(line_parts.next().to_error(RequestError::Request)?),
-                    line_parts
-                        .next()
-                        .to_error(RequestError::Request)?
-                        .trim_start(),
-                );
+    /// Sets the content of the request to the decoded body.
+    /// If the body was chunked, its trailers are added to the headers and the chunked encoding
+    ///   is replaced with the length of the decoded body. Fields which are forbidden in trailers, such as
+    ///   `Content-Length`, `Host` and `Authorization`, are never sent by the decoder so cannot override the headers.
+    fn set_decoded_content(&mut self, decoder: &BodyDecoder, content: Vec<u8>) {
+        if decoder.is_chunked() {
+            for trailer in decoder.trailers().iter() {
+                self.headers.push(trailer);
             }
+
+            self.headers.remove(&HeaderType::TransferEncoding);
+            self.headers.remove(&HeaderType::ContentLength);
+            self.headers
+                .add(HeaderType::ContentLength, content.len().to_string());
         }
 
-        let address =
-            Address::from_headers(&headers, address).map_err(|_| RequestError::Request)?;
+        self.content = Some(content);
+    }
+}
 
-        if let Some(content_length) = headers.get(&HeaderType::ContentLength) {
-            let content_length: usize =
-                content_length.parse().map_err(|_| RequestError::Request)?;
-            let mut content_buf: Vec<u8> = vec![0u8; content_length];
-            reader
-                .read_exact(&mut content_buf)
-                .await
-                .map_err(|_| RequestError::Stream)?;
+/// Parses a single header line into the headers, returning `true` if the line ended the headers.
+fn parse_header_line(line_buf: &[u8], headers: &mut Headers) -> Result<bool, RequestError> {
+    let line = std::str::from_utf8(line_buf).map_err(|_| RequestError::Request)?;
 
-            Ok(Self {
-                method,
-                uri,
-                query,
-                version,
-                headers,
-                content: Some(content_buf),
-                address,
-            })
-        } else {
-            Ok(Self {
-                method,
-                uri,
-                query,
-                version,
-                headers,
-                content: None,
-                address,
-            })
-        }
+    if line == "\r\n" {
+        return Ok(true);
+    }
+
+    safe_assert(line.len() >= 2)?;
+    let line_without_crlf = &line[0..line.len() - 2];
+    let mut line_parts = line_without_crlf.splitn(2, ':');
+    headers.add(
+        HeaderType::from(line_parts.next().to_error(RequestError::Request)?),
+        line_parts
+            .next()
+            .to_error(RequestError::Request)?
+            .trim_start(),
+    );
+
+    Ok(false)
+}
+
+/// Converts an error which occurred while waiting for the first byte of a request into a `RequestError`.
+#[cfg(not(feature = "tokio"))]
+fn first_byte_error(e: std::io::Error) -> RequestError {
+    match e.kind() {
+        ErrorKind::TimedOut => RequestError::Timeout,
+        ErrorKind::WouldBlock => RequestError::Timeout,
+        _ => RequestError::Disconnected,
     }
 }
 
@@ -6,11 +6,48 @@ use crate::http::status::StatusCode;
 
 use std::convert::TryFrom;
 use std::error::Error;
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, BufReader, ErrorKind, Read};
+
+#[cfg(not(feature = "tokio"))]
+use std::io::Write;
+
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The fields which must not be sent as trailers, since they are needed to frame, route, authenticate or process
+///   the message before its body is received, as defined in
+///   [RFC 9110](https://datatracker.ietf.org/doc/html/rfc9110#section-6.5.1). They are dropped from the trailers of
+///   chunked bodies so that they cannot override the headers.
+const FORBIDDEN_TRAILERS: &[&str] = &[
+    "Authorization",
+    "Cache-Control",
+    "Connection",
+    "Content-Encoding",
+    "Content-Length",
+    "Content-Range",
+    "Content-Type",
+    "Cookie",
+    "Expect",
+    "Host",
+    "Keep-Alive",
+    "Max-Forwards",
+    "Proxy-Authenticate",
+    "Proxy-Authorization",
+    "Range",
+    "Set-Cookie",
+    "TE",
+    "Trailer",
+    "Transfer-Encoding",
+    "Upgrade",
+    "WWW-Authenticate",
+];
 
 /// Represents a response from the server.
 /// Implements `Into<Vec<u8>>` so can be serialised into bytes to transmit.
 ///
+/// Large or generated bodies can be streamed to the client instead of being held in memory
+///   by using `Response::with_reader` or `Response::with_chunks`.
+///
 /// ## Simple Creation
 /// ```
 /// Response::new(StatusCode::OK, b"Success")
@@ -32,6 +69,38 @@ pub struct Response {
     pub headers: Headers,
     /// The body of the response.
     pub body: Vec<u8>,
+    /// The streamed body of the response, if any.
+    /// If this is set, it is written to the client in place of `body`, and is not included when
+    ///   the response is converted into bytes.
+    stream: Option<BodyStream>,
+}
+
+/// The type of reader which can be used for a streamed response body.
+#[cfg(not(feature = "tokio"))]
+pub type BodyReader = Box<dyn Read + Send>;
+
+/// The type of reader which can be used for a streamed response body.
+#[cfg(feature = "tokio")]
+pub type BodyReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// A response body which is written to the client as it is produced rather than held in memory.
+///
+/// Bodies of known length are sent with a `Content-Length` header, while bodies of unknown length
+///   are sent using chunked transfer encoding.
+pub enum BodyStream {
+    /// A body read from the given reader, with the total length in bytes if it is known.
+    Reader(BodyReader, Option<u64>),
+    /// A body made up of the chunks produced by the given iterator.
+    Chunks(Box<dyn Iterator<Item = Vec<u8>> + Send>),
+}
+
+impl std::fmt::Debug for BodyStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Reader(_, length) => f.debug_tuple("Reader").field(length).finish(),
+            Self::Chunks(_) => f.debug_tuple("Chunks").finish(),
+        }
+    }
 }
 
 /// An error which occurred during the parsing of a response.
@@ -71,6 +140,7 @@ impl Response {
             status_code,
             headers: Headers::new(),
             body: bytes.as_ref().to_vec(),
+            stream: None,
         }
     }
 
@@ -82,6 +152,7 @@ impl Response {
             status_code,
             headers: Headers::new(),
             body: Vec::new(),
+            stream: None,
         }
     }
 
@@ -129,6 +200,203 @@ impl Response {
         self
     }
 
+    /// Streams the body of the response from the given reader, replacing any existing body.
+    /// If `length` is `None`, the body is sent using chunked transfer encoding.
+    /// Returns itself for use in a builder pattern.
+    ///
+    /// ## Example
+    /// ```
+    /// let file = File::open("video.mp4")?;
+    /// let length = file.metadata()?.len();
+    ///
+    /// Response::empty(StatusCode::OK)
+    ///     .with_header(HeaderType::ContentType, "video/mp4")
+    ///     .with_reader(file, Some(length))
+    /// ```
+    #[cfg(not(feature = "tokio"))]
+    pub fn with_reader<R>(mut self, reader: R, length: Option<u64>) -> Self
+    where
+        R: Read + Send + 'static,
+    {
+        self.body.clear();
+        self.stream = Some(BodyStream::Reader(Box::new(reader), length));
+        self
+    }
+
+    /// Streams the body of the response from the given reader, replacing any existing body.
+    /// If `length` is `None`, the body is sent using chunked transfer encoding.
+    /// Returns itself for use in a builder pattern.
+    ///
+    /// ## Example
+    /// ```
+    /// let file = File::open("video.mp4").await?;
+    /// let length = file.metadata().await?.len();
+    ///
+    /// Response::empty(StatusCode::OK)
+    ///     .with_header(HeaderType::ContentType, "video/mp4")
+    ///     .with_reader(file, Some(length))
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn with_reader<R>(mut self, reader: R, length: Option<u64>) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        self.body.clear();
+        self.stream = Some(BodyStream::Reader(Box::new(reader), length));
+        self
+    }
+
+    /// Streams the body of the response from the chunks produced by the given iterator, replacing any existing body.
+    /// The body is sent using chunked transfer encoding.
+    /// Returns itself for use in a builder pattern.
+    ///
+    /// ## Example
+    /// ```
+    /// let rows = (0..1_000_000).map(|i| format!("{},{}\n", i, i * i).into_bytes());
+    ///
+    /// Response::empty(StatusCode::OK)
+    ///     .with_header(HeaderType::ContentType, "text/csv")
+    ///     .with_chunks(rows)
+    /// ```
+    pub fn with_chunks<I>(mut self, chunks: I) -> Self
+    where
+        I: IntoIterator<Item = Vec<u8>>,
+        I::IntoIter: Send + 'static,
+    {
+        self.body.clear();
+        self.stream = Some(BodyStream::Chunks(Box::new(chunks.into_iter())));
+        self
+    }
+
+    /// Returns `true` if the body of the response is streamed with `with_reader` or `with_chunks` rather than held
+    ///   in `body`.
+    pub fn is_streamed(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Takes the streamed body out of the response, if it has one, leaving the response without a body.
+    pub fn take_stream(&mut self) -> Option<BodyStream> {
+        self.stream.take()
+    }
+
+    /// Returns the length of the body in bytes, or `None` if it is streamed with an unknown length.
+    pub fn body_length(&self) -> Option<u64> {
+        match &self.stream {
+            Some(BodyStream::Reader(_, length)) => *length,
+            Some(BodyStream::Chunks(_)) => None,
+            None => Some(self.body.len() as u64),
+        }
+    }
+
+    /// Writes the response to the given writer, streaming the body if necessary.
+    ///
+    /// If the headers specify chunked transfer encoding, a streamed body is written in chunks,
+    ///   otherwise it is written as-is.
+    #[cfg(not(feature = "tokio"))]
+    pub(crate) fn write_to<W>(mut self, writer: &mut W) -> std::io::Result<()>
+    where
+        W: Write,
+    {
+        let stream = self.stream.take();
+        let chunked = is_chunked(&self.headers);
+        let bytes: Vec<u8> = self.into();
+
+        writer.write_all(&bytes)?;
+
+        match stream {
+            Some(BodyStream::Reader(reader, length)) => {
+                let mut reader: Box<dyn Read> = match length {
+                    Some(length) => Box::new(reader.take(length)),
+                    None => reader,
+                };
+
+                let mut buf = vec![0u8; STREAM_BUFFER_SIZE];
+                let mut written: u64 = 0;
+
+                loop {
+                    let n = reader.read(&mut buf)?;
+
+                    if n == 0 {
+                        break;
+                    }
+
+                    write_chunk(writer, &buf[..n], chunked)?;
+                    written += n as u64;
+                }
+
+                if length.is_some_and(|length| written != length) {
+                    return Err(ErrorKind::UnexpectedEof.into());
+                }
+            }
+            Some(BodyStream::Chunks(chunks)) => {
+                for chunk in chunks.filter(|chunk| !chunk.is_empty()) {
+                    write_chunk(writer, &chunk, chunked)?;
+                }
+            }
+            None => return writer.flush(),
+        }
+
+        if chunked {
+            writer.write_all(b"0\r\n\r\n")?;
+        }
+
+        writer.flush()
+    }
+
+    /// Writes the response to the given asynchronous writer, streaming the body if necessary.
+    ///
+    /// Behaves in the same way as the synchronous `write_to`.
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn write_to<W>(mut self, writer: &mut W) -> std::io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let stream = self.stream.take();
+        let chunked = is_chunked(&self.headers);
+        let bytes: Vec<u8> = self.into();
+
+        writer.write_all(&bytes).await?;
+
+        match stream {
+            Some(BodyStream::Reader(reader, length)) => {
+                let mut reader: Box<dyn AsyncRead + Send + Unpin> = match length {
+                    Some(length) => Box::new(reader.take(length)),
+                    None => reader,
+                };
+
+                let mut buf = vec![0u8; STREAM_BUFFER_SIZE];
+                let mut written: u64 = 0;
+
+                loop {
+                    let n = reader.read(&mut buf).await?;
+
+                    if n == 0 {
+                        break;
+                    }
+
+                    write_chunk_async(writer, &buf[..n], chunked).await?;
+                    written += n as u64;
+                }
+
+                if length.is_some_and(|length| written != length) {
+                    return Err(ErrorKind::UnexpectedEof.into());
+                }
+            }
+            Some(BodyStream::Chunks(chunks)) => {
+                for chunk in chunks.filter(|chunk| !chunk.is_empty()) {
+                    write_chunk_async(writer, &chunk, chunked).await?;
+                }
+            }
+            None => return writer.flush().await,
+        }
+
+        if chunked {
+            writer.write_all(b"0\r\n\r\n").await?;
+        }
+
+        writer.flush().await
+    }
+
     /// Returns a reference to the response's headers.
     pub fn get_headers(&self) -> &Headers {
         &self.headers
@@ -143,6 +411,25 @@ impl Response {
     ///
     /// Converts chunked transfer encoding into a regular body.
     pub fn from_stream<T>(stream: &mut T) -> Result<Self, ResponseError>
+    where
+        T: Read,
+    {
+        Self::from_stream_inner(stream, true)
+    }
+
+    /// Attempts to read and parse one HTTP response which has no body from the given stream,
+    ///   such as the response to a `HEAD` request.
+    ///
+    /// The headers are kept as sent, including `Content-Length`, but no body is read.
+    pub fn from_stream_without_body<T>(stream: &mut T) -> Result<Self, ResponseError>
+    where
+        T: Read,
+    {
+        Self::from_stream_inner(stream, false)
+    }
+
+    /// Attempts to read and parse one HTTP response from the given stream, reading the body only if `read_body` is true.
+    fn from_stream_inner<T>(stream: &mut T, read_body: bool) -> Result<Self, ResponseError>
     where
         T: Read,
     {
@@ -181,18 +468,20 @@ impl Response {
             }
         }
 
-        if headers
-            .get(&HeaderType::TransferEncoding)
-            .and_then(|te| if te == "chunked" { Some(()) } else { None })
-            .is_some()
-        {
+        // Responses with these status codes never have a body, even if their headers describe one
+        let read_body = read_body && status.allows_body();
+
+        if read_body && is_chunked(&headers) {
             let mut body: Vec<u8> = Vec::new();
 
-            while let Some(chunk) = parse_chunk(&mut reader) {
+            while let Some(chunk) = parse_chunk(&mut reader).map_err(|_| ResponseError::Response)? {
                 body.extend(chunk);
             }
 
+            parse_trailers(&mut reader, &mut headers).map_err(|_| ResponseError::Response)?;
+
             headers.remove(&HeaderType::TransferEncoding);
+            headers.remove(&HeaderType::ContentLength);
             headers.add(HeaderType::ContentLength, body.len().to_string());
 
             Ok(Self {
@@ -200,8 +489,12 @@ impl Response {
                 status_code: status,
                 headers,
                 body,
+                stream: None,
             })
-        } else if let Some(content_length) = headers.get(&HeaderType::ContentLength) {
+        } else if let Some(content_length) = headers
+            .get(&HeaderType::ContentLength)
+            .filter(|_| read_body)
+        {
             let content_length: usize = content_length
                 .parse()
                 .map_err(|_| ResponseError::Response)?;
@@ -215,6 +508,7 @@ impl Response {
                 status_code: status,
                 headers,
                 body: content_buf,
+                stream: None,
             })
         } else {
             Ok(Self {
@@ -222,6 +516,7 @@ impl Response {
                 status_code: status,
                 headers,
                 body: Vec::new(),
+                stream: None,
             })
         }
     }
@@ -249,33 +544,153 @@ impl From<Response> for Vec<u8> {
 
         bytes.extend(b"\r\n\r\n");
 
-        if !val.body.is_empty() {
-            bytes.extend(val.body);
-            bytes.extend(b"\r\n");
-        }
+        // The body is framed by its length, so nothing may follow it on a persistent connection
+        bytes.extend(val.body);
 
         bytes
     }
 }
 
+/// The size of the buffer used when streaming a response body from a reader.
+const STREAM_BUFFER_SIZE: usize = 8192;
+
+/// Writes part of a streamed body, framing it as a chunk if chunked transfer encoding is used.
+#[cfg(not(feature = "tokio"))]
+fn write_chunk<W>(writer: &mut W, data: &[u8], chunked: bool) -> std::io::Result<()>
+where
+    W: Write,
+{
+    if chunked {
+        writer.write_all(format!("{:X}\r\n", data.len()).as_bytes())?;
+        writer.write_all(data)?;
+        writer.write_all(b"\r\n")
+    } else {
+        writer.write_all(data)
+    }
+}
+
+/// Writes part of a streamed body to an asynchronous writer, framing it as a chunk if chunked transfer encoding is used.
+#[cfg(feature = "tokio")]
+async fn write_chunk_async<W>(writer: &mut W, data: &[u8], chunked: bool) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    if chunked {
+        writer
+            .write_all(format!("{:X}\r\n", data.len()).as_bytes())
+            .await?;
+        writer.write_all(data).await?;
+        writer.write_all(b"\r\n").await
+    } else {
+        writer.write_all(data).await
+    }
+}
+
+/// Returns `true` if the final transfer coding in the headers is `chunked`.
+pub(crate) fn is_chunked(headers: &Headers) -> bool {
+    headers
+        .get_all(&HeaderType::TransferEncoding)
+        .last()
+        .and_then(|te| te.rsplit(',').next())
+        .map(|te| te.trim().eq_ignore_ascii_case("chunked"))
+        .unwrap_or(false)
+}
+
 /// Parses a chunk using the chunked transfer encoding.
-fn parse_chunk<T>(stream: &mut BufReader<T>) -> Option<Vec<u8>>
+///
+/// Returns `Ok(None)` once the final zero-length chunk has been read, after which any trailers
+///   should be read with `parse_trailers`. Malformed chunks return an error of kind `InvalidData`.
+fn parse_chunk<T>(stream: &mut T) -> std::io::Result<Option<Vec<u8>>>
 where
-    T: Read,
+    T: BufRead,
 {
     let mut length_line_buf: Vec<u8> = Vec::new();
-    stream.read_until(0xA, &mut length_line_buf).ok()?;
-    let length: usize =
-        usize::from_str_radix(std::str::from_utf8(&length_line_buf).ok()?.trim_end(), 16).ok()?;
+    stream.read_until(0xA, &mut length_line_buf)?;
+    let length = parse_chunk_length(&length_line_buf)?;
 
     if length == 0 {
-        stream.read_exact(&mut [0u8, 0]).ok()?;
-        None
-    } else {
-        let mut content_buf: Vec<u8> = vec![0u8; length];
-        stream.read_exact(&mut content_buf).ok()?;
-        stream.read_exact(&mut [0u8, 0]).ok()?;
-        Some(content_buf)
+        return Ok(None);
+    }
+
+    let mut content_buf: Vec<u8> = Vec::new();
+    stream.take(length as u64).read_to_end(&mut content_buf)?;
+
+    if content_buf.len() != length {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+
+    let mut crlf = [0u8; 2];
+    stream.read_exact(&mut crlf)?;
+    safe_assert_chunk(&crlf == b"\r\n")?;
+
+    Ok(Some(content_buf))
+}
+
+/// Parses the trailer section following the final chunk of a chunked body, adding each trailer
+///   field to the given headers except those which are forbidden in trailers.
+fn parse_trailers<T>(stream: &mut T, headers: &mut Headers) -> std::io::Result<()>
+where
+    T: BufRead,
+{
+    loop {
+        let mut line_buf: Vec<u8> = Vec::new();
+        stream.read_until(0xA, &mut line_buf)?;
+
+        if parse_trailer_line(&line_buf, headers)? {
+            return Ok(());
+        }
+    }
+}
+
+/// Parses the hexadecimal length from a chunk size line, ignoring any chunk extensions.
+///
+/// The length must consist only of hexadecimal digits. Whitespace is only allowed between the length and the `;`
+///   which starts a chunk extension.
+pub(crate) fn parse_chunk_length(line: &[u8]) -> std::io::Result<usize> {
+    let line = std::str::from_utf8(line).map_err(|_| ErrorKind::InvalidData)?;
+    let line = line.strip_suffix('\n').ok_or(ErrorKind::InvalidData)?;
+    let line = line.strip_suffix('\r').unwrap_or(line);
+
+    let length = match line.split_once(';') {
+        Some((length, _)) => length.trim_end_matches([' ', '\t']),
+        None => line,
+    };
+    safe_assert_chunk(!length.is_empty() && length.bytes().all(|b| b.is_ascii_hexdigit()))?;
+
+    usize::from_str_radix(length, 16).map_err(|_| ErrorKind::InvalidData.into())
+}
+
+/// Parses a single trailer line into the headers, returning `true` if the line ended the trailer section.
+/// Fields which are forbidden in trailers are ignored.
+pub(crate) fn parse_trailer_line(line: &[u8], headers: &mut Headers) -> std::io::Result<bool> {
+    let line = std::str::from_utf8(line).map_err(|_| ErrorKind::InvalidData)?;
+
+    if line == "\r\n" || line == "\n" {
+        return Ok(true);
+    }
+
+    safe_assert_chunk(line.ends_with('\n'))?;
+
+    let (name, value) = line
+        .trim_end()
+        .split_once(':')
+        .ok_or(ErrorKind::InvalidData)?;
+
+    if !FORBIDDEN_TRAILERS
+        .iter()
+        .any(|forbidden| forbidden.eq_ignore_ascii_case(name))
+    {
+        headers.add(HeaderType::from(name), value.trim_start());
+    }
+
+    Ok(false)
+}
+
+/// Asserts that a condition about a chunked body is true, returning an `InvalidData` error otherwise.
+fn safe_assert_chunk(condition: bool) -> std::io::Result<()> {
+    match condition {
+        true => Ok(()),
+        false => Err(ErrorKind::InvalidData.into()),
     }
 }
 
@@ -98,6 +98,20 @@ pub enum StatusCode {
     VersionNotSupported,
 }
 
+impl StatusCode {
+    /// Returns `true` if responses with this status code can have a body.
+    /// Informational responses, `204 No Content` and `304 Not Modified` never have one.
+    pub fn allows_body(&self) -> bool {
+        !matches!(
+            self,
+            StatusCode::Continue
+                | StatusCode::SwitchingProtocols
+                | StatusCode::NoContent
+                | StatusCode::NotModified
+        )
+    }
+}
+
 /// Represents an error with the status code.
 #[derive(PartialEq, Eq)]
 pub struct StatusCodeError;
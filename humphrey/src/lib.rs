@@ -23,9 +23,12 @@ pub mod stream;
 pub mod client;
 pub mod http;
 pub mod krauss;
+pub mod listener;
 pub mod monitor;
 pub mod percent;
 pub mod route;
+pub(crate) mod route_tree;
+pub mod shutdown;
 pub mod thread;
 
 #[cfg(test)]
@@ -0,0 +1,96 @@
+//! Provides the addresses an app can listen for connections on.
+
+use std::fmt::Display;
+use std::net::TcpListener;
+
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+
+/// Represents an address the app listens for connections on.
+///
+/// An app can listen on several addresses at once using `App::run_listeners`, sharing its routes and state between
+///   them, for example to serve both IPv4 and IPv6 clients, or both plain and TLS connections.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Listener {
+    /// A TCP socket address, such as `0.0.0.0:80`.
+    Tcp(String),
+    /// A TCP socket address on which connections are secured with TLS, using the app's certificate.
+    #[cfg(feature = "tls")]
+    Tls(String),
+    /// The path of a Unix domain socket, which is created when the app starts.
+    ///
+    /// If a socket already exists at the path, it is removed first, so a socket left behind by a previous run does
+    ///   not prevent the app from starting. The socket is removed when the app shuts down.
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl Display for Listener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Listener::Tcp(addr) => write!(f, "http://{}", addr),
+            #[cfg(feature = "tls")]
+            Listener::Tls(addr) => write!(f, "https://{}", addr),
+            #[cfg(unix)]
+            Listener::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Represents a socket which has already been bound, such as one inherited from a service manager using socket
+///   activation.
+///
+/// Unlike the sockets created from a `Listener`, Unix domain sockets passed to the app in this way are not removed
+///   when it shuts down, since they are owned by whatever created them.
+#[derive(Debug)]
+pub enum PreboundListener {
+    /// A bound TCP socket.
+    Tcp(TcpListener),
+    /// A bound TCP socket on which connections are secured with TLS, using the app's certificate.
+    #[cfg(feature = "tls")]
+    Tls(TcpListener),
+    /// A bound Unix domain socket.
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Display for PreboundListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PreboundListener::Tcp(listener) => match listener.local_addr() {
+                Ok(addr) => write!(f, "http://{}", addr),
+                Err(_) => write!(f, "http://<unknown>"),
+            },
+            #[cfg(feature = "tls")]
+            PreboundListener::Tls(listener) => match listener.local_addr() {
+                Ok(addr) => write!(f, "https://{}", addr),
+                Err(_) => write!(f, "https://<unknown>"),
+            },
+            #[cfg(unix)]
+            PreboundListener::Unix(listener) => {
+                match listener
+                    .local_addr()
+                    .ok()
+                    .and_then(|a| a.as_pathname().map(Path::to_path_buf))
+                {
+                    Some(path) => write!(f, "unix:{}", path.display()),
+                    None => write!(f, "unix:<unnamed>"),
+                }
+            }
+        }
+    }
+}
+
+/// Removes a Unix domain socket left behind at the path by a previous run, so it can be bound again.
+/// Files at the path which are not sockets are left in place, so binding to it fails.
+#[cfg(unix)]
+pub(crate) fn remove_stale_socket(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
@@ -1,14 +1,19 @@
 //! Provides functionality for handling app routes.
 
 use crate::app::{
-    PathAwareRequestHandler, RequestHandler, StatelessRequestHandler, WebsocketHandler,
+    Middleware, PathAwareRequestHandler, RequestHandler, StatelessRequestHandler,
+    StreamingRequestHandler, WebsocketHandler,
 };
 use crate::http::cors::Cors;
+use crate::http::method::Method;
+use crate::http::params::Params;
 use crate::krauss;
 use crate::percent::PercentDecode;
+use crate::route_tree::RouteTree;
 
 use std::fs::metadata;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 /// Represents a sub-app to run for a specific host.
 pub struct SubApp<State> {
@@ -21,16 +26,36 @@ pub struct SubApp<State> {
     /// The CORS configuration for this subapp.
     /// If not specified, it is down to the individual routes to specify CORS configuration.
     pub cors: Option<Cors>,
+    /// The middleware which runs for every request to this subapp, in the order it was added.
+    pub middleware: Vec<Arc<dyn Middleware<State>>>,
+    /// The compiled route tree, built when the app starts running.
+    pub(crate) tree: Option<RouteTree>,
 }
 
 /// Encapsulates a route and its handler.
 pub struct RouteHandler<State> {
     /// The route that this handler will match.
     pub route: String,
+    /// The methods that this handler will match.
+    /// If not specified, the handler matches every method.
+    pub methods: Option<Vec<Method>>,
     /// The handler to run when the route is matched.
-    pub handler: Box<dyn RequestHandler<State>>,
+    pub handler: Handler<State>,
     /// The CORS configuration for the route.
     pub cors: Cors,
+    /// The maximum size of the request body in bytes.
+    /// If not specified, the app's maximum body size is used.
+    pub max_body_size: Option<usize>,
+    /// The middleware which runs for requests to this route, after the sub-app's middleware.
+    pub middleware: Vec<Arc<dyn Middleware<State>>>,
+}
+
+/// A handler for a route, which receives the request body either in memory or as a stream.
+pub enum Handler<State> {
+    /// A handler which receives the request with its body already read into memory.
+    Buffered(Box<dyn RequestHandler<State>>),
+    /// A handler which receives the request body as it is read from the client.
+    Streaming(Box<dyn StreamingRequestHandler<State>>),
 }
 
 /// Encapsulates a route and its WebSocket handler.
@@ -48,6 +73,8 @@ impl<State> Default for SubApp<State> {
             routes: Vec::new(),
             websocket_routes: Vec::new(),
             cors: None,
+            middleware: Vec::new(),
+            tree: None,
         }
     }
 }
@@ -66,8 +93,11 @@ impl<State> SubApp<State> {
     {
         self.routes.push(RouteHandler {
             route: route.to_string(),
-            handler: Box::new(handler),
+            methods: None,
+            handler: Handler::Buffered(Box::new(handler)),
             cors: self.cors.as_ref().map_or(Cors::default(), |c| c.clone()),
+            max_body_size: None,
+            middleware: Vec::new(),
         });
         self
     }
@@ -83,12 +113,77 @@ impl<State> SubApp<State> {
     {
         self.routes.push(RouteHandler {
             route: route.to_string(),
-            handler: Box::new(move |request, _| handler.serve(request)),
+            methods: None,
+            handler: Handler::Buffered(Box::new(move |request, _| handler.serve(request))),
+            cors: self.cors.as_ref().map_or(Cors::default(), |c| c.clone()),
+            max_body_size: None,
+            middleware: Vec::new(),
+        });
+        self
+    }
+
+    /// Adds a route and associated handler to the sub-app which only matches requests with the given method.
+    /// Routes can include wildcards, for example `/blog/*`.
+    ///
+    /// Requests to the route with other methods are rejected with 405 Method Not Allowed,
+    ///   unless another handler for the route accepts them.
+    pub fn with_method_route<T>(mut self, method: Method, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.routes.push(RouteHandler {
+            route: route.to_string(),
+            methods: Some(vec![method]),
+            handler: Handler::Buffered(Box::new(handler)),
             cors: self.cors.as_ref().map_or(Cors::default(), |c| c.clone()),
+            max_body_size: None,
+            middleware: Vec::new(),
         });
         self
     }
 
+    /// Adds a route and associated handler to the sub-app which only matches `GET` requests.
+    /// `HEAD` requests to the route are also answered by the handler, with the body removed, unless a route for the
+    ///   path was added for the `HEAD` method with `with_method_route`.
+    pub fn with_get<T>(self, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.with_method_route(Method::Get, route, handler)
+    }
+
+    /// Adds a route and associated handler to the sub-app which only matches `POST` requests.
+    pub fn with_post<T>(self, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.with_method_route(Method::Post, route, handler)
+    }
+
+    /// Adds a route and associated handler to the sub-app which only matches `PUT` requests.
+    pub fn with_put<T>(self, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.with_method_route(Method::Put, route, handler)
+    }
+
+    /// Adds a route and associated handler to the sub-app which only matches `PATCH` requests.
+    pub fn with_patch<T>(self, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.with_method_route(Method::Patch, route, handler)
+    }
+
+    /// Adds a route and associated handler to the sub-app which only matches `DELETE` requests.
+    pub fn with_delete<T>(self, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.with_method_route(Method::Delete, route, handler)
+    }
+
     /// Adds a path-aware route and associated handler to the sub-app.
     /// Routes can include wildcards, for example `/blog/*`.
     /// Will also pass the route to the handler at runtime.
@@ -98,8 +193,31 @@ impl<State> SubApp<State> {
     {
         self.routes.push(RouteHandler {
             route: route.to_string(),
-            handler: Box::new(move |request, state| handler.serve(request, state, route)),
+            methods: None,
+            handler: Handler::Buffered(Box::new(move |request, state| {
+                handler.serve(request, state, route)
+            })),
+            cors: self.cors.as_ref().map_or(Cors::default(), |c| c.clone()),
+            max_body_size: None,
+            middleware: Vec::new(),
+        });
+        self
+    }
+
+    /// Adds a streaming route and associated handler to the sub-app.
+    /// Routes can include wildcards, for example `/upload/*`.
+    /// Instead of being read into memory, the request body is passed to the handler to be read as it arrives.
+    pub fn with_streaming_route<T>(mut self, route: &str, handler: T) -> Self
+    where
+        T: StreamingRequestHandler<State> + 'static,
+    {
+        self.routes.push(RouteHandler {
+            route: route.to_string(),
+            methods: None,
+            handler: Handler::Streaming(Box::new(handler)),
             cors: self.cors.as_ref().map_or(Cors::default(), |c| c.clone()),
+            max_body_size: None,
+            middleware: Vec::new(),
         });
         self
     }
@@ -145,20 +263,214 @@ impl<State> SubApp<State> {
 
         self
     }
+
+    /// Adds middleware which runs for every request to the sub-app, after any middleware already added.
+    pub fn with_middleware<T>(mut self, middleware: T) -> Self
+    where
+        T: Middleware<State> + 'static,
+    {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Adds middleware which runs for requests to a given route, after the sub-app's middleware.
+    pub fn with_route_middleware<T>(mut self, route: &str, middleware: T) -> Self
+    where
+        T: Middleware<State> + 'static,
+    {
+        let middleware: Arc<dyn Middleware<State>> = Arc::new(middleware);
+
+        self.routes.iter_mut().for_each(|r| {
+            if r.route == route {
+                r.middleware.push(middleware.clone());
+            }
+        });
+
+        self
+    }
+
+    /// Sets the maximum size of the request body in bytes for a given route, overriding the app's maximum.
+    /// Requests with larger bodies are rejected with 413 Payload Too Large.
+    pub fn with_max_body_size_config(mut self, route: &str, max_body_size: usize) -> Self {
+        self.routes.iter_mut().for_each(|r| {
+            if r.route == route {
+                r.max_body_size = Some(max_body_size);
+            }
+        });
+
+        self
+    }
+}
+
+impl<State> SubApp<State> {
+    /// Compiles the sub-app's routes into a route tree, so they can be looked up without scanning every route.
+    ///
+    /// This is called when the app starts running, so routes added afterwards will not be matched
+    ///   until it is called again.
+    pub(crate) fn compile(&mut self) {
+        self.tree = Some(RouteTree::new(&self.routes));
+    }
+
+    /// Returns the routes which match the given path regardless of method, in the order they were added.
+    pub(crate) fn matching_routes<'a, 'b>(
+        &'a self,
+        uri: &'b str,
+    ) -> Box<dyn Iterator<Item = &'a RouteHandler<State>> + 'b>
+    where
+        'a: 'b,
+    {
+        match &self.tree {
+            Some(tree) => Box::new(
+                tree.candidates(uri)
+                    .into_iter()
+                    .map(|index| &self.routes[index])
+                    .filter(move |route| route.route.route_matches(uri)),
+            ),
+            None => Box::new(
+                self.routes
+                    .iter()
+                    .filter(move |route| route.route.route_matches(uri)),
+            ),
+        }
+    }
+
+    /// Returns `true` if any of the sub-app's routes match the given path, regardless of method.
+    pub(crate) fn has_route(&self, uri: &str) -> bool {
+        self.matching_routes(uri).next().is_some()
+    }
+
+    /// Returns the methods allowed on the given path, for use in the `Allow` header.
+    ///
+    /// Returns `None` if no route matches the path, or if a matching route accepts every method.
+    /// Otherwise, `HEAD` is included if `GET` is allowed, and `OPTIONS` is always included since it is
+    ///   answered automatically.
+    pub(crate) fn allowed_methods(&self, uri: &str) -> Option<Vec<Method>> {
+        let mut allowed: Vec<Method> = Vec::new();
+
+        for route in self.matching_routes(uri) {
+            for method in route.methods.as_ref()? {
+                if !allowed.contains(method) {
+                    allowed.push(method.clone());
+                }
+            }
+        }
+
+        if allowed.is_empty() {
+            return None;
+        }
+
+        if allowed.contains(&Method::Get) && !allowed.contains(&Method::Head) {
+            allowed.push(Method::Head);
+        }
+
+        if !allowed.contains(&Method::Options) {
+            allowed.push(Method::Options);
+        }
+
+        Some(allowed)
+    }
+}
+
+impl<State> RouteHandler<State> {
+    /// Returns `true` if the handler accepts requests with the given method.
+    pub fn method_matches(&self, method: &Method) -> bool {
+        self.methods
+            .as_ref()
+            .is_none_or(|methods| methods.contains(method))
+    }
+
+    /// Returns `true` if the handler was registered for the given method, rather than for every method.
+    pub fn method_registered(&self, method: &Method) -> bool {
+        self.methods
+            .as_ref()
+            .is_some_and(|methods| methods.contains(method))
+    }
 }
 
 /// An object that can represent a route, currently only `String`.
 pub trait Route {
     /// Returns true if the given route matches the path.
     fn route_matches(&self, route: &str) -> bool;
+
+    /// Matches the given path against the route, returning the parameters captured from it,
+    ///   or `None` if the route does not match.
+    fn route_params(&self, path: &str) -> Option<Params>;
 }
 
 impl Route for String {
     /// Checks whether this route matches the given one, respecting its own wildcards only.
     /// For example, `/blog/*` will match `/blog/my-first-post` but not the other way around.
+    ///
+    /// Segments beginning with `:` match any single non-empty segment, for example `/users/:id`
+    ///   will match `/users/1234`.
     fn route_matches(&self, route: &str) -> bool {
-        krauss::wildcard_match(self, route)
+        if has_named_params(self) {
+            self.route_params(route).is_some()
+        } else {
+            krauss::wildcard_match(self, route)
+        }
     }
+
+    /// Matches the given path against this route, capturing named parameters and the part of the path
+    ///   matched by a trailing wildcard.
+    ///
+    /// Named parameters are percent-decoded, but the wildcard is left as it appears in the path.
+    fn route_params(&self, path: &str) -> Option<Params> {
+        if !has_named_params(self) {
+            if !krauss::wildcard_match(self, path) {
+                return None;
+            }
+
+            // If the route ends with its only wildcard, the rest of the path after the prefix is captured
+            return Some(match self.strip_suffix('*') {
+                Some(prefix) if !prefix.contains('*') => {
+                    Params::new().with_wildcard(path.get(prefix.len()..).unwrap_or_default())
+                }
+                _ => Params::new(),
+            });
+        }
+
+        let mut params = Params::new();
+        let mut route_segments = self.split('/').peekable();
+        let mut path_segments = path.split('/');
+
+        while let Some(route_segment) = route_segments.next() {
+            if route_segment == "*" && route_segments.peek().is_none() {
+                let rest: Vec<&str> = path_segments.collect();
+                return Some(params.with_wildcard(rest.join("/")));
+            }
+
+            let path_segment = path_segments.next()?;
+
+            if let Some(name) = route_segment.strip_prefix(':') {
+                if path_segment.is_empty() {
+                    return None;
+                }
+
+                params = params.with_param(name, decode_param(path_segment));
+            } else if !krauss::wildcard_match(route_segment, path_segment) {
+                return None;
+            }
+        }
+
+        match path_segments.next() {
+            Some(_) => None,
+            None => Some(params),
+        }
+    }
+}
+
+/// Returns `true` if the route contains any named parameters.
+fn has_named_params(route: &str) -> bool {
+    route.split('/').any(|segment| segment.starts_with(':'))
+}
+
+/// Percent-decodes a captured parameter, leaving it unchanged if it is not valid.
+fn decode_param(param: &str) -> String {
+    param
+        .percent_decode()
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .unwrap_or_else(|| param.to_string())
 }
 
 /// A located file or directory path.
@@ -177,13 +489,7 @@ pub fn try_find_path(
     request_path: &str,
     index_files: &[&str],
 ) -> Option<LocatedPath> {
-    let request_path = String::from_utf8(request_path.percent_decode()?).ok()?;
-
-    // Avoid path traversal exploits
-    if request_path.contains("..") || request_path.contains(':') {
-        return None;
-    }
-
+    let request_path = sanitise_request_path(request_path)?;
     let request_path = request_path.trim_start_matches('/');
     let directory = directory.trim_end_matches('/');
 
@@ -214,3 +520,35 @@ pub fn try_find_path(
 
     None
 }
+
+/// Attempts to find a given directory, for example to list its contents when it has no index file.
+/// Only request paths ending in a slash are matched, and the same path traversal protection as `try_find_path` is
+///   applied. If the directory is not found, returns `None`.
+pub fn try_find_directory(directory: &str, request_path: &str) -> Option<PathBuf> {
+    let request_path = sanitise_request_path(request_path)?;
+    let request_path = request_path.trim_start_matches('/');
+    let directory = directory.trim_end_matches('/');
+
+    if !request_path.ends_with('/') && !request_path.is_empty() {
+        return None;
+    }
+
+    let path = format!("{}/{}", directory, request_path);
+
+    match metadata(&path) {
+        Ok(meta) if meta.is_dir() => PathBuf::from(path).canonicalize().ok(),
+        _ => None,
+    }
+}
+
+/// Percent-decodes a request path, returning `None` if it is invalid or could be used for path traversal.
+fn sanitise_request_path(request_path: &str) -> Option<String> {
+    let request_path = String::from_utf8(request_path.percent_decode()?).ok()?;
+
+    // Avoid path traversal exploits
+    if request_path.contains("..") || request_path.contains(':') {
+        return None;
+    }
+
+    Some(request_path)
+}
@@ -0,0 +1,66 @@
+//! Provides a compiled routing structure for looking up routes without scanning every one.
+
+use crate::route::RouteHandler;
+
+use std::collections::HashMap;
+
+/// A tree of routes indexed by the literal segments at the start of their paths.
+///
+/// Each route is stored at the node reached by its leading segments which contain no wildcards or named
+///   parameters, since any path it matches must begin with exactly those segments. Looking up a path therefore
+///   only yields the routes which could match it, in the order they were added, so the first match still wins.
+#[derive(Default)]
+pub(crate) struct RouteTree {
+    root: Node,
+}
+
+/// A node of the route tree.
+#[derive(Default)]
+struct Node {
+    children: HashMap<String, Node>,
+    routes: Vec<usize>,
+}
+
+impl RouteTree {
+    /// Compiles a route tree from the given routes.
+    pub(crate) fn new<State>(routes: &[RouteHandler<State>]) -> Self {
+        let mut tree = Self::default();
+
+        for (index, route) in routes.iter().enumerate() {
+            let mut node = &mut tree.root;
+
+            for segment in route
+                .route
+                .split('/')
+                .take_while(|segment| !segment.contains('*') && !segment.starts_with(':'))
+            {
+                node = node.children.entry(segment.to_string()).or_default();
+            }
+
+            node.routes.push(index);
+        }
+
+        tree
+    }
+
+    /// Returns the indices of the routes which could match the given path, in the order they were added.
+    ///
+    /// The routes must still be checked against the path, since only their literal segments have been compared.
+    pub(crate) fn candidates(&self, path: &str) -> Vec<usize> {
+        let mut node = &self.root;
+        let mut candidates = node.routes.clone();
+
+        for segment in path.split('/') {
+            match node.children.get(segment) {
+                Some(child) => {
+                    node = child;
+                    candidates.extend(&node.routes);
+                }
+                None => break,
+            }
+        }
+
+        candidates.sort_unstable();
+        candidates
+    }
+}
@@ -0,0 +1,300 @@
+//! Provides functionality for shutting down an app gracefully.
+//!
+//! When the app's shutdown signal is received, it stops accepting connections and closes its idle keep-alive
+//!   connections. Connections with requests in progress are given until the end of the grace period to finish
+//!   them, after which they are closed.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[cfg(not(feature = "tokio"))]
+use crate::stream::Stream;
+#[cfg(not(feature = "tokio"))]
+use std::sync::Condvar;
+
+#[cfg(feature = "tokio")]
+use tokio::sync::Notify;
+#[cfg(feature = "tokio")]
+use tokio_util::sync::CancellationToken;
+
+/// A summary of a graceful shutdown, returned when the app stops running.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ShutdownSummary {
+    /// The number of idle connections which were closed when the shutdown began.
+    pub idle_connections: usize,
+    /// The number of connections which finished their requests within the grace period.
+    pub drained_connections: usize,
+    /// The number of connections which still had requests in progress at the end of the grace period, and were
+    ///   closed regardless.
+    pub forced_connections: usize,
+    /// The amount of time the shutdown took.
+    pub duration: Duration,
+}
+
+/// Keeps track of the app's open connections, so they can be drained when it shuts down.
+#[derive(Default)]
+pub(crate) struct ConnectionTracker {
+    state: Mutex<TrackerState>,
+    #[cfg(not(feature = "tokio"))]
+    changed: Condvar,
+    #[cfg(feature = "tokio")]
+    changed: Notify,
+    #[cfg(feature = "tokio")]
+    draining: CancellationToken,
+    #[cfg(feature = "tokio")]
+    closing: CancellationToken,
+}
+
+/// The state of the tracked connections.
+#[derive(Default)]
+struct TrackerState {
+    next_id: usize,
+    connections: HashMap<usize, ConnectionState>,
+    draining: bool,
+}
+
+/// The state of a single tracked connection.
+struct ConnectionState {
+    /// A handle to the connection's socket, used to close it from another thread.
+    #[cfg(not(feature = "tokio"))]
+    socket: Option<Stream>,
+    /// The number of requests currently being handled on the connection.
+    active_requests: usize,
+    /// Whether the connection has been closed by the shutdown.
+    closed: bool,
+}
+
+/// A connection registered with the app, which is removed from its open connections when dropped.
+///
+/// Connection handlers must mark the start and end of every request, so the app knows which connections are
+///   idle and can be closed immediately when it shuts down.
+pub struct TrackedConnection {
+    tracker: Arc<ConnectionTracker>,
+    id: usize,
+}
+
+impl ConnectionTracker {
+    /// Creates a new connection tracker with no connections.
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Registers a new idle connection with the tracker, along with a handle to its socket if one could be made.
+    #[cfg(not(feature = "tokio"))]
+    pub(crate) fn register(self: &Arc<Self>, socket: Option<Stream>) -> TrackedConnection {
+        self.insert(ConnectionState {
+            socket,
+            active_requests: 0,
+            closed: false,
+        })
+    }
+
+    /// Registers a new idle connection with the tracker.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn register(self: &Arc<Self>) -> TrackedConnection {
+        self.insert(ConnectionState {
+            active_requests: 0,
+            closed: false,
+        })
+    }
+
+    /// Adds the connection to the tracked connections.
+    fn insert(self: &Arc<Self>, connection: ConnectionState) -> TrackedConnection {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+
+        state.next_id += 1;
+        state.connections.insert(id, connection);
+
+        TrackedConnection {
+            tracker: self.clone(),
+            id,
+        }
+    }
+
+    /// Drains the tracked connections, closing the idle ones and waiting up to the given grace period for the
+    ///   others to finish their requests before closing them too.
+    ///
+    /// No more connections should be registered once this has been called.
+    #[cfg(not(feature = "tokio"))]
+    pub(crate) fn drain(&self, grace_period: Duration) -> ShutdownSummary {
+        let start = Instant::now();
+        let mut state = self.state.lock().unwrap();
+
+        // Closing the idle connections wakes up the threads waiting for their next requests
+        let (idle_connections, busy_connections) = state.begin_draining();
+
+        for connection in state.connections.values().filter(|c| c.closed) {
+            connection.close();
+        }
+
+        while state.connections.values().any(|c| !c.closed) {
+            match grace_period.checked_sub(start.elapsed()) {
+                Some(remaining) if !remaining.is_zero() => {
+                    state = self.changed.wait_timeout(state, remaining).unwrap().0;
+                }
+                _ => break,
+            }
+        }
+
+        let forced_connections = state.close_remaining();
+
+        for connection in state.connections.values() {
+            connection.close();
+        }
+
+        ShutdownSummary {
+            idle_connections,
+            drained_connections: busy_connections.saturating_sub(forced_connections),
+            forced_connections,
+            duration: start.elapsed(),
+        }
+    }
+
+    /// Drains the tracked connections, closing the idle ones and waiting up to the given grace period for the
+    ///   others to finish their requests before closing them too.
+    ///
+    /// No more connections should be registered once this has been called.
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn drain(&self, grace_period: Duration) -> ShutdownSummary {
+        let start = Instant::now();
+        let (idle_connections, busy_connections) = self.state.lock().unwrap().begin_draining();
+
+        // Cancelling the token wakes up the tasks waiting for the next requests on idle connections
+        self.draining.cancel();
+
+        let finished = tokio::time::timeout(grace_period, async {
+            loop {
+                let changed = self.changed.notified();
+
+                if self
+                    .state
+                    .lock()
+                    .unwrap()
+                    .connections
+                    .values()
+                    .all(|c| c.closed)
+                {
+                    break;
+                }
+
+                changed.await;
+            }
+        })
+        .await
+        .is_ok();
+
+        let forced_connections = match finished {
+            true => 0,
+            false => self.state.lock().unwrap().close_remaining(),
+        };
+
+        self.closing.cancel();
+
+        ShutdownSummary {
+            idle_connections,
+            drained_connections: busy_connections.saturating_sub(forced_connections),
+            forced_connections,
+            duration: start.elapsed(),
+        }
+    }
+
+    /// Returns a token which is cancelled when the connections which are still open at the end of the grace
+    ///   period must be closed.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn closing(&self) -> CancellationToken {
+        self.closing.clone()
+    }
+}
+
+impl TrackerState {
+    /// Starts draining the connections, marking the idle ones as closed.
+    ///
+    /// Returns the number of idle connections and the number of connections with requests in progress.
+    fn begin_draining(&mut self) -> (usize, usize) {
+        self.draining = true;
+
+        let mut idle_connections = 0;
+
+        for connection in self.connections.values_mut() {
+            if connection.active_requests == 0 {
+                connection.closed = true;
+                idle_connections += 1;
+            }
+        }
+
+        (idle_connections, self.connections.len() - idle_connections)
+    }
+
+    /// Marks the connections which are still open as closed, returning how many there were.
+    fn close_remaining(&mut self) -> usize {
+        let mut remaining = 0;
+
+        for connection in self.connections.values_mut().filter(|c| !c.closed) {
+            connection.closed = true;
+            remaining += 1;
+        }
+
+        remaining
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+impl ConnectionState {
+    /// Closes the connection's socket, which causes any reads or writes on it to fail.
+    fn close(&self) {
+        if let Some(socket) = &self.socket {
+            let _ = socket.shutdown();
+        }
+    }
+}
+
+impl TrackedConnection {
+    /// Marks the start of a request on the connection.
+    pub fn start_request(&self) {
+        let mut state = self.tracker.state.lock().unwrap();
+
+        if let Some(connection) = state.connections.get_mut(&self.id) {
+            connection.active_requests += 1;
+        }
+    }
+
+    /// Marks the end of a request on the connection.
+    ///
+    /// Returns `false` if the app is shutting down, in which case the connection should be closed instead of
+    ///   waiting for another request.
+    pub fn finish_request(&self) -> bool {
+        let mut state = self.tracker.state.lock().unwrap();
+
+        if let Some(connection) = state.connections.get_mut(&self.id) {
+            connection.active_requests = connection.active_requests.saturating_sub(1);
+        }
+
+        !state.draining
+    }
+
+    /// Returns `true` if the app is shutting down, so no further requests should be accepted on the connection.
+    pub fn is_draining(&self) -> bool {
+        self.tracker.state.lock().unwrap().draining
+    }
+
+    /// Waits until the app begins shutting down.
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn draining(&self) {
+        self.tracker.draining.cancelled().await
+    }
+}
+
+impl Drop for TrackedConnection {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.tracker.state.lock() {
+            state.connections.remove(&self.id);
+        }
+
+        #[cfg(not(feature = "tokio"))]
+        self.tracker.changed.notify_all();
+        #[cfg(feature = "tokio")]
+        self.tracker.changed.notify_waiters();
+    }
+}
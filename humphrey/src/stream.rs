@@ -6,21 +6,27 @@
 use rustls::ServerConnection;
 
 use std::io::{Error, Read, Write};
-use std::net::SocketAddr;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
 use std::time::Duration;
 
 use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
 
 /// Represents a connection to a remote client or server.
 ///
 /// This is typically a wrapper around `TcpStream`, but is required to allow for a single API
-///   to be used to process both regular and TLS connections.
+///   to be used to process regular, TLS and Unix domain socket connections.
 pub enum Stream {
     /// A regular TCP stream.
     Tcp(TcpStream),
     /// A TLS stream.
     #[cfg(feature = "tls")]
     Tls(rustls::StreamOwned<ServerConnection, TcpStream>),
+    /// A Unix domain socket stream.
+    #[cfg(unix)]
+    Unix(UnixStream),
 }
 
 impl Read for Stream {
@@ -29,6 +35,8 @@ impl Read for Stream {
             Stream::Tcp(stream) => stream.read(buf),
             #[cfg(feature = "tls")]
             Stream::Tls(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.read(buf),
         }
     }
 }
@@ -39,6 +47,8 @@ impl Write for Stream {
             Stream::Tcp(stream) => stream.write(buf),
             #[cfg(feature = "tls")]
             Stream::Tls(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.write(buf),
         }
     }
 
@@ -47,17 +57,55 @@ impl Write for Stream {
             Stream::Tcp(stream) => stream.flush(),
             #[cfg(feature = "tls")]
             Stream::Tls(stream) => stream.flush(),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.flush(),
         }
     }
 }
 
 impl Stream {
     /// Returns the socket address of the remote peer of this connection.
+    ///
+    /// Peers connected over a Unix domain socket have no socket address, so the unspecified address is returned
+    ///   for them, and `Stream::unix_socket` can be used to identify the socket instead.
     pub fn peer_addr(&self) -> Result<SocketAddr, Error> {
         match self {
             Stream::Tcp(stream) => stream.peer_addr(),
             #[cfg(feature = "tls")]
             Stream::Tls(stream) => stream.sock.peer_addr(),
+            #[cfg(unix)]
+            Stream::Unix(_) => Ok(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))),
+        }
+    }
+
+    /// Returns the path of the Unix domain socket this connection was accepted on, or `None` if it is not a Unix
+    ///   domain socket connection.
+    pub fn unix_socket(&self) -> Option<PathBuf> {
+        match self {
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream
+                .local_addr()
+                .ok()
+                .and_then(|addr| addr.as_pathname().map(PathBuf::from)),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if this connection is secured with TLS.
+    pub fn is_tls(&self) -> bool {
+        match self {
+            #[cfg(feature = "tls")]
+            Stream::Tls(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if this is a Unix domain socket connection.
+    pub fn is_unix(&self) -> bool {
+        match self {
+            #[cfg(unix)]
+            Stream::Unix(_) => true,
+            _ => false,
         }
     }
 
@@ -67,6 +115,8 @@ impl Stream {
             Stream::Tcp(stream) => stream.shutdown(std::net::Shutdown::Both),
             #[cfg(feature = "tls")]
             Stream::Tls(stream) => stream.sock.shutdown(std::net::Shutdown::Both),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.shutdown(std::net::Shutdown::Both),
         }
     }
 
@@ -82,6 +132,11 @@ impl Stream {
                 stream.sock.set_read_timeout(timeout)?;
                 stream.sock.set_write_timeout(timeout)
             }
+            #[cfg(unix)]
+            Stream::Unix(stream) => {
+                stream.set_read_timeout(timeout)?;
+                stream.set_write_timeout(timeout)
+            }
         }
     }
 
@@ -91,6 +146,8 @@ impl Stream {
             Stream::Tcp(stream) => stream.set_nonblocking(true),
             #[cfg(feature = "tls")]
             Stream::Tls(stream) => stream.sock.set_nonblocking(true),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.set_nonblocking(true),
         }
     }
 
@@ -100,6 +157,8 @@ impl Stream {
             Stream::Tcp(stream) => stream.set_nonblocking(false),
             #[cfg(feature = "tls")]
             Stream::Tls(stream) => stream.sock.set_nonblocking(false),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.set_nonblocking(false),
         }
     }
 }
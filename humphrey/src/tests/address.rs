@@ -0,0 +1,193 @@
+#![allow(unused_imports)]
+use crate::http::address::{Address, Cidr};
+use crate::http::headers::{HeaderType, Headers};
+use crate::http::{Request, Response, StatusCode};
+use crate::App;
+
+use std::io::{Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+
+#[test]
+fn test_cidr_contains() {
+    let range: Cidr = "10.0.0.0/8".parse().unwrap();
+    assert!(range.contains(&ip("10.1.2.3")));
+    assert!(!range.contains(&ip("11.0.0.1")));
+    assert!(!range.contains(&ip("::1")));
+
+    // IPv4 addresses mapped to IPv6 are treated as IPv4
+    assert!(range.contains(&ip("::ffff:10.1.2.3")));
+
+    let range: Cidr = "fd00::/8".parse().unwrap();
+    assert!(range.contains(&ip("fd12:3456::1")));
+    assert!(!range.contains(&ip("fe80::1")));
+
+    let range: Cidr = "127.0.0.1".parse().unwrap();
+    assert!(range.contains(&ip("127.0.0.1")));
+    assert!(!range.contains(&ip("127.0.0.2")));
+    assert_eq!(range.to_string(), "127.0.0.1/32");
+
+    let range: Cidr = "0.0.0.0/0".parse().unwrap();
+    assert!(range.contains(&ip("203.0.113.7")));
+}
+
+#[test]
+fn test_cidr_mapped_ranges() {
+    // Ranges of IPv4-mapped addresses are converted to IPv4 ranges rather than trusting every address
+    let range: Cidr = "::ffff:10.0.0.0/104".parse().unwrap();
+    assert_eq!(range.to_string(), "10.0.0.0/8");
+    assert!(range.contains(&ip("10.1.2.3")));
+    assert!(range.contains(&ip("::ffff:10.1.2.3")));
+    assert!(!range.contains(&ip("11.0.0.1")));
+
+    let range: Cidr = "::ffff:127.0.0.1".parse().unwrap();
+    assert_eq!(range.to_string(), "127.0.0.1/32");
+    assert!(range.contains(&ip("127.0.0.1")));
+    assert!(!range.contains(&ip("203.0.113.7")));
+
+    assert!("::ffff:10.0.0.0/95".parse::<Cidr>().is_err());
+}
+
+#[test]
+fn test_cidr_parse_errors() {
+    assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+    assert!("::/129".parse::<Cidr>().is_err());
+    assert!("10.0.0/8".parse::<Cidr>().is_err());
+    assert!("10.0.0.0/x".parse::<Cidr>().is_err());
+}
+
+#[test]
+fn test_untrusted_peer() {
+    let mut headers = Headers::new();
+    headers.add("X-Forwarded-For", "9.10.11.12");
+    headers.add("X-Forwarded-Proto", "https");
+
+    // The headers are ignored when there are no trusted proxies, or when the peer is not one of them
+    let address = Address::from_headers(&headers, "1.2.3.4:5678", &[]).unwrap();
+    assert_eq!(address, Address::new("1.2.3.4:5678").unwrap());
+
+    let address = Address::from_headers(&headers, "1.2.3.4:5678", &trusted("10.0.0.0/8")).unwrap();
+    assert_eq!(address, Address::new("1.2.3.4:5678").unwrap());
+}
+
+#[test]
+fn test_x_forwarded_for() {
+    let mut headers = Headers::new();
+    headers.add("X-Forwarded-For", "6.6.6.6, 9.10.11.12, 10.0.0.2");
+    headers.add("X-Forwarded-Proto", "HTTPS");
+    headers.add("X-Forwarded-Host", "example.com");
+
+    // The spoofed first address is not reached, since the client which added it is not a trusted proxy
+    let address = Address::from_headers(&headers, "10.0.0.1:5678", &trusted("10.0.0.0/8")).unwrap();
+    assert_eq!(
+        address,
+        Address {
+            origin_addr: ip("9.10.11.12"),
+            proxies: vec![ip("10.0.0.2"), ip("10.0.0.1")],
+            port: 5678,
+            unix_socket: None,
+            tls: false,
+            forwarded_proto: Some("https".into()),
+            forwarded_host: Some("example.com".into()),
+        }
+    );
+    assert_eq!(address.to_string(), "9.10.11.12 (proxied)");
+}
+
+#[test]
+fn test_forwarded() {
+    let mut headers = Headers::new();
+    headers.add(
+        HeaderType::Forwarded,
+        "for=192.0.2.60;proto=https;host=example.com, for=\"[2001:db8:cafe::17]:4711\"",
+    );
+    headers.add("X-Forwarded-For", "6.6.6.6");
+
+    // The standard header is preferred, and every hop is trusted so the first one is the origin
+    let address =
+        Address::from_headers(&headers, "[::1]:5678", &trusted("::1,2001:db8::/32")).unwrap();
+    assert_eq!(address.origin_addr, ip("192.0.2.60"));
+    assert_eq!(address.proxies, vec![ip("2001:db8:cafe::17"), ip("::1")]);
+    assert_eq!(address.forwarded_proto.as_deref(), Some("https"));
+    assert_eq!(address.forwarded_host.as_deref(), Some("example.com"));
+
+    // Hidden addresses end the walk at the proxy which hid them
+    let mut headers = Headers::new();
+    headers.add(HeaderType::Forwarded, "for=unknown;proto=http, for=_hidden");
+
+    let address = Address::from_headers(&headers, "[::1]:5678", &trusted("::1")).unwrap();
+    assert_eq!(address.origin_addr, ip("::1"));
+    assert!(address.proxies.is_empty());
+    assert_eq!(address.forwarded_proto, None);
+}
+
+#[cfg(not(feature = "tokio"))]
+#[test]
+fn test_app_trusted_proxies() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+
+    let (shutdown, receiver) = std::sync::mpsc::channel();
+    let app: App<()> = App::new_with_config(2, ())
+        .with_shutdown(receiver)
+        .with_trusted_proxies(trusted("127.0.0.1"))
+        .with_route("/", |request: Request, _: Arc<()>| {
+            Response::new(StatusCode::OK, request.address.origin_addr.to_string())
+        });
+
+    let app_thread = thread::spawn(move || app.run_with_listener(listener).unwrap());
+
+    assert!(forwarded_request(&addr).ends_with("\r\n\r\n9.10.11.12"));
+
+    shutdown.send(()).unwrap();
+    app_thread.join().unwrap();
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_app_trusted_proxies() {
+    use tokio_util::sync::CancellationToken;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+
+    let shutdown = CancellationToken::new();
+    let app: App<()> = App::new()
+        .with_shutdown(shutdown.clone())
+        .with_trusted_proxies(trusted("127.0.0.1"))
+        .with_route("/", |request: Request, _: Arc<()>| async move {
+            Response::new(StatusCode::OK, request.address.origin_addr.to_string())
+        });
+
+    let app_task = tokio::spawn(async move { app.run_with_listener(listener).await.unwrap() });
+
+    let response = tokio::task::spawn_blocking(move || forwarded_request(&addr))
+        .await
+        .unwrap();
+    assert!(response.ends_with("\r\n\r\n9.10.11.12"));
+
+    shutdown.cancel();
+    app_task.await.unwrap();
+}
+
+/// Sends a request with a forwarded address to the app, returning the whole response.
+fn forwarded_request(addr: &str) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nX-Forwarded-For: 9.10.11.12\r\nConnection: close\r\n\r\n")
+        .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    response
+}
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+fn trusted(ranges: &str) -> Vec<Cidr> {
+    ranges.split(',').map(|r| r.parse().unwrap()).collect()
+}
@@ -0,0 +1,282 @@
+#![allow(unused_imports)]
+use crate::http::compression::{Compression, Encoding};
+use crate::http::deflate::{adler32, crc32, deflate, gzip, zlib};
+use crate::http::headers::HeaderType;
+use crate::http::method::Method;
+use crate::http::{Request, Response, StatusCode};
+
+#[test]
+fn test_checksums() {
+    assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+    assert_eq!(adler32(b""), 1);
+}
+
+#[test]
+fn test_deflate_round_trip() {
+    let repetitive = "Humphrey is a very fast HTTP server. "
+        .repeat(500)
+        .into_bytes();
+    let mixed: Vec<u8> = (0..70000_u32)
+        .map(|i| ((i.wrapping_mul(2654435761) >> 24) % 16 + i % 200) as u8)
+        .collect();
+
+    for data in [
+        Vec::new(),
+        b"a".to_vec(),
+        b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_vec(),
+        repetitive.clone(),
+        mixed,
+    ] {
+        assert_eq!(inflate(&deflate(&data)), data);
+    }
+
+    assert!(deflate(&repetitive).len() < repetitive.len() / 10);
+}
+
+#[test]
+fn test_gzip_and_zlib_formats() {
+    let data = b"Hello, world! Hello, world! Hello, world!";
+
+    let gzipped = gzip(data);
+    assert_eq!(&gzipped[..3], &[0x1f, 0x8b, 0x08]);
+    assert_eq!(
+        &gzipped[gzipped.len() - 8..][..4],
+        &crc32(data).to_le_bytes()
+    );
+    assert_eq!(
+        &gzipped[gzipped.len() - 4..],
+        &(data.len() as u32).to_le_bytes()
+    );
+    assert_eq!(inflate(&gzipped[10..gzipped.len() - 8]), data);
+
+    let zlibbed = zlib(data);
+    assert_eq!(((zlibbed[0] as u16) << 8 | zlibbed[1] as u16) % 31, 0);
+    assert_eq!(&zlibbed[zlibbed.len() - 4..], &adler32(data).to_be_bytes());
+    assert_eq!(inflate(&zlibbed[2..zlibbed.len() - 4]), data);
+}
+
+#[test]
+fn test_negotiate() {
+    let compression = Compression::new();
+
+    assert_eq!(
+        compression.negotiate("gzip, deflate, br"),
+        Some(Encoding::Gzip)
+    );
+    assert_eq!(compression.negotiate("deflate"), Some(Encoding::Deflate));
+    assert_eq!(
+        compression.negotiate("gzip;q=0.5, deflate"),
+        Some(Encoding::Deflate)
+    );
+    assert_eq!(
+        compression.negotiate("gzip;q=0, *"),
+        Some(Encoding::Deflate)
+    );
+    assert_eq!(compression.negotiate("*"), Some(Encoding::Gzip));
+    assert_eq!(compression.negotiate("X-GZIP"), Some(Encoding::Gzip));
+    assert_eq!(compression.negotiate("br, identity"), None);
+    assert_eq!(compression.negotiate("*;q=0"), None);
+    assert_eq!(compression.negotiate(""), None);
+
+    let compression = compression.with_encodings(&[Encoding::Deflate, Encoding::Gzip]);
+    assert_eq!(
+        compression.negotiate("gzip, deflate"),
+        Some(Encoding::Deflate)
+    );
+    assert_eq!(
+        compression.negotiate("gzip, deflate;q=0.9"),
+        Some(Encoding::Gzip)
+    );
+}
+
+#[test]
+fn test_compress_response() {
+    let body = "<p>Hello, world!</p>".repeat(100);
+    let compression = Compression::new();
+
+    let mut response = html_response(&body).with_header(HeaderType::ETag, "\"abc\"");
+    compression.compress(
+        &Request::new(Method::Get, "/").with_header(HeaderType::AcceptEncoding, "gzip, deflate"),
+        &mut response,
+    );
+
+    assert_eq!(
+        response.headers.get(HeaderType::ContentEncoding),
+        Some("gzip")
+    );
+    assert_eq!(
+        response.headers.get(HeaderType::Vary),
+        Some("Accept-Encoding")
+    );
+    assert_eq!(response.headers.get(HeaderType::ETag), Some("W/\"abc\""));
+    assert_eq!(
+        inflate(&response.body[10..response.body.len() - 8]),
+        body.as_bytes()
+    );
+
+    // Clients which do not accept any encoding still need the `Vary` header
+    let mut response = html_response(&body).with_header(HeaderType::Vary, "Origin");
+    compression.compress(
+        &Request::new(Method::Get, "/").with_header(HeaderType::AcceptEncoding, "identity"),
+        &mut response,
+    );
+
+    assert_eq!(response.headers.get(HeaderType::ContentEncoding), None);
+    assert_eq!(
+        response.headers.get(HeaderType::Vary),
+        Some("Origin, Accept-Encoding")
+    );
+    assert_eq!(response.body, body.as_bytes());
+
+    let mut response = html_response(&body).with_header(HeaderType::ContentLength, "2000");
+    compression.compress(
+        &Request::new(Method::Get, "/").with_header(HeaderType::AcceptEncoding, "deflate"),
+        &mut response,
+    );
+
+    assert_eq!(
+        response.headers.get(HeaderType::ContentEncoding),
+        Some("deflate")
+    );
+    assert_eq!(
+        response.headers.get(HeaderType::ContentLength),
+        Some(response.body.len().to_string().as_str())
+    );
+}
+
+#[test]
+fn test_skip_ineligible_responses() {
+    let body = "<p>Hello, world!</p>".repeat(100);
+    let compression = Compression::new();
+
+    let ineligible = [
+        html_response("<p>Too small</p>"),
+        html_response(&body).with_header(HeaderType::ContentEncoding, "br"),
+        Response::new(StatusCode::OK, body.as_bytes())
+            .with_header(HeaderType::ContentType, "image/png"),
+        Response::new(StatusCode::OK, body.as_bytes()),
+        Response::new(StatusCode::PartialContent, body.as_bytes())
+            .with_header(HeaderType::ContentType, "text/html"),
+    ];
+
+    for mut response in ineligible {
+        let original = response.body.clone();
+        compression.compress(
+            &Request::new(Method::Get, "/").with_header(HeaderType::AcceptEncoding, "gzip"),
+            &mut response,
+        );
+
+        assert_eq!(response.body, original);
+        assert_ne!(
+            response.headers.get(HeaderType::ContentEncoding),
+            Some("gzip")
+        );
+        assert_eq!(response.headers.get(HeaderType::Vary), None);
+    }
+
+    let compression = Compression::new()
+        .with_content_types(&["application/json"])
+        .with_min_size(10);
+
+    let mut response = Response::new(StatusCode::OK, body.as_bytes())
+        .with_header(HeaderType::ContentType, "Application/JSON; charset=utf-8");
+    compression.compress(
+        &Request::new(Method::Get, "/").with_header(HeaderType::AcceptEncoding, "gzip"),
+        &mut response,
+    );
+    assert_eq!(
+        response.headers.get(HeaderType::ContentEncoding),
+        Some("gzip")
+    );
+
+    let mut response = html_response(&body);
+    compression.compress(
+        &Request::new(Method::Get, "/").with_header(HeaderType::AcceptEncoding, "gzip"),
+        &mut response,
+    );
+    assert_eq!(response.headers.get(HeaderType::ContentEncoding), None);
+}
+
+fn html_response(body: &str) -> Response {
+    Response::new(StatusCode::OK, body.as_bytes())
+        .with_header(HeaderType::ContentType, "text/html; charset=utf-8")
+}
+
+/// Decompresses a raw DEFLATE stream made of blocks using the fixed Huffman codes.
+fn inflate(data: &[u8]) -> Vec<u8> {
+    const LENGTH_BASES: [usize; 29] = [
+        3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+        131, 163, 195, 227, 258,
+    ];
+    const DISTANCE_BASES: [usize; 30] = [
+        1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+        2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+    ];
+
+    let mut position = 0;
+    let mut bits = |count: usize| {
+        let mut value = 0;
+        for i in 0..count {
+            value |= ((data[position / 8] >> (position % 8)) as usize & 1) << i;
+            position += 1;
+        }
+        value
+    };
+
+    let mut output = Vec::new();
+
+    loop {
+        let last = bits(1) == 1;
+        assert_eq!(bits(2), 1, "only fixed Huffman blocks are supported");
+
+        loop {
+            // Huffman codes are packed starting from their most significant bit
+            let mut code = 0;
+            let mut length = 0;
+            let symbol = loop {
+                code = code << 1 | bits(1);
+                length += 1;
+
+                match (length, code) {
+                    (7, 0..=0x17) => break code + 256,
+                    (8, 0x30..=0xbf) => break code - 0x30,
+                    (8, 0xc0..=0xc7) => break code - 0xc0 + 280,
+                    (9, 0x190..=0x1ff) => break code - 0x190 + 144,
+                    (9, _) => panic!("invalid code"),
+                    _ => (),
+                }
+            };
+
+            match symbol {
+                0..=255 => output.push(symbol as u8),
+                256 => break,
+                _ => {
+                    let index = symbol - 257;
+                    let extra = if (8..28).contains(&index) {
+                        (index - 4) / 4
+                    } else {
+                        0
+                    };
+                    let length = LENGTH_BASES[index] + bits(extra);
+
+                    let mut code = 0;
+                    for _ in 0..5 {
+                        code = code << 1 | bits(1);
+                    }
+                    let extra = if code >= 4 { code / 2 - 1 } else { 0 };
+                    let distance = DISTANCE_BASES[code] + bits(extra);
+
+                    for _ in 0..length {
+                        output.push(output[output.len() - distance]);
+                    }
+                }
+            }
+        }
+
+        if last {
+            return output;
+        }
+    }
+}
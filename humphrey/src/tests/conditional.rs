@@ -0,0 +1,110 @@
+#![allow(unused_imports)]
+use crate::http::conditional::Validators;
+use crate::http::date::DateTime;
+use crate::http::headers::HeaderType;
+use crate::http::method::Method;
+use crate::http::{Request, StatusCode};
+
+#[test]
+fn test_if_none_match() {
+    let validators = Validators::new().with_etag("abc");
+    assert_eq!(validators.etag(), Some("\"abc\""));
+
+    assert!(validators.is_not_modified(
+        &Request::new(Method::Get, "/").with_header(HeaderType::IfNoneMatch, "\"abc\"")
+    ));
+    assert!(validators.is_not_modified(
+        &Request::new(Method::Get, "/").with_header(HeaderType::IfNoneMatch, "W/\"abc\"")
+    ));
+    assert!(validators.is_not_modified(
+        &Request::new(Method::Get, "/")
+            .with_header(HeaderType::IfNoneMatch, "\"x,y\", W/\"z\",\"abc\"")
+    ));
+    assert!(validators.is_not_modified(
+        &Request::new(Method::Get, "/").with_header(HeaderType::IfNoneMatch, "*")
+    ));
+
+    assert!(!validators.is_not_modified(
+        &Request::new(Method::Get, "/").with_header(HeaderType::IfNoneMatch, "\"abcd\"")
+    ));
+    assert!(!validators.is_not_modified(
+        &Request::new(Method::Get, "/").with_header(HeaderType::IfNoneMatch, "abc")
+    ));
+    assert!(!validators.is_not_modified(&Request::new(Method::Get, "/")));
+    assert!(!validators.is_not_modified(
+        &Request::new(Method::Post, "/").with_header(HeaderType::IfNoneMatch, "\"abc\"")
+    ));
+
+    let weak = Validators::new().with_etag("W/\"abc\"");
+    assert!(weak.is_not_modified(
+        &Request::new(Method::Get, "/").with_header(HeaderType::IfNoneMatch, "\"abc\"")
+    ));
+}
+
+#[test]
+fn test_if_modified_since() {
+    let validators = Validators::new().with_last_modified(DateTime::from(784111777));
+
+    assert!(validators.is_not_modified(
+        &Request::new(Method::Get, "/")
+            .with_header(HeaderType::IfModifiedSince, "Sun, 06 Nov 1994 08:49:37 GMT")
+    ));
+    assert!(validators.is_not_modified(
+        &Request::new(Method::Get, "/")
+            .with_header(HeaderType::IfModifiedSince, "Mon, 07 Nov 1994 08:49:37 GMT")
+    ));
+    assert!(!validators.is_not_modified(
+        &Request::new(Method::Get, "/")
+            .with_header(HeaderType::IfModifiedSince, "Sun, 06 Nov 1994 08:49:36 GMT")
+    ));
+    assert!(!validators.is_not_modified(
+        &Request::new(Method::Get, "/").with_header(HeaderType::IfModifiedSince, "yesterday")
+    ));
+
+    // `If-None-Match` takes precedence when both are present
+    let validators = validators.with_etag("abc");
+    assert!(!validators.is_not_modified(
+        &Request::new(Method::Get, "/")
+            .with_header(HeaderType::IfNoneMatch, "\"def\"")
+            .with_header(HeaderType::IfModifiedSince, "Mon, 07 Nov 1994 08:49:37 GMT")
+    ));
+}
+
+#[test]
+fn test_validator_headers() {
+    let future = DateTime::from(DateTime::now().timestamp + 3600);
+    let validators = Validators::new()
+        .with_etag("abc")
+        .with_last_modified(future);
+
+    assert!(validators.last_modified().unwrap().timestamp < future.timestamp);
+
+    let response = validators.not_modified();
+    assert_eq!(response.status_code, StatusCode::NotModified);
+    assert!(response.body.is_empty());
+    assert_eq!(response.headers.get(HeaderType::ETag), Some("\"abc\""));
+    assert_eq!(
+        response.headers.get(HeaderType::LastModified),
+        Some(validators.last_modified().unwrap().to_string().as_str())
+    );
+
+    assert_eq!(
+        Validators::from_content(b"123456789").etag(),
+        Some("\"cbf43926-9\"")
+    );
+}
+
+#[test]
+fn test_validators_from_metadata() {
+    let metadata = std::fs::metadata(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml")).unwrap();
+    let validators = Validators::from_metadata(&metadata);
+
+    assert!(validators
+        .etag()
+        .unwrap()
+        .ends_with(&format!("-{:x}\"", metadata.len())));
+    assert_eq!(
+        validators.last_modified(),
+        Some(&DateTime::from(metadata.modified().unwrap()))
+    );
+}
@@ -1,5 +1,7 @@
 #[allow(unused_imports)]
-use crate::http::date::DateTime;
+use crate::http::date::{DateTime, DateTimeError};
+
+use std::time::{Duration, SystemTime};
 
 #[test]
 fn test_date_from_timestamp() {
@@ -34,3 +36,65 @@ fn test_date_from_timestamp() {
     assert_eq!(output_4, expected_output_4);
     assert_eq!(output_5, expected_output_5);
 }
+
+#[test]
+fn test_parse_date() {
+    let expected = DateTime::from(784111777);
+
+    assert_eq!("Sun, 06 Nov 1994 08:49:37 GMT".parse(), Ok(expected));
+    assert_eq!("Sunday, 06-Nov-94 08:49:37 GMT".parse(), Ok(expected));
+    assert_eq!("Sun Nov  6 08:49:37 1994".parse(), Ok(expected));
+
+    assert_eq!(
+        "Thu, 01 Jan 1970 00:00:00 GMT"
+            .parse::<DateTime>()
+            .map(|d| d.timestamp),
+        Ok(0)
+    );
+    assert_eq!(
+        "Tue, 29 Feb 2000 23:59:59 GMT"
+            .parse::<DateTime>()
+            .map(|d| d.timestamp),
+        Ok(951868799)
+    );
+    assert_eq!(
+        "Wednesday, 01-Jan-25 00:00:00 GMT"
+            .parse::<DateTime>()
+            .map(|d| d.year),
+        Ok(2025)
+    );
+
+    for timestamp in [1628437415, 1094474096, 1582979696, -84337067, -28504100829] {
+        let date = DateTime::from(timestamp);
+        assert_eq!(date.to_string().parse(), Ok(date));
+    }
+}
+
+#[test]
+fn test_parse_invalid_date() {
+    for input in [
+        "",
+        "Sun, 06 Nov 1994 08:49:37 UTC",
+        "Sun, 06 Nov 94 08:49:37 GMT",
+        "Sun, 06 Foo 1994 08:49:37 GMT",
+        "Sun, 31 Nov 1994 08:49:37 GMT",
+        "Mon, 29 Feb 2100 00:00:00 GMT",
+        "Sun, 06 Nov 1994 24:00:00 GMT",
+        "Sun, 06 Nov 1994 08:49 GMT",
+        "Sun, 06 Nov 1994 8:49:37 GMT",
+        "Sunday, 06-Nov-1994 08:49:37 GMT",
+        "Sun Nov  6 08:49:37",
+    ] {
+        assert_eq!(input.parse::<DateTime>(), Err(DateTimeError), "{}", input);
+    }
+}
+
+#[test]
+fn test_date_from_system_time() {
+    let time =
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1628437415) + Duration::from_millis(999);
+    assert_eq!(DateTime::from(time), DateTime::from(1628437415));
+
+    let time = SystemTime::UNIX_EPOCH - Duration::from_millis(1500);
+    assert_eq!(DateTime::from(time).timestamp, -2);
+}
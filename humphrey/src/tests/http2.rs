@@ -0,0 +1,207 @@
+use crate::http::body::RequestBody;
+use crate::http::headers::HeaderType;
+use crate::http::method::Method;
+use crate::http::{Request, Response, StatusCode};
+use crate::http2::PREFACE;
+use crate::route::SubApp;
+use crate::App;
+
+use bytes::Bytes;
+use h2::client::SendRequest;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const ADDRESS: &str = "127.0.0.1:48614";
+
+#[tokio::test]
+async fn test_http2_prior_knowledge() {
+    let app: App<()> = App::new()
+        .with_host(
+            "example.com",
+            SubApp::new().with_route("/", |_: Request, _: Arc<()>| async move {
+                Response::new(StatusCode::OK, "example.com")
+            }),
+        )
+        .with_route("/slow", |_: Request, _: Arc<()>| async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            Response::new(StatusCode::OK, "slow")
+        })
+        .with_route("/user/:name", |request: Request, _: Arc<()>| async move {
+            Response::new(
+                StatusCode::OK,
+                format!(
+                    "{} {}",
+                    request.version,
+                    request.params.get("name").unwrap()
+                ),
+            )
+        })
+        .with_route("/large", |_: Request, _: Arc<()>| async move {
+            Response::new(StatusCode::OK, vec![b'a'; 200_000])
+        })
+        .with_post("/echo", |request: Request, _: Arc<()>| async move {
+            Response::new(StatusCode::OK, request.content.unwrap_or_default())
+        })
+        .with_streaming_route(
+            "/upload",
+            |_: Request, mut body: RequestBody, _: Arc<()>| async move {
+                let mut content = Vec::new();
+                body.read_to_end(&mut content).await.unwrap();
+
+                Response::new(StatusCode::OK, content.len().to_string())
+            },
+        );
+
+    tokio::spawn(async move { app.run(ADDRESS).await.unwrap() });
+
+    let client = connect().await;
+
+    // Streams are answered concurrently, so a quick response is not held up by a slow one
+    let start = Instant::now();
+    let slow = tokio::spawn(request(client.clone(), Method::Get, "/slow", None));
+    let (status, headers, body) =
+        request(client.clone(), Method::Get, "/user/humphrey", None).await;
+
+    assert!(start.elapsed() < Duration::from_millis(400));
+    assert_eq!(status, 200);
+    assert_eq!(body, b"HTTP/2.0 humphrey");
+    assert_eq!(headers.get("server").unwrap(), "Humphrey");
+    assert!(headers.get("connection").is_none());
+
+    let (status, _, body) = slow.await.unwrap();
+    assert_eq!(status, 200);
+    assert_eq!(body, b"slow");
+
+    // Bodies larger than the initial flow control window are sent as the client makes room for them
+    let (_, headers, body) = request(client.clone(), Method::Get, "/large", None).await;
+    assert_eq!(body, vec![b'a'; 200_000]);
+    assert_eq!(headers.get("content-length").unwrap(), "200000");
+
+    let (_, headers, body) = request(client.clone(), Method::Head, "/large", None).await;
+    assert!(body.is_empty());
+    assert_eq!(headers.get("content-length").unwrap(), "200000");
+
+    // Request bodies are read for buffered handlers and streamed to streaming handlers
+    let (status, _, body) = request(
+        client.clone(),
+        Method::Post,
+        "/echo",
+        Some(b"Hello, world!".to_vec()),
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(body, b"Hello, world!");
+
+    let (status, _, body) = request(
+        client.clone(),
+        Method::Post,
+        "/upload",
+        Some(vec![0; 100_000]),
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(body, b"100000");
+
+    let (status, _, _) = request(client.clone(), Method::Get, "/missing", None).await;
+    assert_eq!(status, 404);
+
+    // The `:authority` pseudo-header selects the sub-app in the same way as the `Host` header
+    let (_, _, body) = request(client, Method::Get, "http://example.com/", None).await;
+    assert_eq!(body, b"example.com");
+
+    // Clients without prior knowledge of HTTP/2 support are still served using HTTP/1.1
+    let mut stream = TcpStream::connect(ADDRESS).await.unwrap();
+    stream
+        .write_all(b"GET /user/humphrey HTTP/1.1\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("\r\n\r\nHTTP/1.1 humphrey"));
+
+    // The protocol is only decided once enough has been received to tell the preface apart from HTTP/1.1 requests
+    let mut stream = TcpStream::connect(ADDRESS).await.unwrap();
+    stream.write_all(b"PR").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    stream
+        .write_all(b"OPFIND /user/humphrey HTTP/1.1\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("\r\n\r\nHTTP/1.1 humphrey"));
+
+    let mut stream = TcpStream::connect(ADDRESS).await.unwrap();
+    stream.write_all(b"PR").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    stream.write_all(&PREFACE[2..]).await.unwrap();
+
+    // The server's connection preface starts with a `SETTINGS` frame
+    let mut frame_header = [0; 9];
+    stream.read_exact(&mut frame_header).await.unwrap();
+    assert_eq!(frame_header[3], 0x4);
+}
+
+/// Connects to the server with prior knowledge of HTTP/2 support, retrying until it has started.
+async fn connect() -> SendRequest<Bytes> {
+    for _ in 0..50 {
+        if let Ok(stream) = TcpStream::connect(ADDRESS).await {
+            let (client, connection) = h2::client::handshake(stream).await.unwrap();
+            tokio::spawn(connection);
+
+            return client;
+        }
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    panic!("failed to connect to the server");
+}
+
+/// Sends a request on a new stream, returning the status code, headers and body of the response.
+async fn request(
+    client: SendRequest<Bytes>,
+    method: Method,
+    uri: &str,
+    body: Option<Vec<u8>>,
+) -> (u16, http::HeaderMap, Vec<u8>) {
+    let uri = match uri.starts_with('/') {
+        true => format!("http://{}{}", ADDRESS, uri),
+        false => uri.to_string(),
+    };
+
+    let request = http::Request::builder()
+        .method(method.to_string().as_str())
+        .uri(uri)
+        .header(HeaderType::UserAgent.to_string(), "humphrey-test")
+        .body(())
+        .unwrap();
+
+    let mut client = client.ready().await.unwrap();
+    let (response, mut send) = client.send_request(request, body.is_none()).unwrap();
+
+    if let Some(body) = body {
+        send.send_data(body.into(), true).unwrap();
+    }
+
+    let (parts, mut body) = response.await.unwrap().into_parts();
+    let mut content = Vec::new();
+
+    while let Some(data) = body.data().await {
+        let data = data.unwrap();
+        body.flow_control().release_capacity(data.len()).unwrap();
+        content.extend_from_slice(&data);
+    }
+
+    (parts.status.as_u16(), parts.headers, content)
+}
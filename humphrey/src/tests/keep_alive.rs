@@ -0,0 +1,243 @@
+#![allow(unused_imports)]
+use crate::http::headers::HeaderType;
+use crate::http::method::Method;
+use crate::http::{Request, Response, StatusCode};
+use crate::App;
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_is_keep_alive() {
+    assert!(Request::new(Method::Get, "/").is_keep_alive());
+    assert!(Request::new(Method::Get, "/")
+        .with_header(HeaderType::Connection, "Keep-Alive")
+        .is_keep_alive());
+    assert!(!Request::new(Method::Get, "/")
+        .with_header(HeaderType::Connection, "close")
+        .is_keep_alive());
+    assert!(!Request::new(Method::Get, "/")
+        .with_header(HeaderType::Connection, "Upgrade, Close")
+        .is_keep_alive());
+
+    assert!(!Request::new(Method::Get, "/")
+        .with_version("HTTP/1.0")
+        .is_keep_alive());
+    assert!(Request::new(Method::Get, "/")
+        .with_version("HTTP/1.0")
+        .with_header(HeaderType::Connection, "keep-alive")
+        .is_keep_alive());
+    assert!(Request::new(Method::Get, "/")
+        .with_version("HTTP/1.0")
+        .with_header(HeaderType::Connection, "TE, Keep-Alive")
+        .is_keep_alive());
+}
+
+#[cfg(not(feature = "tokio"))]
+#[test]
+fn test_pipelined_requests() {
+    let (shutdown, receiver) = std::sync::mpsc::channel();
+    let app: App<()> = App::new_with_config(2, ())
+        .with_max_keep_alive_requests(Some(3))
+        .with_keep_alive_timeout(Some(Duration::from_millis(200)))
+        .with_shutdown(receiver)
+        .with_route("/*", |request: Request, _: Arc<()>| {
+            Response::new(StatusCode::OK, request.uri)
+        });
+
+    thread::spawn(move || app.run("127.0.0.1:48611").unwrap());
+
+    // HTTP/1.1 connections are persistent by default, and pipelined requests are answered in order
+    let responses = send(
+        "127.0.0.1:48611",
+        "GET /a HTTP/1.1\r\n\r\nGET /bb HTTP/1.1\r\n\r\n",
+    );
+    assert_eq!(responses.len(), 2);
+    assert_response(&responses[0], "Keep-Alive", "/a");
+    assert_response(&responses[1], "Keep-Alive", "/bb");
+
+    // The connection is closed once the client asks for it to be
+    let responses = send(
+        "127.0.0.1:48611",
+        "GET /a HTTP/1.1\r\nConnection: close\r\n\r\nGET /b HTTP/1.1\r\n\r\n",
+    );
+    assert_eq!(responses.len(), 1);
+    assert_response(&responses[0], "Close", "/a");
+
+    // HTTP/1.0 connections are closed unless the client asks for them to be kept open
+    let responses = send(
+        "127.0.0.1:48611",
+        "GET /a HTTP/1.0\r\n\r\nGET /b HTTP/1.0\r\n\r\n",
+    );
+    assert_eq!(responses.len(), 1);
+    assert!(responses[0].starts_with("HTTP/1.0 200 OK"));
+
+    // The connection is closed after the maximum number of requests
+    let responses = send(
+        "127.0.0.1:48611",
+        "GET /a HTTP/1.0\r\nConnection: keep-alive\r\n\r\n"
+            .repeat(4)
+            .as_str(),
+    );
+    assert_eq!(responses.len(), 3);
+    assert_response(&responses[1], "Keep-Alive", "/a");
+    assert_response(&responses[2], "Close", "/a");
+
+    shutdown.send(()).unwrap();
+}
+
+#[cfg(not(feature = "tokio"))]
+#[test]
+fn test_keep_alive_timeout() {
+    let (shutdown, receiver) = std::sync::mpsc::channel();
+    let app: App<()> = App::new_with_config(2, ())
+        .with_keep_alive_timeout(Some(Duration::from_millis(200)))
+        .with_shutdown(receiver)
+        .with_route("/", |_: Request, _: Arc<()>| {
+            Response::new(StatusCode::OK, "Hello")
+        });
+
+    thread::spawn(move || app.run("127.0.0.1:48612").unwrap());
+
+    // The idle connection is closed without a response once the timeout has passed
+    let responses = send("127.0.0.1:48612", "GET / HTTP/1.1\r\n\r\n");
+    assert_eq!(responses.len(), 1);
+    assert!(responses[0].ends_with("Hello"));
+
+    shutdown.send(()).unwrap();
+}
+
+#[cfg(not(feature = "tokio"))]
+#[test]
+fn test_options_not_found() {
+    let (shutdown, receiver) = std::sync::mpsc::channel();
+    let app: App<()> = App::new_with_config(2, ())
+        .with_shutdown(receiver)
+        .with_route("/", |_: Request, _: Arc<()>| {
+            Response::new(StatusCode::OK, "Hello")
+        });
+
+    thread::spawn(move || app.run("127.0.0.1:48623").unwrap());
+
+    // The error response describes its body, so the connection can be reused for the next request
+    let responses = send(
+        "127.0.0.1:48623",
+        "OPTIONS /missing HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\nConnection: close\r\n\r\n",
+    );
+    assert_eq!(responses.len(), 2);
+    assert_options_not_found(&responses[0]);
+    assert_response(&responses[1], "Close", "Hello");
+
+    shutdown.send(()).unwrap();
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_options_not_found() {
+    let app: App<()> = App::new().with_route("/", |_: Request, _: Arc<()>| async move {
+        Response::new(StatusCode::OK, "Hello")
+    });
+
+    tokio::spawn(async move { app.run("127.0.0.1:48624").await.unwrap() });
+
+    let responses = tokio::task::spawn_blocking(|| {
+        send(
+            "127.0.0.1:48624",
+            "OPTIONS /missing HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\nConnection: close\r\n\r\n",
+        )
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(responses.len(), 2);
+    assert_options_not_found(&responses[0]);
+    assert_response(&responses[1], "Close", "Hello");
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_pipelined_requests() {
+    let app: App<()> = App::new()
+        .with_max_keep_alive_requests(Some(3))
+        .with_keep_alive_timeout(Some(Duration::from_millis(200)))
+        .with_route("/*", |request: Request, _: Arc<()>| async move {
+            Response::new(StatusCode::OK, request.uri)
+        });
+
+    tokio::spawn(async move { app.run("127.0.0.1:48613").await.unwrap() });
+
+    let responses = tokio::task::spawn_blocking(|| {
+        [
+            send(
+                "127.0.0.1:48613",
+                "GET /a HTTP/1.1\r\n\r\nGET /bb HTTP/1.1\r\n\r\n",
+            ),
+            send(
+                "127.0.0.1:48613",
+                "GET /a HTTP/1.1\r\nConnection: close\r\n\r\nGET /b HTTP/1.1\r\n\r\n",
+            ),
+            send(
+                "127.0.0.1:48613",
+                "GET /a HTTP/1.0\r\nConnection: keep-alive\r\n\r\n"
+                    .repeat(4)
+                    .as_str(),
+            ),
+        ]
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(responses[0].len(), 2);
+    assert_response(&responses[0][0], "Keep-Alive", "/a");
+    assert_response(&responses[0][1], "Keep-Alive", "/bb");
+
+    assert_eq!(responses[1].len(), 1);
+    assert_response(&responses[1][0], "Close", "/a");
+
+    assert_eq!(responses[2].len(), 3);
+    assert_response(&responses[2][2], "Close", "/a");
+}
+
+/// Sends the data on a new connection, then reads and splits the responses until the server closes it.
+fn send(address: &str, data: &str) -> Vec<String> {
+    let mut stream = (0..50)
+        .find_map(|_| {
+            TcpStream::connect(address)
+                .map_err(|_| thread::sleep(Duration::from_millis(20)))
+                .ok()
+        })
+        .unwrap();
+
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    stream.write_all(data.as_bytes()).unwrap();
+
+    let mut received = String::new();
+    stream.read_to_string(&mut received).unwrap();
+
+    // Every response body is a path or a word, so the responses can be split at their status lines
+    received
+        .split("HTTP/1.")
+        .filter(|response| !response.is_empty())
+        .map(|response| format!("HTTP/1.{}", response))
+        .collect()
+}
+
+/// Asserts that the response has the given `Connection` header and body.
+fn assert_response(response: &str, connection: &str, body: &str) {
+    assert!(response.contains(&format!("\r\nConnection: {}\r\n", connection)));
+    assert!(response.ends_with(&format!("\r\n\r\n{}", body)));
+}
+
+/// Asserts that the response is a 404 error with the headers needed to keep the connection open.
+fn assert_options_not_found(response: &str) {
+    assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    assert!(response.contains("\r\nConnection: Keep-Alive\r\n"));
+    assert!(response.contains("\r\nContent-Length: "));
+    assert!(response.contains("\r\nDate: "));
+    assert!(response.contains("\r\nServer: Humphrey\r\n"));
+}
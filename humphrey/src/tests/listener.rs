@@ -0,0 +1,556 @@
+#![allow(unused_imports)]
+use crate::http::{Request, Response, StatusCode};
+use crate::listener::{Listener, PreboundListener};
+use crate::App;
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+#[cfg(not(feature = "tokio"))]
+#[test]
+fn test_multiple_listeners() {
+    let socket = socket_path("humphrey-test-sync.sock");
+    let listeners = listeners(48619, 48620, &socket);
+
+    let (shutdown, receiver) = std::sync::mpsc::channel();
+    let app: App<()> = App::new_with_config(4, ())
+        .with_shutdown(receiver)
+        .with_route("/", |request: Request, _: Arc<()>| {
+            Response::new(StatusCode::OK, request.address.to_string())
+        });
+
+    let app_thread = thread::spawn(move || app.run_listeners(&listeners).unwrap());
+
+    assert_responses(48619, 48620, &socket);
+
+    shutdown.send(()).unwrap();
+    app_thread.join().unwrap();
+
+    assert!(!socket.exists());
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_multiple_listeners() {
+    use tokio_util::sync::CancellationToken;
+
+    let socket = socket_path("humphrey-test-tokio.sock");
+    let listeners = listeners(48621, 48622, &socket);
+
+    let shutdown = CancellationToken::new();
+    let app: App<()> = App::new().with_shutdown(shutdown.clone()).with_route(
+        "/",
+        |request: Request, _: Arc<()>| async move {
+            Response::new(StatusCode::OK, request.address.to_string())
+        },
+    );
+
+    let app_task = tokio::spawn(async move { app.run_listeners(&listeners).await.unwrap() });
+
+    let cloned_socket = socket.clone();
+    tokio::task::spawn_blocking(move || assert_responses(48621, 48622, &cloned_socket))
+        .await
+        .unwrap();
+
+    shutdown.cancel();
+    app_task.await.unwrap();
+
+    assert!(!socket.exists());
+}
+
+#[cfg(not(feature = "tokio"))]
+#[test]
+fn test_prebound_listeners() {
+    let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap().to_string();
+    let socket = socket_path("humphrey-test-prebound-sync.sock");
+    let listeners = prebound_listeners(tcp, &socket);
+
+    let (shutdown, receiver) = std::sync::mpsc::channel();
+    let app: App<()> = App::new_with_config(4, ())
+        .with_shutdown(receiver)
+        .with_route("/", |request: Request, _: Arc<()>| {
+            Response::new(StatusCode::OK, request.address.to_string())
+        });
+
+    let app_thread = thread::spawn(move || app.run_with_listeners(listeners).unwrap());
+
+    assert_prebound_responses(&addr, &socket);
+
+    shutdown.send(()).unwrap();
+    app_thread.join().unwrap();
+
+    // Sockets which were passed to the app are not its own to remove
+    #[cfg(unix)]
+    assert!(socket.exists());
+    let _ = std::fs::remove_file(&socket);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_prebound_listeners() {
+    use tokio_util::sync::CancellationToken;
+
+    let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap().to_string();
+    let socket = socket_path("humphrey-test-prebound-tokio.sock");
+    let listeners = prebound_listeners(tcp, &socket);
+
+    let shutdown = CancellationToken::new();
+    let app: App<()> = App::new().with_shutdown(shutdown.clone()).with_route(
+        "/",
+        |request: Request, _: Arc<()>| async move {
+            Response::new(StatusCode::OK, request.address.to_string())
+        },
+    );
+
+    let app_task = tokio::spawn(async move { app.run_with_listeners(listeners).await.unwrap() });
+
+    let cloned_socket = socket.clone();
+    tokio::task::spawn_blocking(move || assert_prebound_responses(&addr, &cloned_socket))
+        .await
+        .unwrap();
+
+    shutdown.cancel();
+    app_task.await.unwrap();
+
+    // Sockets which were passed to the app are not its own to remove
+    #[cfg(unix)]
+    assert!(socket.exists());
+    let _ = std::fs::remove_file(&socket);
+}
+
+#[cfg(not(feature = "tokio"))]
+#[test]
+fn test_run_with_listener() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+
+    let (shutdown, receiver) = std::sync::mpsc::channel();
+    let app: App<()> = App::new_with_config(2, ())
+        .with_shutdown(receiver)
+        .with_route("/", |_: Request, _: Arc<()>| {
+            Response::new(StatusCode::OK, "Hello")
+        });
+
+    let app_thread = thread::spawn(move || app.run_with_listener(listener).unwrap());
+
+    assert!(request(connect(&addr)).ends_with("\r\n\r\nHello"));
+
+    shutdown.send(()).unwrap();
+    app_thread.join().unwrap();
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_run_with_listener() {
+    use tokio_util::sync::CancellationToken;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+
+    let shutdown = CancellationToken::new();
+    let app: App<()> = App::new()
+        .with_shutdown(shutdown.clone())
+        .with_route("/", |_: Request, _: Arc<()>| async move {
+            Response::new(StatusCode::OK, "Hello")
+        });
+
+    let app_task = tokio::spawn(async move { app.run_with_listener(listener).await.unwrap() });
+
+    let response = tokio::task::spawn_blocking(move || request(connect(&addr)))
+        .await
+        .unwrap();
+    assert!(response.ends_with("\r\n\r\nHello"));
+
+    shutdown.cancel();
+    app_task.await.unwrap();
+}
+
+#[cfg(all(unix, not(feature = "tokio")))]
+#[test]
+fn test_unix_socket_trust() {
+    for trust in [false, true] {
+        let socket = socket_path(&format!("humphrey-test-trust-{}-sync.sock", trust));
+        let listeners = vec![Listener::Unix(socket.clone())];
+
+        let (shutdown, receiver) = std::sync::mpsc::channel();
+        let app: App<AtomicUsize> = App::new_with_config(2, AtomicUsize::new(0))
+            .with_shutdown(receiver)
+            .with_connection_condition(count_unix_connections)
+            .with_trusted_proxies(vec!["0.0.0.0/32".parse().unwrap()])
+            .with_trust_unix_sockets(trust)
+            .with_route("/", |request: Request, state: Arc<AtomicUsize>| {
+                let body = format!("{} {}", state.load(Ordering::SeqCst), request.address);
+                Response::new(StatusCode::OK, body)
+            });
+
+        let app_thread = thread::spawn(move || app.run_listeners(&listeners).unwrap());
+
+        assert_unix_trust(&socket, trust);
+
+        shutdown.send(()).unwrap();
+        app_thread.join().unwrap();
+    }
+}
+
+#[cfg(all(unix, feature = "tokio"))]
+#[tokio::test]
+async fn test_unix_socket_trust() {
+    use tokio_util::sync::CancellationToken;
+
+    for trust in [false, true] {
+        let socket = socket_path(&format!("humphrey-test-trust-{}-tokio.sock", trust));
+        let listeners = vec![Listener::Unix(socket.clone())];
+
+        let shutdown = CancellationToken::new();
+        let app: App<AtomicUsize> = App::new_with_config(AtomicUsize::new(0))
+            .with_shutdown(shutdown.clone())
+            .with_connection_condition(count_unix_connections)
+            .with_trusted_proxies(vec!["0.0.0.0/32".parse().unwrap()])
+            .with_trust_unix_sockets(trust)
+            .with_route(
+                "/",
+                |request: Request, state: Arc<AtomicUsize>| async move {
+                    let body = format!("{} {}", state.load(Ordering::SeqCst), request.address);
+                    Response::new(StatusCode::OK, body)
+                },
+            );
+
+        let app_task = tokio::spawn(async move { app.run_listeners(&listeners).await.unwrap() });
+
+        let cloned_socket = socket.clone();
+        tokio::task::spawn_blocking(move || assert_unix_trust(&cloned_socket, trust))
+            .await
+            .unwrap();
+
+        shutdown.cancel();
+        app_task.await.unwrap();
+    }
+}
+
+#[cfg(all(feature = "tls", not(feature = "tokio")))]
+#[test]
+fn test_tls_forwarded_proto() {
+    let backend = TcpListener::bind("127.0.0.1:0").unwrap();
+    let target = backend.local_addr().unwrap();
+    let proxied_request = accept_proxied_request(backend);
+
+    let (shutdown, receiver) = std::sync::mpsc::channel();
+    let app: App<()> = App::new_with_config(2, ())
+        .with_shutdown(receiver)
+        .with_cert(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/src/tests/testcases/tls_cert.pem"
+            ),
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/src/tests/testcases/tls_key.pem"
+            ),
+        )
+        .with_route("/*", move |request: Request, _: Arc<()>| {
+            crate::http::proxy::try_proxy_request(&request, target, Duration::from_secs(5))
+                .unwrap_or_else(|_| Response::empty(StatusCode::BadGateway))
+        });
+
+    thread::spawn(move || app.run_tls("127.0.0.1:48625").unwrap());
+
+    let response = send_tls("127.0.0.1:48625");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("\r\n\r\nOK"));
+
+    let proxied_request = proxied_request.recv().unwrap();
+    assert!(proxied_request
+        .to_ascii_lowercase()
+        .contains("\r\nx-forwarded-proto: https\r\n"));
+
+    shutdown.send(()).unwrap();
+}
+
+#[cfg(all(feature = "tls", feature = "tokio"))]
+#[tokio::test]
+async fn test_tls_forwarded_proto() {
+    let backend = TcpListener::bind("127.0.0.1:0").unwrap();
+    let target = backend.local_addr().unwrap();
+    let proxied_request = accept_proxied_request(backend);
+
+    let app: App<()> = App::new()
+        .with_cert(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/src/tests/testcases/tls_cert.pem"
+            ),
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/src/tests/testcases/tls_key.pem"
+            ),
+        )
+        .with_route("/*", move |request: Request, _: Arc<()>| async move {
+            tokio::task::spawn_blocking(move || {
+                crate::http::proxy::try_proxy_request(&request, target, Duration::from_secs(5))
+                    .unwrap_or_else(|_| Response::empty(StatusCode::BadGateway))
+            })
+            .await
+            .unwrap()
+        });
+
+    tokio::spawn(async move { app.run_tls("127.0.0.1:48626").await.unwrap() });
+
+    let response = tokio::task::spawn_blocking(|| send_tls("127.0.0.1:48626"))
+        .await
+        .unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("\r\n\r\nOK"));
+
+    let proxied_request = proxied_request.recv().unwrap();
+    assert!(proxied_request
+        .to_ascii_lowercase()
+        .contains("\r\nx-forwarded-proto: https\r\n"));
+}
+
+#[test]
+fn test_listener_display() {
+    assert_eq!(
+        Listener::Tcp("0.0.0.0:80".into()).to_string(),
+        "http://0.0.0.0:80"
+    );
+
+    #[cfg(feature = "tls")]
+    assert_eq!(
+        Listener::Tls("[::]:443".into()).to_string(),
+        "https://[::]:443"
+    );
+
+    #[cfg(unix)]
+    assert_eq!(
+        Listener::Unix("/run/humphrey.sock".into()).to_string(),
+        "unix:/run/humphrey.sock"
+    );
+
+    let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+    assert_eq!(
+        PreboundListener::Tcp(tcp).to_string(),
+        format!("http://{}", addr)
+    );
+}
+
+/// Returns the path of a Unix domain socket in the temporary directory.
+fn socket_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(name)
+}
+
+/// Returns an IPv4 and an IPv6 listener on the given ports, as well as a Unix domain socket listener at the given
+///   path where supported.
+#[allow(unused_variables)]
+fn listeners(ipv4_port: u16, ipv6_port: u16, socket: &Path) -> Vec<Listener> {
+    #[allow(unused_mut)]
+    let mut listeners = vec![
+        Listener::Tcp(format!("127.0.0.1:{}", ipv4_port)),
+        Listener::Tcp(format!("[::1]:{}", ipv6_port)),
+    ];
+
+    #[cfg(unix)]
+    {
+        // A socket left behind by a previous run is replaced
+        let _ = std::os::unix::net::UnixListener::bind(socket);
+        listeners.push(Listener::Unix(socket.to_path_buf()));
+    }
+
+    listeners
+}
+
+/// Returns the given TCP listener, as well as a Unix domain socket listener bound at the given path where supported.
+#[allow(unused_variables)]
+fn prebound_listeners(tcp: TcpListener, socket: &Path) -> Vec<PreboundListener> {
+    #[allow(unused_mut)]
+    let mut listeners = vec![PreboundListener::Tcp(tcp)];
+
+    #[cfg(unix)]
+    {
+        let _ = std::fs::remove_file(socket);
+        let unix = std::os::unix::net::UnixListener::bind(socket).unwrap();
+        listeners.push(PreboundListener::Unix(unix));
+    }
+
+    listeners
+}
+
+/// Checks that each of the listeners passed to the app is served by it.
+#[allow(unused_variables)]
+fn assert_prebound_responses(addr: &str, socket: &Path) {
+    let response = request(connect(addr));
+    assert!(response.ends_with("\r\n\r\n127.0.0.1"));
+
+    #[cfg(unix)]
+    {
+        let response = request(UnixStream::connect(socket).unwrap());
+        assert!(response.ends_with(&format!("\r\n\r\nunix:{}", socket.display())));
+    }
+}
+
+/// Checks that each of the listeners is served by the app, and that the peer's address is reported correctly.
+#[allow(unused_variables)]
+fn assert_responses(ipv4_port: u16, ipv6_port: u16, socket: &Path) {
+    let response = request(connect(&format!("127.0.0.1:{}", ipv4_port)));
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("\r\n\r\n127.0.0.1"));
+
+    let response = request(connect(&format!("[::1]:{}", ipv6_port)));
+    assert!(response.ends_with("\r\n\r\n::1"));
+
+    #[cfg(unix)]
+    {
+        let stream = UnixStream::connect(socket).unwrap();
+        let response = request(stream);
+        assert!(response.ends_with(&format!("\r\n\r\nunix:{}", socket.display())));
+    }
+}
+
+/// Counts the connections over a Unix domain socket, which are checked without a TCP stream.
+#[cfg(unix)]
+fn count_unix_connections<S>(stream: Option<&mut S>, state: Arc<AtomicUsize>) -> bool {
+    if stream.is_none() {
+        state.fetch_add(1, Ordering::SeqCst);
+    }
+
+    true
+}
+
+/// Checks that the forwarding headers of a peer connected over the Unix domain socket are only honoured if Unix
+///   domain sockets are trusted, and that the connection was checked by the connection condition.
+#[cfg(unix)]
+fn assert_unix_trust(socket: &Path, trust: bool) {
+    let mut stream = connect_unix(socket);
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nX-Forwarded-For: 203.0.113.7\r\nConnection: close\r\n\r\n")
+        .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let expected = match trust {
+        true => "1 203.0.113.7 (proxied)".to_string(),
+        false => format!("1 unix:{}", socket.display()),
+    };
+
+    assert!(response.ends_with(&format!("\r\n\r\n{}", expected)));
+}
+
+/// Connects to the server over the Unix domain socket, retrying until it has started.
+#[cfg(unix)]
+fn connect_unix(socket: &Path) -> UnixStream {
+    for _ in 0..50 {
+        if let Ok(stream) = UnixStream::connect(socket) {
+            return stream;
+        }
+
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    panic!("failed to connect to the server");
+}
+
+/// Connects to the server, retrying until it has started.
+fn connect(addr: &str) -> TcpStream {
+    for _ in 0..50 {
+        if let Ok(stream) = TcpStream::connect(addr) {
+            return stream;
+        }
+
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    panic!("failed to connect to the server");
+}
+
+/// Sends a request on the stream, returning the whole response.
+fn request(mut stream: impl Read + Write) -> String {
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+        .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    response
+}
+
+/// Accepts one connection on the listener, responds to it and returns the head of the request it received.
+#[cfg(feature = "tls")]
+fn accept_proxied_request(listener: TcpListener) -> std::sync::mpsc::Receiver<String> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+
+        while !request.ends_with(b"\r\n\r\n") {
+            let read = stream.read(&mut buf).unwrap();
+            assert_ne!(read, 0);
+            request.extend_from_slice(&buf[..read]);
+        }
+
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nOK")
+            .unwrap();
+        sender.send(String::from_utf8(request).unwrap()).unwrap();
+    });
+
+    receiver
+}
+
+/// Sends a request to the app over TLS, trusting the test certificate authority, and returns the response.
+#[cfg(feature = "tls")]
+fn send_tls(addr: &str) -> String {
+    use rustls::{Certificate, ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+    use std::io::ErrorKind;
+
+    let mut ca = include_bytes!("testcases/tls_ca.pem").as_slice();
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut ca).unwrap() {
+        roots.add(&Certificate(cert)).unwrap();
+    }
+
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let connection =
+        ClientConnection::new(Arc::new(config), "localhost".try_into().unwrap()).unwrap();
+
+    // The app may still be starting up
+    let mut attempts = 0;
+    let stream = loop {
+        match TcpStream::connect(addr) {
+            Ok(stream) => break stream,
+            Err(e) if attempts >= 50 => panic!("{}", e),
+            Err(_) => {
+                attempts += 1;
+                thread::sleep(Duration::from_millis(20));
+            }
+        }
+    };
+
+    let mut stream = StreamOwned::new(connection, stream);
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .unwrap();
+
+    let mut response = Vec::new();
+    if let Err(e) = stream.read_to_end(&mut response) {
+        assert_eq!(e.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    String::from_utf8(response).unwrap()
+}
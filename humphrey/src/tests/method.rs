@@ -8,7 +8,32 @@ fn test_from_name() {
     assert_eq!(Method::from_name("POST"), Ok(Method::Post));
     assert_eq!(Method::from_name("PUT"), Ok(Method::Put));
     assert_eq!(Method::from_name("DELETE"), Ok(Method::Delete));
+    assert_eq!(Method::from_name("HEAD"), Ok(Method::Head));
+    assert_eq!(Method::from_name("PATCH"), Ok(Method::Patch));
+    assert_eq!(Method::from_name("CONNECT"), Ok(Method::Connect));
+    assert_eq!(Method::from_name("TRACE"), Ok(Method::Trace));
+    assert_eq!(
+        Method::from_name("PROPFIND"),
+        Ok(Method::Other("PROPFIND".into()))
+    );
     assert_eq!(Method::from_name("get"), Err(RequestError::Request));
     assert_eq!(Method::from_name("method"), Err(RequestError::Request));
     assert_eq!(Method::from_name(""), Err(RequestError::Request));
+    assert_eq!(Method::from_name("GET\r\n"), Err(RequestError::Request));
+}
+
+#[test]
+fn test_display() {
+    assert_eq!(Method::Patch.to_string(), "PATCH");
+    assert_eq!(Method::Other("M-SEARCH".into()).to_string(), "M-SEARCH");
+}
+
+#[test]
+fn test_is_idempotent() {
+    assert!(Method::Get.is_idempotent());
+    assert!(Method::Put.is_idempotent());
+    assert!(Method::Delete.is_idempotent());
+    assert!(!Method::Post.is_idempotent());
+    assert!(!Method::Patch.is_idempotent());
+    assert!(!Method::Other("PROPFIND".into()).is_idempotent());
 }
@@ -4,11 +4,25 @@ pub mod request;
 #[cfg(feature = "tokio")]
 pub mod request_tokio;
 
+pub mod address;
 pub mod client;
+pub mod compression;
+pub mod conditional;
 pub mod date;
+#[cfg(feature = "http2")]
+pub mod http2;
+pub mod keep_alive;
 pub mod krauss;
+pub mod listener;
 pub mod method;
 pub mod mock_stream;
+pub mod multipart;
 pub mod percent;
+#[cfg(not(feature = "tokio"))]
+pub mod proxy;
+pub mod query;
+pub mod range;
 pub mod response;
+pub mod route;
+pub mod shutdown;
 pub mod status;
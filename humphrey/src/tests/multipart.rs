@@ -0,0 +1,104 @@
+#![allow(unused_imports)]
+use crate::http::headers::{HeaderType, Headers};
+use crate::http::multipart::{Multipart, MultipartError, Part};
+
+#[cfg(not(feature = "tokio"))]
+use std::io::Read;
+
+const BODY: &[u8] = b"preamble\r\n--XyZ\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nHello\r\n--XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a;b \\\"c\\\".txt\"\r\nContent-Type: text/plain\r\n\r\nline one\r\n--Xy not a boundary\r\n--XyZ--\r\nepilogue";
+
+/// A reader which returns one byte at a time, to test data split across reads.
+#[cfg(not(feature = "tokio"))]
+struct ByteReader<'a>(&'a [u8]);
+
+#[cfg(not(feature = "tokio"))]
+impl Read for ByteReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.0.split_first() {
+            Some((byte, rest)) if !buf.is_empty() => {
+                buf[0] = *byte;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+fn assert_parts(parts: Vec<Part>) {
+    assert_eq!(parts.len(), 2);
+
+    assert_eq!(parts[0].name.as_deref(), Some("title"));
+    assert_eq!(parts[0].filename, None);
+    assert_eq!(parts[0].content_type, None);
+    assert_eq!(parts[0].body, b"Hello");
+
+    assert_eq!(parts[1].name.as_deref(), Some("file"));
+    assert_eq!(parts[1].filename.as_deref(), Some("a;b \"c\".txt"));
+    assert_eq!(parts[1].content_type.as_deref(), Some("text/plain"));
+    assert_eq!(
+        parts[1].headers.get(HeaderType::ContentType),
+        Some("text/plain")
+    );
+    assert_eq!(parts[1].body, b"line one\r\n--Xy not a boundary");
+}
+
+#[test]
+fn test_parse_multipart() {
+    let mut headers = Headers::new();
+    headers.add(
+        HeaderType::ContentType,
+        "multipart/form-data; boundary=\"XyZ\"",
+    );
+
+    let parts = Multipart::from_headers(&headers)
+        .unwrap()
+        .parse(BODY)
+        .unwrap();
+
+    assert_parts(parts);
+}
+
+#[test]
+fn test_read_multipart() {
+    #[cfg(not(feature = "tokio"))]
+    let parts = Multipart::new("XyZ")
+        .read_from(&mut ByteReader(BODY))
+        .unwrap();
+
+    #[cfg(feature = "tokio")]
+    let parts =
+        futures::executor::block_on(Multipart::new("XyZ").read_from(&mut &BODY[..])).unwrap();
+
+    assert_parts(parts);
+}
+
+#[test]
+fn test_multipart_errors() {
+    let mut headers = Headers::new();
+    assert_eq!(
+        Multipart::from_headers(&headers).unwrap_err(),
+        MultipartError::Boundary
+    );
+
+    headers.add(HeaderType::ContentType, "multipart/form-data");
+    assert_eq!(
+        Multipart::from_headers(&headers).unwrap_err(),
+        MultipartError::Boundary
+    );
+
+    assert_eq!(
+        Multipart::new("XyZ").parse(&BODY[..100]),
+        Err(MultipartError::Malformed)
+    );
+    assert_eq!(
+        Multipart::new("XyZ")
+            .with_max_part_size(Some(10))
+            .parse(BODY),
+        Err(MultipartError::PartTooLarge)
+    );
+    assert_eq!(
+        Multipart::new("XyZ").with_max_size(Some(100)).parse(BODY),
+        Err(MultipartError::TooLarge)
+    );
+}
@@ -0,0 +1,286 @@
+#![allow(unused_imports)]
+use crate::http::address::{Address, Cidr};
+use crate::http::headers::{HeaderType, Headers};
+use crate::http::proxy::{
+    forwarded_request, remove_hop_by_hop_headers, try_proxy_request, ConnectionPool, ProxyError,
+};
+use crate::http::response::ResponseError;
+use crate::http::{Request, StatusCode};
+use crate::tests::mock_stream::MockStream;
+
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::iter::FromIterator;
+use std::net::TcpListener;
+use std::thread::spawn;
+use std::time::Duration;
+
+fn request() -> Request {
+    let test_data = b"GET /test HTTP/1.1\r\nHost: localhost\r\n\r\n";
+    let mut stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    Request::from_stream(&mut stream, "1.2.3.4:5678".parse().unwrap()).unwrap()
+}
+
+/// Reads the request, which has no body, from the stream.
+fn read_request(stream: &mut impl Read) {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+
+    while !request.ends_with(b"\r\n\r\n") {
+        let read = stream.read(&mut buf).unwrap();
+        request.extend_from_slice(&buf[..read]);
+    }
+}
+
+#[test]
+fn test_try_proxy_request() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let target = listener.local_addr().unwrap();
+
+    spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        read_request(&mut stream);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello")
+            .unwrap();
+    });
+
+    let response = try_proxy_request(&request(), target, Duration::from_secs(5)).unwrap();
+
+    assert_eq!(response.status_code, StatusCode::OK);
+    assert_eq!(response.body, b"hello");
+}
+
+#[test]
+fn test_try_proxy_request_errors() {
+    // The port is free once the listener is dropped, so nothing is listening on it
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let target = listener.local_addr().unwrap();
+    drop(listener);
+
+    assert_eq!(
+        try_proxy_request(&request(), target, Duration::from_secs(5)).unwrap_err(),
+        ProxyError::Connect
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let target = listener.local_addr().unwrap();
+
+    spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        read_request(&mut stream);
+        stream.write_all(b"not a response").unwrap();
+    });
+
+    assert!(matches!(
+        try_proxy_request(&request(), target, Duration::from_secs(5)),
+        Err(ProxyError::Response(_))
+    ));
+}
+
+#[test]
+fn test_remove_hop_by_hop_headers() {
+    let mut headers = Headers::new();
+    headers.add(HeaderType::Connection, "keep-alive, X-Secret");
+    headers.add("Keep-Alive", "timeout=5");
+    headers.add("X-Secret", "value");
+    headers.add(HeaderType::Upgrade, "websocket");
+    headers.add("TE", "trailers");
+    headers.add(HeaderType::ContentType, "text/plain");
+
+    remove_hop_by_hop_headers(&mut headers);
+
+    assert_eq!(headers.len(), 1);
+    assert_eq!(headers.get(HeaderType::ContentType), Some("text/plain"));
+}
+
+#[test]
+fn test_forwarded_request() {
+    let test_data = b"GET /test HTTP/1.1\r\nHost: example.com\r\nConnection: Upgrade\r\nUpgrade: websocket\r\nX-Forwarded-For: 9.9.9.9\r\nX-Forwarded-Proto: https\r\n\r\n";
+    let request =
+        Request::from_stream(&mut &test_data[..], "1.2.3.4:5678".parse().unwrap()).unwrap();
+    let forwarded = forwarded_request(&request);
+
+    // Forwarding headers from untrusted clients are replaced
+    assert_eq!(forwarded.headers.get(HeaderType::Connection), None);
+    assert_eq!(forwarded.headers.get(HeaderType::Upgrade), None);
+    assert_eq!(
+        forwarded.headers.get_all("X-Forwarded-For"),
+        vec!["1.2.3.4"]
+    );
+    assert_eq!(forwarded.headers.get_all("X-Forwarded-Proto"), vec!["http"]);
+    assert_eq!(
+        forwarded.headers.get("X-Forwarded-Host"),
+        Some("example.com")
+    );
+    assert_eq!(forwarded.headers.get(HeaderType::Host), Some("example.com"));
+    assert_eq!(forwarded.headers.get(HeaderType::Via), Some("1.1 humphrey"));
+
+    // Requests received over TLS are forwarded as HTTPS
+    let mut tls_request = request.clone();
+    tls_request.address.tls = true;
+    let forwarded = forwarded_request(&tls_request);
+
+    assert_eq!(
+        forwarded.headers.get_all("X-Forwarded-Proto"),
+        vec!["https"]
+    );
+
+    // Forwarding headers from trusted proxies are passed on
+    let mut request = request;
+    let trusted: Cidr = "1.2.3.4".parse().unwrap();
+    request.address = Address::from_headers(&request.headers, "1.2.3.4:5678", &[trusted]).unwrap();
+    let forwarded = forwarded_request(&request);
+
+    assert_eq!(
+        forwarded.headers.get_all("X-Forwarded-For"),
+        vec!["9.9.9.9, 1.2.3.4"]
+    );
+    assert_eq!(
+        forwarded.headers.get_all("X-Forwarded-Proto"),
+        vec!["https"]
+    );
+}
+
+#[test]
+fn test_forwarded_response() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let target = listener.local_addr().unwrap();
+
+    spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        read_request(&mut stream);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nKeep-Alive: timeout=5\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n")
+            .unwrap();
+    });
+
+    let response = try_proxy_request(&request(), target, Duration::from_secs(5)).unwrap();
+
+    assert_eq!(response.body, b"hello");
+    assert_eq!(response.headers.get("Keep-Alive"), None);
+    assert_eq!(response.headers.get(HeaderType::TransferEncoding), None);
+    assert_eq!(response.headers.get(HeaderType::Via), Some("1.1 humphrey"));
+}
+
+#[test]
+fn test_pool_reuses_connections() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let target = listener.local_addr().unwrap();
+
+    // Only one connection is accepted, so the second request must reuse it
+    spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+
+        for body in ["one", "two"] {
+            read_request(&mut stream);
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n{}",
+                body
+            )
+            .unwrap();
+        }
+    });
+
+    let pool = ConnectionPool::new();
+
+    let response = pool.proxy_request(&request(), target, Duration::from_secs(5));
+    assert_eq!(response.unwrap().body, b"one");
+    assert_eq!(pool.idle_connections(target), 1);
+
+    let response = pool.proxy_request(&request(), target, Duration::from_secs(5));
+    assert_eq!(response.unwrap().body, b"two");
+    assert_eq!(pool.idle_connections(target), 1);
+}
+
+#[test]
+fn test_pool_retries_stale_connections() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let target = listener.local_addr().unwrap();
+
+    spawn(move || {
+        // The first connection is closed after receiving the second request without responding to it
+        let (mut stream, _) = listener.accept().unwrap();
+        read_request(&mut stream);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\none")
+            .unwrap();
+        read_request(&mut stream);
+        drop(stream);
+
+        let (mut stream, _) = listener.accept().unwrap();
+        read_request(&mut stream);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\ntwo")
+            .unwrap();
+    });
+
+    let pool = ConnectionPool::new();
+
+    let response = pool.proxy_request(&request(), target, Duration::from_secs(5));
+    assert_eq!(response.unwrap().body, b"one");
+
+    let response = pool.proxy_request(&request(), target, Duration::from_secs(5));
+    assert_eq!(response.unwrap().body, b"two");
+}
+
+#[test]
+fn test_pool_closes_connections() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let target = listener.local_addr().unwrap();
+
+    spawn(move || {
+        for response in [
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.0 200 OK\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n",
+        ] {
+            let (mut stream, _) = listener.accept().unwrap();
+            read_request(&mut stream);
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    // Targets which do not allow the connection to be kept open
+    let pool = ConnectionPool::new();
+    pool.proxy_request(&request(), target, Duration::from_secs(5))
+        .unwrap();
+    assert_eq!(pool.idle_connections(target), 0);
+    pool.proxy_request(&request(), target, Duration::from_secs(5))
+        .unwrap();
+    assert_eq!(pool.idle_connections(target), 0);
+
+    // Pools which do not keep idle connections
+    let pool = ConnectionPool::new().with_max_idle_connections(0);
+    pool.proxy_request(&request(), target, Duration::from_secs(5))
+        .unwrap();
+    assert_eq!(pool.idle_connections(target), 0);
+}
+
+#[test]
+fn test_pool_idle_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let target = listener.local_addr().unwrap();
+
+    // Expired connections are not reused, so a new connection is accepted for each request
+    spawn(move || {
+        let mut streams = Vec::new();
+
+        for _ in 0..2 {
+            let (mut stream, _) = listener.accept().unwrap();
+            read_request(&mut stream);
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            streams.push(stream);
+        }
+    });
+
+    let pool = ConnectionPool::new().with_idle_timeout(Duration::ZERO);
+
+    for _ in 0..2 {
+        pool.proxy_request(&request(), target, Duration::from_secs(5))
+            .unwrap();
+    }
+}
@@ -0,0 +1,67 @@
+#![allow(unused_imports)]
+use crate::http::address::Address;
+use crate::http::headers::{HeaderType, Headers};
+use crate::http::method::Method;
+use crate::http::params::Params;
+use crate::http::query::Query;
+use crate::http::Request;
+
+#[test]
+fn test_decode_query() {
+    let query = Query::decode("name=John+Smith&tag=a&tag=b%20c&empty=&flag&&age=42");
+
+    assert_eq!(query.get("name"), Some("John Smith"));
+    assert_eq!(query.get("tag"), Some("a"));
+    assert_eq!(query.get_all("tag"), vec!["a", "b c"]);
+    assert_eq!(query.get("empty"), Some(""));
+    assert_eq!(query.get("flag"), Some(""));
+    assert_eq!(query.get("missing"), None);
+    assert!(query.contains("flag"));
+    assert_eq!(query.len(), 6);
+
+    assert_eq!(query.parse::<u8>("age"), Some(42));
+    assert_eq!(query.parse::<u8>("name"), None);
+    assert_eq!(query.parse::<u8>("missing"), None);
+}
+
+#[test]
+fn test_decode_invalid_escapes() {
+    let query = Query::decode("a=100%&b=%zz&c=%+1&d=%e2%9C%93&e%3D=1");
+
+    assert_eq!(
+        query.iter().collect::<Vec<_>>(),
+        vec![
+            ("a", "100%"),
+            ("b", "%zz"),
+            ("c", "% 1"),
+            ("d", "\u{2713}"),
+            ("e=", "1")
+        ]
+    );
+}
+
+#[test]
+fn test_request_form() {
+    let mut request = Request {
+        method: Method::Post,
+        uri: "/submit".into(),
+        query: "page=2".into(),
+        version: "HTTP/1.1".into(),
+        headers: Headers::new(),
+        content: Some(b"name=Humphrey&lang=rust".to_vec()),
+        address: Address::new("1.2.3.4:5678").unwrap(),
+        params: Params::new(),
+    };
+
+    assert_eq!(request.get_query().parse::<u32>("page"), Some(2));
+    assert_eq!(request.get_form(), None);
+
+    request.headers.add(
+        HeaderType::ContentType,
+        "application/x-www-form-urlencoded; charset=utf-8",
+    );
+
+    let form = request.get_form().unwrap();
+    assert_eq!(form.get("name"), Some("Humphrey"));
+    assert_eq!(form.get("lang"), Some("rust"));
+}
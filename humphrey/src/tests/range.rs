@@ -0,0 +1,284 @@
+#![allow(unused_imports)]
+use crate::http::headers::HeaderType;
+use crate::http::method::Method;
+use crate::http::range::{serve_ranges, ByteRange, RangeRequest};
+use crate::http::{Request, Response, StatusCode};
+
+#[test]
+fn test_parse_range() {
+    assert_eq!(
+        RangeRequest::parse("bytes=0-499", 1000),
+        RangeRequest::Partial(vec![ByteRange::new(0, 499)])
+    );
+    assert_eq!(
+        RangeRequest::parse("bytes=500-", 1000),
+        RangeRequest::Partial(vec![ByteRange::new(500, 999)])
+    );
+    assert_eq!(
+        RangeRequest::parse("bytes=-200", 1000),
+        RangeRequest::Partial(vec![ByteRange::new(800, 999)])
+    );
+    assert_eq!(
+        RangeRequest::parse("bytes=-2000", 1000),
+        RangeRequest::Partial(vec![ByteRange::new(0, 999)])
+    );
+    assert_eq!(
+        RangeRequest::parse("Bytes = 900-1500", 1000),
+        RangeRequest::Partial(vec![ByteRange::new(900, 999)])
+    );
+    assert_eq!(
+        RangeRequest::parse("bytes=0-9, 100-199,", 1000),
+        RangeRequest::Partial(vec![ByteRange::new(0, 9), ByteRange::new(100, 199)])
+    );
+}
+
+#[test]
+fn test_combine_ranges() {
+    assert_eq!(
+        RangeRequest::parse("bytes=100-199,0-49,150-299,50-59", 1000),
+        RangeRequest::Partial(vec![ByteRange::new(0, 59), ByteRange::new(100, 299)])
+    );
+    assert_eq!(
+        RangeRequest::parse("bytes=0-0,-1,1000-", 1000),
+        RangeRequest::Partial(vec![ByteRange::new(0, 0), ByteRange::new(999, 999)])
+    );
+}
+
+#[test]
+fn test_invalid_range() {
+    for header in [
+        "bytes=",
+        "bytes=abc",
+        "bytes=5-1",
+        "bytes=-",
+        "bytes=1-2-3",
+        "bytes=+1-2",
+        "items=0-1",
+        "0-1",
+    ] {
+        assert_eq!(RangeRequest::parse(header, 1000), RangeRequest::Full);
+    }
+
+    let too_many = format!("bytes={}", vec!["0-0"; 33].join(","));
+    assert_eq!(RangeRequest::parse(&too_many, 1000), RangeRequest::Full);
+
+    assert_eq!(
+        RangeRequest::parse("bytes=1000-", 1000),
+        RangeRequest::Unsatisfiable
+    );
+    assert_eq!(
+        RangeRequest::parse("bytes=-0", 1000),
+        RangeRequest::Unsatisfiable
+    );
+    assert_eq!(
+        RangeRequest::parse("bytes=-10", 0),
+        RangeRequest::Unsatisfiable
+    );
+}
+
+#[test]
+fn test_serve_single_range() {
+    let response = serve_ranges(
+        &Request::new(Method::Get, "/").with_header(HeaderType::Range, "bytes=2-5"),
+        full_response(),
+    );
+
+    assert_eq!(response.status_code, StatusCode::PartialContent);
+    assert_eq!(response.body, b"2345");
+    assert_eq!(
+        response.headers.get(HeaderType::ContentRange),
+        Some("bytes 2-5/10")
+    );
+    assert_eq!(
+        response.headers.get(HeaderType::AcceptRanges),
+        Some("bytes")
+    );
+    assert_eq!(
+        response.headers.get(HeaderType::ContentType),
+        Some("text/plain")
+    );
+
+    let response = serve_ranges(&Request::new(Method::Get, "/"), full_response());
+
+    assert_eq!(response.status_code, StatusCode::OK);
+    assert_eq!(response.body, b"0123456789");
+    assert_eq!(
+        response.headers.get(HeaderType::AcceptRanges),
+        Some("bytes")
+    );
+}
+
+#[test]
+fn test_serve_multiple_ranges() {
+    let response = serve_ranges(
+        &Request::new(Method::Get, "/").with_header(HeaderType::Range, "bytes=0-1,-2"),
+        full_response(),
+    );
+
+    assert_eq!(response.status_code, StatusCode::PartialContent);
+
+    let content_type = response.headers.get(HeaderType::ContentType).unwrap();
+    let boundary = content_type
+        .strip_prefix("multipart/byteranges; boundary=")
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(response.body).unwrap(),
+        format!(
+            "\r\n--{0}\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/10\r\n\r\n01\
+             \r\n--{0}\r\nContent-Type: text/plain\r\nContent-Range: bytes 8-9/10\r\n\r\n89\
+             \r\n--{0}--\r\n",
+            boundary
+        )
+    );
+}
+
+#[test]
+fn test_serve_unsatisfiable_range() {
+    let response = serve_ranges(
+        &Request::new(Method::Get, "/").with_header(HeaderType::Range, "bytes=20-"),
+        full_response(),
+    );
+
+    assert_eq!(
+        response.status_code,
+        StatusCode::RequestedRangeNotSatisfiable
+    );
+    assert!(response.body.is_empty());
+    assert_eq!(
+        response.headers.get(HeaderType::ContentRange),
+        Some("bytes */10")
+    );
+}
+
+#[test]
+fn test_if_range() {
+    let date = "Sun, 08 Aug 2021 15:43:35 GMT";
+    let full = || {
+        full_response()
+            .with_header(HeaderType::ETag, "\"abc\"")
+            .with_header(HeaderType::LastModified, date)
+    };
+
+    for if_range in ["\"abc\"", date] {
+        let response = serve_ranges(
+            &Request::new(Method::Get, "/")
+                .with_header(HeaderType::Range, "bytes=0-0")
+                .with_header(HeaderType::IfRange, if_range),
+            full(),
+        );
+
+        assert_eq!(response.status_code, StatusCode::PartialContent);
+    }
+
+    for if_range in [
+        "\"abcd\"",
+        "W/\"abc\"",
+        "Sun, 08 Aug 2021 15:43:36 GMT",
+        "yesterday",
+    ] {
+        let response = serve_ranges(
+            &Request::new(Method::Get, "/")
+                .with_header(HeaderType::Range, "bytes=0-0")
+                .with_header(HeaderType::IfRange, if_range),
+            full(),
+        );
+
+        assert_eq!(response.status_code, StatusCode::OK);
+        assert_eq!(response.body, b"0123456789");
+    }
+}
+
+#[test]
+fn test_ignore_ineligible_responses() {
+    let range = Request::new(Method::Get, "/").with_header(HeaderType::Range, "bytes=0-0");
+
+    let response = serve_ranges(&range, Response::new(StatusCode::NotFound, "Not Found"));
+    assert_eq!(response.status_code, StatusCode::NotFound);
+    assert_eq!(response.headers.get(HeaderType::AcceptRanges), None);
+
+    let response = serve_ranges(
+        &range,
+        full_response().with_header(HeaderType::ContentEncoding, "gzip"),
+    );
+    assert_eq!(response.status_code, StatusCode::OK);
+    assert_eq!(response.headers.get(HeaderType::AcceptRanges), None);
+
+    let mut post = Request::new(Method::Get, "/").with_header(HeaderType::Range, "bytes=0-0");
+    post.method = Method::Post;
+
+    let response = serve_ranges(&post, full_response());
+    assert_eq!(response.status_code, StatusCode::OK);
+    assert_eq!(response.body, b"0123456789");
+}
+
+#[test]
+#[cfg(not(feature = "tokio"))]
+fn test_serve_file_ranges() {
+    use crate::http::range::serve_file_ranges;
+    use std::fs::{remove_file, write, File};
+
+    let path = std::env::temp_dir().join(format!("humphrey-range-{}.txt", std::process::id()));
+    write(&path, "0123456789").unwrap();
+
+    // Streams the response and returns its body, which fails if the length of the stream is wrong
+    let serve = |range: &str| {
+        let response =
+            Response::empty(StatusCode::OK).with_header(HeaderType::ContentType, "text/plain");
+        let file = File::open(&path).unwrap();
+        let response = serve_file_ranges(
+            &Request::new(Method::Get, "/").with_header(HeaderType::Range, range),
+            response,
+            file,
+            &path,
+        )
+        .unwrap();
+
+        let status_code = response.status_code;
+        let content_type = response
+            .headers
+            .get(HeaderType::ContentType)
+            .unwrap_or_default()
+            .to_string();
+        let mut bytes: Vec<u8> = Vec::new();
+        response.write_to(&mut bytes).unwrap();
+
+        let body = String::from_utf8(bytes).unwrap();
+        let body = body.split_once("\r\n\r\n").unwrap().1.to_string();
+
+        (status_code, content_type, body)
+    };
+
+    let (status_code, _, body) = serve("bytes=2-5");
+    assert_eq!(status_code, StatusCode::PartialContent);
+    assert_eq!(body, "2345");
+
+    let (status_code, content_type, body) = serve("bytes=0-1,-2");
+    let boundary = content_type
+        .strip_prefix("multipart/byteranges; boundary=")
+        .unwrap();
+    assert_eq!(status_code, StatusCode::PartialContent);
+    assert_eq!(
+        body,
+        format!(
+            "\r\n--{0}\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/10\r\n\r\n01\
+             \r\n--{0}\r\nContent-Type: text/plain\r\nContent-Range: bytes 8-9/10\r\n\r\n89\
+             \r\n--{0}--\r\n",
+            boundary
+        )
+    );
+
+    let (status_code, _, body) = serve("bytes=20-");
+    assert_eq!(status_code, StatusCode::RequestedRangeNotSatisfiable);
+    assert!(body.is_empty());
+
+    let (status_code, _, body) = serve("items=0-1");
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body, "0123456789");
+
+    remove_file(&path).unwrap();
+}
+
+fn full_response() -> Response {
+    Response::new(StatusCode::OK, "0123456789").with_header(HeaderType::ContentType, "text/plain")
+}
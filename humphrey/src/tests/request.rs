@@ -1,13 +1,16 @@
 #![allow(dead_code, unused_imports)]
 use crate::http::address::Address;
+use crate::http::body::{BodyDecoder, RequestBody};
 use crate::http::cookie::Cookie;
 use crate::http::headers::{Header, HeaderType, Headers};
 use crate::http::method::Method;
+use crate::http::params::Params;
+use crate::http::request::RequestError;
 use crate::http::Request;
 use crate::tests::mock_stream::MockStream;
 
 use std::collections::{BTreeMap, VecDeque};
-use std::io::Read;
+use std::io::{BufReader, Read};
 use std::iter::FromIterator;
 use std::net::{SocketAddr, ToSocketAddrs};
 
@@ -57,6 +60,7 @@ fn test_bytes_from_request() {
         headers: Headers::new(),
         content: Some(b"this is a test".to_vec()),
         address: Address::new("1.2.3.4:5678").unwrap(),
+        params: Params::new(),
     };
 
     test_data.headers.add(HeaderType::ContentLength, "14");
@@ -82,14 +86,9 @@ fn test_proxied_request_from_stream() {
     assert_eq!(request.uri, expected_uri);
     assert_eq!(request.version, "HTTP/1.1");
     assert_eq!(request.content, None);
-    assert_eq!(
-        request.address,
-        Address {
-            origin_addr: "13.14.15.16".parse().unwrap(),
-            proxies: vec!["9.10.11.12".parse().unwrap(), "1.2.3.4".parse().unwrap()],
-            port: 5678
-        }
-    );
+
+    // Forwarding headers are not honoured until the app has checked that the peer is a trusted proxy
+    assert_eq!(request.address, Address::new("1.2.3.4:5678").unwrap());
 
     let mut expected_headers: Headers = Headers::new();
     expected_headers.add(HeaderType::Host, "localhost");
@@ -97,3 +96,134 @@ fn test_proxied_request_from_stream() {
 
     assert_eq!(request.headers, expected_headers);
 }
+
+#[test]
+fn test_chunked_request_from_stream() {
+    let test_data = b"POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n7\r\nHello, \r\n6;name=value\r\nworld!\r\n0\r\nX-Checksum: 1234\r\n\r\n";
+    let mut stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    let request = Request::from_stream(&mut stream, "1.2.3.4:5678".parse().unwrap()).unwrap();
+
+    assert_eq!(request.method, Method::Post);
+    assert_eq!(request.content, Some(b"Hello, world!".to_vec()));
+
+    let mut expected_headers: Headers = Headers::new();
+    expected_headers.add(HeaderType::Host, "localhost");
+    expected_headers.add("X-Checksum", "1234");
+    expected_headers.add(HeaderType::ContentLength, "13");
+    assert_eq!(request.headers, expected_headers);
+}
+
+#[test]
+fn test_forbidden_request_trailers() {
+    let test_data = b"POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHello\r\n0\r\nContent-Length: 999\r\nhost: example.com\r\nAuthorization: Basic dXNlcjpwYXNz\r\nContent-Type: text/html\r\nX-Checksum: 1234\r\n\r\n";
+    let mut stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    let request = Request::from_stream(&mut stream, "1.2.3.4:5678".parse().unwrap()).unwrap();
+
+    assert_eq!(request.content, Some(b"Hello".to_vec()));
+
+    // Trailers which could override the framing, routing or authentication of the request are dropped
+    let mut expected_headers: Headers = Headers::new();
+    expected_headers.add(HeaderType::Host, "localhost");
+    expected_headers.add("X-Checksum", "1234");
+    expected_headers.add(HeaderType::ContentLength, "5");
+    assert_eq!(request.headers, expected_headers);
+}
+
+#[test]
+fn test_malformed_chunked_request() {
+    for length in ["zz", "+5", " 5", "5 "] {
+        let test_data = format!("POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n{}\r\nHello\r\n0\r\n\r\n", length);
+        let mut stream = MockStream::with_data(VecDeque::from_iter(test_data.bytes()));
+        let request = Request::from_stream(&mut stream, "1.2.3.4:5678".parse().unwrap());
+
+        assert_eq!(request.unwrap_err(), RequestError::Request);
+    }
+}
+
+#[test]
+fn test_ambiguous_request_framing() {
+    let requests: [&[u8]; 4] = [
+        b"POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: gzip\r\n\r\nHello",
+        b"POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\nTransfer-Encoding: gzip\r\n\r\n0\r\n\r\n",
+        b"POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\nContent-Length: 5\r\n\r\n0\r\n\r\n",
+        b"POST /upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\nContent-Length: 6\r\n\r\nHello!",
+    ];
+
+    for test_data in requests {
+        let mut stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+        let request = Request::from_stream(&mut stream, "1.2.3.4:5678".parse().unwrap());
+
+        assert_eq!(request.unwrap_err(), RequestError::Request);
+    }
+}
+
+#[test]
+fn test_chunked_request_trailers_too_large() {
+    let mut test_data = b"POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHello\r\n0\r\n".to_vec();
+    for i in 0..1024 {
+        test_data.extend_from_slice(format!("X-Trailer-{}: value\r\n", i).as_bytes());
+    }
+    test_data.extend_from_slice(b"\r\n");
+
+    let mut stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    let request = Request::from_stream(&mut stream, "1.2.3.4:5678".parse().unwrap());
+
+    assert_eq!(request.unwrap_err(), RequestError::Request);
+
+    let mut test_data =
+        b"POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n5".to_vec();
+    test_data.extend_from_slice(&[b'0'; 8192]);
+    test_data.extend_from_slice(b"\r\nHello\r\n0\r\n\r\n");
+
+    let mut stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    let request = Request::from_stream(&mut stream, "1.2.3.4:5678".parse().unwrap());
+
+    assert_eq!(request.unwrap_err(), RequestError::Request);
+}
+
+#[test]
+fn test_request_body_too_large() {
+    let test_data =
+        b"POST /upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: 99999999999\r\n\r\n";
+    let stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    let mut reader = BufReader::new(stream);
+    let mut request =
+        Request::head_from_reader(&mut reader, "1.2.3.4:5678".parse().unwrap()).unwrap();
+
+    assert_eq!(
+        request.read_body(&mut reader, Some(1024)),
+        Err(RequestError::TooLarge)
+    );
+    assert_eq!(request.content, None);
+}
+
+#[test]
+fn test_chunked_request_body_too_large() {
+    let test_data = b"POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n7\r\nHello, \r\n6\r\nworld!\r\n0\r\n\r\n";
+    let stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    let mut reader = BufReader::new(stream);
+    let mut request =
+        Request::head_from_reader(&mut reader, "1.2.3.4:5678".parse().unwrap()).unwrap();
+
+    assert_eq!(
+        request.read_body(&mut reader, Some(10)),
+        Err(RequestError::TooLarge)
+    );
+}
+
+#[test]
+fn test_streamed_request_body() {
+    let test_data = b"POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n7\r\nHello, \r\n6\r\nworld!\r\n0\r\n\r\n";
+    let stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    let mut reader = BufReader::new(stream);
+    let request = Request::head_from_reader(&mut reader, "1.2.3.4:5678".parse().unwrap()).unwrap();
+
+    let decoder = BodyDecoder::new(&request.headers, Some(1024)).unwrap();
+    let mut body = RequestBody::new(&mut reader, decoder);
+    let mut content = String::new();
+
+    assert!(!body.is_complete());
+    body.read_to_string(&mut content).unwrap();
+    assert!(body.is_complete());
+    assert_eq!(content, "Hello, world!");
+}
@@ -4,6 +4,8 @@ use crate::http::address::Address;
 use crate::http::cookie::Cookie;
 use crate::http::headers::{Header, HeaderType, Headers};
 use crate::http::method::Method;
+use crate::http::params::Params;
+use crate::http::request::RequestError;
 use crate::http::Request;
 use crate::tests::mock_stream::MockStream;
 
@@ -13,6 +15,7 @@ use std::iter::FromIterator;
 use std::net::{SocketAddr, ToSocketAddrs};
 
 use futures::executor::block_on;
+use tokio::io::BufReader;
 
 #[test]
 fn test_request_from_stream() {
@@ -67,6 +70,7 @@ fn test_bytes_from_request() {
         headers: Headers::new(),
         content: Some(b"this is a test".to_vec()),
         address: Address::new("1.2.3.4:5678").unwrap(),
+        params: Params::new(),
     };
 
     test_data.headers.add(HeaderType::ContentLength, "14");
@@ -95,14 +99,9 @@ fn test_proxied_request_from_stream() {
     assert_eq!(request.uri, expected_uri);
     assert_eq!(request.version, "HTTP/1.1");
     assert_eq!(request.content, None);
-    assert_eq!(
-        request.address,
-        Address {
-            origin_addr: "13.14.15.16".parse().unwrap(),
-            proxies: vec!["9.10.11.12".parse().unwrap(), "1.2.3.4".parse().unwrap()],
-            port: 5678
-        }
-    );
+
+    // Forwarding headers are not honoured until the app has checked that the peer is a trusted proxy
+    assert_eq!(request.address, Address::new("1.2.3.4:5678").unwrap());
 
     let mut expected_headers: Headers = Headers::new();
     expected_headers.add(HeaderType::Host, "localhost");
@@ -110,3 +109,93 @@ fn test_proxied_request_from_stream() {
 
     assert_eq!(request.headers, expected_headers);
 }
+
+#[test]
+fn test_chunked_request_from_stream() {
+    let test_data = b"POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n7\r\nHello, \r\n6;name=value\r\nworld!\r\n0\r\nX-Checksum: 1234\r\n\r\n";
+    let mut stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    let request = block_on(Request::from_stream(
+        &mut stream,
+        "1.2.3.4:5678".parse().unwrap(),
+    ))
+    .unwrap();
+
+    assert_eq!(request.method, Method::Post);
+    assert_eq!(request.content, Some(b"Hello, world!".to_vec()));
+
+    let mut expected_headers: Headers = Headers::new();
+    expected_headers.add(HeaderType::Host, "localhost");
+    expected_headers.add("X-Checksum", "1234");
+    expected_headers.add(HeaderType::ContentLength, "13");
+    assert_eq!(request.headers, expected_headers);
+}
+
+#[test]
+fn test_forbidden_request_trailers() {
+    let test_data = b"POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHello\r\n0\r\nContent-Length: 999\r\nhost: example.com\r\nAuthorization: Basic dXNlcjpwYXNz\r\nContent-Type: text/html\r\nX-Checksum: 1234\r\n\r\n";
+    let mut stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    let request = block_on(Request::from_stream(
+        &mut stream,
+        "1.2.3.4:5678".parse().unwrap(),
+    ))
+    .unwrap();
+
+    assert_eq!(request.content, Some(b"Hello".to_vec()));
+
+    // Trailers which could override the framing, routing or authentication of the request are dropped
+    let mut expected_headers: Headers = Headers::new();
+    expected_headers.add(HeaderType::Host, "localhost");
+    expected_headers.add("X-Checksum", "1234");
+    expected_headers.add(HeaderType::ContentLength, "5");
+    assert_eq!(request.headers, expected_headers);
+}
+
+#[test]
+fn test_malformed_chunked_request() {
+    for length in ["zz", "+5", " 5", "5 "] {
+        let test_data = format!("POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n{}\r\nHello\r\n0\r\n\r\n", length);
+        let mut stream = MockStream::with_data(VecDeque::from_iter(test_data.bytes()));
+        let request = block_on(Request::from_stream(
+            &mut stream,
+            "1.2.3.4:5678".parse().unwrap(),
+        ));
+
+        assert_eq!(request.unwrap_err(), RequestError::Request);
+    }
+}
+
+#[test]
+fn test_request_body_too_large() {
+    let test_data =
+        b"POST /upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: 99999999999\r\n\r\n";
+    let stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    let mut reader = BufReader::new(stream);
+    let mut request = block_on(Request::head_from_reader(
+        &mut reader,
+        "1.2.3.4:5678".parse().unwrap(),
+    ))
+    .unwrap();
+
+    assert_eq!(
+        block_on(request.read_body(&mut reader, Some(1024))),
+        Err(RequestError::TooLarge)
+    );
+    assert_eq!(request.content, None);
+}
+
+#[test]
+fn test_chunked_request_body_too_large() {
+    let test_data = b"POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n7\r\nHello, \r\n6\r\nworld!\r\n0\r\n\r\n";
+    let stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    let mut reader = BufReader::new(stream);
+    let mut request = block_on(Request::head_from_reader(
+        &mut reader,
+        "1.2.3.4:5678".parse().unwrap(),
+    ))
+    .unwrap();
+
+    assert_eq!(
+        block_on(request.read_body(&mut reader, Some(10))),
+        Err(RequestError::TooLarge)
+    );
+}
@@ -22,7 +22,7 @@ fn test_response() {
         Some("text/html")
     );
 
-    let expected_bytes: Vec<u8> = b"HTTP/1.1 200 OK\r\nDate: Thu, 1 Jan 1970 00:00:00 GMT\r\nContent-Language: en-GB\r\nContent-Type: text/html\r\n\r\n<body>test</body>\r\n".to_vec();
+    let expected_bytes: Vec<u8> = b"HTTP/1.1 200 OK\r\nDate: Thu, 1 Jan 1970 00:00:00 GMT\r\nContent-Language: en-GB\r\nContent-Type: text/html\r\n\r\n<body>test</body>".to_vec();
     let bytes: Vec<u8> = response.into();
 
     assert_eq!(bytes, expected_bytes);
@@ -54,7 +54,7 @@ fn test_cookie_response() {
     );
 
     let expected_bytes: Vec<u8> =
-        b"HTTP/1.1 200 OK\r\nSet-Cookie: X-Example-Cookie=example-value; Max-Age=3600; Path=/; Secure\r\nSet-Cookie: X-Example-Token=example-token; Domain=example.com; SameSite=Strict; Secure\r\n\r\nHello, world!\r\n"
+        b"HTTP/1.1 200 OK\r\nSet-Cookie: X-Example-Cookie=example-value; Max-Age=3600; Path=/; Secure\r\nSet-Cookie: X-Example-Token=example-token; Domain=example.com; SameSite=Strict; Secure\r\n\r\nHello, world!"
             .to_vec();
     let bytes: Vec<u8> = response.into();
 
@@ -79,3 +79,129 @@ fn test_response_from_stream() {
     expected_headers.add(HeaderType::ContentLength, "51");
     assert_eq!(response.headers, expected_headers);
 }
+
+#[test]
+fn test_chunked_response_from_stream() {
+    let test_data = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHello\r\n0\r\n\r\n";
+    let mut stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    let response = Response::from_stream(&mut stream).unwrap();
+
+    assert_eq!(response.body, b"Hello".to_vec());
+
+    let mut expected_headers: Headers = Headers::new();
+    expected_headers.add(HeaderType::ContentLength, "5");
+    assert_eq!(response.headers, expected_headers);
+}
+
+#[test]
+fn test_forbidden_response_trailers() {
+    let test_data = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHello\r\n0\r\nContent-Length: 999\r\nSet-Cookie: session=1\r\nContent-Encoding: gzip\r\nX-Checksum: 1234\r\n\r\n";
+    let mut stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    let response = Response::from_stream(&mut stream).unwrap();
+
+    assert_eq!(response.body, b"Hello".to_vec());
+
+    let mut expected_headers: Headers = Headers::new();
+    expected_headers.add("X-Checksum", "1234");
+    expected_headers.add(HeaderType::ContentLength, "5");
+    assert_eq!(response.headers, expected_headers);
+}
+
+#[test]
+fn test_malformed_chunked_response() {
+    for length in ["zz", "+5", " 5", "5 ", "5\t", ""] {
+        let test_data = format!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{}\r\nHello\r\n0\r\n\r\n",
+            length
+        );
+        let mut stream = MockStream::with_data(VecDeque::from_iter(test_data.bytes()));
+
+        assert!(Response::from_stream(&mut stream).is_err(), "{:?}", length);
+    }
+
+    // Whitespace is allowed before a chunk extension
+    let test_data = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5 \t;name=value\r\nHello\r\n0\r\n\r\n";
+    let mut stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    let response = Response::from_stream(&mut stream).unwrap();
+
+    assert_eq!(response.body, b"Hello".to_vec());
+}
+
+#[test]
+fn test_response_without_body_from_stream() {
+    let test_data = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n";
+    let mut stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    let response = Response::from_stream_without_body(&mut stream).unwrap();
+
+    assert!(response.body.is_empty());
+
+    let mut expected_headers: Headers = Headers::new();
+    expected_headers.add(HeaderType::ContentLength, "5");
+    assert_eq!(response.headers, expected_headers);
+}
+
+#[test]
+fn test_not_modified_response_from_stream() {
+    let test_data = b"HTTP/1.1 304 Not Modified\r\nContent-Length: 5\r\nETag: \"abc\"\r\n\r\n";
+    let mut stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    let response = Response::from_stream(&mut stream).unwrap();
+
+    assert_eq!(response.status_code, StatusCode::NotModified);
+    assert!(response.body.is_empty());
+    assert_eq!(response.headers.get(HeaderType::ContentLength), Some("5"));
+    assert_eq!(response.headers.get(HeaderType::ETag), Some("\"abc\""));
+}
+
+#[test]
+fn test_chunks_response_to_stream() {
+    let response = Response::empty(StatusCode::OK)
+        .with_header(HeaderType::TransferEncoding, "chunked")
+        .with_chunks(vec![b"Hello, ".to_vec(), Vec::new(), b"world!".to_vec()]);
+
+    assert_eq!(response.body_length(), None);
+
+    let expected_bytes: Vec<u8> =
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n7\r\nHello, \r\n6\r\nworld!\r\n0\r\n\r\n"
+            .to_vec();
+    let mut bytes: Vec<u8> = Vec::new();
+
+    #[cfg(not(feature = "tokio"))]
+    response.write_to(&mut bytes).unwrap();
+    #[cfg(feature = "tokio")]
+    futures::executor::block_on(response.write_to(&mut bytes)).unwrap();
+
+    assert_eq!(bytes, expected_bytes);
+}
+
+#[test]
+fn test_take_stream() {
+    let mut response = Response::empty(StatusCode::OK).with_chunks(vec![b"Hello".to_vec()]);
+    assert!(response.is_streamed());
+
+    assert!(response.take_stream().is_some());
+    assert!(!response.is_streamed());
+    assert_eq!(response.body_length(), Some(0));
+}
+
+#[test]
+fn test_reader_response_to_stream() {
+    let test_data = b"The requested resource was streamed from a reader.";
+    let stream = MockStream::with_data(VecDeque::from_iter(test_data.iter().cloned()));
+    let response = Response::empty(StatusCode::OK)
+        .with_header(HeaderType::ContentLength, "50")
+        .with_reader(stream, Some(50));
+
+    assert_eq!(response.body_length(), Some(50));
+
+    let expected_bytes: Vec<u8> =
+        b"HTTP/1.1 200 OK\r\nContent-Length: 50\r\n\r\nThe requested resource was streamed from a reader."
+            .to_vec();
+    let mut bytes: Vec<u8> = Vec::new();
+
+    #[cfg(not(feature = "tokio"))]
+    response.write_to(&mut bytes).unwrap();
+    #[cfg(feature = "tokio")]
+    futures::executor::block_on(response.write_to(&mut bytes)).unwrap();
+
+    assert_eq!(bytes, expected_bytes);
+}
@@ -0,0 +1,264 @@
+#![allow(unused_imports)]
+use crate::app::{get_handler, is_implicit_head};
+use crate::http::method::Method;
+use crate::http::params::Params;
+use crate::http::{Request, Response, StatusCode};
+use crate::route::{try_find_directory, Route, SubApp};
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+
+#[cfg(not(feature = "tokio"))]
+fn handler(_: Request, _: Arc<()>) -> Response {
+    Response::empty(StatusCode::OK)
+}
+
+#[cfg(feature = "tokio")]
+async fn handler(_: Request, _: Arc<()>) -> Response {
+    Response::empty(StatusCode::OK)
+}
+
+#[test]
+fn test_method_matches() {
+    let subapp: SubApp<()> = SubApp::new()
+        .with_get("/items", handler)
+        .with_route("/any", handler);
+
+    assert!(subapp.routes[0].method_matches(&Method::Get));
+    assert!(!subapp.routes[0].method_matches(&Method::Post));
+    assert!(subapp.routes[1].method_matches(&Method::Post));
+}
+
+#[test]
+fn test_allowed_methods() {
+    let mut subapp: SubApp<()> = SubApp::new()
+        .with_get("/items", handler)
+        .with_post("/items", handler)
+        .with_delete("/items/*", handler)
+        .with_route("/any", handler);
+
+    // The methods are the same whether the routes are scanned or looked up in the compiled route tree
+    for compiled in [false, true] {
+        if compiled {
+            subapp.compile();
+        }
+
+        assert_eq!(
+            subapp.allowed_methods("/items"),
+            Some(vec![
+                Method::Get,
+                Method::Post,
+                Method::Head,
+                Method::Options
+            ])
+        );
+        assert_eq!(
+            subapp.allowed_methods("/items/1"),
+            Some(vec![Method::Delete, Method::Options])
+        );
+        assert_eq!(subapp.allowed_methods("/any"), None);
+        assert_eq!(subapp.allowed_methods("/missing"), None);
+    }
+}
+
+#[test]
+fn test_head_routes() {
+    let subapp: SubApp<()> = SubApp::new()
+        .with_route("/any", handler)
+        .with_method_route(Method::Head, "/any", handler)
+        .with_get("/items", handler);
+
+    // Routes registered for `HEAD` requests are preferred to earlier routes which accept every method
+    let request = Request::new(Method::Head, "/any");
+    assert!(!is_implicit_head(&request, &[], &subapp));
+    assert!(std::ptr::eq(
+        get_handler(&request, &[], &subapp).unwrap(),
+        &subapp.routes[1]
+    ));
+
+    // Otherwise they are answered by the `GET` handler
+    assert!(is_implicit_head(
+        &Request::new(Method::Head, "/items"),
+        &[],
+        &subapp
+    ));
+    assert!(!is_implicit_head(
+        &Request::new(Method::Get, "/items"),
+        &[],
+        &subapp
+    ));
+}
+
+#[test]
+fn test_named_params() {
+    let route = "/users/:id/posts/:post_id".to_string();
+
+    assert!(route.route_matches("/users/1234/posts/5"));
+    assert!(!route.route_matches("/users/1234/posts"));
+    assert!(!route.route_matches("/users//posts/5"));
+    assert!(!route.route_matches("/users/1234/posts/5/comments"));
+
+    let params = route.route_params("/users/john%20smith/posts/5").unwrap();
+
+    assert_eq!(
+        params,
+        Params::new()
+            .with_param("id", "john smith")
+            .with_param("post_id", "5")
+    );
+    assert_eq!(params.get("id"), Some("john smith"));
+    assert_eq!(params.parse::<u32>("post_id"), Some(5));
+    assert_eq!(params.parse::<u32>("id"), None);
+    assert_eq!(params.get("missing"), None);
+
+    // Segments beginning with `:` are always parameters, so literal ones are matched with a wildcard
+    assert!("/files/:latest".to_string().route_matches("/files/1.0"));
+    assert!("/files/*latest".to_string().route_matches("/files/:latest"));
+}
+
+#[test]
+fn test_wildcard_params() {
+    let route = "/static/*".to_string();
+
+    assert_eq!(
+        route.route_params("/static/css/style.css"),
+        Some(Params::new().with_wildcard("css/style.css"))
+    );
+    assert_eq!(route.route_params("/other"), None);
+
+    let route = "/users/:id/files/*".to_string();
+
+    assert_eq!(
+        route.route_params("/users/1/files/a/b%20c"),
+        Some(Params::new().with_param("id", "1").with_wildcard("a/b%20c"))
+    );
+
+    let route = "/blog/*/comments".to_string();
+
+    assert_eq!(
+        route.route_params("/blog/post/comments"),
+        Some(Params::new())
+    );
+}
+
+/// Builds an app with a large number of routes, along with a set of paths to look up.
+fn large_route_table() -> (SubApp<()>, Vec<String>) {
+    let mut subapp: SubApp<()> = SubApp::new()
+        .with_route("/", handler)
+        .with_route("/static/*", handler)
+        .with_get("/api/v1/users/:id", handler);
+
+    for resource in 0..100 {
+        subapp = subapp
+            .with_get(&format!("/api/v1/resource{}", resource), handler)
+            .with_post(&format!("/api/v1/resource{}", resource), handler)
+            .with_get(&format!("/api/v1/resource{}/:id", resource), handler)
+            .with_route(&format!("/api/v1/resource{}/:id/*", resource), handler)
+            .with_route(&format!("/api/v2/resource{}*", resource), handler);
+    }
+
+    subapp = subapp.with_route("*", handler);
+
+    let paths: Vec<String> = (0..100)
+        .flat_map(|resource| {
+            [
+                format!("/api/v1/resource{}", resource),
+                format!("/api/v1/resource{}/1234", resource),
+                format!("/api/v1/resource{}/1234/comments/5", resource),
+                format!("/api/v2/resource{}/anything", resource),
+                format!("/missing/{}", resource),
+            ]
+        })
+        .chain(["/".to_string(), "/static/css/style.css".to_string()])
+        .collect();
+
+    (subapp, paths)
+}
+
+/// Finds the index of the first matching route for each path by scanning every route in turn.
+fn linear_indices(subapp: &SubApp<()>, paths: &[String]) -> Vec<Option<usize>> {
+    paths
+        .iter()
+        .map(|path| {
+            subapp
+                .routes
+                .iter()
+                .position(|route| route.route.route_matches(path))
+        })
+        .collect()
+}
+
+/// Finds the index of the first matching route for each path using the compiled route tree.
+fn tree_indices(subapp: &SubApp<()>, paths: &[String]) -> Vec<Option<usize>> {
+    paths
+        .iter()
+        .map(|path| {
+            subapp.matching_routes(path).next().map(|matched| {
+                subapp
+                    .routes
+                    .iter()
+                    .position(|route| std::ptr::eq(route, matched))
+                    .unwrap()
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_route_tree_matches_linear_scan() {
+    let (mut subapp, paths) = large_route_table();
+
+    let linear = linear_indices(&subapp, &paths);
+    subapp.compile();
+    let tree = tree_indices(&subapp, &paths);
+
+    assert_eq!(tree, linear);
+}
+
+#[test]
+#[ignore = "timing comparison, run with `cargo test --release -- --ignored`"]
+fn test_route_tree_faster_than_linear_scan() {
+    let (mut subapp, paths) = large_route_table();
+
+    let start = Instant::now();
+    let linear = linear_indices(&subapp, &paths);
+    let linear_time = start.elapsed();
+
+    subapp.compile();
+
+    let start = Instant::now();
+    let tree = tree_indices(&subapp, &paths);
+    let tree_time = start.elapsed();
+
+    println!(
+        "{} lookups over {} routes: linear scan {:?}, route tree {:?}",
+        paths.len(),
+        subapp.routes.len(),
+        linear_time,
+        tree_time
+    );
+
+    assert_eq!(tree, linear);
+    assert!(tree_time < linear_time);
+}
+
+#[test]
+fn test_try_find_directory() {
+    let directory = env!("CARGO_MANIFEST_DIR");
+
+    assert_eq!(
+        try_find_directory(directory, "/src/"),
+        Some(PathBuf::from(directory).join("src").canonicalize().unwrap())
+    );
+    assert_eq!(
+        try_find_directory(directory, ""),
+        Some(PathBuf::from(directory).canonicalize().unwrap())
+    );
+
+    assert_eq!(try_find_directory(directory, "/src"), None);
+    assert_eq!(try_find_directory(directory, "/Cargo.toml/"), None);
+    assert_eq!(try_find_directory(directory, "/missing/"), None);
+    assert_eq!(try_find_directory(directory, "/src/../"), None);
+    assert_eq!(try_find_directory(directory, "/src/%2E%2E/"), None);
+}
@@ -0,0 +1,196 @@
+#![allow(unused_imports)]
+use crate::http::{Request, Response, StatusCode};
+use crate::shutdown::ShutdownSummary;
+use crate::App;
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(not(feature = "tokio"))]
+#[test]
+fn test_shutdown_drains_connections() {
+    let (shutdown, receiver) = std::sync::mpsc::channel();
+    let app: App<()> = App::new_with_config(4, ())
+        .with_shutdown(receiver)
+        .with_route("/", |_: Request, _: Arc<()>| {
+            Response::new(StatusCode::OK, "Hello")
+        })
+        .with_route("/slow", |_: Request, _: Arc<()>| {
+            thread::sleep(Duration::from_millis(500));
+            Response::new(StatusCode::OK, "Slow")
+        });
+
+    let app_thread = thread::spawn(move || app.run("127.0.0.1:48615").unwrap());
+
+    let mut idle = connect("127.0.0.1:48615");
+    idle.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    assert!(read_until(&mut idle, "Hello").ends_with("Hello"));
+
+    let mut busy = connect("127.0.0.1:48615");
+    busy.write_all(b"GET /slow HTTP/1.1\r\n\r\n").unwrap();
+    thread::sleep(Duration::from_millis(100));
+
+    shutdown.send(()).unwrap();
+
+    // The idle connection is closed straight away, while the request in progress is allowed to finish
+    let start = Instant::now();
+    assert!(read_until(&mut idle, "").is_empty());
+    assert!(start.elapsed() < Duration::from_millis(300));
+    assert!(read_until(&mut busy, "").ends_with("Slow"));
+
+    let summary = app_thread.join().unwrap();
+    assert_summary(summary, 1, 1, 0);
+}
+
+#[cfg(not(feature = "tokio"))]
+#[test]
+fn test_shutdown_grace_period() {
+    let (shutdown, receiver) = std::sync::mpsc::channel();
+    let app: App<()> = App::new_with_config(2, ())
+        .with_shutdown(receiver)
+        .with_shutdown_timeout(Duration::from_millis(100))
+        .with_route("/slow", |_: Request, _: Arc<()>| {
+            thread::sleep(Duration::from_millis(1000));
+            Response::new(StatusCode::OK, "Slow")
+        });
+
+    let app_thread = thread::spawn(move || app.run("127.0.0.1:48616").unwrap());
+
+    let mut busy = connect("127.0.0.1:48616");
+    busy.write_all(b"GET /slow HTTP/1.1\r\n\r\n").unwrap();
+    thread::sleep(Duration::from_millis(100));
+
+    shutdown.send(()).unwrap();
+
+    // The request is still in progress at the end of the grace period, so its connection is closed regardless
+    let summary = app_thread.join().unwrap();
+    assert_summary(summary, 0, 0, 1);
+    assert!(summary.duration < Duration::from_millis(500));
+    assert!(read_until(&mut busy, "").is_empty());
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_shutdown_drains_connections() {
+    use tokio_util::sync::CancellationToken;
+
+    let shutdown = CancellationToken::new();
+    let app: App<()> = App::new()
+        .with_shutdown(shutdown.clone())
+        .with_route("/", |_: Request, _: Arc<()>| async move {
+            Response::new(StatusCode::OK, "Hello")
+        })
+        .with_route("/slow", |_: Request, _: Arc<()>| async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            Response::new(StatusCode::OK, "Slow")
+        });
+
+    let app_task = tokio::spawn(async move { app.run("127.0.0.1:48617").await.unwrap() });
+
+    let clients = tokio::task::spawn_blocking(move || {
+        let mut idle = connect("127.0.0.1:48617");
+        idle.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        assert!(read_until(&mut idle, "Hello").ends_with("Hello"));
+
+        let mut busy = connect("127.0.0.1:48617");
+        busy.write_all(b"GET /slow HTTP/1.1\r\n\r\n").unwrap();
+        thread::sleep(Duration::from_millis(100));
+
+        shutdown.cancel();
+
+        // The idle connection is closed straight away, while the request in progress is allowed to finish
+        let start = Instant::now();
+        assert!(read_until(&mut idle, "").is_empty());
+        assert!(start.elapsed() < Duration::from_millis(300));
+        assert!(read_until(&mut busy, "").ends_with("Slow"));
+    });
+
+    clients.await.unwrap();
+
+    let summary = app_task.await.unwrap();
+    assert_summary(summary, 1, 1, 0);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_shutdown_grace_period() {
+    use tokio_util::sync::CancellationToken;
+
+    let shutdown = CancellationToken::new();
+    let app: App<()> = App::new()
+        .with_shutdown(shutdown.clone())
+        .with_shutdown_timeout(Duration::from_millis(100))
+        .with_route("/slow", |_: Request, _: Arc<()>| async move {
+            tokio::time::sleep(Duration::from_millis(1000)).await;
+            Response::new(StatusCode::OK, "Slow")
+        });
+
+    let app_task = tokio::spawn(async move { app.run("127.0.0.1:48618").await.unwrap() });
+
+    let mut busy = tokio::task::spawn_blocking(|| {
+        let mut busy = connect("127.0.0.1:48618");
+        busy.write_all(b"GET /slow HTTP/1.1\r\n\r\n").unwrap();
+        busy
+    })
+    .await
+    .unwrap();
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    shutdown.cancel();
+
+    // The request is still in progress at the end of the grace period, so its connection is closed regardless
+    let summary = app_task.await.unwrap();
+    assert_summary(summary, 0, 0, 1);
+    assert!(summary.duration < Duration::from_millis(500));
+
+    let response = tokio::task::spawn_blocking(move || read_until(&mut busy, ""))
+        .await
+        .unwrap();
+    assert!(response.is_empty());
+}
+
+/// Connects to the server, retrying until it has started.
+fn connect(addr: &str) -> TcpStream {
+    for _ in 0..50 {
+        if let Ok(stream) = TcpStream::connect(addr) {
+            stream
+                .set_read_timeout(Some(Duration::from_secs(2)))
+                .unwrap();
+
+            return stream;
+        }
+
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    panic!("failed to connect to the server");
+}
+
+/// Reads from the stream until the data ends with the given string, or until the connection is closed if the string
+///   is empty.
+fn read_until(stream: &mut TcpStream, end: &str) -> String {
+    let mut data = Vec::new();
+    let mut buf = [0; 1024];
+
+    loop {
+        match stream.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => data.extend_from_slice(&buf[..n]),
+        }
+
+        if !end.is_empty() && data.ends_with(end.as_bytes()) {
+            break;
+        }
+    }
+
+    String::from_utf8(data).unwrap()
+}
+
+fn assert_summary(summary: ShutdownSummary, idle: usize, drained: usize, forced: usize) {
+    assert_eq!(summary.idle_connections, idle);
+    assert_eq!(summary.drained_connections, drained);
+    assert_eq!(summary.forced_connections, forced);
+}
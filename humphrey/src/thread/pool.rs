@@ -102,10 +102,14 @@ impl ThreadPool {
         self.started = true;
     }
 
-    /// Stops the thread pool.
+    /// Stops the thread pool, shutting down each thread once it has finished its current task.
     pub fn stop(&mut self) {
         self.recovery_thread = None;
-        self.tx.send(Message::Shutdown).unwrap();
+
+        for _ in 0..self.thread_count {
+            let _ = self.tx.send(Message::Shutdown);
+        }
+
         self.monitor = None;
         self.started = false;
     }
@@ -2,27 +2,52 @@
 
 #![allow(clippy::new_without_default)]
 
+use crate::http::address::{is_trusted, Address, Cidr};
+use crate::http::body::{pump_body, BodyDecoder, RequestBody};
+use crate::http::compression::Compression;
 use crate::http::cors::Cors;
 use crate::http::date::DateTime;
 use crate::http::headers::HeaderType;
-use crate::http::method::Method;
+use crate::http::method::{join_methods, Method};
 use crate::http::request::{Request, RequestError};
 use crate::http::response::Response;
 use crate::http::status::StatusCode;
 use crate::krauss::wildcard_match;
+use crate::listener::{Listener, PreboundListener};
 use crate::monitor::event::{Event, EventType};
 use crate::monitor::MonitorConfig;
-use crate::route::{Route, RouteHandler, SubApp};
+use crate::route::{Handler, Route, RouteHandler, SubApp};
+use crate::shutdown::{ConnectionTracker, ShutdownSummary, TrackedConnection};
 use crate::stream::Stream;
 
+#[cfg(feature = "http2")]
+use crate::http2;
+
+use std::future::Future;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::mpsc::{channel, unbounded_channel, Sender, UnboundedSender};
 use tokio_util::sync::CancellationToken;
 
+#[cfg(unix)]
+use crate::listener::remove_stale_socket;
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+
 #[cfg(feature = "tls")]
 use rustls::ServerConfig;
+#[cfg(feature = "tls")]
+use tokio::io::AsyncWriteExt;
+
+#[cfg(feature = "http2")]
+use std::io::Cursor;
+#[cfg(feature = "http2")]
+use tokio::io::AsyncReadExt;
 
 /// Represents the Humphrey app.
 ///
@@ -39,15 +64,32 @@ where
     state: Arc<State>,
     monitor: MonitorConfig,
     connection_condition: ConnectionCondition<State>,
+    max_body_size: Option<usize>,
+    keep_alive_timeout: Option<Duration>,
+    max_keep_alive_requests: Option<usize>,
+    trusted_proxies: Arc<Vec<Cidr>>,
+    trust_unix_sockets: bool,
+    middleware: Vec<Arc<dyn Middleware<State>>>,
     #[cfg(feature = "tls")]
     tls_config: Option<Arc<ServerConfig>>,
     #[cfg(feature = "tls")]
     force_https: bool,
     shutdown: Option<CancellationToken>,
+    shutdown_timeout: Duration,
 }
 
+/// The default amount of time an idle connection is kept open waiting for another request.
+pub const DEFAULT_KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The default amount of time requests in progress are given to finish when the app is shut down.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The number of body chunks which can be read ahead of a streaming handler.
+const BODY_CHANNEL_CAPACITY: usize = 4;
+
 /// Represents a function able to calculate whether a connection will be accepted.
-pub type ConnectionCondition<State> = fn(&mut TcpStream, Arc<State>) -> bool;
+/// The stream is `None` for connections over a Unix domain socket, which have no TCP stream.
+pub type ConnectionCondition<State> = fn(Option<&mut TcpStream>, Arc<State>) -> bool;
 
 pub use crate::handler_traits::*;
 
@@ -96,11 +138,18 @@ where
             state: Arc::new(State::default()),
             monitor: MonitorConfig::default(),
             connection_condition: |_, _| true,
+            max_body_size: None,
+            keep_alive_timeout: Some(DEFAULT_KEEP_ALIVE_TIMEOUT),
+            max_keep_alive_requests: None,
+            trusted_proxies: Arc::new(Vec::new()),
+            trust_unix_sockets: false,
+            middleware: Vec::new(),
             #[cfg(feature = "tls")]
             tls_config: None,
             #[cfg(feature = "tls")]
             force_https: false,
             shutdown: None,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
         }
     }
 
@@ -113,102 +162,130 @@ where
             state: Arc::new(state),
             monitor: MonitorConfig::default(),
             connection_condition: |_, _| true,
+            max_body_size: None,
+            keep_alive_timeout: Some(DEFAULT_KEEP_ALIVE_TIMEOUT),
+            max_keep_alive_requests: None,
+            trusted_proxies: Arc::new(Vec::new()),
+            trust_unix_sockets: false,
+            middleware: Vec::new(),
             #[cfg(feature = "tls")]
             tls_config: None,
             #[cfg(feature = "tls")]
             force_https: false,
             shutdown: None,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
         }
     }
 
     /// Runs the Humphrey app on the given socket address.
-    /// This function will only return if a fatal error is thrown such as the port being in use.
-    pub async fn run<A>(self, addr: A) -> Result<(), HumphreyError>
+    /// This function will only return if a fatal error is thrown such as the port being in use, or once the app
+    ///   has been shut down using the shutdown signal, in which case a summary of the shutdown is returned.
+    pub async fn run<A>(self, addr: A) -> Result<ShutdownSummary, HumphreyError>
     where
         A: ToSocketAddrs,
     {
-        let socket = TcpListener::bind(addr).await?;
-        let subapps = Arc::new(self.subapps);
-        let default_subapp = Arc::new(self.default_subapp);
-        let error_handler = Arc::new(self.error_handler);
+        let listener = BoundListener::Tcp(TcpListener::bind(addr).await?);
 
-        loop {
-            let shutdown = async {
-                if let Some(sd) = self.shutdown.as_ref() {
-                    sd.cancelled().await
-                } else {
-                    futures::future::pending().await
-                }
-            };
-            tokio::select! {
-                () = shutdown => { break Ok(()); }
-                s = socket.accept() => {
-                    match s {
-                        Ok((mut stream, _)) => {
-                            let cloned_state = self.state.clone();
-
-                            // Check that the client is allowed to connect
-                            if (self.connection_condition)(&mut stream, cloned_state) {
-                                let cloned_state = self.state.clone();
-                                let cloned_monitor = self.monitor.clone();
-                                let cloned_subapps = subapps.clone();
-                                let cloned_default_subapp = default_subapp.clone();
-                                let cloned_error_handler = error_handler.clone();
-
-                                cloned_monitor.send(
-                                    Event::new(EventType::ConnectionSuccess)
-                                        .with_peer_result(stream.peer_addr()),
-                                );
-
-                                // Spawn a new thread to handle the connection
-                                tokio::spawn(async move {
-                                    cloned_monitor.send(
-                                        Event::new(EventType::ThreadPoolProcessStarted)
-                                            .with_peer_result(stream.peer_addr()),
-                                    );
-
-                                    client_handler(
-                                        Stream::Tcp(stream),
-                                        cloned_subapps,
-                                        cloned_default_subapp,
-                                        cloned_error_handler,
-                                        cloned_state,
-                                        cloned_monitor,
-                                    )
-                                        .await
-                                });
-                            } else {
-                                self.monitor.send(
-                                    Event::new(EventType::ConnectionDenied)
-                                        .with_peer_result(stream.peer_addr()),
-                                );
-                            }
-                        }
-                        Err(e) => self
-                            .monitor
-                            .send(Event::new(EventType::ConnectionError).with_info(e.to_string())),
-                    }
-                }
-            };
-        }
+        self.run_bound(vec![listener]).await
     }
 
     /// Securely runs the Humphrey app on the given socket address.
-    /// This function will only return if a fatal error is thrown such as the port being in use or the TLS certificate being invalid.
+    /// This function will only return if a fatal error is thrown such as the port being in use or the TLS certificate being invalid,
+    ///   or once the app has been shut down using the shutdown signal, in which case a summary of the shutdown is returned.
     #[cfg(feature = "tls")]
-    pub async fn run_tls<A>(self, addr: A) -> Result<(), HumphreyError>
+    pub async fn run_tls<A>(self, addr: A) -> Result<ShutdownSummary, HumphreyError>
     where
         A: ToSocketAddrs,
     {
-        use tokio_rustls::TlsAcceptor;
+        let listener = BoundListener::Tls(TcpListener::bind(addr).await?);
+
+        self.run_bound(vec![listener]).await
+    }
+
+    /// Runs the Humphrey app on the given listener, which has already been bound.
+    /// This is useful when the socket is created elsewhere, for example by binding to port 0 to get an ephemeral
+    ///   port, or when it is inherited from a service manager.
+    /// This function will only return if a fatal error is thrown, or once the app has been shut down using the
+    ///   shutdown signal, in which case a summary of the shutdown is returned.
+    pub async fn run_with_listener(
+        self,
+        listener: TcpListener,
+    ) -> Result<ShutdownSummary, HumphreyError> {
+        self.run_bound(vec![BoundListener::Tcp(listener)]).await
+    }
+
+    /// Securely runs the Humphrey app on the given listener, which has already been bound.
+    /// This function will only return if a fatal error is thrown such as the TLS certificate being invalid, or once
+    ///   the app has been shut down using the shutdown signal, in which case a summary of the shutdown is returned.
+    #[cfg(feature = "tls")]
+    pub async fn run_tls_with_listener(
+        self,
+        listener: TcpListener,
+    ) -> Result<ShutdownSummary, HumphreyError> {
+        self.run_bound(vec![BoundListener::Tls(listener)]).await
+    }
+
+    /// Runs the Humphrey app on all of the given listeners at once, which have already been bound, sharing its
+    ///   routes and state between them.
+    /// This function will only return if a fatal error is thrown, or once the app has been shut down using the
+    ///   shutdown signal, in which case a summary of the shutdown is returned.
+    ///
+    /// The listeners are standard library sockets, so they are switched to non-blocking mode to be used with Tokio.
+    pub async fn run_with_listeners(
+        self,
+        listeners: Vec<PreboundListener>,
+    ) -> Result<ShutdownSummary, HumphreyError> {
+        if listeners.is_empty() {
+            return Err("No listeners were specified".into());
+        }
+
+        let listeners = listeners
+            .into_iter()
+            .map(BoundListener::from_prebound)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.run_bound(listeners).await
+    }
+
+    /// Runs the Humphrey app on all of the given listeners at once, sharing its routes and state between them.
+    /// This function will only return if a fatal error is thrown such as one of the addresses being in use, or once
+    ///   the app has been shut down using the shutdown signal, in which case a summary of the shutdown is returned.
+    pub async fn run_listeners(
+        self,
+        listeners: &[Listener],
+    ) -> Result<ShutdownSummary, HumphreyError> {
+        if listeners.is_empty() {
+            return Err("No listeners were specified".into());
+        }
+
+        let mut bound = Vec::with_capacity(listeners.len());
+
+        for listener in listeners {
+            bound.push(BoundListener::bind(listener).await?);
+        }
+
+        self.run_bound(bound).await
+    }
+
+    /// Runs the app on the given bound listeners, each of which is given its own task to accept connections.
+    async fn run_bound(
+        mut self,
+        listeners: Vec<BoundListener>,
+    ) -> Result<ShutdownSummary, HumphreyError> {
+        #[cfg(feature = "tls")]
+        let acceptor = match listeners.iter().any(BoundListener::is_tls) {
+            true => Some(self.tls_acceptor()?),
+            false => None,
+        };
+
+        self.prepare_subapps();
 
-        let socket = TcpListener::bind(addr).await?;
         let subapps = Arc::new(self.subapps);
         let default_subapp = Arc::new(self.default_subapp);
         let error_handler = Arc::new(self.error_handler);
-        let tls_config = self.tls_config.expect("TLS certificate not supplied");
 
-        if self.force_https {
+        #[cfg(feature = "tls")]
+        if self.force_https && acceptor.is_some() {
             let cloned_monitor = self.monitor.clone();
 
             tokio::spawn(async move {
@@ -216,7 +293,20 @@ where
             });
         }
 
-        let acceptor = TlsAcceptor::from(tls_config);
+        let (tx, mut rx) = unbounded_channel();
+        let mut accept_tasks = Vec::with_capacity(listeners.len());
+        let socket_paths: Vec<PathBuf> = listeners
+            .iter()
+            .filter_map(BoundListener::socket_path)
+            .collect();
+
+        for listener in listeners {
+            accept_tasks.push(tokio::spawn(listener.accept(tx.clone())));
+        }
+
+        drop(tx);
+
+        let connections = ConnectionTracker::new();
 
         loop {
             let shutdown = async {
@@ -227,67 +317,119 @@ where
                 }
             };
 
-            tokio::select! {
-                () = shutdown => { break Ok(()); }
-                 s = socket.accept() => {
-                    match s {
-                        Ok((mut sock, _)) => {
-                            let cloned_state = self.state.clone();
-
-                            // Check that the client is allowed to connect
-                            if (self.connection_condition)(&mut sock, cloned_state) {
-                                let cloned_state = self.state.clone();
-                                let cloned_subapps = subapps.clone();
-                                let cloned_default_subapp = default_subapp.clone();
-                                let cloned_error_handler = error_handler.clone();
-                                let cloned_monitor = self.monitor.clone();
-                                let cloned_acceptor = acceptor.clone();
-
-                                cloned_monitor.send(
-                                    Event::new(EventType::ConnectionSuccess)
-                                        .with_peer_result(sock.peer_addr()),
-                                );
-
-                                // Spawn a new thread to handle the connection
-                                tokio::spawn(async move {
-                                    cloned_monitor.send(
-                                        Event::new(EventType::ThreadPoolProcessStarted)
-                                            .with_peer_result(sock.peer_addr()),
-                                    );
-
-                                    match cloned_acceptor.accept(sock).await {
-                                        Ok(tls_stream) => {
-                                            let stream = Stream::Tls(tls_stream);
-
-                                            client_handler(
-                                                stream,
-                                                cloned_subapps,
-                                                cloned_default_subapp,
-                                                cloned_error_handler,
-                                                cloned_state,
-                                                cloned_monitor,
-                                            )
-                                                .await
-                                        }
-                                        Err(e) => cloned_monitor.send(
-                                            Event::new(EventType::ConnectionError).with_info(e.to_string()),
-                                        ),
-                                    }
-                                });
-                            } else {
-                                self.monitor.send(
-                                    Event::new(EventType::ConnectionDenied)
-                                        .with_peer_result(sock.peer_addr()),
-                                );
-                            }
-                        }
-                        Err(e) => self
+            let incoming = tokio::select! {
+                () = shutdown => { break; }
+                incoming = rx.recv() => match incoming {
+                    Some(incoming) => incoming,
+                    None => break,
+                }
+            };
+
+            let mut incoming = match incoming {
+                Ok(incoming) => incoming,
+                Err(e) => {
+                    self.monitor
+                        .send(Event::new(EventType::ConnectionError).with_info(e.to_string()));
+
+                    continue;
+                }
+            };
+
+            // Check that the client is allowed to connect
+            let allowed = (self.connection_condition)(incoming.tcp_stream(), self.state.clone());
+
+            if !allowed {
+                self.monitor.send(
+                    Event::new(EventType::ConnectionDenied).with_peer_result(incoming.peer_addr()),
+                );
+
+                continue;
+            }
+
+            #[cfg(feature = "tls")]
+            let cloned_acceptor = acceptor.clone();
+
+            let context = ConnectionContext {
+                subapps: subapps.clone(),
+                default_subapp: default_subapp.clone(),
+                error_handler: error_handler.clone(),
+                state: self.state.clone(),
+                monitor: self.monitor.clone(),
+                max_body_size: self.max_body_size,
+                keep_alive_timeout: self.keep_alive_timeout,
+                max_keep_alive_requests: self.max_keep_alive_requests,
+                trusted_proxies: self.trusted_proxies.clone(),
+                trust_unix_sockets: self.trust_unix_sockets,
+                connection: connections.register(),
+            };
+            let closing = connections.closing();
+
+            context.monitor.send(
+                Event::new(EventType::ConnectionSuccess).with_peer_result(incoming.peer_addr()),
+            );
+
+            // Spawn a new thread to handle the connection
+            tokio::spawn(async move {
+                context.monitor.send(
+                    Event::new(EventType::ThreadPoolProcessStarted)
+                        .with_peer_result(incoming.peer_addr()),
+                );
+
+                let handler = async move {
+                    #[cfg(feature = "tls")]
+                    let stream = incoming.into_stream(cloned_acceptor.as_ref()).await;
+                    #[cfg(not(feature = "tls"))]
+                    let stream = incoming.into_stream().await;
+
+                    match stream {
+                        Ok(stream) => client_handler(stream, context).await,
+                        Err(e) => context
                             .monitor
                             .send(Event::new(EventType::ConnectionError).with_info(e.to_string())),
                     }
+                };
+
+                // Connections still open at the end of the shutdown's grace period are dropped
+                tokio::select! {
+                    () = handler => (),
+                    () = closing.cancelled() => (),
                 }
-            }
+            });
+        }
+
+        // Stop accepting connections before draining the ones which are already open
+        for task in accept_tasks {
+            task.abort();
+            let _ = task.await;
+        }
+
+        for path in socket_paths {
+            let _ = std::fs::remove_file(path);
         }
+
+        Ok(connections.drain(self.shutdown_timeout).await)
+    }
+
+    /// Creates the TLS acceptor for the app's secure listeners.
+    #[cfg(feature = "tls")]
+    fn tls_acceptor(&self) -> Result<tokio_rustls::TlsAcceptor, HumphreyError> {
+        let tls_config = self
+            .tls_config
+            .clone()
+            .ok_or("TLS certificate not supplied")?;
+
+        // Offer HTTP/2 to clients which support it, falling back to HTTP/1.1 for those which do not
+        #[cfg(feature = "http2")]
+        let tls_config = match tls_config.alpn_protocols.is_empty() {
+            true => {
+                let mut config = (*tls_config).clone();
+                config.alpn_protocols = vec![http2::ALPN_PROTOCOL.to_vec(), b"http/1.1".to_vec()];
+                Arc::new(config)
+            }
+            false => tls_config,
+        };
+
+        Ok(tokio_rustls::TlsAcceptor::from(tls_config))
     }
 
     /// Sets the default state for the server.
@@ -338,6 +480,63 @@ where
         self
     }
 
+    /// Adds a route and associated handler to the server which only matches requests with the given method.
+    /// Routes can include wildcards, for example `/blog/*`.
+    ///
+    /// Requests to the route with other methods are rejected with 405 Method Not Allowed,
+    ///   unless another handler for the route accepts them.
+    pub fn with_method_route<T>(mut self, method: Method, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.default_subapp = self
+            .default_subapp
+            .with_method_route(method, route, handler);
+        self
+    }
+
+    /// Adds a route and associated handler to the server which only matches `GET` requests.
+    /// `HEAD` requests to the route are also answered by the handler, with the body removed, unless a route for the
+    ///   path was added for the `HEAD` method with `with_method_route`.
+    pub fn with_get<T>(self, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.with_method_route(Method::Get, route, handler)
+    }
+
+    /// Adds a route and associated handler to the server which only matches `POST` requests.
+    pub fn with_post<T>(self, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.with_method_route(Method::Post, route, handler)
+    }
+
+    /// Adds a route and associated handler to the server which only matches `PUT` requests.
+    pub fn with_put<T>(self, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.with_method_route(Method::Put, route, handler)
+    }
+
+    /// Adds a route and associated handler to the server which only matches `PATCH` requests.
+    pub fn with_patch<T>(self, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.with_method_route(Method::Patch, route, handler)
+    }
+
+    /// Adds a route and associated handler to the server which only matches `DELETE` requests.
+    pub fn with_delete<T>(self, route: &str, handler: T) -> Self
+    where
+        T: RequestHandler<State> + 'static,
+    {
+        self.with_method_route(Method::Delete, route, handler)
+    }
+
     /// Adds a path-aware route and associated handler to the server.
     /// Routes can include wildcards, for example `/blog/*`.
     /// Will also pass the route to the handler at runtime.
@@ -349,6 +548,17 @@ where
         self
     }
 
+    /// Adds a streaming route and associated handler to the server.
+    /// Routes can include wildcards, for example `/upload/*`.
+    /// Instead of being read into memory, the request body is passed to the handler to be read as it arrives.
+    pub fn with_streaming_route<T>(mut self, route: &str, handler: T) -> Self
+    where
+        T: StreamingRequestHandler<State> + 'static,
+    {
+        self.default_subapp = self.default_subapp.with_streaming_route(route, handler);
+        self
+    }
+
     /// Adds a WebSocket route and associated handler to the server.
     /// Routes can include wildcards, for example `/ws/*`.
     /// The handler is passed the stream, state, and the request which triggered its calling.
@@ -374,11 +584,86 @@ where
 
     /// Sets the connection condition, a function which decides whether to accept the connection.
     /// For example, this could be used for implementing whitelists and blacklists.
+    /// Connections over a Unix domain socket are also checked, but are given no TCP stream.
     pub fn with_connection_condition(mut self, condition: ConnectionCondition<State>) -> Self {
         self.connection_condition = condition;
         self
     }
 
+    /// Sets the keep-alive timeout, the amount of time an idle connection is kept open waiting for another request.
+    /// Idle connections are closed without a response. Defaults to five seconds, and `None` disables the timeout.
+    pub fn with_keep_alive_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.keep_alive_timeout = timeout;
+        self
+    }
+
+    /// Sets the maximum number of requests which can be served on one connection.
+    /// The connection is closed after the response to the last request. Defaults to no maximum.
+    pub fn with_max_keep_alive_requests(mut self, max_requests: Option<usize>) -> Self {
+        self.max_keep_alive_requests = max_requests;
+        self
+    }
+
+    /// Sets the trusted proxies, the ranges of addresses from which forwarding headers such as `X-Forwarded-For`
+    ///   are honoured when finding the origin address of a request. Defaults to none, so the headers are ignored.
+    ///
+    /// Peers connected over a Unix domain socket have no address, so are only trusted if `with_trust_unix_sockets`
+    ///   is enabled.
+    pub fn with_trusted_proxies(mut self, proxies: Vec<Cidr>) -> Self {
+        self.trusted_proxies = Arc::new(proxies);
+        self
+    }
+
+    /// Sets whether peers connected over a Unix domain socket are trusted to forward the origin address of requests,
+    ///   like the trusted proxies. This should only be enabled if the sockets can only be connected to by a proxy.
+    ///   Defaults to `false`.
+    pub fn with_trust_unix_sockets(mut self, trust: bool) -> Self {
+        self.trust_unix_sockets = trust;
+        self
+    }
+
+    /// Sets the maximum size of request bodies in bytes, which can be overridden for individual routes.
+    /// Requests with larger bodies are rejected with 413 Payload Too Large. Defaults to no maximum.
+    pub fn with_max_body_size(mut self, max_body_size: Option<usize>) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Sets the maximum size of the request body in bytes for the specified route, overriding the app's maximum.
+    pub fn with_max_body_size_config(mut self, route: &str, max_body_size: usize) -> Self {
+        self.default_subapp = self
+            .default_subapp
+            .with_max_body_size_config(route, max_body_size);
+        self
+    }
+
+    /// Adds middleware which runs for every request to the app, before any sub-app or route middleware.
+    pub fn with_middleware<T>(mut self, middleware: T) -> Self
+    where
+        T: Middleware<State> + 'static,
+    {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Enables compression of responses with the given configuration.
+    ///
+    /// Responses are compressed after every other middleware has run, so the body which is compressed is the one
+    ///   which would otherwise have been sent.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.middleware.insert(0, Arc::new(compression));
+        self
+    }
+
+    /// Adds middleware which runs for requests to a given route, after the app's middleware.
+    pub fn with_route_middleware<T>(mut self, route: &str, middleware: T) -> Self
+    where
+        T: Middleware<State> + 'static,
+    {
+        self.default_subapp = self.default_subapp.with_route_middleware(route, middleware);
+        self
+    }
+
     /// Sets the CORS configuration for the app.
     ///
     /// This overrides the CORS configuration for existing and future individual routes.
@@ -452,6 +737,29 @@ where
         self
     }
 
+    /// Sets the amount of time requests in progress are given to finish when the app is shut down.
+    /// Idle connections are closed immediately, and connections still handling requests at the end of this time
+    ///   are closed regardless. Defaults to 30 seconds.
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Prepares the sub-apps to be run by adding the app's middleware to them and compiling their routes
+    ///   so they can be looked up quickly.
+    fn prepare_subapps(&mut self) {
+        for subapp in self
+            .subapps
+            .iter_mut()
+            .chain(std::iter::once(&mut self.default_subapp))
+        {
+            subapp
+                .middleware
+                .splice(0..0, self.middleware.iter().cloned());
+            subapp.compile();
+        }
+    }
+
     /// Gets a reference to the app's state.
     /// This should only be used in the main thread, as the state is passed to request handlers otherwise.
     pub fn get_state(&self) -> Arc<State> {
@@ -459,18 +767,54 @@ where
     }
 }
 
+/// The app's routes, state and settings, given to the connection handler along with each connection.
+pub(crate) struct ConnectionContext<State> {
+    /// The host sub-apps of the app.
+    pub(crate) subapps: Arc<Vec<SubApp<State>>>,
+    /// The sub-app used when no host sub-app matches the request.
+    pub(crate) default_subapp: Arc<SubApp<State>>,
+    /// The function used to generate error pages.
+    pub(crate) error_handler: Arc<ErrorHandler>,
+    /// The state of the app.
+    pub(crate) state: Arc<State>,
+    /// The monitor to which events are sent.
+    pub(crate) monitor: MonitorConfig,
+    /// The maximum size of a request body, unless overridden by the route.
+    pub(crate) max_body_size: Option<usize>,
+    /// The time an idle connection is kept open waiting for another request.
+    pub(crate) keep_alive_timeout: Option<Duration>,
+    /// The maximum number of requests served over a single connection.
+    pub(crate) max_keep_alive_requests: Option<usize>,
+    /// The ranges of addresses whose forwarding headers are trusted.
+    pub(crate) trusted_proxies: Arc<Vec<Cidr>>,
+    /// Whether peers connected over a Unix domain socket are trusted to forward the origin address.
+    pub(crate) trust_unix_sockets: bool,
+    /// The connection's entry in the app's connection tracker, used to shut down gracefully.
+    pub(crate) connection: TrackedConnection,
+}
+
 /// Handles a connection with a client.
-/// The connection will be opened upon the first request and closed as soon as a request is
-///   received without the `Connection: Keep-Alive` header.
-#[allow(clippy::too_many_arguments)]
-async fn client_handler<State>(
-    mut stream: Stream,
-    subapps: Arc<Vec<SubApp<State>>>,
-    default_subapp: Arc<SubApp<State>>,
-    error_handler: Arc<ErrorHandler>,
-    state: Arc<State>,
-    monitor: MonitorConfig,
-) {
+/// The connection is kept open between requests unless the client asks for it to be closed, as determined by
+///   `Request::is_keep_alive`, until it is idle for the keep-alive timeout or the maximum number of requests
+///   has been served. Pipelined requests are read from the same buffer, so none of their data is lost.
+async fn client_handler<State>(stream: Stream, context: ConnectionContext<State>)
+where
+    State: Send + Sync + 'static,
+{
+    let ConnectionContext {
+        subapps,
+        default_subapp,
+        error_handler,
+        state,
+        monitor,
+        max_body_size,
+        keep_alive_timeout,
+        max_keep_alive_requests,
+        trusted_proxies,
+        trust_unix_sockets,
+        connection,
+    } = &context;
+
     let addr = if let Ok(addr) = stream.peer_addr() {
         addr
     } else {
@@ -479,9 +823,72 @@ async fn client_handler<State>(
         return;
     };
 
+    // Peers connected over a Unix domain socket are identified by the path of the socket, and have no address to
+    //   match against the trusted proxies
+    let unix_socket = stream.unix_socket();
+    let tls = stream.is_tls();
+    let peer_trusted = if stream.is_unix() {
+        *trust_unix_sockets
+    } else {
+        is_trusted(&addr.ip(), trusted_proxies)
+    };
+
+    // The reader persists between requests, since it may have buffered the start of the next request
+    let mut reader = BufReader::new(stream);
+    let mut requests_served: usize = 0;
+
     loop {
-        // Parses the request from the stream
-        let request = Request::from_stream(&mut stream, addr).await;
+        // Later requests must start within the keep-alive timeout, and idle connections are closed quietly, as are
+        //   those waiting for a request when the app begins shutting down
+        if reader.buffer().is_empty() {
+            let request_timeout = match requests_served {
+                0 => None,
+                _ => *keep_alive_timeout,
+            };
+
+            let next_request = async {
+                let buf = match request_timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, reader.fill_buf()).await.ok(),
+                    None => Some(reader.fill_buf().await),
+                };
+
+                buf.is_some_and(|buf| buf.is_ok_and(|buf| !buf.is_empty()))
+            };
+
+            tokio::select! {
+                () = connection.draining() => break,
+                started = next_request => if !started { break },
+            }
+        }
+
+        // HTTP/2 connections are handed over along with any of their data which has already been received
+        #[cfg(feature = "http2")]
+        let mut head: Vec<u8> = Vec::new();
+        #[cfg(feature = "http2")]
+        if requests_served == 0 && http2::is_http2(&mut reader, &mut head).await {
+            let (read, write) = tokio::io::split(reader);
+            let io = tokio::io::join(Cursor::new(head).chain(read), write);
+
+            return http2::serve_connection(io, addr, unix_socket, peer_trusted, tls, context)
+                .await;
+        }
+
+        // Parses the start line and headers of the request, leaving the body to be read once the route is known
+        #[cfg(feature = "http2")]
+        let request =
+            Request::head_from_reader(&mut head.as_slice().chain(&mut reader), addr).await;
+        #[cfg(not(feature = "http2"))]
+        let request = Request::head_from_reader(&mut reader, addr).await;
+
+        connection.start_request();
+
+        let request = request.map(|mut request| {
+            request.address =
+                Address::from_forwarded(&request.headers, addr, trusted_proxies, peer_trusted);
+            request.address.unix_socket = unix_socket.clone();
+            request.address.tls = tls;
+            request
+        });
 
         let cloned_state = state.clone();
 
@@ -490,73 +897,120 @@ async fn client_handler<State>(
             if req.headers.get(&HeaderType::Upgrade) == Some("websocket") {
                 monitor.send(Event::new(EventType::WebsocketConnectionRequested).with_peer(addr));
 
-                call_websocket_handler(req, &subapps, &default_subapp, cloned_state, stream).await;
+                call_websocket_handler(
+                    req,
+                    subapps,
+                    default_subapp,
+                    cloned_state,
+                    reader.into_inner(),
+                )
+                .await;
 
                 monitor.send(Event::new(EventType::WebsocketConnectionClosed).with_peer(addr));
                 break;
             }
         }
 
-        // Get the keep alive information from the request before it is consumed by the handler
-        let keep_alive = if let Ok(request) = &request {
-            if let Some(connection) = request.headers.get(&HeaderType::Connection) {
-                connection.to_ascii_lowercase() == "keep-alive"
-            } else {
-                false
+        // HEAD requests are answered by a route registered for them if there is one, and otherwise by the GET handler,
+        //   with the body removed from the response either way
+        let is_head = matches!(&request, Ok(request) if request.method == Method::Head);
+        let request = request.map(|mut request| {
+            if is_implicit_head(&request, subapps, default_subapp) {
+                request.method = Method::Get;
             }
-        } else {
-            false
-        };
 
-        // Generate the response based on the handlers
-        let response = match &request {
-            Ok(request) if request.method == Method::Options => {
-                let handler = get_handler(request, &subapps, &default_subapp);
-
-                match handler {
-                    Some(handler) => {
-                        let mut response = Response::empty(StatusCode::NoContent)
-                            .with_header(HeaderType::Date, DateTime::now().to_string())
-                            .with_header(HeaderType::Server, "Humphrey")
-                            .with_header(
-                                HeaderType::Connection,
-                                match keep_alive {
-                                    true => "Keep-Alive",
-                                    false => "Close",
-                                },
-                            );
-
-                        handler.cors.set_headers(&mut response.headers);
+            request
+        });
+
+        let handler = request
+            .as_ref()
+            .ok()
+            .and_then(|request| get_handler(request, subapps, default_subapp));
+        let max_body_size = handler
+            .and_then(|handler| handler.max_body_size)
+            .or(*max_body_size);
+
+        // Capture the parameters from the path using the matched route
+        let request = request.map(|mut request| {
+            if let Some(params) =
+                handler.and_then(|handler| handler.route.route_params(&request.uri))
+            {
+                request.params = params;
+            }
 
-                        response
-                    }
-                    None => error_handler(StatusCode::NotFound),
+            request
+        });
+
+        // Read the body of the request, unless the route streams it to the handler
+        let mut decoder: Option<BodyDecoder> = None;
+        let request = match request {
+            Ok(mut request) => match handler.map(|handler| &handler.handler) {
+                Some(Handler::Streaming(_)) if request.method != Method::Options => {
+                    BodyDecoder::new(&request.headers, max_body_size).map(|body_decoder| {
+                        decoder = Some(body_decoder);
+                        request
+                    })
                 }
-            }
+                _ => request
+                    .read_body(&mut reader, max_body_size)
+                    .await
+                    .map(|_| request),
+            },
+            Err(e) => Err(e),
+        };
+
+        // Get the keep alive information from the request before it is consumed by the handler
+        let keep_alive = match &request {
             Ok(request) => {
-                let handler = get_handler(request, &subapps, &default_subapp);
+                request.is_keep_alive()
+                    && max_keep_alive_requests.is_none_or(|max| requests_served + 1 < max)
+                    && !connection.is_draining()
+            }
+            Err(_) => false,
+        };
 
-                let mut response = match handler {
-                    Some(handler) => {
-                        let mut response: Response =
-                            handler.handler.serve(request.clone(), state.clone()).await;
+        // Whether the route streams a request body, which must be read in full for the connection to be reused
+        let has_streamed_body = decoder.as_ref().is_some_and(BodyDecoder::has_body);
 
-                        handler.cors.set_headers(&mut response.headers);
+        // Generate the response based on the handlers
+        let mut request = request;
+        let response = match &mut request {
+            Ok(request) => {
+                let pump = decoder.take().map(|mut decoder| {
+                    let reader = &mut reader;
+
+                    // The body is read from the stream concurrently with the handler reading it
+                    move |sender| async move { pump_body(&mut decoder, reader, sender).await }
+                });
+
+                let (mut response, body_read) = generate_response(
+                    request,
+                    handler,
+                    subapps,
+                    default_subapp,
+                    **error_handler,
+                    state.clone(),
+                    pump,
+                )
+                .await;
 
-                        response
-                    }
-                    None => error_handler(StatusCode::NotFound),
-                };
+                // The connection cannot be reused if the handler did not read the whole request body
+                if has_streamed_body && !body_read {
+                    response.headers.remove(HeaderType::Connection);
+                    response.headers.add(HeaderType::Connection, "Close");
+                }
 
                 // Automatically generate required headers
                 match response.headers.get_mut(HeaderType::Connection) {
                     Some(_) => (),
                     None => {
-                        if let Some(connection) = &request.headers.get(&HeaderType::Connection) {
-                            response.headers.add(HeaderType::Connection, connection);
-                        } else {
-                            response.headers.add(HeaderType::Connection, "Close");
-                        }
+                        response.headers.add(
+                            HeaderType::Connection,
+                            match keep_alive {
+                                true => "Keep-Alive",
+                                false => "Close",
+                            },
+                        );
                     }
                 }
 
@@ -578,33 +1032,58 @@ async fn client_handler<State>(
 
                 match response.headers.get_mut(HeaderType::ContentLength) {
                     Some(_) => (),
-                    None => {
-                        response
-                            .headers
-                            .add(HeaderType::ContentLength, response.body.len().to_string());
-                    }
+                    None if !response.status_code.allows_body() => (),
+                    None => match response.body_length() {
+                        Some(length) => {
+                            response
+                                .headers
+                                .add(HeaderType::ContentLength, length.to_string());
+                        }
+                        None if request.version == "HTTP/1.0" => {
+                            // Chunked encoding is unavailable, so the end of the body is marked by closing the connection
+                            response.headers.remove(HeaderType::Connection);
+                            response.headers.add(HeaderType::Connection, "Close");
+                        }
+                        None => {
+                            response
+                                .headers
+                                .add(HeaderType::TransferEncoding, "chunked");
+                        }
+                    },
                 }
 
                 // Set HTTP version
                 response.version = request.version.clone();
 
+                // Remove the body of responses to HEAD requests, keeping the headers which describe it
+                if is_head {
+                    response.body.clear();
+                    response.take_stream();
+                }
+
                 response
             }
             Err(e) => match e {
                 RequestError::Request => error_handler(StatusCode::BadRequest),
                 RequestError::Timeout => error_handler(StatusCode::RequestTimeout),
+                RequestError::TooLarge => error_handler(StatusCode::RequestEntityTooLarge),
                 RequestError::Disconnected => return,
                 RequestError::Stream => {
                     return monitor.send(Event::new(EventType::RequestServedError))
                 }
-            },
+            }
+            .with_header(HeaderType::Connection, "Close"),
         };
 
         // Write the response to the stream
         let status = response.status_code;
-        let response_bytes: Vec<u8> = response.into();
+        let keep_alive = keep_alive
+            && !response
+                .headers
+                .get(HeaderType::Connection)
+                .is_some_and(|connection| connection.eq_ignore_ascii_case("close"));
 
-        if let Err(e) = stream.write_all(&response_bytes).await {
+        if let Err(e) = response.write_to(reader.get_mut()).await {
             monitor.send(
                 Event::new(EventType::RequestServedError)
                     .with_peer(addr)
@@ -644,8 +1123,10 @@ async fn client_handler<State>(
             }
         }
 
-        // If the request specified to keep the connection open, respect this
-        if !keep_alive {
+        requests_served += 1;
+
+        // If the request specified to keep the connection open, respect this unless the app is shutting down
+        if !connection.finish_request() || !keep_alive {
             break;
         }
 
@@ -655,40 +1136,187 @@ async fn client_handler<State>(
     monitor.send(Event::new(EventType::ConnectionClosed).with_peer(addr));
 }
 
-/// Gets the correct handler for the given request.
-pub(crate) fn get_handler<'a, State>(
-    request: &'a Request,
+/// Generates the response to a request by running the middleware and the matched handler.
+///
+/// The body of the request must already have been read, unless the route streams it to the handler, in which case
+///   `pump` is called with the sender of the body's channel and must send the body while the handler runs.
+///   Alongside the response, returns whether the pump read the whole body, which is `false` if it was never called.
+///
+/// `OPTIONS` requests to a route are answered automatically, and the returned response is not yet given the
+///   headers which depend on the version of HTTP being used.
+pub(crate) async fn generate_response<State, P, F>(
+    request: &mut Request,
+    handler: Option<&RouteHandler<State>>,
+    subapps: &[SubApp<State>],
+    default_subapp: &SubApp<State>,
+    error_handler: ErrorHandler,
+    state: Arc<State>,
+    pump: Option<P>,
+) -> (Response, bool)
+where
+    P: FnOnce(Sender<std::io::Result<Vec<u8>>>) -> F,
+    F: Future<Output = bool>,
+{
+    // Find the middleware for the request, with the sub-app's middleware before the route's
+    let middleware: Vec<&Arc<dyn Middleware<State>>> = get_subapp(request, subapps, default_subapp)
+        .middleware
+        .iter()
+        .chain(
+            handler
+                .into_iter()
+                .flat_map(|handler| handler.middleware.iter()),
+        )
+        .collect();
+
+    // Run the middleware before the request is handled, stopping if any of them responds
+    let mut middleware_run = 0;
+    let mut early_response: Option<Response> = None;
+
+    for middleware in &middleware {
+        middleware_run += 1;
+
+        if let Some(response) = middleware.before(request, state.clone()).await {
+            early_response = Some(response);
+            break;
+        }
+    }
+
+    let mut body_read = pump.is_none();
+
+    let mut response = match (early_response, handler) {
+        (Some(response), _) => response,
+        (None, Some(handler)) if request.method == Method::Options => {
+            let mut response = Response::empty(StatusCode::NoContent)
+                .with_header(HeaderType::Date, DateTime::now().to_string())
+                .with_header(HeaderType::Server, "Humphrey");
+
+            handler.cors.set_headers(&mut response.headers);
+
+            // Report the methods accepted by the route, unless the CORS configuration specifies them
+            if let Some(methods) =
+                get_subapp(request, subapps, default_subapp).allowed_methods(&request.uri)
+            {
+                let methods = join_methods(&methods);
+
+                if response
+                    .headers
+                    .get(HeaderType::AccessControlAllowMethods)
+                    .is_none()
+                {
+                    response
+                        .headers
+                        .add(HeaderType::AccessControlAllowMethods, &methods);
+                }
+
+                response.headers.add(HeaderType::Allow, methods);
+            }
+
+            response
+        }
+        (None, Some(handler)) => {
+            let mut response: Response = match (&handler.handler, pump) {
+                (Handler::Buffered(handler), _) => {
+                    handler.serve(request.clone(), state.clone()).await
+                }
+                (Handler::Streaming(handler), Some(pump)) => {
+                    let (sender, receiver) = channel(BODY_CHANNEL_CAPACITY);
+                    let body = RequestBody::new(receiver);
+
+                    let (response, complete) = tokio::join!(
+                        handler.serve(request.clone(), body, state.clone()),
+                        pump(sender)
+                    );
+                    body_read = complete;
+
+                    response
+                }
+                (Handler::Streaming(_), None) => error_handler(StatusCode::InternalError),
+            };
+
+            handler.cors.set_headers(&mut response.headers);
+
+            response
+        }
+        (None, None) => {
+            match get_subapp(request, subapps, default_subapp).allowed_methods(&request.uri) {
+                Some(methods) => error_handler(StatusCode::MethodNotAllowed)
+                    .with_header(HeaderType::Allow, join_methods(&methods)),
+                None => error_handler(StatusCode::NotFound),
+            }
+        }
+    };
+
+    // Run the middleware after the response has been generated, in reverse order
+    for middleware in middleware[..middleware_run].iter().rev() {
+        middleware
+            .after(request, &mut response, state.clone())
+            .await;
+    }
+
+    (response, body_read)
+}
+
+/// Gets the sub-app which should handle the given request.
+///
+/// This is the sub-app matching the request's host if it has a route for the path, otherwise the default sub-app.
+pub(crate) fn get_subapp<'a, State>(
+    request: &Request,
     subapps: &'a [SubApp<State>],
     default_subapp: &'a SubApp<State>,
-) -> Option<&'a RouteHandler<State>> {
+) -> &'a SubApp<State> {
     // Iterate over the sub-apps and find the one which matches the host
     if let Some(host) = request.headers.get(&HeaderType::Host) {
         if let Some(subapp) = subapps
             .iter()
             .find(|subapp| wildcard_match(&subapp.host, host))
         {
-            // If the sub-app has a handler for this route, call it
-            if let Some(handler) = subapp
-                .routes // Get the routes of the sub-app
-                .iter() // Iterate over the routes
-                .find(|route| route.route.route_matches(&request.uri))
-            // Find the route that matches
-            {
-                return Some(handler);
+            // If the sub-app has a route for this path, use it
+            if subapp.has_route(&request.uri) {
+                return subapp;
             }
         }
     }
 
-    // If no sub-app was found, try to use the handler on the default sub-app
-    if let Some(handler) = default_subapp
-        .routes
-        .iter()
-        .find(|route| route.route.route_matches(&request.uri))
-    {
-        return Some(handler);
+    // If no sub-app was found, use the default sub-app
+    default_subapp
+}
+
+/// Gets the correct handler for the given request.
+///
+/// `OPTIONS` requests match a route regardless of its methods, since they are answered automatically. `HEAD` requests
+///   match a route registered for the `HEAD` method in preference to an earlier one which accepts every method.
+pub(crate) fn get_handler<'a, State>(
+    request: &Request,
+    subapps: &'a [SubApp<State>],
+    default_subapp: &'a SubApp<State>,
+) -> Option<&'a RouteHandler<State>> {
+    let subapp = get_subapp(request, subapps, default_subapp);
+
+    if request.method == Method::Head {
+        if let Some(route) = subapp
+            .matching_routes(&request.uri)
+            .find(|route| route.method_registered(&Method::Head))
+        {
+            return Some(route);
+        }
     }
 
-    None
+    // Find the first route which matches the path and accepts the method
+    subapp
+        .matching_routes(&request.uri)
+        .find(|route| request.method == Method::Options || route.method_matches(&request.method))
+}
+
+/// Returns `true` if the request is a `HEAD` request which should be answered by the `GET` handler for its path,
+///   since no route was registered for `HEAD` requests to it.
+pub(crate) fn is_implicit_head<State>(
+    request: &Request,
+    subapps: &[SubApp<State>],
+    default_subapp: &SubApp<State>,
+) -> bool {
+    request.method == Method::Head
+        && get_handler(request, subapps, default_subapp)
+            .is_none_or(|route| !route.method_registered(&Method::Head))
 }
 
 /// Calls the correct WebSocket handler for the given request.
@@ -759,7 +1387,7 @@ async fn force_https_thread(monitor: MonitorConfig) -> Result<(), Box<dyn std::e
 
 /// The default error handler for every Humphrey app.
 /// This can be overridden by using the `with_error_handler` method when building the app.
-pub(crate) fn error_handler(status_code: StatusCode) -> Response {
+pub fn error_handler(status_code: StatusCode) -> Response {
     let body = format!(
         "<html><body><h1>{} {}</h1></body></html>",
         Into::<u16>::into(status_code),
@@ -768,3 +1396,133 @@ pub(crate) fn error_handler(status_code: StatusCode) -> Response {
 
     Response::new(status_code, body.as_bytes())
 }
+
+/// A listener which has been bound to its address, ready to accept connections.
+///
+/// Unix domain sockets created by the app hold their path, so they can be removed when it shuts down.
+enum BoundListener {
+    Tcp(TcpListener),
+    #[cfg(feature = "tls")]
+    Tls(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, Option<PathBuf>),
+}
+
+/// A connection accepted by one of the app's listeners.
+enum Incoming {
+    Tcp(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl BoundListener {
+    /// Binds the listener to its address.
+    async fn bind(listener: &Listener) -> Result<Self, HumphreyError> {
+        Ok(match listener {
+            Listener::Tcp(addr) => Self::Tcp(TcpListener::bind(addr).await?),
+            #[cfg(feature = "tls")]
+            Listener::Tls(addr) => Self::Tls(TcpListener::bind(addr).await?),
+            #[cfg(unix)]
+            Listener::Unix(path) => {
+                remove_stale_socket(path)?;
+                Self::Unix(UnixListener::bind(path)?, Some(path.clone()))
+            }
+        })
+    }
+
+    /// Converts a listener which has already been bound into one which can be used with Tokio.
+    fn from_prebound(listener: PreboundListener) -> std::io::Result<Self> {
+        Ok(match listener {
+            PreboundListener::Tcp(listener) => {
+                listener.set_nonblocking(true)?;
+                Self::Tcp(TcpListener::from_std(listener)?)
+            }
+            #[cfg(feature = "tls")]
+            PreboundListener::Tls(listener) => {
+                listener.set_nonblocking(true)?;
+                Self::Tls(TcpListener::from_std(listener)?)
+            }
+            #[cfg(unix)]
+            PreboundListener::Unix(listener) => {
+                listener.set_nonblocking(true)?;
+                Self::Unix(UnixListener::from_std(listener)?, None)
+            }
+        })
+    }
+
+    /// Returns `true` if connections accepted by the listener are secured with TLS.
+    #[cfg(feature = "tls")]
+    fn is_tls(&self) -> bool {
+        matches!(self, Self::Tls(_))
+    }
+
+    /// Returns the path of the listener's Unix domain socket, or `None` if it is not a Unix domain socket.
+    fn socket_path(&self) -> Option<PathBuf> {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(_, path) => path.clone(),
+            _ => None,
+        }
+    }
+
+    /// Accepts connections until the task is aborted, sending them to the app.
+    async fn accept(self, tx: UnboundedSender<std::io::Result<Incoming>>) {
+        loop {
+            let incoming = match &self {
+                Self::Tcp(listener) => listener.accept().await.map(|(s, _)| Incoming::Tcp(s)),
+                #[cfg(feature = "tls")]
+                Self::Tls(listener) => listener.accept().await.map(|(s, _)| Incoming::Tls(s)),
+                #[cfg(unix)]
+                Self::Unix(listener, _) => listener.accept().await.map(|(s, _)| Incoming::Unix(s)),
+            };
+
+            if tx.send(incoming).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+impl Incoming {
+    /// Returns the underlying TCP stream, or `None` if the connection is not over TCP.
+    fn tcp_stream(&mut self) -> Option<&mut TcpStream> {
+        match self {
+            Self::Tcp(stream) => Some(stream),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => Some(stream),
+            #[cfg(unix)]
+            Self::Unix(_) => None,
+        }
+    }
+
+    /// Returns the socket address of the remote peer of the connection.
+    fn peer_addr(&self) -> std::io::Result<SocketAddr> {
+        match self {
+            Self::Tcp(stream) => stream.peer_addr(),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => stream.peer_addr(),
+            #[cfg(unix)]
+            Self::Unix(_) => Ok(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))),
+        }
+    }
+
+    /// Converts the connection into a stream, completing the TLS handshake if the listener is secure.
+    async fn into_stream(
+        self,
+        #[cfg(feature = "tls")] acceptor: Option<&tokio_rustls::TlsAcceptor>,
+    ) -> std::io::Result<Stream> {
+        match self {
+            Self::Tcp(stream) => Ok(Stream::Tcp(stream)),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => acceptor
+                .expect("TLS certificate not supplied")
+                .accept(stream)
+                .await
+                .map(Stream::Tls),
+            #[cfg(unix)]
+            Self::Unix(stream) => Ok(Stream::Unix(stream)),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::http::body::RequestBody;
 use crate::http::{Request, Response};
 use crate::stream::Stream;
 
@@ -120,3 +121,91 @@ where
         Box::pin(self(request, state, route))
     }
 }
+
+/// Represents a function able to handle a request whose body is streamed from the client.
+/// It is passed the request without its content, the request body to read from, and the app's state,
+///   and must return a response.
+///
+/// ## Example
+/// A streaming request handler which saves the body to a file would be as follows:
+/// ```
+/// async fn handler(_: Request, mut body: RequestBody, _: Arc<()>) -> Response {
+///     let mut file = File::create("upload.bin").await.unwrap();
+///     tokio::io::copy(&mut body, &mut file).await.unwrap();
+///
+///     Response::new(StatusCode::OK, b"Uploaded")
+/// }
+/// ```
+pub trait StreamingRequestHandler<State>: Send + Sync {
+    #[allow(missing_docs)]
+    fn serve(
+        &self,
+        request: Request,
+        body: RequestBody,
+        state: Arc<State>,
+    ) -> Pin<Box<dyn Future<Output = Response> + Send>>;
+}
+impl<F, Fut, State> StreamingRequestHandler<State> for F
+where
+    F: Fn(Request, RequestBody, Arc<State>) -> Fut + Send + Sync,
+    Fut: Future<Output = Response> + Send + 'static,
+{
+    fn serve(
+        &self,
+        request: Request,
+        body: RequestBody,
+        state: Arc<State>,
+    ) -> Pin<Box<dyn Future<Output = Response> + Send>> {
+        Box::pin(self(request, body, state))
+    }
+}
+
+/// Represents middleware which runs before and after requests are handled.
+/// Middleware can be registered for the whole app, for a sub-app, or for an individual route.
+///
+/// Before the request is handled, `before` is called with mutable access to the request. Returning a response
+///   from it short-circuits the request, so the handler and any later middleware are skipped.
+/// After the response has been generated, `after` is called with mutable access to the response, in the
+///   reverse order to `before`, for every middleware whose `before` was called.
+///
+/// ## Example
+/// A middleware which adds a header to every response would be as follows:
+/// ```
+/// struct PoweredBy;
+///
+/// impl Middleware<()> for PoweredBy {
+///     fn after<'a>(
+///         &'a self,
+///         _: &'a Request,
+///         response: &'a mut Response,
+///         _: Arc<()>,
+///     ) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+///         Box::pin(async move {
+///             response.headers.add("X-Powered-By", "Humphrey");
+///         })
+///     }
+/// }
+/// ```
+pub trait Middleware<State>: Send + Sync {
+    /// Called before the request is handled.
+    /// Returning a response skips the handler and sends the response instead.
+    fn before<'a>(
+        &'a self,
+        request: &'a mut Request,
+        state: Arc<State>,
+    ) -> Pin<Box<dyn Future<Output = Option<Response>> + Send + 'a>> {
+        let _ = (request, state);
+        Box::pin(async { None })
+    }
+
+    /// Called after the response has been generated.
+    fn after<'a>(
+        &'a self,
+        request: &'a Request,
+        response: &'a mut Response,
+        state: Arc<State>,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        let _ = (request, response, state);
+        Box::pin(async {})
+    }
+}
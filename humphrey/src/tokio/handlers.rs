@@ -4,8 +4,10 @@
 // When they are stabilised, this code will look a lot nicer.
 
 use crate::app::{error_handler, PathAwareRequestHandler, RequestHandler};
+use crate::http::conditional::Validators;
 use crate::http::headers::HeaderType;
 use crate::http::mime::MimeType;
+use crate::http::range::{serve_file_ranges, serve_ranges};
 use crate::http::{Request, Response, StatusCode};
 use crate::route::{try_find_path, LocatedPath};
 
@@ -14,7 +16,7 @@ use tokio::io::AsyncReadExt;
 
 use futures::Future;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::sync::Arc;
 
@@ -32,25 +34,13 @@ struct FileServer {
 }
 
 impl<S> RequestHandler<S> for FileServer {
-    fn serve(&self, _: Request, _: Arc<S>) -> Pin<Box<dyn Future<Output = Response> + Send>> {
+    fn serve(&self, request: Request, _: Arc<S>) -> Pin<Box<dyn Future<Output = Response> + Send>> {
         let path_buf = self.path_buf.clone();
 
         Box::pin(async move {
-            if let Ok(mut file) = File::open(&path_buf).await {
-                let mut buf = Vec::new();
-                if file.read_to_end(&mut buf).await.is_ok() {
-                    return if let Some(extension) = &path_buf.extension() {
-                        Response::new(StatusCode::OK, buf).with_header(
-                            HeaderType::ContentType,
-                            MimeType::from_extension(extension.to_str().unwrap()).to_string(),
-                        )
-                    } else {
-                        Response::new(StatusCode::OK, buf)
-                    };
-                }
-            }
-
-            error_handler(StatusCode::NotFound)
+            file_response(&request, &path_buf)
+                .await
+                .unwrap_or_else(|| error_handler(StatusCode::NotFound))
         })
     }
 }
@@ -83,21 +73,9 @@ impl<S> RequestHandler<S> for FilePathServer {
 
             let path_buf = PathBuf::from(path);
 
-            if let Ok(mut file) = File::open(&path_buf).await {
-                let mut buf = Vec::new();
-                if file.read_to_end(&mut buf).await.is_ok() {
-                    return if let Some(extension) = path_buf.extension() {
-                        Response::new(StatusCode::OK, buf).with_header(
-                            HeaderType::ContentType,
-                            MimeType::from_extension(extension.to_str().unwrap()).to_string(),
-                        )
-                    } else {
-                        Response::new(StatusCode::OK, buf)
-                    };
-                }
-            }
-
-            error_handler(StatusCode::NotFound)
+            file_response(&request, &path_buf)
+                .await
+                .unwrap_or_else(|| error_handler(StatusCode::NotFound))
         })
     }
 }
@@ -137,24 +115,9 @@ impl<S> PathAwareRequestHandler<S> for DirServer {
                 match located {
                     LocatedPath::Directory => Response::empty(StatusCode::MovedPermanently)
                         .with_header(HeaderType::Location, format!("{}/", &request.uri)),
-                    LocatedPath::File(path) => {
-                        if let Ok(mut file) = File::open(&path).await {
-                            let mut buf = Vec::new();
-                            if file.read_to_end(&mut buf).await.is_ok() {
-                                return if let Some(extension) = path.extension() {
-                                    Response::new(StatusCode::OK, buf).with_header(
-                                        HeaderType::ContentType,
-                                        MimeType::from_extension(extension.to_str().unwrap())
-                                            .to_string(),
-                                    )
-                                } else {
-                                    Response::new(StatusCode::OK, buf)
-                                };
-                            }
-                        }
-
-                        error_handler(StatusCode::InternalError)
-                    }
+                    LocatedPath::File(path) => file_response(&request, &path)
+                        .await
+                        .unwrap_or_else(|| error_handler(StatusCode::InternalError)),
                 }
             } else {
                 error_handler(StatusCode::NotFound)
@@ -163,6 +126,54 @@ impl<S> PathAwareRequestHandler<S> for DirServer {
     }
 }
 
+/// Serves the file at the given path, answering with 304 Not Modified if the client's copy is up to date,
+///   or with only the requested ranges of the file if the request has a `Range` header.
+/// Returns `None` if the file could not be read.
+async fn file_response(request: &Request, path: &Path) -> Option<Response> {
+    let mut file = File::open(path).await.ok()?;
+    let validators = file
+        .metadata()
+        .await
+        .map(|metadata| Validators::from_metadata(&metadata))
+        .unwrap_or_default();
+
+    if validators.is_not_modified(request) {
+        return Some(validators.not_modified());
+    }
+
+    // Range requests are streamed from the file, so large files are not read into memory just to send part of them
+    if request.headers.get(HeaderType::Range).is_some() {
+        let mut response = Response::empty(StatusCode::OK);
+
+        if let Some(extension) = path.extension() {
+            response.headers.add(
+                HeaderType::ContentType,
+                MimeType::from_extension(extension.to_str().unwrap()).to_string(),
+            );
+        }
+
+        validators.set_headers(&mut response.headers);
+
+        return serve_file_ranges(request, response, file, path).await.ok();
+    }
+
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).await.ok()?;
+
+    let mut response = if let Some(extension) = path.extension() {
+        Response::new(StatusCode::OK, buf).with_header(
+            HeaderType::ContentType,
+            MimeType::from_extension(extension.to_str().unwrap()).to_string(),
+        )
+    } else {
+        Response::new(StatusCode::OK, buf)
+    };
+
+    validators.set_headers(&mut response.headers);
+
+    Some(serve_ranges(request, response))
+}
+
 /// Redirects requests to the given location with status code 301.
 pub fn redirect<S>(location: &'static str) -> impl RequestHandler<S> {
     RedirectServer { location }
@@ -0,0 +1,576 @@
+//! Provides HTTP/2 support for the app, as defined in [RFC 9113](https://datatracker.ietf.org/doc/html/rfc9113).
+//!
+//! The framing, HPACK header compression and stream multiplexing are handled by the `h2` crate. Each stream is
+//!   converted into a `Request` and answered by the app's routes and middleware in the same way as an HTTP/1.1
+//!   request, so existing handlers work unchanged.
+
+use crate::app::{generate_response, get_handler, is_implicit_head, ConnectionContext};
+use crate::http::address::Address;
+use crate::http::date::DateTime;
+use crate::http::headers::{HeaderType, Headers};
+use crate::http::method::Method;
+use crate::http::params::Params;
+use crate::http::request::{Request, RequestError};
+use crate::http::response::{BodyStream, Response};
+use crate::http::status::StatusCode;
+use crate::monitor::event::{Event, EventType};
+use crate::monitor::MonitorConfig;
+use crate::route::{Handler, Route};
+use crate::stream::Stream;
+
+use bytes::Bytes;
+use h2::server::SendResponse;
+use h2::{Reason, RecvStream, SendStream};
+use http::header::{HeaderName, HeaderValue};
+
+use std::future::poll_fn;
+use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, BufReader};
+use tokio::sync::mpsc::Sender;
+
+/// The connection preface sent by clients at the start of every HTTP/2 connection.
+pub(crate) const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// The protocol identifier used to negotiate HTTP/2 over TLS with ALPN.
+#[cfg(feature = "tls")]
+pub(crate) const ALPN_PROTOCOL: &[u8] = b"h2";
+
+/// The headers which only apply to HTTP/1.1 connections, and must not be sent over HTTP/2.
+const CONNECTION_HEADERS: [&str; 5] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// The size of the buffer used when streaming a response body from a reader.
+const STREAM_BUFFER_SIZE: usize = 16384;
+
+/// The number of bytes needed to tell the preface apart from an HTTP/1.1 request, since the `PROPFIND` and
+///   `PROPPATCH` methods share their first two bytes with it.
+const PREFACE_PREFIX_LENGTH: usize = 3;
+
+/// Returns `true` if the client is using HTTP/2, either having negotiated it with ALPN, or by starting the connection
+///   with the HTTP/2 preface, as clients with prior knowledge of HTTP/2 support do over cleartext.
+///
+/// The preface is usually only looked at, so it remains in the reader's buffer. If too little of the connection has
+///   been received to tell, what has been received is moved into `head` while waiting for more, so it must be read
+///   before the rest of the reader.
+pub(crate) async fn is_http2(reader: &mut BufReader<Stream>, head: &mut Vec<u8>) -> bool {
+    #[cfg(feature = "tls")]
+    if let Stream::Tls(stream) = reader.get_ref() {
+        if stream.get_ref().1.alpn_protocol() == Some(ALPN_PROTOCOL) {
+            return true;
+        }
+    }
+
+    loop {
+        let buf = match reader.fill_buf().await {
+            Ok(buf) if !buf.is_empty() => buf,
+            _ => return false,
+        };
+
+        let matches = head
+            .iter()
+            .chain(buf)
+            .zip(PREFACE)
+            .all(|(received, expected)| received == expected);
+
+        if !matches || head.len() + buf.len() >= PREFACE_PREFIX_LENGTH {
+            return matches;
+        }
+
+        let length = buf.len();
+        head.extend_from_slice(buf);
+        reader.consume(length);
+    }
+}
+
+/// Handles an HTTP/2 connection with a client, answering each of its streams concurrently.
+/// The forwarding headers of its requests are only followed if the peer is trusted, and `tls` records whether the
+///   connection is secured with TLS.
+///
+/// The connection is closed gracefully, allowing the streams in progress to finish, once no streams have been open
+///   for the keep-alive timeout, once the maximum number of requests has been received or once the app begins
+///   shutting down.
+pub(crate) async fn serve_connection<State, S>(
+    io: S,
+    addr: SocketAddr,
+    unix_socket: Option<PathBuf>,
+    peer_trusted: bool,
+    tls: bool,
+    context: ConnectionContext<State>,
+) where
+    State: Send + Sync + 'static,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut connection = match h2::server::handshake(io).await {
+        Ok(connection) => connection,
+        Err(e) => {
+            context.monitor.send(
+                Event::new(EventType::ConnectionError)
+                    .with_peer(addr)
+                    .with_info(e.to_string()),
+            );
+
+            return;
+        }
+    };
+
+    // Every stream being answered holds a clone of this, so the connection is idle when no clones remain
+    let context = Arc::new(context);
+    let mut requests_received: usize = 0;
+    let mut closing = false;
+
+    loop {
+        // The connection must keep being polled for the open streams to make progress, so the timeout and the
+        //   app shutting down only start the shutdown of the connection
+        let idle = async {
+            match context.keep_alive_timeout {
+                Some(timeout) if !closing => tokio::time::sleep(timeout).await,
+                _ => std::future::pending().await,
+            }
+        };
+
+        let next = tokio::select! {
+            next = connection.accept() => next,
+            () = idle => {
+                if Arc::strong_count(&context) == 1 {
+                    connection.graceful_shutdown();
+                    closing = true;
+                }
+
+                continue;
+            }
+            () = context.connection.draining(), if !closing => {
+                connection.graceful_shutdown();
+                closing = true;
+
+                continue;
+            }
+        };
+
+        let (request, respond) = match next {
+            Some(Ok(stream)) => stream,
+            Some(Err(e)) => {
+                context.monitor.send(
+                    Event::new(EventType::RequestServedError)
+                        .with_peer(addr)
+                        .with_info(e.to_string()),
+                );
+
+                break;
+            }
+            None => break,
+        };
+
+        requests_received += 1;
+
+        if !closing
+            && context
+                .max_keep_alive_requests
+                .is_some_and(|max| requests_received >= max)
+        {
+            connection.graceful_shutdown();
+            closing = true;
+        }
+
+        let context = context.clone();
+        let unix_socket = unix_socket.clone();
+
+        context.connection.start_request();
+
+        tokio::spawn(async move {
+            handle_stream(
+                request,
+                respond,
+                addr,
+                unix_socket,
+                peer_trusted,
+                tls,
+                &context,
+            )
+            .await;
+
+            context.connection.finish_request();
+        });
+    }
+
+    context
+        .monitor
+        .send(Event::new(EventType::ConnectionClosed).with_peer(addr));
+}
+
+/// Answers a single stream of an HTTP/2 connection.
+async fn handle_stream<State>(
+    request: http::Request<RecvStream>,
+    mut respond: SendResponse<Bytes>,
+    addr: SocketAddr,
+    unix_socket: Option<PathBuf>,
+    peer_trusted: bool,
+    tls: bool,
+    context: &ConnectionContext<State>,
+) {
+    let (parts, mut body) = request.into_parts();
+
+    let mut request = match convert_request(&parts, addr) {
+        Ok(request) => request,
+        Err(_) => {
+            let response = (context.error_handler)(StatusCode::BadRequest);
+            let result = send_response(&mut respond, response, false).await;
+
+            return log_response(&context.monitor, addr, StatusCode::BadRequest, None, result);
+        }
+    };
+
+    request.address = Address::from_forwarded(
+        &request.headers,
+        addr,
+        &context.trusted_proxies,
+        peer_trusted,
+    );
+    request.address.unix_socket = unix_socket;
+    request.address.tls = tls;
+
+    // HEAD requests are answered by a route registered for them if there is one, and otherwise by the GET handler,
+    //   with the body removed from the response either way
+    let is_head = request.method == Method::Head;
+    if is_implicit_head(&request, &context.subapps, &context.default_subapp) {
+        request.method = Method::Get;
+    }
+
+    let handler = get_handler(&request, &context.subapps, &context.default_subapp);
+    let max_body_size = handler
+        .and_then(|handler| handler.max_body_size)
+        .or(context.max_body_size);
+
+    // Capture the parameters from the path using the matched route
+    if let Some(params) = handler.and_then(|handler| handler.route.route_params(&request.uri)) {
+        request.params = params;
+    }
+
+    let is_streaming = request.method != Method::Options
+        && matches!(
+            handler.map(|handler| &handler.handler),
+            Some(Handler::Streaming(_))
+        );
+
+    // Read the body of the request, unless the route streams it to the handler
+    let response = match is_streaming {
+        true => Ok(()),
+        false => read_body(&mut body, max_body_size)
+            .await
+            .map(|content| set_content(&mut request, content)),
+    };
+
+    let response = match response {
+        Ok(()) => {
+            let pump =
+                move |sender| async move { pump_stream(&mut body, sender, max_body_size).await };
+
+            generate_response(
+                &mut request,
+                handler,
+                &context.subapps,
+                &context.default_subapp,
+                *context.error_handler,
+                context.state.clone(),
+                is_streaming.then_some(pump),
+            )
+            .await
+            .0
+        }
+        Err(RequestError::TooLarge) => (context.error_handler)(StatusCode::RequestEntityTooLarge),
+        Err(_) => {
+            respond.send_reset(Reason::CANCEL);
+
+            return context
+                .monitor
+                .send(Event::new(EventType::RequestServedError).with_peer(addr));
+        }
+    };
+
+    let status = response.status_code;
+    let result = send_response(&mut respond, response, is_head).await;
+
+    log_response(&context.monitor, addr, status, Some(&request), result);
+}
+
+/// Converts the head of an HTTP/2 request into a `Request`, whose body is read separately.
+///
+/// The `:authority` pseudo-header is used as the `Host` header if the client did not send one, and separate
+///   `Cookie` headers are combined into one as they would be in an HTTP/1.1 request.
+fn convert_request(
+    parts: &http::request::Parts,
+    addr: SocketAddr,
+) -> Result<Request, RequestError> {
+    let method = Method::from_name(parts.method.as_str())?;
+
+    let mut headers = Headers::new();
+    let mut cookies: Vec<&str> = Vec::new();
+
+    for (name, value) in &parts.headers {
+        let value = value.to_str().map_err(|_| RequestError::Request)?;
+
+        if name == http::header::COOKIE {
+            cookies.push(value);
+        } else {
+            headers.add(name.as_str(), value);
+        }
+    }
+
+    if !cookies.is_empty() {
+        headers.add(HeaderType::Cookie, cookies.join("; "));
+    }
+
+    if headers.get(HeaderType::Host).is_none() {
+        if let Some(authority) = parts.uri.authority() {
+            headers.add(HeaderType::Host, authority.as_str());
+        }
+    }
+
+    let address = Address::from_socket_addr(addr);
+
+    Ok(Request {
+        method,
+        uri: parts.uri.path().to_string(),
+        query: parts.uri.query().unwrap_or_default().to_string(),
+        version: "HTTP/2.0".into(),
+        headers,
+        content: None,
+        address,
+        params: Params::new(),
+    })
+}
+
+/// Sets the body of the request, adding a `Content-Length` header if the client did not send one.
+fn set_content(request: &mut Request, content: Option<Vec<u8>>) {
+    if let Some(content) = &content {
+        if request.headers.get(HeaderType::ContentLength).is_none() {
+            request
+                .headers
+                .add(HeaderType::ContentLength, content.len().to_string());
+        }
+    }
+
+    request.content = content;
+}
+
+/// Reads the whole body of a stream, returning `RequestError::TooLarge` if it exceeds the maximum size.
+///
+/// Returns `None` if the request has no body.
+async fn read_body(
+    body: &mut RecvStream,
+    max_size: Option<usize>,
+) -> Result<Option<Vec<u8>>, RequestError> {
+    let mut content: Vec<u8> = Vec::new();
+
+    while let Some(data) = body.data().await {
+        let data = data.map_err(|_| RequestError::Stream)?;
+        let _ = body.flow_control().release_capacity(data.len());
+
+        content.extend_from_slice(&data);
+
+        if max_size.is_some_and(|max_size| content.len() > max_size) {
+            return Err(RequestError::TooLarge);
+        }
+    }
+
+    Ok((!content.is_empty()).then_some(content))
+}
+
+/// Reads the body of a stream and sends it over the channel to a `RequestBody`.
+///
+/// Returns `true` if the entire body was read, or `false` if the receiver was dropped or an error occurred
+///   before the end of the body.
+async fn pump_stream(
+    body: &mut RecvStream,
+    sender: Sender<std::io::Result<Vec<u8>>>,
+    max_size: Option<usize>,
+) -> bool {
+    let mut length: usize = 0;
+
+    while let Some(data) = body.data().await {
+        let data = match data {
+            Ok(data) => data,
+            Err(e) => {
+                let _ = sender.send(Err(Error::other(e))).await;
+                return false;
+            }
+        };
+
+        let _ = body.flow_control().release_capacity(data.len());
+        length += data.len();
+
+        if max_size.is_some_and(|max_size| length > max_size) {
+            let _ = sender
+                .send(Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "request body exceeds maximum size",
+                )))
+                .await;
+
+            return false;
+        }
+
+        if sender.send(Ok(data.to_vec())).await.is_err() {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Sends the response on a stream, streaming the body if necessary.
+///
+/// The `Server`, `Date` and `Content-Length` headers are added if they are missing, and headers which only apply to
+///   HTTP/1.1 connections are removed. If `is_head` is true, the body is left out but the headers describing it
+///   are kept.
+async fn send_response(
+    respond: &mut SendResponse<Bytes>,
+    mut response: Response,
+    is_head: bool,
+) -> std::io::Result<()> {
+    if response.headers.get(HeaderType::Server).is_none() {
+        response.headers.add(HeaderType::Server, "Humphrey");
+    }
+
+    if response.headers.get(HeaderType::Date).is_none() {
+        response
+            .headers
+            .add(HeaderType::Date, DateTime::now().to_string());
+    }
+
+    if response.headers.get(HeaderType::ContentLength).is_none()
+        && response.status_code.allows_body()
+    {
+        if let Some(length) = response.body_length() {
+            response
+                .headers
+                .add(HeaderType::ContentLength, length.to_string());
+        }
+    }
+
+    let mut head = http::Response::new(());
+    *head.status_mut() =
+        http::StatusCode::from_u16(response.status_code.into()).map_err(Error::other)?;
+
+    for header in response.headers.iter() {
+        let name = match HeaderName::from_bytes(header.name.to_string().as_bytes()) {
+            Ok(name) if !CONNECTION_HEADERS.contains(&name.as_str()) => name,
+            _ => continue,
+        };
+
+        if let Ok(value) = HeaderValue::from_str(&header.value) {
+            head.headers_mut().append(name, value);
+        }
+    }
+
+    let stream = response.take_stream();
+    let body = std::mem::take(&mut response.body);
+
+    let end_of_stream =
+        is_head || !response.status_code.allows_body() || (stream.is_none() && body.is_empty());
+
+    let mut send = respond
+        .send_response(head, end_of_stream)
+        .map_err(Error::other)?;
+
+    if end_of_stream {
+        return Ok(());
+    }
+
+    match stream {
+        Some(BodyStream::Reader(reader, length)) => {
+            let mut reader: Box<dyn AsyncRead + Send + Unpin> = match length {
+                Some(length) => Box::new(reader.take(length)),
+                None => reader,
+            };
+
+            let mut written: u64 = 0;
+
+            loop {
+                let mut buf = vec![0u8; STREAM_BUFFER_SIZE];
+                let n = reader.read(&mut buf).await?;
+
+                if n == 0 {
+                    break;
+                }
+
+                buf.truncate(n);
+                send_data(&mut send, buf.into()).await?;
+                written += n as u64;
+            }
+
+            if length.is_some_and(|length| written != length) {
+                send.send_reset(Reason::INTERNAL_ERROR);
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+        }
+        Some(BodyStream::Chunks(chunks)) => {
+            for chunk in chunks {
+                send_data(&mut send, chunk.into()).await?;
+            }
+        }
+        None => send_data(&mut send, body.into()).await?,
+    }
+
+    send.send_data(Bytes::new(), true).map_err(Error::other)
+}
+
+/// Sends data on a stream, waiting for the client's flow control window to allow each part of it to be sent.
+async fn send_data(send: &mut SendStream<Bytes>, mut data: Bytes) -> std::io::Result<()> {
+    while !data.is_empty() {
+        send.reserve_capacity(data.len());
+
+        let capacity = match poll_fn(|cx| send.poll_capacity(cx)).await {
+            Some(Ok(capacity)) => capacity,
+            Some(Err(e)) => return Err(Error::other(e)),
+            None => return Err(ErrorKind::BrokenPipe.into()),
+        };
+
+        if capacity > 0 {
+            let part = data.split_to(capacity.min(data.len()));
+            send.send_data(part, false).map_err(Error::other)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends the monitor event describing the outcome of a stream.
+fn log_response(
+    monitor: &MonitorConfig,
+    addr: SocketAddr,
+    status: StatusCode,
+    request: Option<&Request>,
+    result: std::io::Result<()>,
+) {
+    let status_str: &str = status.into();
+
+    let event = match (result, request) {
+        (Err(e), _) => Event::new(EventType::RequestServedError).with_info(e.to_string()),
+        (Ok(()), Some(request)) if status == StatusCode::OK => {
+            Event::new(EventType::RequestServedSuccess)
+                .with_info(format!("200 OK {} (HTTP/2)", request.uri))
+        }
+        (Ok(()), Some(request)) => Event::new(EventType::RequestServedError).with_info(format!(
+            "{} {} {} (HTTP/2)",
+            u16::from(status),
+            status_str,
+            request.uri
+        )),
+        (Ok(()), None) => Event::new(EventType::RequestServedError).with_info(format!(
+            "{} {} (HTTP/2)",
+            u16::from(status),
+            status_str
+        )),
+    };
+
+    monitor.send(event.with_peer(addr));
+}
@@ -1,4 +1,6 @@
 pub mod app;
 pub mod handler_traits;
 pub mod handlers;
+#[cfg(feature = "http2")]
+pub(crate) mod http2;
 pub mod stream;
@@ -5,23 +5,29 @@
 #[cfg(feature = "tls")]
 use tokio_rustls::server::TlsStream;
 
-use std::net::SocketAddr;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
 use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
 
 /// Represents a connection to a remote client or server.
 ///
 /// This is typically a wrapper around `TcpStream`, but is required to allow for a single API
-///   to be used to process both regular and TLS connections.
+///   to be used to process regular, TLS and Unix domain socket connections.
 pub enum Stream {
     /// A regular TCP stream.
     Tcp(TcpStream),
     /// A TLS stream.
     #[cfg(feature = "tls")]
     Tls(TlsStream<TcpStream>),
+    /// A Unix domain socket stream.
+    #[cfg(unix)]
+    Unix(UnixStream),
 }
 
 impl AsyncRead for Stream {
@@ -34,6 +40,8 @@ impl AsyncRead for Stream {
             Stream::Tcp(inner) => Pin::new(inner).poll_read(cx, buf),
             #[cfg(feature = "tls")]
             Stream::Tls(inner) => Pin::new(inner).poll_read(cx, buf),
+            #[cfg(unix)]
+            Stream::Unix(inner) => Pin::new(inner).poll_read(cx, buf),
         }
     }
 }
@@ -48,6 +56,8 @@ impl AsyncWrite for Stream {
             Stream::Tcp(inner) => Pin::new(inner).poll_write(cx, buf),
             #[cfg(feature = "tls")]
             Stream::Tls(inner) => Pin::new(inner).poll_write(cx, buf),
+            #[cfg(unix)]
+            Stream::Unix(inner) => Pin::new(inner).poll_write(cx, buf),
         }
     }
 
@@ -56,6 +66,8 @@ impl AsyncWrite for Stream {
             Stream::Tcp(inner) => Pin::new(inner).poll_flush(cx),
             #[cfg(feature = "tls")]
             Stream::Tls(inner) => Pin::new(inner).poll_flush(cx),
+            #[cfg(unix)]
+            Stream::Unix(inner) => Pin::new(inner).poll_flush(cx),
         }
     }
 
@@ -64,17 +76,55 @@ impl AsyncWrite for Stream {
             Stream::Tcp(inner) => Pin::new(inner).poll_shutdown(cx),
             #[cfg(feature = "tls")]
             Stream::Tls(inner) => Pin::new(inner).poll_shutdown(cx),
+            #[cfg(unix)]
+            Stream::Unix(inner) => Pin::new(inner).poll_shutdown(cx),
         }
     }
 }
 
 impl Stream {
     /// Returns the socket address of the remote peer of this connection.
+    ///
+    /// Peers connected over a Unix domain socket have no socket address, so the unspecified address is returned
+    ///   for them, and `Stream::unix_socket` can be used to identify the socket instead.
     pub fn peer_addr(&self) -> std::io::Result<SocketAddr> {
         match self {
             Stream::Tcp(stream) => stream.peer_addr(),
             #[cfg(feature = "tls")]
             Stream::Tls(stream) => stream.get_ref().0.peer_addr(),
+            #[cfg(unix)]
+            Stream::Unix(_) => Ok(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))),
+        }
+    }
+
+    /// Returns the path of the Unix domain socket this connection was accepted on, or `None` if it is not a Unix
+    ///   domain socket connection.
+    pub fn unix_socket(&self) -> Option<PathBuf> {
+        match self {
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream
+                .local_addr()
+                .ok()
+                .and_then(|addr| addr.as_pathname().map(PathBuf::from)),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if this connection is secured with TLS.
+    pub fn is_tls(&self) -> bool {
+        match self {
+            #[cfg(feature = "tls")]
+            Stream::Tls(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if this is a Unix domain socket connection.
+    pub fn is_unix(&self) -> bool {
+        match self {
+            #[cfg(unix)]
+            Stream::Unix(_) => true,
+            _ => false,
         }
     }
 
@@ -84,6 +134,8 @@ impl Stream {
             Stream::Tcp(stream) => stream.shutdown().await,
             #[cfg(feature = "tls")]
             Stream::Tls(stream) => stream.get_mut().0.shutdown().await,
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.shutdown().await,
         }
     }
 }
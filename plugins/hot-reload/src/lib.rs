@@ -34,7 +34,7 @@ impl Plugin for HotReloadPlugin {
         config: &HashMap<String, String>,
         state: Arc<AppState>,
     ) -> PluginLoadResult<(), &'static str> {
-        if !state.config.hosts.is_empty() {
+        if !state.config().hosts.is_empty() {
             return PluginLoadResult::NonFatal(
                 "Warning: Hot Reload plugin cannot be used with custom host configuration",
             );